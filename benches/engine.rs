@@ -0,0 +1,70 @@
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+#[path = "../src/bitboard.rs"]
+mod bitboard;
+#[path = "../src/engine.rs"]
+mod engine;
+#[path = "../src/game.rs"]
+mod game;
+#[path = "../src/invite.rs"]
+mod invite;
+#[path = "../src/messages.rs"]
+mod messages;
+#[path = "../src/opening_book.rs"]
+mod opening_book;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/resources.rs"]
+mod resources;
+#[path = "../src/transport.rs"]
+mod transport;
+
+use game::Game;
+
+// classic midgames with no four in a row yet, past the opening book
+const MIDGAMES: [(&str, &[usize]); 3] = [
+    ("midgame_10", &[3, 3, 4, 2, 2, 4, 3, 3, 5, 1]),
+    ("midgame_14", &[3, 2, 3, 3, 4, 4, 2, 5, 4, 3, 1, 2, 5, 5]),
+    (
+        "midgame_16",
+        &[3, 3, 2, 4, 4, 2, 5, 1, 1, 5, 6, 0, 0, 6, 2, 4],
+    ),
+];
+
+fn position(columns: &[usize]) -> Game {
+    let mut game = Game::new();
+    for &column in columns {
+        game.apply_move(column).unwrap();
+    }
+    game
+}
+
+// the search of the engine opponent and the evaluation bar, at `engine::SEARCH_DEPTH`.
+// the transposition table is emptied before every search, untimed, or after the first
+// iteration each search would only be reading back its own scores
+fn engine_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engine");
+    for (name, columns) in MIDGAMES {
+        let game = position(columns);
+        group.bench_function(format!("best_move_{}", name), |b| {
+            b.iter_batched(
+                engine::clear_table,
+                |()| engine::best_move(black_box(game.moves()), game.player_turn()),
+                BatchSize::PerIteration,
+            )
+        });
+        group.bench_function(format!("scored_moves_{}", name), |b| {
+            b.iter_batched(
+                engine::clear_table,
+                |()| engine::scored_moves(black_box(game.moves()), game.player_turn()),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engine_search);
+criterion_main!(benches);
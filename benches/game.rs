@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bitboard.rs"]
+mod bitboard;
+#[path = "../src/game.rs"]
+mod game;
+#[path = "../src/invite.rs"]
+mod invite;
+#[path = "../src/messages.rs"]
+mod messages;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/resources.rs"]
+mod resources;
+#[path = "../src/transport.rs"]
+mod transport;
+
+use game::{Game, MoveError, Outcome};
+
+// a full board (42 moves) with no four in a row for either player
+const DRAWN_GAME: [usize; 42] = [
+    0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 2, 3, 2, 3, 2, 3, 3, 2, 3, 2, 3, 2, 4, 5, 4, 5, 4, 5, 5, 4,
+    5, 4, 5, 4, 6, 6, 6, 6, 6, 6,
+];
+
+fn play(columns: &[usize]) -> Result<Outcome, MoveError> {
+    let mut game = Game::new();
+    let mut outcome = Outcome::Ongoing;
+    for &column in columns {
+        outcome = game.apply_move(column)?;
+    }
+    Ok(outcome)
+}
+
+fn game_rules(c: &mut Criterion) {
+    let mut group = c.benchmark_group("game");
+    group.bench_function("apply_drawn_game", |b| {
+        b.iter(|| play(black_box(&DRAWN_GAME)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, game_rules);
+criterion_main!(benches);
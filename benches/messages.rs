@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nostr_sdk::serde_json;
+
+#[allow(dead_code)]
+#[path = "../src/messages.rs"]
+mod messages;
+
+use messages::{GameConfig, NetworkMessage, PlayerInput};
+
+fn message_serialization(c: &mut Criterion) {
+    let input = NetworkMessage::Input(PlayerInput::new(3, 10));
+    let new_game = NetworkMessage::NewGame(
+        Some("satoshi@getalby.com".to_string()),
+        1,
+        GameConfig::default(),
+    );
+    let serialized_input = serde_json::to_string(&input).unwrap();
+    let serialized_new_game = serde_json::to_string(&new_game).unwrap();
+
+    let mut group = c.benchmark_group("messages");
+    group.bench_function("serialize_input", |b| {
+        b.iter(|| serde_json::to_string(black_box(&input)).unwrap())
+    });
+    group.bench_function("deserialize_input", |b| {
+        b.iter(|| serde_json::from_str::<NetworkMessage>(black_box(&serialized_input)).unwrap())
+    });
+    group.bench_function("serialize_new_game", |b| {
+        b.iter(|| serde_json::to_string(black_box(&new_game)).unwrap())
+    });
+    group.bench_function("deserialize_new_game", |b| {
+        b.iter(|| serde_json::from_str::<NetworkMessage>(black_box(&serialized_new_game)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, message_serialization);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bitboard.rs"]
+mod bitboard;
+#[path = "../src/invite.rs"]
+mod invite;
+#[path = "../src/messages.rs"]
+mod messages;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/resources.rs"]
+mod resources;
+#[path = "../src/transport.rs"]
+mod transport;
+
+use resources::{Board, PlayerMove};
+
+const MID_GAME: [usize; 20] = [3, 3, 2, 4, 4, 2, 5, 1, 1, 5, 0, 6, 6, 0, 3, 3, 2, 4, 4, 2];
+
+fn build_board(columns: &[usize]) -> Board {
+    let mut board = Board::new();
+    for (i, &column) in columns.iter().enumerate() {
+        let row = board.landing_row(column).unwrap();
+        board.moves.push(PlayerMove::new(i % 2 + 1, column, row));
+    }
+    board
+}
+
+fn move_generation(c: &mut Criterion) {
+    let board = build_board(&MID_GAME);
+
+    let mut group = c.benchmark_group("move_generation");
+    group.bench_function("landing_rows", |b| {
+        b.iter(|| black_box(&board).landing_rows())
+    });
+    group.bench_function("landing_row_per_column", |b| {
+        b.iter(|| {
+            (0..7)
+                .map(|column| black_box(&board).landing_row(column))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, move_generation);
+criterion_main!(benches);
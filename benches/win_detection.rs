@@ -0,0 +1,124 @@
+#![allow(dead_code)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bitboard.rs"]
+mod bitboard;
+#[path = "../src/invite.rs"]
+mod invite;
+#[path = "../src/messages.rs"]
+mod messages;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/resources.rs"]
+mod resources;
+#[path = "../src/transport.rs"]
+mod transport;
+
+use bitboard::{Bitboard, COLUMNS, ROWS};
+use resources::PlayerMove;
+
+// a full board (42 moves) with no four in a row for either player
+const DRAWN_GAME: [usize; 42] = [
+    0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 2, 3, 2, 3, 2, 3, 3, 2, 3, 2, 3, 2, 4, 5, 4, 5, 4, 5, 5, 4,
+    5, 4, 5, 4, 6, 6, 6, 6, 6, 6,
+];
+
+fn build_moves(columns: &[usize]) -> Vec<PlayerMove> {
+    let mut bitboard = Bitboard::default();
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, &column)| {
+            let player = i % 2 + 1;
+            let row = bitboard.play(column, player).unwrap();
+            PlayerMove::new(player, column, row)
+        })
+        .collect()
+}
+
+// the cell scan the board used before the bitboard, kept here to compare against: from
+// the coin, count the player's coins in a line each way by looking through the moves
+fn scan_direction(
+    coin: &PlayerMove,
+    moves: &[PlayerMove],
+    column_step: isize,
+    row_step: isize,
+) -> usize {
+    let mut count = 0;
+    let mut column = coin.column as isize + column_step;
+    let mut row = coin.row as isize + row_step;
+
+    while column >= 0
+        && row >= 0
+        && column < COLUMNS as isize
+        && row < ROWS as isize
+        && moves.iter().any(|m| {
+            m.player == coin.player && m.column == column as usize && m.row == row as usize
+        })
+    {
+        count += 1;
+        column += column_step;
+        row += row_step;
+    }
+
+    count
+}
+
+fn scan_wins(coin: &PlayerMove, moves: &[PlayerMove]) -> bool {
+    [(0, 1), (1, 0), (1, 1), (1, -1)]
+        .iter()
+        .any(|&(column_step, row_step)| {
+            scan_direction(coin, moves, column_step, row_step)
+                + scan_direction(coin, moves, -column_step, -row_step)
+                + 1
+                >= 4
+        })
+}
+
+// the board built from the moves, then both players checked
+fn from_moves(moves: &[PlayerMove]) -> bool {
+    let bitboard = Bitboard::from_moves(moves);
+    bitboard.wins(1) || bitboard.wins(2)
+}
+
+fn win_detection(c: &mut Criterion) {
+    let moves = build_moves(&DRAWN_GAME);
+    let full = Bitboard::from_moves(&moves);
+
+    let mut group = c.benchmark_group("win_detection");
+    group.bench_function("from_moves", |b| b.iter(|| from_moves(black_box(&moves))));
+    group.bench_function("full_board", |b| {
+        b.iter(|| black_box(&full).wins(1) || black_box(&full).wins(2))
+    });
+    group.bench_function("cell_scan_full_board", |b| {
+        b.iter(|| moves.iter().any(|m| scan_wins(m, black_box(&moves))))
+    });
+    group.bench_function("wrapped", |b| {
+        b.iter(|| black_box(&full).wins_wrapped(1) || black_box(&full).wins_wrapped(2))
+    });
+    // the way a game checks: one coin dropped, then the player who dropped it
+    group.bench_function("every_move", |b| {
+        b.iter(|| {
+            let mut bitboard = Bitboard::default();
+            moves
+                .iter()
+                .filter(|m| {
+                    bitboard.play(m.column, m.player);
+                    bitboard.wins(black_box(m.player))
+                })
+                .count()
+        })
+    });
+    group.bench_function("cell_scan_every_move", |b| {
+        b.iter(|| {
+            (1..=moves.len())
+                .filter(|&n| scan_wins(&moves[n - 1], black_box(&moves[..n])))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, win_detection);
+criterion_main!(benches);
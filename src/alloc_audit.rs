@@ -0,0 +1,80 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, LogDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    prelude::*,
+};
+
+pub const ALLOCATIONS_PER_FRAME: DiagnosticId =
+    DiagnosticId::from_u128(176_412_944_093_327_519_835_246_017_265_119_384_771);
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+// the average of the diagnostic as f64 bits, read by `debugState`
+static AVERAGE: AtomicU64 = AtomicU64::new(0);
+
+// the system allocator, counting every allocation and reallocation
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// `--features alloc_audit` logs the heap allocations per frame every second, an idle
+// board should get by with the few of the engine itself
+pub struct AllocAuditPlugin;
+
+impl Plugin for AllocAuditPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(
+            ALLOCATIONS_PER_FRAME,
+            "allocations_per_frame",
+            120,
+        ))
+        .add_plugins(LogDiagnosticsPlugin::filtered(vec![ALLOCATIONS_PER_FRAME]))
+        .add_systems(Last, (count_allocations, store_average).chain());
+    }
+}
+
+fn count_allocations(mut diagnostics: Diagnostics) {
+    let allocations = ALLOCATIONS.swap(0, Ordering::Relaxed);
+    diagnostics.add_measurement(ALLOCATIONS_PER_FRAME, || allocations as f64);
+}
+
+fn store_average(store: Res<DiagnosticsStore>) {
+    if let Some(average) = store
+        .get(ALLOCATIONS_PER_FRAME)
+        .and_then(|diagnostic| diagnostic.average())
+    {
+        AVERAGE.store(average.to_bits(), Ordering::Relaxed);
+    }
+}
+
+pub fn average_allocations() -> f64 {
+    f64::from_bits(AVERAGE.load(Ordering::Relaxed))
+}
@@ -0,0 +1,390 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    challenge_plugin::share_challenge,
+    components::{AnalysisAction, AnalysisCell, AnalysisColumn, AnalysisText, AnalysisUi},
+    engine,
+    game::{Game, Outcome},
+    locale_plugin::{Locale, Phrase},
+    resources::{Board, GameState},
+    study_plugin::StudySession,
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+const CELL_SIZE: f32 = 36.0;
+
+static ANALYSIS_CALLED: AtomicBool = AtomicBool::new(false);
+
+// a detached copy of the game, moves played here never reach the live board
+#[derive(Resource)]
+pub struct Analysis {
+    game: Game,
+    // the live position it was opened from, restored by reset
+    start: Game,
+    suggestion: Option<usize>,
+}
+
+impl Analysis {
+    // the columns from the empty board, what a study shares
+    pub fn line(&self) -> Vec<usize> {
+        self.game.moves().iter().map(|m| m.column).collect()
+    }
+
+    // the position of a study's line, studies are only shared on classic games
+    pub fn show_line(&mut self, line: &[usize]) {
+        let mut game = Game::new();
+        for &column in line {
+            if let Err(e) = game.apply_move(column) {
+                error!("Error replaying study line: {}", e);
+                return;
+            }
+        }
+        self.suggestion = suggest(&game);
+        self.game = game;
+    }
+}
+
+pub struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                show_analysis_button,
+                open_analysis,
+                analysis_input,
+                update_analysis,
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// every finished game between two players is rated, so the analysis board is only
+// offered to spectators, in practice games and once the game is over
+pub fn analysis_allowed(board: &Board, game_state: &GameState) -> bool {
+    game_state.ghost.is_some()
+        || (game_state.player_type != 1 && game_state.player_type != 2)
+        || board.winner.is_some()
+        || board.draw
+}
+
+pub fn analysis_closed(ui: Query<(), With<AnalysisUi>>) -> bool {
+    ui.is_empty()
+}
+
+fn show_analysis_button(board: Res<Board>, game_state: Res<GameState>) {
+    if game_state.start && analysis_allowed(&board, &game_state) {
+        showAnalysisButton();
+    } else {
+        hideAnalysisButton();
+    }
+}
+
+fn open_analysis(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    ui: Query<(), With<AnalysisUi>>,
+) {
+    if !ANALYSIS_CALLED.swap(false, Ordering::SeqCst) || !ui.is_empty() {
+        return;
+    }
+
+    if !analysis_allowed(&board, &game_state) {
+        info!("analysis board is disabled in rated games");
+        return;
+    }
+
+    let game = match Game::from_board(&board) {
+        Ok(game) => game,
+        Err(e) => {
+            error!("Error replaying board for analysis: {}", e);
+            return;
+        }
+    };
+
+    info!("opening analysis board at move {}", board.moves.len());
+
+    commands.insert_resource(Analysis {
+        suggestion: suggest(&game),
+        start: game.clone(),
+        game,
+    });
+
+    spawn_analysis_ui(&mut commands, theme.ui, &locale);
+}
+
+fn spawn_analysis_ui(commands: &mut Commands, ui: UiColors, locale: &Locale) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: ui.panel.with_a(0.97).into(),
+            ..default()
+        })
+        .insert(AnalysisUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(AnalysisText);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        padding: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.35, 0.6).into(),
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for c in 0..7 {
+                        grid.spawn(ButtonBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::ColumnReverse,
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        })
+                        .insert(AnalysisColumn(c))
+                        .with_children(|column| {
+                            for r in 0..6 {
+                                column
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(CELL_SIZE),
+                                            height: Val::Px(CELL_SIZE),
+                                            ..default()
+                                        },
+                                        background_color: ui.background.into(),
+                                        ..default()
+                                    })
+                                    .insert(AnalysisCell { c, r });
+                            }
+                        });
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|buttons| {
+                    for (action, label) in [
+                        (AnalysisAction::Back, Phrase::StepBack),
+                        (AnalysisAction::Reset, Phrase::ResetToGame),
+                        (AnalysisAction::Challenge, Phrase::ShareAsChallenge),
+                        (AnalysisAction::Close, Phrase::Close),
+                    ] {
+                        buttons
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                    ..default()
+                                },
+                                background_color: ui.button.into(),
+                                ..default()
+                            })
+                            .insert(action)
+                            .with_children(|button| {
+                                button.spawn(TextBundle::from_section(
+                                    locale.get(label),
+                                    TextStyle {
+                                        color: ui.text,
+                                        font_size: 14.0,
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn analysis_input(
+    mut commands: Commands,
+    analysis: Option<ResMut<Analysis>>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    study: Res<StudySession>,
+    columns: Query<(&Interaction, &AnalysisColumn), Changed<Interaction>>,
+    actions: Query<(&Interaction, &AnalysisAction), Changed<Interaction>>,
+    ui: Query<Entity, With<AnalysisUi>>,
+) {
+    let Some(mut analysis) = analysis else {
+        return;
+    };
+    // following a study, the board moves with whoever has control
+    let controls = study.controls(&game_state.nostr_keys.public_key());
+
+    for (interaction, column) in columns.iter() {
+        if *interaction != Interaction::Pressed || !controls {
+            continue;
+        }
+
+        if analysis.game.apply_move(column.0).is_ok() {
+            analysis.suggestion = suggest(&analysis.game);
+        }
+    }
+
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            AnalysisAction::Back | AnalysisAction::Reset if !controls => continue,
+            AnalysisAction::Back => {
+                analysis.game.undo();
+            }
+            AnalysisAction::Reset => {
+                analysis.game = analysis.start.clone();
+            }
+            // the position as it is on the board, for followers to find the win
+            AnalysisAction::Challenge => {
+                share_challenge(analysis.line(), &game_state, &locale);
+                continue;
+            }
+            AnalysisAction::Close => {
+                for entity in ui.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                commands.remove_resource::<Analysis>();
+                return;
+            }
+        }
+
+        analysis.suggestion = suggest(&analysis.game);
+    }
+}
+
+fn suggest(game: &Game) -> Option<usize> {
+    if game.outcome() != Outcome::Ongoing {
+        return None;
+    }
+
+    engine::best_move(game.coins(), game.player_turn())
+}
+
+fn update_analysis(
+    analysis: Option<Res<Analysis>>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    board: Res<Board>,
+    mut cells: Query<(&AnalysisCell, &mut BackgroundColor)>,
+    mut text: Query<&mut Text, With<AnalysisText>>,
+) {
+    let Some(analysis) = analysis else {
+        return;
+    };
+
+    // the live game keeps going underneath, its move count is shown for reference
+    if !analysis.is_changed() && !board.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    for (cell, mut color) in cells.iter_mut() {
+        let player = analysis
+            .game
+            .coins()
+            .iter()
+            .find(|player_move| player_move.column == cell.c && player_move.row == cell.r)
+            .map(|player_move| player_move.player);
+
+        *color = match player {
+            Some(1) => Color::rgb(0.87, 0.19, 0.19),
+            Some(_) => Color::rgb(0.97, 0.8, 0.08),
+            None => theme.ui.background,
+        }
+        .into();
+    }
+
+    let side = |player| {
+        locale.get(if player == 1 {
+            Phrase::Red
+        } else {
+            Phrase::Yellow
+        })
+    };
+    let status = match analysis.game.outcome() {
+        Outcome::Win(player) => locale.fill(Phrase::SideWins, &[side(player)]),
+        Outcome::Draw => locale.get(Phrase::Drawn).to_string(),
+        Outcome::Ongoing => {
+            let side = side(analysis.game.player_turn());
+            match analysis.suggestion {
+                Some(column) => {
+                    locale.fill(Phrase::ToMoveSuggestion, &[side, &(column + 1).to_string()])
+                }
+                None => locale.fill(Phrase::ToMove, &[side]),
+            }
+        }
+    };
+
+    let value = locale.fill(
+        Phrase::AnalysisBoard,
+        &[&board.moves.len().to_string(), &status],
+    );
+
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn showAnalysisButton();
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn hideAnalysisButton();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(non_snake_case)]
+mod page {
+    pub fn showAnalysisButton() {}
+    pub fn hideAnalysisButton() {}
+}
+#[cfg(not(target_arch = "wasm32"))]
+use page::*;
+
+#[wasm_bindgen]
+pub fn open_analysis_board() {
+    ANALYSIS_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{serde_json, Event as NostrEvent, Filter, Kind, Tag, Timestamp};
+use serde::Serialize;
+
+use crate::{
+    components::{AnnouncementDismiss, AnnouncementUi},
+    locale_plugin::{Locale, Phrase},
+    messages::{Announcement, AnnouncementLevel, ANNOUNCEMENT_KIND, ANNOUNCEMENT_TAG},
+    nostr_plugin::fetch_events,
+    platform,
+    resources::ProtocolConfig,
+    storage, AppState,
+};
+
+// the id of the last announcement the player closed, the page reads the same key
+const DISMISSED_KEY: &str = "dismissedAnnouncement";
+
+// the maintainer's latest announcement, fetched whenever the menu opens
+#[derive(Resource, Default)]
+struct Announcements {
+    read: Option<Receiver<Vec<NostrEvent>>>,
+    current: Option<(String, Announcement)>,
+}
+
+// what the page's banner gets
+#[derive(Serialize)]
+struct PageBanner<'a> {
+    id: &'a str,
+    title: &'a str,
+    message: &'a str,
+}
+
+pub struct AnnouncementPlugin;
+
+impl Plugin for AnnouncementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtocolConfig>()
+            .init_resource::<Announcements>()
+            .add_systems(OnEnter(AppState::Menu), fetch_announcement)
+            .add_systems(
+                Update,
+                (receive_announcement, dismiss_announcement, show_banner)
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(OnExit(AppState::Menu), hide_banner);
+    }
+}
+
+fn title(level: AnnouncementLevel, locale: &Locale) -> &'static str {
+    locale.get(match level {
+        AnnouncementLevel::Maintenance => Phrase::Maintenance,
+        AnnouncementLevel::Release => Phrase::NewVersion,
+        AnnouncementLevel::Tournament => Phrase::Tournament,
+    })
+}
+
+fn fetch_announcement(config: Res<ProtocolConfig>, mut announcements: ResMut<Announcements>) {
+    let Some(maintainer) = config.maintainer else {
+        return;
+    };
+
+    let (mut events_tx, events_rx) = futures::channel::mpsc::channel::<Vec<NostrEvent>>(1);
+    announcements.read = Some(events_rx);
+
+    platform::spawn(async move {
+        let filter = Filter::new()
+            .kind(Kind::ParameterizedReplaceable(ANNOUNCEMENT_KIND))
+            .author(maintainer)
+            .identifier(ANNOUNCEMENT_TAG);
+        let events = fetch_events(vec![filter], Vec::new()).await;
+
+        if let Err(e) = events_tx.try_send(events) {
+            error!("Error sending announcement: {}", e);
+        }
+    });
+}
+
+fn receive_announcement(config: Res<ProtocolConfig>, mut announcements: ResMut<Announcements>) {
+    let Some(ref mut events_rx) = announcements.read else {
+        return;
+    };
+    let Ok(Some(events)) = events_rx.try_next() else {
+        return;
+    };
+    announcements.read = None;
+
+    let now = Timestamp::now();
+    let dismissed = storage::get_item(DISMISSED_KEY);
+
+    // relays may keep an older version of the replaceable event, the newest one counts
+    let latest = events
+        .iter()
+        .filter(|event| Some(event.pubkey) == config.maintainer)
+        .max_by_key(|event| event.created_at);
+
+    let current = latest.and_then(|event| {
+        let expired = event.tags.iter().any(|tag| match tag {
+            Tag::Expiration(expires) => *expires <= now,
+            _ => false,
+        });
+        let id = event.id.to_hex();
+        if expired || dismissed.as_deref() == Some(id.as_str()) {
+            return None;
+        }
+
+        match serde_json::from_str::<Announcement>(&event.content) {
+            Ok(announcement) => Some((id, announcement)),
+            Err(e) => {
+                error!("Error reading announcement: {}", e);
+                None
+            }
+        }
+    });
+
+    let shown = announcements.current.as_ref().map(|(id, _)| id);
+    if shown != current.as_ref().map(|(id, _)| id) {
+        announcements.current = current;
+    }
+}
+
+fn dismiss_announcement(
+    dismiss: Query<&Interaction, (Changed<Interaction>, With<AnnouncementDismiss>)>,
+    mut announcements: ResMut<Announcements>,
+) {
+    if !dismiss
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    if let Some((id, _)) = announcements.current.take() {
+        storage::set_item(DISMISSED_KEY, &id);
+    }
+}
+
+// the page draws its own banner above its menu, the desktop menu gets one at the top of
+// the window
+fn show_banner(
+    mut commands: Commands,
+    announcements: Res<Announcements>,
+    locale: Res<Locale>,
+    banner: Query<Entity, With<AnnouncementUi>>,
+) {
+    if !announcements.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    for entity in banner.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some((ref id, ref announcement)) = announcements.current else {
+        return;
+    };
+
+    if platform::page_menu() {
+        let page_banner = PageBanner {
+            id,
+            title: title(announcement.level, &locale),
+            message: &announcement.message,
+        };
+        platform::dispatch_event(
+            "announcement",
+            Some(&serde_json::to_string(&page_banner).unwrap()),
+        );
+        return;
+    }
+
+    let text_style = |font_size: f32| TextStyle {
+        color: Color::BLACK,
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: Color::rgb(1.0, 0.93, 0.6).into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(AnnouncementUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!(
+                    "{}: {}",
+                    title(announcement.level, &locale),
+                    announcement.message
+                ),
+                text_style(18.0),
+            ));
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.9, 0.9, 0.9).into(),
+                    ..default()
+                })
+                .insert(AnnouncementDismiss)
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section("x", text_style(18.0)));
+                });
+        });
+}
+
+fn hide_banner(mut commands: Commands, banner: Query<Entity, With<AnnouncementUi>>) {
+    for entity in banner.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    game::Game,
+    mini_board::mini_board_png,
+    platform,
+    resources::{Board, GameState},
+    storage::{self, ArchivedMoves},
+    training::{self, TrainingGame},
+    AppState,
+};
+
+pub struct ArchivePlugin;
+
+impl Plugin for ArchivePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            archive_finished_game.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn archive_finished_game(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut archived: Local<HashSet<String>>,
+) {
+    if archived.contains(&game_state.game_id)
+        || board.in_progress
+        || (board.winner.is_none() && !board.draw)
+    {
+        return;
+    }
+
+    archived.insert(game_state.game_id.clone());
+
+    if game_state.ghost.is_some() {
+        return;
+    }
+
+    // the ghost replays classic games, modifiers and pops would change its moves and a
+    // team game's moves are two players' each
+    if (game_state.player_type == 1 || game_state.player_type == 2)
+        && board.classic()
+        && game_state.teams.is_none()
+    {
+        let moves = ArchivedMoves {
+            opponent: game_state
+                .p2_ln_address
+                .clone()
+                .unwrap_or_else(|| "Player 2".to_string()),
+            opponent_player: if game_state.player_type == 1 { 2 } else { 1 },
+            columns: board.moves.iter().map(|m| m.column).collect(),
+        };
+        storage::archive_moves(&game_state.game_id, &moves);
+    }
+
+    let thumbnail = match storage::load_thumbnail(&game_state.game_id) {
+        Some(thumbnail) => thumbnail,
+        None => {
+            // the coins left on the board, popped ones are gone
+            let coins = match Game::from_board(&board) {
+                Ok(game) => game.coins().to_vec(),
+                Err(_) => board.moves.clone(),
+            };
+            let Some(png) = mini_board_png(&coins) else {
+                return;
+            };
+
+            let thumbnail = format!("data:image/png;base64,{}", STANDARD.encode(png));
+            storage::archive_game(&game_state.game_id, &thumbnail);
+            info!("archived game {}", game_state.game_id);
+            thumbnail
+        }
+    };
+
+    platform::dispatch_event("send_thumbnail", Some(&thumbnail));
+}
+
+// the archived games as training data, `json` or `npz`, see `training`
+pub fn training_export(format: &str) -> Option<Vec<u8>> {
+    let archived: Vec<ArchivedMoves> = storage::archived_games()
+        .iter()
+        .filter_map(|game_id| storage::load_moves(game_id))
+        .collect();
+    let games: Vec<TrainingGame> = archived
+        .iter()
+        .map(|record| TrainingGame {
+            columns: &record.columns,
+            opponent_player: record.opponent_player,
+        })
+        .collect();
+
+    let samples = training::samples(&games);
+    info!(
+        "{} training samples from {} archived games",
+        samples.len(),
+        games.len()
+    );
+    match format {
+        "json" => Some(training::to_json(&samples).into_bytes()),
+        "npz" => Some(training::to_npz(&samples)),
+        _ => {
+            error!("unknown training data format {}", format);
+            None
+        }
+    }
+}
+
+// downloaded by the page, empty for an unknown format
+#[wasm_bindgen(js_name = trainingData)]
+pub fn training_data(format: &str) -> Vec<u8> {
+    training_export(format).unwrap_or_default()
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    loading_plugin::GameAssets,
+    settings_plugin::{self, Settings},
+};
+
+// sent by the game systems, played by the audio plugin unless muted
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameSound {
+    CoinDrop,
+    Win,
+    Loss,
+    Draw,
+    YourTurn,
+}
+
+impl GameSound {
+    // spectators hear the win sound for either player
+    pub fn result(winner: Option<usize>, player_type: usize) -> Self {
+        match winner {
+            None => Self::Draw,
+            Some(winner) if winner == player_type => Self::Win,
+            Some(_) if player_type == 1 || player_type == 2 => Self::Loss,
+            Some(_) => Self::Win,
+        }
+    }
+}
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameSound>()
+            .add_systems(Update, play_sounds);
+    }
+}
+
+fn play_sounds(
+    mut commands: Commands,
+    mut sounds: EventReader<GameSound>,
+    assets: Res<GameAssets>,
+    mut played: Local<Vec<GameSound>>,
+    settings: Res<Settings>,
+) {
+    if !settings.sound {
+        sounds.clear();
+        return;
+    }
+
+    // several coins landing in the same frame (a replay) play one sound
+    played.clear();
+    for sound in sounds.read() {
+        if played.contains(sound) {
+            continue;
+        }
+        played.push(*sound);
+
+        commands.spawn(AudioBundle {
+            source: assets.sound(*sound),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
+
+// returns the new state so the page can update the mute button
+#[wasm_bindgen]
+pub fn toggle_mute() -> bool {
+    let mut settings = Settings::load();
+    settings.sound = !settings.sound;
+    settings.save();
+    settings_plugin::settings_changed();
+    !settings.sound
+}
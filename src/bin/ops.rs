@@ -0,0 +1,323 @@
+// operator dashboard for hosted deployments. `watch` follows the game events on the
+// relays and prints live games, move latency and error rates, `announce` publishes the
+// service announcement clients show as a banner
+//
+//   UNITE4_RELAYS=wss://relay.one,wss://relay.two cargo run --features ops --bin ops -- watch
+//   UNITE4_OPS_NSEC=nsec1... cargo run --features ops --bin ops -- announce maintenance "..."
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use nostr_sdk::{
+    serde_json, Client, Event, EventBuilder, Filter, Keys, Kind, RelayMessage,
+    RelayPoolNotification, RelayStatus, Tag, Timestamp,
+};
+
+#[allow(dead_code)]
+#[path = "../messages.rs"]
+mod messages;
+
+use messages::{
+    Announcement, AnnouncementLevel, NetworkMessage, ANNOUNCEMENT_KIND, ANNOUNCEMENT_TAG,
+    GAME_TAG_PREFIX, MOVE_KIND,
+};
+
+const USAGE: &str = "usage: ops watch [report seconds]
+       ops announce <maintenance|release|tournament> <message> [hours]
+
+UNITE4_RELAYS   comma separated relays to watch or publish to
+UNITE4_OPS_NSEC key of the maintainer, signs announcements";
+
+const DEFAULT_REPORT_INTERVAL: u64 = 60;
+// a game with no event for this many seconds is no longer counted as live
+const LIVE_WINDOW: u64 = 600;
+
+// counted between two reports, games and unacked moves carry over until they go quiet
+#[derive(Default)]
+struct Stats {
+    last_event: HashMap<String, u64>,
+    // created_at of moves not acknowledged yet, by game id and move index
+    unacked: HashMap<(String, usize), u64>,
+    latency_total: u64,
+    latency_count: u64,
+    events: u64,
+    moves: u64,
+    malformed: u64,
+    sync_requests: u64,
+    relay_errors: BTreeMap<String, RelayErrors>,
+}
+
+#[derive(Default)]
+struct RelayErrors {
+    notices: u64,
+    disconnects: u64,
+}
+
+impl Stats {
+    fn record(&mut self, event: &Event) {
+        let Some(game_id) = game_id(event) else {
+            return;
+        };
+        let created_at = event.created_at.as_u64();
+
+        self.events += 1;
+        self.last_event.insert(game_id.clone(), created_at);
+
+        match serde_json::from_str::<NetworkMessage>(&event.content) {
+            Ok(NetworkMessage::Input(input))
+            | Ok(NetworkMessage::Pop(input))
+            | Ok(NetworkMessage::Anvil(input))
+            | Ok(NetworkMessage::Bomb(input)) => {
+                self.moves += 1;
+                self.unacked
+                    .entry((game_id, input.index))
+                    .or_insert(created_at);
+            }
+            // the opponent's ack closes the round trip of a move through the relays
+            Ok(NetworkMessage::Ack(seq)) | Ok(NetworkMessage::TimedAck(seq, _)) => {
+                if let Some(sent) = self.unacked.remove(&(game_id, seq)) {
+                    self.latency_total += created_at.saturating_sub(sent);
+                    self.latency_count += 1;
+                }
+            }
+            Ok(NetworkMessage::SyncRequest) => self.sync_requests += 1,
+            Ok(_) => {}
+            Err(_) => self.malformed += 1,
+        }
+    }
+
+    fn relay_errors(&mut self, relay_url: String) -> &mut RelayErrors {
+        self.relay_errors.entry(relay_url).or_default()
+    }
+
+    // one line per report, the counters start over
+    fn report(&mut self, now: u64) -> String {
+        self.last_event
+            .retain(|_, last| now.saturating_sub(*last) < LIVE_WINDOW);
+        self.unacked
+            .retain(|_, sent| now.saturating_sub(*sent) < LIVE_WINDOW);
+
+        let latency = match self.latency_count {
+            0 => "-".to_string(),
+            count => format!("{:.1}s", self.latency_total as f64 / count as f64),
+        };
+        let errors: Vec<String> = self
+            .relay_errors
+            .iter()
+            .map(|(relay, errors)| {
+                format!(
+                    "{} {} notices {} disconnects",
+                    relay, errors.notices, errors.disconnects
+                )
+            })
+            .collect();
+
+        let line = format!(
+            "live games: {} | moves: {} | move latency: {} | malformed: {} | sync requests: {} | relay errors: {}",
+            self.last_event.len(),
+            self.moves,
+            latency,
+            percent(self.malformed, self.events),
+            percent(self.sync_requests, self.moves),
+            if errors.is_empty() {
+                "none".to_string()
+            } else {
+                errors.join(", ")
+            },
+        );
+
+        self.latency_total = 0;
+        self.latency_count = 0;
+        self.events = 0;
+        self.moves = 0;
+        self.malformed = 0;
+        self.sync_requests = 0;
+        self.relay_errors.clear();
+
+        line
+    }
+}
+
+fn percent(count: u64, total: u64) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", count as f64 * 100.0 / total as f64)
+}
+
+fn game_id(event: &Event) -> Option<String> {
+    event.tags.iter().find_map(|tag| match tag {
+        Tag::Hashtag(hashtag) => hashtag
+            .strip_prefix(GAME_TAG_PREFIX)
+            .map(|game_id| game_id.to_string()),
+        _ => None,
+    })
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let relays: Vec<String> = env::var("UNITE4_RELAYS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|relay| relay.trim().to_string())
+        .filter(|relay| !relay.is_empty())
+        .collect();
+    if relays.is_empty() {
+        exit_with("no relays, set UNITE4_RELAYS");
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start the tokio runtime");
+
+    match args.first().map(String::as_str) {
+        Some("watch") => {
+            let interval = match args.get(1) {
+                Some(seconds) => seconds
+                    .parse()
+                    .unwrap_or_else(|_| exit_with("report seconds must be a number")),
+                None => DEFAULT_REPORT_INTERVAL,
+            };
+            runtime.block_on(watch(relays, interval));
+        }
+        Some("announce") => {
+            let level = match args.get(1).map(String::as_str) {
+                Some("maintenance") => AnnouncementLevel::Maintenance,
+                Some("release") => AnnouncementLevel::Release,
+                Some("tournament") => AnnouncementLevel::Tournament,
+                _ => exit_with(USAGE),
+            };
+            let Some(message) = args.get(2).cloned() else {
+                exit_with(USAGE);
+            };
+            let hours = args.get(3).map(|hours| {
+                hours
+                    .parse::<u64>()
+                    .unwrap_or_else(|_| exit_with("hours must be a number"))
+            });
+            let keys = env::var("UNITE4_OPS_NSEC")
+                .ok()
+                .and_then(|nsec| Keys::from_sk_str(&nsec).ok())
+                .unwrap_or_else(|| exit_with("set UNITE4_OPS_NSEC to the maintainer key"));
+
+            runtime.block_on(announce(
+                relays,
+                keys,
+                Announcement { level, message },
+                hours,
+            ));
+        }
+        _ => exit_with(USAGE),
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+async fn connect(relays: &[String], keys: &Keys) -> Client {
+    let client = Client::new(keys);
+    for relay in relays {
+        if let Err(e) = client.add_relay(relay.as_str()).await {
+            eprintln!("error adding relay {}: {}", relay, e);
+        }
+    }
+    client.connect().await;
+    client
+}
+
+async fn watch(relays: Vec<String>, interval: u64) {
+    let client = connect(&relays, &Keys::generate()).await;
+    let stats = Arc::new(Mutex::new(Stats::default()));
+
+    // relays can't filter on a tag prefix, every event of the game kinds is checked for it
+    client
+        .subscribe(vec![Filter::new()
+            .kinds(vec![Kind::Regular(4444), Kind::Ephemeral(MOVE_KIND)])
+            .since(Timestamp::now())])
+        .await;
+
+    let report_stats = stats.clone();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(interval));
+        if let Ok(mut stats) = report_stats.lock() {
+            println!("{}", stats.report(Timestamp::now().as_u64()));
+        }
+    });
+
+    println!(
+        "watching {} relays, reporting every {}s",
+        relays.len(),
+        interval
+    );
+
+    let handled = client
+        .handle_notifications(|notification| {
+            let stats = stats.clone();
+            async move {
+                let Ok(mut stats) = stats.lock() else {
+                    return Ok(false);
+                };
+                match notification {
+                    RelayPoolNotification::Event { event, .. } => stats.record(&event),
+                    RelayPoolNotification::Message {
+                        relay_url,
+                        message: RelayMessage::Notice { message },
+                    } => {
+                        eprintln!("notice from {}: {}", relay_url, message);
+                        stats.relay_errors(relay_url.to_string()).notices += 1;
+                    }
+                    RelayPoolNotification::RelayStatus {
+                        relay_url,
+                        status: RelayStatus::Disconnected | RelayStatus::Terminated,
+                    } => stats.relay_errors(relay_url.to_string()).disconnects += 1,
+                    _ => {}
+                }
+                Ok(false)
+            }
+        })
+        .await;
+
+    if let Err(e) = handled {
+        exit_with(&format!("error watching relays: {}", e));
+    }
+}
+
+async fn announce(relays: Vec<String>, keys: Keys, announcement: Announcement, hours: Option<u64>) {
+    let client = connect(&relays, &keys).await;
+
+    let mut tags = vec![
+        Tag::Identifier(ANNOUNCEMENT_TAG.to_string()),
+        Tag::Hashtag(ANNOUNCEMENT_TAG.to_string()),
+    ];
+    if let Some(hours) = hours {
+        tags.push(Tag::Expiration(
+            Timestamp::now() + Duration::from_secs(hours * 3600),
+        ));
+    }
+
+    let event = EventBuilder::new(
+        Kind::ParameterizedReplaceable(ANNOUNCEMENT_KIND),
+        serde_json::to_string(&announcement).unwrap(),
+        tags,
+    )
+    .to_event(&keys)
+    .unwrap_or_else(|e| exit_with(&format!("error signing announcement: {}", e)));
+
+    match client.send_event(event).await {
+        Ok(event_id) => println!(
+            "announcement {} published by {}",
+            event_id,
+            keys.public_key()
+        ),
+        Err(e) => exit_with(&format!("error publishing announcement: {}", e)),
+    }
+}
@@ -0,0 +1,304 @@
+// engine self-play for tuning the engine and mining puzzles. plays classic games of the
+// engine against itself without a window, each move the engine's pick softened by a
+// temperature and now and then a random move, and writes the games in the training data
+// format with a summary of the results
+//
+//   cargo run --release --features selfplay --bin selfplay -- --games 1000 --out selfplay.npz
+
+#![allow(dead_code)]
+
+use std::{
+    collections::HashSet,
+    env, fs, thread,
+    time::{Duration, Instant},
+};
+
+#[path = "../bitboard.rs"]
+mod bitboard;
+#[path = "../engine.rs"]
+mod engine;
+#[path = "../game.rs"]
+mod game;
+#[path = "../invite.rs"]
+mod invite;
+#[path = "../messages.rs"]
+mod messages;
+#[path = "../opening_book.rs"]
+mod opening_book;
+#[path = "../platform.rs"]
+mod platform;
+#[path = "../resources.rs"]
+mod resources;
+#[path = "../training.rs"]
+mod training;
+#[path = "../transport.rs"]
+mod transport;
+
+use game::{Game, GameRng, Outcome, COLUMNS};
+use training::TrainingGame;
+
+const USAGE: &str = "usage: selfplay [options]
+
+--games <n>           games to play, 100 by default
+--seed <n>            seed of the random moves, the same seed plays the same games
+--temperature <t>     softens the engine's pick, 0 always plays its best move
+--noise <p>           chance of a uniformly random move, 0 to 1
+--random-opening <n>  plies played at random before the engine takes over
+--threads <n>         games played at once, all cores by default
+--out <path>          training data to write, .npz or .json";
+
+const DEFAULT_GAMES: usize = 100;
+const DEFAULT_OUT: &str = "selfplay.json";
+
+struct Options {
+    games: usize,
+    seed: u64,
+    temperature: f64,
+    noise: f64,
+    random_opening: usize,
+    threads: usize,
+    out: String,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            games: DEFAULT_GAMES,
+            seed: 0,
+            temperature: 0.0,
+            noise: 0.0,
+            random_opening: 0,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            out: DEFAULT_OUT.to_string(),
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--games" => options.games = value.parse().map_err(|_| invalid())?,
+                "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+                "--temperature" => options.temperature = value.parse().map_err(|_| invalid())?,
+                "--noise" => options.noise = value.parse().map_err(|_| invalid())?,
+                "--random-opening" => {
+                    options.random_opening = value.parse().map_err(|_| invalid())?
+                }
+                "--threads" => options.threads = value.parse().map_err(|_| invalid())?,
+                "--out" => options.out = value.clone(),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        if options.temperature < 0.0 || !(0.0..=1.0).contains(&options.noise) {
+            return Err("the temperature can't be negative and the noise is 0 to 1".to_string());
+        }
+        options.threads = options.threads.max(1);
+        Ok(options)
+    }
+}
+
+// a number from 0 to 1
+fn unit(rng: &mut GameRng) -> f64 {
+    (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn pick_move(game: &Game, options: &Options, rng: &mut GameRng) -> Option<usize> {
+    let scored = engine::scored_moves(game.moves(), game.player_turn());
+    if scored.is_empty() {
+        return None;
+    }
+
+    if game.moves().len() < options.random_opening || unit(rng) < options.noise {
+        return Some(scored[rng.below(scored.len())].0);
+    }
+
+    let best = scored.iter().map(|&(_, score)| score).max()?;
+    if options.temperature == 0.0 {
+        return scored
+            .iter()
+            .find(|&&(_, score)| score == best)
+            .map(|&(column, _)| column);
+    }
+
+    // softmax over the scores, a coin is worth 1 to 4 by its column and a win about 1000
+    let weights: Vec<f64> = scored
+        .iter()
+        .map(|&(_, score)| ((score - best) as f64 / options.temperature).exp())
+        .collect();
+    let mut pick = unit(rng) * weights.iter().sum::<f64>();
+    for (&(column, _), weight) in scored.iter().zip(weights.iter()) {
+        if pick < *weight {
+            return Some(column);
+        }
+        pick -= weight;
+    }
+    scored.last().map(|&(column, _)| column)
+}
+
+// the columns played, each game draws from its own stream so the threads don't matter
+fn play_game(index: usize, options: &Options) -> Vec<usize> {
+    let mut rng = GameRng::at(options.seed, index as u64);
+    let mut game = Game::new();
+
+    while game.outcome() == Outcome::Ongoing {
+        let Some(column) = pick_move(&game, options, &mut rng) else {
+            break;
+        };
+        if let Err(e) = game.apply_move(column) {
+            eprintln!("game {}: the engine played an invalid move: {}", index, e);
+            break;
+        }
+    }
+
+    game.moves().iter().map(|m| m.column).collect()
+}
+
+fn play_games(options: &Options) -> Vec<Vec<usize>> {
+    let indices: Vec<usize> = (0..options.games).collect();
+    let chunk = options.games.div_ceil(options.threads).max(1);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = indices
+            .chunks(chunk)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&index| play_game(index, options))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+#[derive(Default)]
+struct Summary {
+    wins: [usize; 2],
+    draws: usize,
+    unfinished: usize,
+    distinct: usize,
+    plies: usize,
+    shortest: Option<usize>,
+    longest: usize,
+    first_moves: [usize; COLUMNS],
+}
+
+impl Summary {
+    fn of(games: &[Vec<usize>]) -> Self {
+        let mut summary = Summary::default();
+        let mut seen = HashSet::new();
+
+        for columns in games {
+            if seen.insert(columns) {
+                summary.distinct += 1;
+            }
+
+            let mut game = Game::new();
+            for &column in columns {
+                if game.apply_move(column).is_err() {
+                    break;
+                }
+            }
+            match game.outcome() {
+                Outcome::Win(winner) => summary.wins[winner - 1] += 1,
+                Outcome::Draw => summary.draws += 1,
+                Outcome::Ongoing => summary.unfinished += 1,
+            }
+
+            summary.plies += columns.len();
+            summary.shortest = Some(
+                summary
+                    .shortest
+                    .map_or(columns.len(), |shortest| shortest.min(columns.len())),
+            );
+            summary.longest = summary.longest.max(columns.len());
+            if let Some(&first) = columns.first() {
+                summary.first_moves[first] += 1;
+            }
+        }
+
+        summary
+    }
+
+    fn print(&self, games: usize, samples: usize, out: &str, elapsed: Duration) {
+        let share = |count: usize| 100.0 * count as f64 / games.max(1) as f64;
+        println!("games           {} ({} distinct)", games, self.distinct);
+        println!(
+            "player 1 wins   {} ({:.1}%)",
+            self.wins[0],
+            share(self.wins[0])
+        );
+        println!(
+            "player 2 wins   {} ({:.1}%)",
+            self.wins[1],
+            share(self.wins[1])
+        );
+        println!("draws           {} ({:.1}%)", self.draws, share(self.draws));
+        if self.unfinished > 0 {
+            println!("unfinished      {}", self.unfinished);
+        }
+        println!(
+            "plies           avg {:.1}, min {}, max {}",
+            self.plies as f64 / games.max(1) as f64,
+            self.shortest.unwrap_or(0),
+            self.longest
+        );
+        let first_moves: Vec<String> = self
+            .first_moves
+            .iter()
+            .enumerate()
+            .map(|(column, count)| format!("{}:{}", column, count))
+            .collect();
+        println!("first moves     {}", first_moves.join(" "));
+        println!("positions       {} written to {}", samples, out);
+        println!("elapsed         {:.1}s", elapsed.as_secs_f64());
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let started = Instant::now();
+    let games = play_games(&options);
+
+    // both sides are the engine, every move counts as its own
+    let training_games: Vec<TrainingGame> = games
+        .iter()
+        .map(|columns| TrainingGame {
+            columns,
+            opponent_player: 0,
+        })
+        .collect();
+    let samples = training::samples(&training_games);
+    let data = if options.out.ends_with(".npz") {
+        training::to_npz(&samples)
+    } else {
+        training::to_json(&samples).into_bytes()
+    };
+    if let Err(e) = fs::write(&options.out, data) {
+        eprintln!("Error writing {}: {}", options.out, e);
+        std::process::exit(1);
+    }
+
+    Summary::of(&games).print(games.len(), samples.len(), &options.out, started.elapsed());
+}
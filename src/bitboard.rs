@@ -0,0 +1,272 @@
+use crate::resources::PlayerMove;
+
+pub const COLUMNS: usize = 7;
+pub const ROWS: usize = 6;
+// each column has a spare cell on top, so no line runs from the top of one column into the
+// bottom of the next. 64 bits take boards up to 8 columns of 7 rows
+const HEIGHT: usize = ROWS + 1;
+const BOTTOM: u64 = bottom_row();
+const CELLS: u64 = BOTTOM * ((1 << ROWS) - 1);
+// the steps to the neighbouring cell up, right and along both diagonals
+const DIRECTIONS: [usize; 4] = [1, HEIGHT, HEIGHT + 1, HEIGHT - 1];
+
+const fn bottom_row() -> u64 {
+    let mut bottom = 0;
+    let mut column = 0;
+    while column < COLUMNS {
+        bottom |= 1 << (column * HEIGHT);
+        column += 1;
+    }
+    bottom
+}
+
+fn cell(column: usize, row: usize) -> u64 {
+    1 << (column * HEIGHT + row)
+}
+
+fn column_cells(column: usize) -> u64 {
+    ((1 << ROWS) - 1) << (column * HEIGHT)
+}
+
+fn side(player: usize) -> usize {
+    usize::from(player != 1)
+}
+
+// four in a line among the coins, one shift and mask per direction finds three neighbours
+fn has_line(coins: u128) -> bool {
+    DIRECTIONS.iter().any(|&step| {
+        let pairs = coins & (coins >> step);
+        pairs & (pairs >> (2 * step)) != 0
+    })
+}
+
+// the coins on a classic board as a bit per cell and player, column by column from the
+// bottom. a win and the landing rows are a few shifts and masks instead of a scan of the
+// moves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bitboard {
+    coins: [u64; 2],
+}
+
+impl Bitboard {
+    // the coins where they are, cells off the board are left out
+    pub fn from_coins(coins: &[PlayerMove]) -> Self {
+        let mut bitboard = Self::default();
+        for coin in coins {
+            if coin.column < COLUMNS && coin.row < ROWS {
+                bitboard.coins[side(coin.player)] |= cell(coin.column, coin.row);
+            }
+        }
+        bitboard
+    }
+
+    // the moves dropped one after the other, a move into a full column is left out
+    pub fn from_moves(moves: &[PlayerMove]) -> Self {
+        let mut bitboard = Self::default();
+        for player_move in moves {
+            bitboard.play(player_move.column, player_move.player);
+        }
+        bitboard
+    }
+
+    fn filled(&self) -> u64 {
+        self.coins[0] | self.coins[1]
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.filled() == CELLS
+    }
+
+    pub fn player_at(&self, column: usize, row: usize) -> Option<usize> {
+        if column >= COLUMNS || row >= ROWS {
+            return None;
+        }
+        [1, 2]
+            .into_iter()
+            .find(|&player| self.coins[side(player)] & cell(column, row) != 0)
+    }
+
+    // the coins from the bottom up to the first empty cell
+    fn height(&self, column: usize) -> usize {
+        let filled = (self.filled() & column_cells(column)) >> (column * HEIGHT);
+        filled.trailing_ones() as usize
+    }
+
+    pub fn landing_row(&self, column: usize) -> Option<usize> {
+        if column >= COLUMNS {
+            return None;
+        }
+        let height = self.height(column);
+        (height < ROWS).then_some(height)
+    }
+
+    pub fn landing_rows(&self) -> [Option<usize>; COLUMNS] {
+        std::array::from_fn(|column| self.landing_row(column))
+    }
+
+    // adding the bottom row to the filled cells carries up each column to its landing
+    // cell, a full column carries into the spare cell off the board
+    pub fn playable(&self, column: usize) -> bool {
+        column < COLUMNS && (self.filled() + BOTTOM) & CELLS & column_cells(column) != 0
+    }
+
+    // drops the player's coin, the row it landed in or None when the column is full
+    pub fn play(&mut self, column: usize, player: usize) -> Option<usize> {
+        let row = self.landing_row(column)?;
+        self.coins[side(player)] |= cell(column, row);
+        Some(row)
+    }
+
+    // takes the top coin of the column back, the row it was in or None when the column
+    // is empty
+    pub fn undo(&mut self, column: usize) -> Option<usize> {
+        let row = self.height(column).checked_sub(1)?;
+        let kept = !cell(column, row);
+        self.coins[0] &= kept;
+        self.coins[1] &= kept;
+        Some(row)
+    }
+
+    pub fn column_coins(&self, column: usize, player: usize) -> u32 {
+        (self.coins[side(player)] & column_cells(column)).count_ones()
+    }
+
+    pub fn wins(&self, player: usize) -> bool {
+        has_line(self.coins[side(player)] as u128)
+    }
+
+    // on a cylinder lines continue across the left and right edge, so the board is
+    // checked with a copy of itself to its right
+    pub fn wins_wrapped(&self, player: usize) -> bool {
+        let coins = self.coins[side(player)] as u128;
+        has_line(coins | coins << (COLUMNS * HEIGHT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coins(player: usize, cells: &[(usize, usize)]) -> Bitboard {
+        let coins: Vec<PlayerMove> = cells
+            .iter()
+            .map(|&(column, row)| PlayerMove::new(player, column, row))
+            .collect();
+        Bitboard::from_coins(&coins)
+    }
+
+    #[test]
+    fn wins_in_every_direction() {
+        // red's bottom row 0 to 3
+        assert!(coins(1, &[(0, 0), (1, 0), (2, 0), (3, 0)]).wins(1));
+        // red's column 3
+        assert!(coins(1, &[(3, 2), (3, 3), (3, 4), (3, 5)]).wins(1));
+        // red rising from column 0 to 3, then falling from column 3 to 6
+        assert!(coins(1, &[(0, 0), (1, 1), (2, 2), (3, 3)]).wins(1));
+        assert!(coins(1, &[(3, 3), (4, 2), (5, 1), (6, 0)]).wins(1));
+        // yellow's top row, and not red's
+        let yellow = coins(2, &[(3, 5), (4, 5), (5, 5), (6, 5)]);
+        assert!(yellow.wins(2));
+        assert!(!yellow.wins(1));
+    }
+
+    #[test]
+    fn no_win_without_four_in_a_line() {
+        // three in a row
+        assert!(!coins(1, &[(0, 0), (1, 0), (2, 0)]).wins(1));
+        // four with a gap
+        assert!(!coins(1, &[(0, 0), (1, 0), (2, 0), (4, 0)]).wins(1));
+        // the top of column 0 and the bottom of column 1, the spare cell keeps them apart
+        assert!(!coins(1, &[(0, 3), (0, 4), (0, 5), (1, 0)]).wins(1));
+        // the right edge and the left edge
+        assert!(!coins(1, &[(5, 0), (6, 0), (0, 0), (1, 0)]).wins(1));
+    }
+
+    #[test]
+    fn cylinder_lines_cross_the_edge() {
+        // straight across from column 5 to 1
+        let across = coins(1, &[(5, 0), (6, 0), (0, 0), (1, 0)]);
+        assert!(across.wins_wrapped(1));
+        assert!(!across.wins(1));
+        // rising from column 5 to 1
+        assert!(coins(1, &[(5, 0), (6, 1), (0, 2), (1, 3)]).wins_wrapped(1));
+        // a line away from the edge wins too
+        assert!(coins(1, &[(2, 1), (3, 1), (4, 1), (5, 1)]).wins_wrapped(1));
+        // three across the edge and one past a gap
+        assert!(!coins(1, &[(6, 0), (0, 0), (1, 0), (3, 0)]).wins_wrapped(1));
+        // the top of column 6 and the bottom of column 0
+        assert!(!coins(1, &[(6, 3), (6, 4), (6, 5), (0, 0)]).wins_wrapped(1));
+    }
+
+    #[test]
+    fn coins_land_on_top_of_each_other() {
+        let mut bitboard = Bitboard::default();
+        assert_eq!(bitboard.landing_rows(), [Some(0); COLUMNS]);
+        assert_eq!(bitboard.play(3, 1), Some(0));
+        assert_eq!(bitboard.play(3, 2), Some(1));
+        assert_eq!(bitboard.landing_row(3), Some(2));
+        assert_eq!(bitboard.player_at(3, 0), Some(1));
+        assert_eq!(bitboard.player_at(3, 1), Some(2));
+        assert_eq!(bitboard.player_at(3, 2), None);
+        assert_eq!(bitboard.column_coins(3, 1), 1);
+        assert_eq!(bitboard.landing_row(COLUMNS), None);
+        assert!(!bitboard.playable(COLUMNS));
+    }
+
+    #[test]
+    fn full_columns_take_no_coin() {
+        let mut bitboard = Bitboard::default();
+        for row in 0..ROWS {
+            assert!(bitboard.playable(0));
+            assert_eq!(bitboard.play(0, 1 + row % 2), Some(row));
+        }
+        assert!(!bitboard.playable(0));
+        assert_eq!(bitboard.landing_row(0), None);
+        assert_eq!(bitboard.play(0, 1), None);
+        assert!(bitboard.playable(1));
+        assert!(!bitboard.is_full());
+
+        for column in 1..COLUMNS {
+            for row in 0..ROWS {
+                bitboard.play(column, 1 + row % 2);
+            }
+        }
+        assert!(bitboard.is_full());
+        assert_eq!(bitboard.landing_rows(), [None; COLUMNS]);
+    }
+
+    #[test]
+    fn undo_takes_back_the_top_coin() {
+        let mut bitboard = Bitboard::default();
+        assert_eq!(bitboard.undo(2), None);
+        bitboard.play(2, 1);
+        let before = bitboard;
+        bitboard.play(2, 2);
+        assert_eq!(bitboard.undo(2), Some(1));
+        assert_eq!(bitboard, before);
+        assert_eq!(bitboard.undo(2), Some(0));
+        assert_eq!(bitboard, Bitboard::default());
+    }
+
+    #[test]
+    fn popped_out_coins_drop_down() {
+        // red's bottom coin of column 2 popped out, yellow's coin above drops to the bottom
+        let moves = [
+            PlayerMove::new(1, 2, 0),
+            PlayerMove::new(2, 2, 1),
+            PlayerMove::new(1, 4, 0),
+        ];
+        let popped = [PlayerMove::new(2, 2, 0), PlayerMove::new(1, 4, 0)];
+        assert_eq!(
+            Bitboard::from_moves(&moves[1..]),
+            Bitboard::from_coins(&popped)
+        );
+        assert_eq!(Bitboard::from_coins(&popped).player_at(2, 0), Some(2));
+        assert_eq!(Bitboard::from_coins(&popped).landing_row(2), Some(1));
+        // cells off the board are left out
+        assert_eq!(
+            Bitboard::from_coins(&[PlayerMove::new(1, COLUMNS, 0), PlayerMove::new(1, 0, ROWS)]),
+            Bitboard::default()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{BlitzCell, BlitzColumn, BlitzText, BlitzUi},
+    game::{Game, Outcome, Rules, COLUMNS, ROWS},
+    locale_plugin::{Locale, Phrase},
+    platform,
+    theme_plugin::Theme,
+    AppState,
+};
+
+const CELL_SIZE: f32 = 36.0;
+const MIN_LIGHT_DELAY: f64 = 0.8;
+const MAX_LIGHT_DELAY: f64 = 2.5;
+
+static BLITZ_CALLED: AtomicBool = AtomicBool::new(false);
+
+// a hotseat round, the target column lights up after a random delay and the first
+// player to press claims the drop. pressing early locks a player out until the next drop
+#[derive(Resource)]
+struct Blitz {
+    game: Game,
+    target: Option<usize>,
+    lights_at: f64,
+    locked_out: [bool; 2],
+    last_claim: Option<(usize, usize)>,
+}
+
+impl Blitz {
+    fn new(now: f64) -> Self {
+        Self {
+            game: Game::with_rules(Rules::BlitzDrop),
+            target: None,
+            lights_at: now + light_delay(),
+            locked_out: [false; 2],
+            last_claim: None,
+        }
+    }
+}
+
+fn light_delay() -> f64 {
+    MIN_LIGHT_DELAY + platform::random() * (MAX_LIGHT_DELAY - MIN_LIGHT_DELAY)
+}
+
+pub struct BlitzPlugin;
+
+impl Plugin for BlitzPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, check_blitz_system.run_if(in_state(AppState::Menu)))
+            .add_systems(OnEnter(AppState::Blitz), setup_blitz)
+            .add_systems(
+                Update,
+                (light_target, blitz_input, update_blitz)
+                    .chain()
+                    .run_if(in_state(AppState::Blitz)),
+            )
+            .add_systems(OnExit(AppState::Blitz), cleanup_blitz);
+    }
+}
+
+fn check_blitz_system(mut next_state: ResMut<NextState<AppState>>) {
+    if BLITZ_CALLED.swap(false, Ordering::SeqCst) {
+        next_state.set(AppState::Blitz);
+    }
+}
+
+fn setup_blitz(mut commands: Commands, theme: Res<Theme>, time: Res<Time>) {
+    commands.insert_resource(Blitz::new(time.elapsed_seconds_f64()));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(BlitzUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(BlitzText);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        padding: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.35, 0.6).into(),
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for c in 0..COLUMNS {
+                        grid.spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::ColumnReverse,
+                                row_gap: Val::Px(4.0),
+                                padding: UiRect::all(Val::Px(2.0)),
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        })
+                        .insert(BlitzColumn(c))
+                        .with_children(|column| {
+                            for r in 0..ROWS {
+                                column
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(CELL_SIZE),
+                                            height: Val::Px(CELL_SIZE),
+                                            ..default()
+                                        },
+                                        background_color: theme.ui.background.into(),
+                                        ..default()
+                                    })
+                                    .insert(BlitzCell { c, r });
+                            }
+                        });
+                    }
+                });
+        });
+}
+
+fn light_target(mut blitz: ResMut<Blitz>, time: Res<Time>) {
+    if blitz.target.is_some() || time.elapsed_seconds_f64() < blitz.lights_at {
+        return;
+    }
+
+    let columns = blitz.game.open_columns();
+    if columns.is_empty() {
+        return;
+    }
+
+    let index = (platform::random() * columns.len() as f64) as usize;
+    blitz.target = columns.get(index).copied();
+}
+
+// red presses A or taps the left half of the screen, yellow presses L or taps the right
+fn blitz_input(
+    mut blitz: ResMut<Blitz>,
+    keys: Res<Input<KeyCode>>,
+    touches: Res<Touches>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    let mut presses: Vec<usize> = Vec::new();
+    if keys.just_pressed(KeyCode::A) {
+        presses.push(1);
+    }
+    if keys.just_pressed(KeyCode::L) {
+        presses.push(2);
+    }
+    if let Some(window) = windows.iter().next() {
+        for touch in touches.iter_just_pressed() {
+            let player = if touch.position().x < window.width() / 2.0 {
+                1
+            } else {
+                2
+            };
+            if !presses.contains(&player) {
+                presses.push(player);
+            }
+        }
+    }
+
+    if blitz.game.outcome() != Outcome::Ongoing {
+        if keys.just_pressed(KeyCode::Space) || touches.iter_just_pressed().next().is_some() {
+            *blitz = Blitz::new(now);
+        }
+        return;
+    }
+
+    let Some(column) = blitz.target else {
+        for player in presses {
+            info!("player {} pressed too early", player);
+            blitz.locked_out[player - 1] = true;
+        }
+
+        // both jumped the gun, start the wait again
+        if blitz.locked_out == [true, true] {
+            blitz.locked_out = [false, false];
+            blitz.lights_at = now + light_delay();
+        }
+        return;
+    };
+
+    presses.retain(|player| !blitz.locked_out[player - 1]);
+
+    // a tie in the same frame is settled by a coin flip
+    let player = match presses.as_slice() {
+        [] => return,
+        [player] => *player,
+        _ => {
+            if platform::random() < 0.5 {
+                1
+            } else {
+                2
+            }
+        }
+    };
+
+    match blitz.game.claim_drop(player, column) {
+        Ok(outcome) => info!("player {} claimed column {}: {:?}", player, column, outcome),
+        Err(e) => error!("Error claiming drop: {}", e),
+    }
+
+    blitz.last_claim = Some((player, column));
+    blitz.target = None;
+    blitz.locked_out = [false, false];
+    blitz.lights_at = now + light_delay();
+}
+
+fn player_name(player: usize, locale: &Locale) -> &'static str {
+    if player == 1 {
+        locale.get(Phrase::RedPlayer)
+    } else {
+        locale.get(Phrase::YellowPlayer)
+    }
+}
+
+fn update_blitz(
+    blitz: Res<Blitz>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut cells: Query<(&BlitzCell, &mut BackgroundColor), Without<BlitzColumn>>,
+    mut columns: Query<(&BlitzColumn, &mut BackgroundColor), Without<BlitzCell>>,
+    mut text: Query<&mut Text, With<BlitzText>>,
+) {
+    if !blitz.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    for (cell, mut color) in cells.iter_mut() {
+        let player = blitz
+            .game
+            .moves()
+            .iter()
+            .find(|player_move| player_move.column == cell.c && player_move.row == cell.r)
+            .map(|player_move| player_move.player);
+
+        *color = match player {
+            Some(1) => Color::rgb(0.87, 0.19, 0.19),
+            Some(_) => Color::rgb(0.97, 0.8, 0.08),
+            None => theme.ui.background,
+        }
+        .into();
+    }
+
+    for (column, mut color) in columns.iter_mut() {
+        *color = if blitz.target == Some(column.0) {
+            Color::rgb(0.3, 0.9, 0.4)
+        } else {
+            Color::NONE
+        }
+        .into();
+    }
+
+    let status = match blitz.game.outcome() {
+        Outcome::Win(player) => locale.fill(Phrase::BlitzWins, &[player_name(player, &locale)]),
+        Outcome::Draw => locale.get(Phrase::BlitzDraw).to_string(),
+        Outcome::Ongoing => match (blitz.target, blitz.last_claim) {
+            (Some(column), _) => locale.fill(Phrase::BlitzGo, &[&(column + 1).to_string()]),
+            (None, Some((player, column))) => locale.fill(
+                Phrase::BlitzClaimed,
+                &[player_name(player, &locale), &(column + 1).to_string()],
+            ),
+            (None, None) => locale.get(Phrase::BlitzWait).to_string(),
+        },
+    };
+
+    let mut locked = String::new();
+    for player in [1, 2] {
+        if blitz.locked_out[player - 1] {
+            locked.push('\n');
+            locked.push_str(&locale.fill(Phrase::JumpedTheGun, &[player_name(player, &locale)]));
+        }
+    }
+
+    let value = format!("{}\n\n{}{}", locale.get(Phrase::BlitzDrop), status, locked);
+
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn cleanup_blitz(mut commands: Commands, ui: Query<Entity, With<BlitzUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Blitz>();
+}
+
+#[wasm_bindgen]
+pub fn show_blitz() {
+    BLITZ_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use nostr_sdk::{
+    event::tag::Marker, secp256k1::XOnlyPublicKey, EventBuilder, Filter, FromBech32, Keys, Kind,
+    Tag, ToBech32,
+};
+
+use crate::{
+    locale_plugin::{Locale, Phrase},
+    messages::{challenge_tag, CHALLENGE_TAG},
+    nostr_plugin::{fetch_events, publish_events},
+    platform,
+    puzzle::Puzzle,
+    puzzle_plugin,
+    resources::GameState,
+    AppState,
+};
+
+// a position from the analysis board goes out as a note to the player's followers, its
+// link `/?challenge={columns}&by={npub}` opens the position against the engine and the
+// outcome is sent back as a reply to the note
+pub struct ChallengePlugin;
+
+impl Plugin for ChallengePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, open_challenge_link.run_if(in_state(AppState::Menu)));
+    }
+}
+
+fn side(player: usize, locale: &Locale) -> &'static str {
+    if player == 1 {
+        locale.get(Phrase::Red)
+    } else {
+        locale.get(Phrase::Yellow)
+    }
+}
+
+pub fn moves_text(moves: usize, locale: &Locale) -> String {
+    match moves {
+        1 => locale.get(Phrase::OneMove).to_string(),
+        moves => locale.fill(Phrase::ManyMoves, &[&moves.to_string()]),
+    }
+}
+
+// the engine checks there is a win to find before the note goes out
+pub fn share_challenge(columns: Vec<usize>, game_state: &GameState, locale: &Locale) {
+    let Some(puzzle) = Puzzle::from_position(columns) else {
+        platform::alert(locale.get(Phrase::NoForcedWinToShare));
+        return;
+    };
+
+    let Ok(npub) = game_state.nostr_keys.public_key().to_bech32() else {
+        return;
+    };
+    let position: String = puzzle
+        .columns
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    let link = format!("{}/?challenge={}&by={}", platform::origin(), position, npub);
+    let content = locale.fill(
+        Phrase::FindTheWin,
+        &[
+            side(puzzle.player(), locale),
+            &moves_text(puzzle.moves, locale),
+            &link,
+        ],
+    );
+
+    let note = EventBuilder::new(
+        Kind::TextNote,
+        content,
+        [
+            Tag::Hashtag(CHALLENGE_TAG.to_string()),
+            Tag::Hashtag(challenge_tag(&puzzle.columns)),
+        ],
+    );
+    match note.to_event(&game_state.nostr_keys) {
+        Ok(event) => {
+            info!("sharing challenge {}", position);
+            platform::spawn(publish_events(vec![event]));
+            platform::prompt(locale.get(Phrase::ChallengePosted), Some(&link));
+        }
+        Err(e) => error!("Error signing challenge: {}", e),
+    }
+}
+
+// once, when the app was opened on a challenge link
+fn open_challenge_link(locale: Res<Locale>, mut checked: Local<bool>) {
+    if *checked {
+        return;
+    }
+    *checked = true;
+
+    let search = platform::query();
+    let param = |name: &str| {
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|param| param.strip_prefix(name))
+            .map(|value| value.to_string())
+    };
+    let Some(position) = param("challenge=") else {
+        return;
+    };
+
+    let columns: Option<Vec<usize>> = position
+        .chars()
+        .map(|c| c.to_digit(10).map(|column| column as usize))
+        .collect();
+    let author = param("by=").and_then(|npub| XOnlyPublicKey::from_bech32(npub).ok());
+    // the engine solves it again, the link's author could be wrong
+    match columns.and_then(Puzzle::from_position) {
+        Some(puzzle) => {
+            info!("challenge {} by {:?}", position, author);
+            puzzle_plugin::play_challenge(puzzle, author);
+        }
+        None => platform::alert(locale.get(Phrase::ChallengeNoWin)),
+    }
+}
+
+// a reply to the challenge's note, or a mention of its author when the note isn't found
+pub fn report_challenge(
+    puzzle: &Puzzle,
+    author: XOnlyPublicKey,
+    solved: bool,
+    keys: Keys,
+    locale: &Locale,
+) {
+    let tag = challenge_tag(&puzzle.columns);
+    let content = if solved {
+        locale.fill(
+            Phrase::SolvedChallenge,
+            &[&moves_text(puzzle.moves, locale)],
+        )
+    } else {
+        locale.get(Phrase::MissedChallenge).to_string()
+    };
+
+    platform::spawn(async move {
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .author(author)
+            .hashtag(tag.clone());
+        let note = fetch_events(vec![filter], Vec::new()).await.pop();
+
+        let tags = note
+            .map(|note| Tag::Event(note.id, None, Some(Marker::Root)))
+            .into_iter()
+            .chain([Tag::PubKey(author, None), Tag::Hashtag(tag)]);
+        match EventBuilder::new(Kind::TextNote, content, tags).to_event(&keys) {
+            Ok(reply) => publish_events(vec![reply]).await,
+            Err(e) => error!("Error signing challenge reply: {}", e),
+        }
+    });
+}
@@ -0,0 +1,151 @@
+use nostr_sdk::{
+    nips::nip01::Coordinate, secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent,
+    EventBuilder, FromBech32, Kind, Tag, ToBech32,
+};
+
+use crate::messages::{
+    club_tag, ClubInfo, ClubTournament, GameResult, CLUB_KIND, CLUB_TAG, TOURNAMENT_KIND,
+};
+
+// how long a tournament stays listed after its start
+const TOURNAMENT_LISTED: u64 = 2 * 60 * 60;
+
+// a club's follow set as read from the relays, or as just created
+#[derive(Debug, Clone)]
+pub struct Club {
+    pub author: XOnlyPublicKey,
+    pub identifier: String,
+    pub info: ClubInfo,
+    pub members: Vec<XOnlyPublicKey>,
+}
+
+impl Club {
+    // the author is its first member
+    pub fn new(author: XOnlyPublicKey, identifier: String, info: ClubInfo) -> Self {
+        Self {
+            author,
+            identifier,
+            info,
+            members: vec![author],
+        }
+    }
+
+    // relays may keep older versions of the list, the newest one counts
+    pub fn from_events(events: &[NostrEvent], coordinate: &Coordinate) -> Option<Self> {
+        let event = events
+            .iter()
+            .filter(|event| {
+                event.kind == Kind::ParameterizedReplaceable(CLUB_KIND)
+                    && event.pubkey == coordinate.pubkey
+                    && event.tags.iter().any(|tag| match tag {
+                        Tag::Identifier(identifier) => *identifier == coordinate.identifier,
+                        _ => false,
+                    })
+            })
+            .max_by_key(|event| event.created_at)?;
+
+        Some(Self {
+            author: event.pubkey,
+            identifier: coordinate.identifier.clone(),
+            info: serde_json::from_str(&event.content).ok()?,
+            members: event
+                .tags
+                .iter()
+                .filter_map(|tag| match tag {
+                    Tag::PubKey(pubkey, _) => Some(*pubkey),
+                    _ => None,
+                })
+                .collect(),
+        })
+    }
+
+    // the naddr of the follow set, shared to invite members
+    pub fn address(&self) -> String {
+        let coordinate = Coordinate {
+            kind: Kind::ParameterizedReplaceable(CLUB_KIND),
+            pubkey: self.author,
+            identifier: self.identifier.clone(),
+            relays: Vec::new(),
+        };
+        coordinate.to_bech32().unwrap_or_default()
+    }
+
+    pub fn tag(&self) -> String {
+        club_tag(&self.author, &self.identifier)
+    }
+
+    pub fn admin(&self, pubkey: &XOnlyPublicKey) -> bool {
+        *pubkey == self.author || self.info.admins.contains(pubkey)
+    }
+
+    pub fn member(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.members.contains(pubkey)
+    }
+
+    // signed by the author, a new version replaces the last
+    pub fn event(&self) -> EventBuilder {
+        let tags = [
+            Tag::Identifier(self.identifier.clone()),
+            Tag::Hashtag(CLUB_TAG.to_string()),
+        ]
+        .into_iter()
+        .chain(self.members.iter().map(|member| Tag::PubKey(*member, None)));
+
+        EventBuilder::new(
+            Kind::ParameterizedReplaceable(CLUB_KIND),
+            serde_json::to_string(&self.info).unwrap(),
+            tags,
+        )
+    }
+
+    pub fn tournament_event(&self, tournament: &ClubTournament) -> EventBuilder {
+        EventBuilder::new(
+            Kind::ParameterizedReplaceable(TOURNAMENT_KIND),
+            serde_json::to_string(tournament).unwrap(),
+            [
+                Tag::Identifier(format!("{}:{}", self.identifier, tournament.starts_at)),
+                Tag::Hashtag(self.tag()),
+            ],
+        )
+    }
+
+    // tournaments scheduled by an admin that didn't start long ago, the soonest first
+    pub fn tournaments(&self, events: &[NostrEvent], now: u64) -> Vec<ClubTournament> {
+        let mut tournaments: Vec<ClubTournament> = events
+            .iter()
+            .filter(|event| {
+                event.kind == Kind::ParameterizedReplaceable(TOURNAMENT_KIND)
+                    && self.admin(&event.pubkey)
+            })
+            .filter_map(|event| serde_json::from_str::<ClubTournament>(&event.content).ok())
+            .filter(|tournament| tournament.starts_at + TOURNAMENT_LISTED > now)
+            .collect();
+        tournaments.sort_by_key(|tournament| tournament.starts_at);
+        tournaments.dedup_by(|a, b| a.starts_at == b.starts_at && a.name == b.name);
+        tournaments
+    }
+
+    // the results of games between two members, for the club leaderboard
+    pub fn results(&self, results: &[GameResult]) -> Vec<GameResult> {
+        results
+            .iter()
+            .filter(|result| self.member(&result.p1_pubkey) && self.member(&result.p2_pubkey))
+            .cloned()
+            .collect()
+    }
+}
+
+// the follow set a club address points to
+pub fn coordinate(address: &str) -> Option<Coordinate> {
+    Coordinate::from_bech32(address)
+        .ok()
+        .filter(|coordinate| coordinate.kind == Kind::ParameterizedReplaceable(CLUB_KIND))
+}
+
+// games created in a club lobby are kept out of the public quick match
+pub fn club_game(event: &NostrEvent) -> bool {
+    event.tags.iter().any(|tag| match tag {
+        Tag::Hashtag(hashtag) => hashtag.starts_with(CLUB_TAG),
+        _ => false,
+    })
+}
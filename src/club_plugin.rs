@@ -0,0 +1,535 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nanoid::nanoid;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, Event as NostrEvent, Filter, FromBech32, Kind, Timestamp,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    club::{self, Club},
+    components::{ClubAction, ClubText, ClubUi},
+    gui_plugin::new_game,
+    leaderboard_plugin::{fetch_game_results, short_pubkey},
+    locale_plugin::{Locale, Phrase},
+    menu_plugin::{open_game_id, QUICK_MATCH_WINDOW},
+    messages::{
+        club_tag, ClubInfo, ClubTournament, GameResult, CLUB_KIND, GAME_TAG_PREFIX, TOURNAMENT_KIND,
+    },
+    nostr_plugin::{fetch_events, game_id_from_tags, publish_events},
+    platform,
+    rating::leaderboard,
+    resources::GameState,
+    settings_plugin::Settings,
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+const CLUB_LEADERBOARD_SIZE: usize = 10;
+
+// the club to open and the name and description of a club to create, from the page
+static OPEN_CLUB: Mutex<Option<String>> = Mutex::new(None);
+static NEW_CLUB: Mutex<Option<(String, String)>> = Mutex::new(None);
+// the hashtag of the club whose lobby found no open game, taken by the game created next
+static LOBBY_CLUB: Mutex<Option<String>> = Mutex::new(None);
+
+// the club on the page, its tournament events and the results of the leaderboard
+#[derive(Resource, Default)]
+struct ClubPage {
+    address: String,
+    read: Option<Receiver<(Vec<NostrEvent>, Vec<GameResult>)>>,
+    club: Option<Club>,
+    tournaments: Vec<ClubTournament>,
+    results: Vec<GameResult>,
+    lobby: Option<Receiver<Option<String>>>,
+}
+
+// a club is a follow set of member pubkeys. its page lists the members, the tournaments
+// the admins scheduled and a leaderboard of the games between members, and its lobby
+// matches members only
+pub struct ClubPlugin;
+
+impl Plugin for ClubPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClubPage>()
+            .add_systems(Update, check_club_system.run_if(in_state(AppState::Menu)))
+            .add_systems(OnEnter(AppState::Club), (setup_club, fetch_club))
+            .add_systems(
+                Update,
+                (
+                    receive_club,
+                    club_actions,
+                    update_club_lobby,
+                    update_club_text,
+                )
+                    .run_if(in_state(AppState::Club)),
+            )
+            .add_systems(OnExit(AppState::Club), cleanup_club);
+    }
+}
+
+fn check_club_system(
+    mut page: ResMut<ClubPage>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Some((name, description)) = NEW_CLUB.lock().ok().and_then(|mut new| new.take()) {
+        let info = ClubInfo {
+            name,
+            description: Some(description).filter(|description| !description.is_empty()),
+            admins: Vec::new(),
+        };
+        let club = Club::new(game_state.nostr_keys.public_key(), nanoid!(10), info);
+        info!("creating club {}", club.address());
+        publish(club.event(), &game_state);
+
+        *page = ClubPage {
+            address: club.address(),
+            club: Some(club),
+            ..default()
+        };
+        storage::set_current_club(&page.address);
+        next_state.set(AppState::Club);
+        return;
+    }
+
+    if let Some(address) = OPEN_CLUB.lock().ok().and_then(|mut open| open.take()) {
+        if club::coordinate(&address).is_none() {
+            platform::alert(locale.get(Phrase::NotClubAddress));
+            return;
+        }
+        if address != page.address {
+            *page = ClubPage {
+                address,
+                ..default()
+            };
+        }
+        storage::set_current_club(&page.address);
+        next_state.set(AppState::Club);
+    }
+}
+
+fn publish(event: nostr_sdk::EventBuilder, game_state: &GameState) {
+    match event.to_event(&game_state.nostr_keys) {
+        Ok(event) => platform::spawn(publish_events(vec![event])),
+        Err(e) => error!("Error signing club event: {}", e),
+    }
+}
+
+fn fetch_club(mut page: ResMut<ClubPage>) {
+    let Some(coordinate) = club::coordinate(&page.address) else {
+        return;
+    };
+
+    let (mut club_tx, club_rx) =
+        futures::channel::mpsc::channel::<(Vec<NostrEvent>, Vec<GameResult>)>(1);
+    page.read = Some(club_rx);
+
+    platform::spawn(async move {
+        let club_tag = club_tag(&coordinate.pubkey, &coordinate.identifier);
+        let filters = vec![
+            Filter::new()
+                .kind(Kind::ParameterizedReplaceable(CLUB_KIND))
+                .author(coordinate.pubkey)
+                .identifier(coordinate.identifier.clone()),
+            Filter::new()
+                .kind(Kind::ParameterizedReplaceable(TOURNAMENT_KIND))
+                .hashtag(club_tag),
+        ];
+        let events = fetch_events(filters, Vec::new()).await;
+        let results = fetch_game_results().await;
+
+        if let Err(e) = club_tx.try_send((events, results)) {
+            error!("Error sending club: {}", e);
+        }
+    });
+}
+
+fn receive_club(mut page: ResMut<ClubPage>) {
+    let Some(ref mut club_rx) = page.read else {
+        return;
+    };
+    let Ok(Some((events, results))) = club_rx.try_next() else {
+        return;
+    };
+    page.read = None;
+
+    // a club just created may not have reached the relays yet
+    let fetched = club::coordinate(&page.address)
+        .and_then(|coordinate| Club::from_events(&events, &coordinate));
+    if let Some(club) = fetched {
+        page.club = Some(club);
+    }
+    if let Some(club) = page.club.as_ref() {
+        page.tournaments = club.tournaments(&events, Timestamp::now().as_u64());
+    }
+    page.results = results;
+}
+
+fn club_actions(
+    actions: Query<(&Interaction, &ClubAction), Changed<Interaction>>,
+    mut page: ResMut<ClubPage>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    let own = game_state.nostr_keys.public_key();
+
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(club) = page.club.clone() else {
+            continue;
+        };
+
+        match action {
+            ClubAction::Lobby => {
+                if page.lobby.is_some() {
+                    continue;
+                }
+                page.lobby = Some(find_club_game(&club, own));
+            }
+            ClubAction::Share => {
+                platform::prompt(locale.get(Phrase::ShareClub), Some(&club.address()));
+            }
+            ClubAction::AddMember => {
+                let Some(npub) = platform::prompt(locale.get(Phrase::NewMemberNpub), None) else {
+                    continue;
+                };
+                let member = match XOnlyPublicKey::from_bech32(npub.trim()) {
+                    Ok(member) => member,
+                    Err(e) => {
+                        platform::alert(&locale.fill(Phrase::NotAnNpub, &[&e.to_string()]));
+                        continue;
+                    }
+                };
+                let mut club = club;
+                if !club.member(&member) {
+                    club.members.push(member);
+                    publish(club.event(), &game_state);
+                }
+                page.club = Some(club);
+            }
+            ClubAction::Schedule => {
+                let Some(name) = platform::prompt(locale.get(Phrase::TournamentName), None) else {
+                    continue;
+                };
+                let hours = platform::prompt(locale.get(Phrase::TournamentHours), Some("24"))
+                    .and_then(|hours| hours.trim().parse::<f64>().ok())
+                    .filter(|hours| *hours >= 0.0);
+                let Some(hours) = hours else {
+                    platform::alert(locale.get(Phrase::TournamentHoursInvalid));
+                    continue;
+                };
+                let tournament = ClubTournament {
+                    name: name.trim().to_string(),
+                    starts_at: Timestamp::now().as_u64() + (hours * 3600.0) as u64,
+                    time_control: settings.time_control,
+                };
+                info!("scheduling {:?}", tournament);
+                publish(club.tournament_event(&tournament), &game_state);
+                page.tournaments.push(tournament);
+                page.tournaments
+                    .sort_by_key(|tournament| tournament.starts_at);
+            }
+        }
+    }
+}
+
+// the newest open game of another member on the club's hashtag. the joins carry the game's
+// hashtag only, so the club's games are looked up again
+fn find_club_game(club: &Club, own: XOnlyPublicKey) -> Receiver<Option<String>> {
+    let (mut match_tx, match_rx) = futures::channel::mpsc::channel::<Option<String>>(1);
+    let club_tag = club.tag();
+    let members = club.members.clone();
+
+    platform::spawn(async move {
+        let since = Timestamp::now() - QUICK_MATCH_WINDOW;
+        let filter = Filter::new()
+            .kind(Kind::Regular(4444))
+            .hashtag(club_tag)
+            .since(since);
+        let created: Vec<NostrEvent> = fetch_events(vec![filter], Vec::new())
+            .await
+            .into_iter()
+            .filter(|event| members.contains(&event.pubkey))
+            .collect();
+
+        let game_tags: Vec<String> = created
+            .iter()
+            .filter_map(|event| {
+                game_id_from_tags(event).map(|game_id| format!("{}{}", GAME_TAG_PREFIX, game_id))
+            })
+            .collect();
+        let game_id = if game_tags.is_empty() {
+            None
+        } else {
+            let filter = Filter::new()
+                .kind(Kind::Regular(4444))
+                .hashtags(game_tags)
+                .since(since);
+            let events = fetch_events(vec![filter], Vec::new()).await;
+            let events: Vec<NostrEvent> = created.into_iter().chain(events).collect();
+            open_game_id(&events, own)
+        };
+
+        if let Err(e) = match_tx.try_send(game_id) {
+            error!("Error sending club match: {}", e);
+        }
+    });
+
+    match_rx
+}
+
+// joins the game that was found, or creates one in the lobby
+fn update_club_lobby(mut page: ResMut<ClubPage>, mut next_state: ResMut<NextState<AppState>>) {
+    let Some(ref mut match_rx) = page.lobby else {
+        return;
+    };
+    let Ok(Some(game_id)) = match_rx.try_next() else {
+        return;
+    };
+    page.lobby = None;
+
+    match game_id {
+        Some(game_id) => {
+            info!("club lobby joins {}", game_id);
+            platform::push_url(&format!("{}{}", platform::origin(), game_id));
+            next_state.set(AppState::InGame);
+        }
+        // games are created from the menu
+        None => {
+            info!("no open club game, creating one");
+            if let (Ok(mut lobby_club), Some(club)) = (LOBBY_CLUB.lock(), page.club.as_ref()) {
+                *lobby_club = Some(club.tag());
+            }
+            new_game();
+            next_state.set(AppState::Menu);
+        }
+    }
+}
+
+// taken by the game being created, which is then tagged with the club
+pub fn take_lobby_club() -> Option<String> {
+    LOBBY_CLUB
+        .lock()
+        .ok()
+        .and_then(|mut lobby_club| lobby_club.take())
+}
+
+fn setup_club(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::top(Val::Px(60.0)),
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(ClubUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    locale.get(Phrase::ClubLoading),
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(ClubText);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (action, label) in [
+                        (ClubAction::Lobby, Phrase::ClubLobby),
+                        (ClubAction::Share, Phrase::Invite),
+                        (ClubAction::AddMember, Phrase::AddMember),
+                        (ClubAction::Schedule, Phrase::ScheduleTournament),
+                    ] {
+                        row.spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: theme.ui.button.into(),
+                            ..default()
+                        })
+                        .insert(action)
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(
+                                locale.get(label),
+                                TextStyle {
+                                    color: theme.ui.text,
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+// admin buttons are shown to everyone, only the admins' events count
+fn update_club_text(
+    page: Res<ClubPage>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<ClubText>>,
+    mut buttons: Query<(&ClubAction, &mut Visibility)>,
+    spawned: Query<(), Added<ClubText>>,
+) {
+    if !page.is_changed() && !locale.is_changed() && spawned.is_empty() {
+        return;
+    }
+
+    let own = game_state.nostr_keys.public_key();
+    for (action, mut visibility) in buttons.iter_mut() {
+        let shown = match action {
+            ClubAction::Lobby | ClubAction::Share => page.club.is_some(),
+            // only the author can change the follow set
+            ClubAction::AddMember => page.club.as_ref().is_some_and(|club| club.author == own),
+            ClubAction::Schedule => page.club.as_ref().is_some_and(|club| club.admin(&own)),
+        };
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    let value = club_text(&page, own, &locale);
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn club_text(page: &ClubPage, own: XOnlyPublicKey, locale: &Locale) -> String {
+    let Some(club) = page.club.as_ref() else {
+        return locale
+            .get(if page.read.is_some() {
+                Phrase::ClubLoading
+            } else {
+                Phrase::ClubNotFound
+            })
+            .to_string();
+    };
+    let line = |phrase| format!("{}\n", locale.get(phrase));
+
+    let mut value = format!("{}\n", club.info.name);
+    if let Some(description) = club.info.description.as_ref() {
+        value.push_str(&format!("{}\n", description));
+    }
+    let admins: Vec<String> = std::iter::once(&club.author)
+        .chain(club.info.admins.iter())
+        .map(short_pubkey)
+        .collect();
+    value.push('\n');
+    value.push_str(&locale.fill(
+        Phrase::ClubMembers,
+        &[&club.members.len().to_string(), &admins.join(", ")],
+    ));
+    value.push('\n');
+    if !club.member(&own) {
+        value.push_str(&line(Phrase::NotMember));
+    }
+    if page.lobby.is_some() {
+        value.push_str(&line(Phrase::LookingForClubGame));
+    }
+
+    value.push('\n');
+    value.push_str(&line(Phrase::Tournaments));
+    if page.tournaments.is_empty() {
+        value.push_str(&line(Phrase::NoneScheduled));
+    }
+    let now = Timestamp::now().as_u64();
+    for tournament in &page.tournaments {
+        let when = if tournament.starts_at <= now {
+            locale.get(Phrase::UnderWay).to_string()
+        } else {
+            let minutes = (tournament.starts_at - now) / 60;
+            locale.fill(
+                Phrase::StartsIn,
+                &[
+                    &platform::format_date(tournament.starts_at),
+                    &(minutes / 60).to_string(),
+                    &(minutes % 60).to_string(),
+                ],
+            )
+        };
+        let clock = tournament
+            .time_control
+            .map(|seconds| locale.fill(Phrase::PerMove, &[&seconds.to_string()]))
+            .unwrap_or_default();
+        value.push_str(&format!("{}: {}{}\n", tournament.name, when, clock));
+    }
+
+    value.push('\n');
+    value.push_str(&line(Phrase::ClubLeaderboard));
+    let entries = leaderboard(&club.results(&page.results));
+    if entries.is_empty() {
+        value.push_str(&line(Phrase::NoMemberGames));
+    }
+    for (rank, entry) in entries.iter().take(CLUB_LEADERBOARD_SIZE).enumerate() {
+        let name = entry
+            .name
+            .clone()
+            .unwrap_or_else(|| short_pubkey(&entry.pubkey));
+        value.push_str(&locale.fill(
+            Phrase::LeaderboardEntry,
+            &[
+                &(rank + 1).to_string(),
+                &name,
+                &entry.rating.to_string(),
+                &entry.games.to_string(),
+            ],
+        ));
+        value.push('\n');
+    }
+
+    value
+}
+
+fn cleanup_club(mut commands: Commands, ui: Query<Entity, With<ClubUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// opens the club page of a club address, an naddr
+#[wasm_bindgen]
+pub fn show_club(address: String) {
+    if let Ok(mut open) = OPEN_CLUB.lock() {
+        *open = Some(address.trim().to_string());
+    }
+}
+
+#[wasm_bindgen]
+pub fn create_club(name: String, description: String) {
+    if let Ok(mut new) = NEW_CLUB.lock() {
+        *new = Some((name.trim().to_string(), description.trim().to_string()));
+    }
+}
@@ -1,6 +1,7 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, Vec2, Vec3};
+use nostr_sdk::secp256k1::XOnlyPublicKey;
 
-use crate::resources::PlayerMove;
+use crate::{game::Outcome, resources::PlayerMove};
 
 #[derive(Component)]
 pub struct CoinSlot {
@@ -14,26 +15,369 @@ impl CoinSlot {
     }
 }
 
+// the front of the board, tinted with the skin's board color
+#[derive(Component)]
+pub struct BoardFrame;
+
+// outcome is the state of the game after this move, shown once the coin lands
 #[derive(Component)]
 pub struct CoinMove {
     pub player_move: PlayerMove,
+    pub outcome: Outcome,
     pub reached_target: bool,
 }
 
 impl CoinMove {
-    pub fn new(player_move: PlayerMove) -> Self {
+    pub fn new(player_move: PlayerMove, outcome: Outcome) -> Self {
         Self {
             player_move,
+            outcome,
             reached_target: false,
         }
     }
 }
 
+// a coin of a spectator's time-lapse, it waits hidden above the board until its turn to
+// drop and then falls faster than a live move
+#[derive(Component)]
+pub struct TimeLapseCoin {
+    pub waiting: bool,
+}
+
+// a coin taken from the bottom of its column, it slides out of the board and fades
+#[derive(Component)]
+pub struct PoppedCoin;
+
+// a bomb and the coins it took, they grow and fade where they were
+#[derive(Component)]
+pub struct ExplodingCoin;
+
+// a glow on the left and right edge when a cylinder game is won across them
+#[derive(Component)]
+pub struct EdgeGlow;
+
+// the engine's suggested move for the local player, see the hint plugin
+#[derive(Component)]
+pub struct HintGlow;
+
+// a coin going round with the board in gravity shift, from where it was before the turn
+#[derive(Component)]
+pub struct TurningCoin {
+    pub from: Vec3,
+}
+
 #[derive(Component)]
 pub struct TopRow;
 
+// the faint coin showing where a drop in the hovered column lands
+#[derive(Component)]
+pub struct DropGhost;
+
+// the opponent's faint coin above the column they aim at
+#[derive(Component)]
+pub struct OpponentHover;
+
+// a piece of the confetti of a win, thrown up and falling under gravity
+#[derive(Component)]
+pub struct Confetti {
+    pub velocity: Vec2,
+    pub spin: f32,
+    pub age: f32,
+}
+
+#[derive(Component)]
+pub struct ScreenFlash;
+
+#[derive(Component)]
+pub struct LossVeil;
+
 #[derive(Component)]
 pub struct TextChanges;
 
 #[derive(Component)]
 pub struct DisplayTurn;
+
+#[derive(Component)]
+pub struct OpponentAvatar;
+
+#[derive(Component)]
+pub struct OpponentName;
+
+#[derive(Component)]
+pub struct OpponentBadges;
+
+// online, thinking or when the opponent was last seen
+#[derive(Component)]
+pub struct OpponentPresence;
+
+#[derive(Component)]
+pub struct LeaderboardUi;
+
+#[derive(Component)]
+pub struct LeaderboardText;
+
+#[derive(Component)]
+pub struct HistoryUi;
+
+#[derive(Component)]
+pub struct HistoryText;
+
+#[derive(Component)]
+pub struct SeriesUi;
+
+#[derive(Component)]
+pub struct SeriesText;
+
+#[derive(Component)]
+pub struct HistoryGame {
+    pub game_id: String,
+}
+
+#[derive(Component)]
+pub struct AnalysisUi;
+
+#[derive(Component)]
+pub struct AnalysisText;
+
+#[derive(Component)]
+pub struct AnalysisColumn(pub usize);
+
+#[derive(Component)]
+pub struct AnalysisCell {
+    pub c: usize,
+    pub r: usize,
+}
+
+#[derive(Component)]
+pub enum AnalysisAction {
+    Back,
+    Reset,
+    Challenge,
+    Close,
+}
+
+// the progress of the loading screen, see the loading plugin
+#[derive(Component)]
+pub struct LoadingUi;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+// the live evaluation bar above the board, see the evaluation plugin
+#[derive(Component)]
+pub struct EvaluationUi;
+
+// red's part of the bar
+#[derive(Component)]
+pub struct EvaluationBar;
+
+#[derive(Component)]
+pub struct EvaluationText;
+
+// the post-game review, see the review plugin
+#[derive(Component)]
+pub struct ReviewUi;
+
+#[derive(Component)]
+pub struct ReviewText;
+
+// red's part of the evaluation bar
+#[derive(Component)]
+pub struct ReviewBar;
+
+#[derive(Component)]
+pub struct ReviewCell {
+    pub c: usize,
+    pub r: usize,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum ReviewAction {
+    First,
+    Back,
+    Forward,
+    Last,
+    Line,
+    Close,
+}
+
+#[derive(Component)]
+pub struct StudyUi;
+
+#[derive(Component)]
+pub struct StudyText;
+
+#[derive(Component, Debug, Clone, PartialEq)]
+pub enum StudyAction {
+    Share,
+    Follow,
+    RequestControl,
+    HandOver(XOnlyPublicKey),
+    TakeControl,
+    Note,
+    Leave,
+}
+
+#[derive(Component)]
+pub struct ClubUi;
+
+#[derive(Component)]
+pub struct ClubText;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClubAction {
+    Lobby,
+    Share,
+    AddMember,
+    Schedule,
+}
+
+#[derive(Component)]
+pub struct LeagueUi;
+
+#[derive(Component)]
+pub struct LeagueText;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeagueAction {
+    Play,
+    Share,
+    AddPlayer,
+}
+
+#[derive(Component)]
+pub struct StatsUi;
+
+#[derive(Component)]
+pub struct StatsText;
+
+#[derive(Component)]
+pub struct MenuUi;
+
+#[derive(Component)]
+pub struct MenuText;
+
+#[derive(Component)]
+pub struct GameIdText;
+
+// a correspondence game in progress on the desktop menu, by its game id
+#[derive(Component)]
+pub struct OngoingGameButton(pub String);
+
+#[derive(Component)]
+pub struct ScheduledGameButton(pub String);
+
+#[derive(Component)]
+pub enum MenuAction {
+    CreateGame,
+    CreateCorrespondence,
+    JoinById,
+    QuickMatch,
+    VsAi,
+    Puzzle,
+    Club,
+    League,
+    Settings,
+    Join,
+    Back,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingToggle {
+    Sound,
+    AnimationSpeed,
+    DropStyle,
+    DropPreview,
+    CoinTheme,
+    Skin,
+    UnlockSkin,
+    Colorblind,
+    HighContrast,
+    Appearance,
+    Accent,
+    Language,
+    MessagePack,
+    TimeControl,
+    PublishSpeed,
+    Username,
+    Privacy,
+    Relays,
+    Performance,
+    Difficulty,
+    EvaluationBar,
+}
+
+#[derive(Component)]
+pub struct AnnouncementUi;
+
+#[derive(Component)]
+pub struct AnnouncementDismiss;
+
+#[derive(Component)]
+pub struct UsernameUi;
+
+// the typed name, and the hint or validation error below it
+#[derive(Component)]
+pub struct UsernameText;
+
+#[derive(Component)]
+pub struct UsernameHint;
+
+#[derive(Component)]
+pub enum UsernameAction {
+    Save,
+    Cancel,
+}
+
+#[derive(Component)]
+pub struct BlitzUi;
+
+#[derive(Component)]
+pub struct BlitzText;
+
+#[derive(Component)]
+pub struct BlitzColumn(pub usize);
+
+#[derive(Component)]
+pub struct BlitzCell {
+    pub c: usize,
+    pub r: usize,
+}
+
+// the row of open games above the board
+#[derive(Component)]
+pub struct GameSwitcherUi;
+
+// a game of the switcher, by its game id
+#[derive(Component)]
+pub struct GameTab(pub String);
+
+// the profiling overlay's numbers, toggled with F3
+#[derive(Component)]
+pub struct ProfilingText;
+
+// shown to a latecomer whose join player 1 turned down
+#[derive(Component)]
+pub struct GameFullUi;
+
+#[derive(Component, Clone, Copy)]
+pub enum GameFullAction {
+    Spectate,
+    Menu,
+}
+
+// the winner's row of messages to send the loser
+#[derive(Component)]
+pub struct EndMessageUi;
+
+// a message of the winner's pack, by its index
+#[derive(Component, Clone, Copy)]
+pub struct EndMessageButton(pub usize);
+
+// the loser's banner with the winner's message
+#[derive(Component)]
+pub struct EndMessageNotice;
+
+// the banner telling the game switched to reduced mode by itself
+#[derive(Component)]
+pub struct ReducedModeNotice;
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use nostr_sdk::{serde_json, Timestamp};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    locale_plugin::{Locale, Phrase},
+    resources::{Board, GameState},
+    storage::{self, OngoingGame},
+    AppState,
+};
+
+// correspondence games last days, each one played is kept in the list of ongoing games
+// with whose turn it is, so the menu can switch between them. a finished game leaves it
+pub struct CorrespondencePlugin;
+
+impl Plugin for CorrespondencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            track_ongoing_game.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn track_ongoing_game(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    mut saved: Local<Option<OngoingGame>>,
+) {
+    if !game_state.is_changed() && !board.is_changed() {
+        return;
+    }
+    let player = game_state.player_type;
+    if !game_state.correspondence || !game_state.start || (player != 1 && player != 2) {
+        return;
+    }
+
+    if board.winner.is_some() || board.draw {
+        *saved = None;
+        storage::remove_ongoing_game(&game_state.game_id);
+        return;
+    }
+
+    let ongoing = OngoingGame {
+        game_id: game_state.game_id.clone(),
+        opponent: game_state.p2_ln_address.clone(),
+        your_turn: board.player_turn == player,
+        last_move_at: game_state.last_move_at,
+    };
+    if saved.as_ref() != Some(&ongoing) {
+        storage::save_ongoing_game(ongoing.clone());
+        *saved = Some(ongoing);
+    }
+}
+
+// "3h ago", rounded down to the largest unit
+pub fn time_ago(at: u64, locale: &Locale) -> String {
+    let seconds = Timestamp::now().as_u64().saturating_sub(at);
+    match seconds {
+        0..=59 => locale.get(Phrase::JustNow).to_string(),
+        60..=3_599 => locale.fill(Phrase::MinutesAgo, &[&(seconds / 60).to_string()]),
+        3_600..=86_399 => locale.fill(Phrase::HoursAgo, &[&(seconds / 3_600).to_string()]),
+        _ => locale.fill(Phrase::DaysAgo, &[&(seconds / 86_400).to_string()]),
+    }
+}
+
+// what the page's list of ongoing games gets
+#[derive(Serialize)]
+struct PageGame {
+    game_id: String,
+    opponent: Option<String>,
+    your_turn: bool,
+    last_move: Option<String>,
+}
+
+#[wasm_bindgen(js_name = ongoingGames)]
+pub fn ongoing_games() -> String {
+    let locale = Locale::saved();
+    let games: Vec<PageGame> = storage::ongoing_games()
+        .into_iter()
+        .map(|game| PageGame {
+            last_move: game.last_move_at.map(|at| time_ago(at, &locale)),
+            game_id: game.game_id,
+            opponent: game.opponent,
+            your_turn: game.your_turn,
+        })
+        .collect();
+
+    serde_json::to_string(&games).unwrap()
+}
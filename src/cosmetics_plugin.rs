@@ -0,0 +1,415 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent, EventBuilder, Filter, JsonUtil,
+    Keys, Kind, Tag, TagKind,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    locale_plugin::{Locale, Phrase},
+    messages::Skin,
+    nostr_plugin::{fetch_events, relay_pool, stored_relays},
+    platform,
+    resources::{GameState, ProtocolConfig},
+    settings_plugin::Settings,
+    storage, AppState,
+};
+
+// the premium skins whose zap receipts were verified, so they show before the receipts
+// are fetched again
+const UNLOCKED_KEY: &str = "unlocked_skins";
+// the tag of a zap request naming the skin it pays for
+const SKIN_TAG: &str = "skin";
+// once the wallet has the invoice the receipts are fetched again every few seconds until
+// the zap shows up
+const RECHECK_SECONDS: f32 = 10.0;
+const RECHECKS: u32 = 12;
+
+// the skin to unlock, from the settings menu or the page
+static UNLOCK_REQUESTED: Mutex<Option<Skin>> = Mutex::new(None);
+
+// the skins the player may use, the free ones and those zapped for
+#[derive(Resource)]
+pub struct Cosmetics {
+    unlocked: Vec<Skin>,
+}
+
+impl Default for Cosmetics {
+    fn default() -> Self {
+        let unlocked = storage::get_item(UNLOCKED_KEY)
+            .and_then(|unlocked| serde_json::from_str(&unlocked).ok())
+            .unwrap_or_default();
+        Self { unlocked }
+    }
+}
+
+impl Cosmetics {
+    pub fn is_unlocked(&self, skin: Skin) -> bool {
+        skin.price().is_none() || self.unlocked.contains(&skin)
+    }
+
+    // the skin the player shows, a locked one they picked shows as classic
+    pub fn skin(&self, settings: &Settings) -> Skin {
+        if self.is_unlocked(settings.skin) {
+            settings.skin
+        } else {
+            Skin::Classic
+        }
+    }
+}
+
+// the zap and the receipt fetch in flight, kept apart from `Cosmetics` so polling them
+// doesn't mark the skins changed
+#[derive(Resource, Default)]
+struct ZapTasks {
+    receipts: Option<Receiver<Vec<Skin>>>,
+    invoice: Option<Receiver<Result<String, String>>>,
+    // the skin zapped for and the receipt fetches left until it's given up on
+    pending: Option<(Skin, Timer, u32)>,
+}
+
+// what the page's wallet gets
+#[derive(Serialize)]
+struct ZapInvoice<'a> {
+    skin: &'a str,
+    invoice: &'a str,
+}
+
+// the lnurl-pay endpoint of a lightning address, see lud-06, lud-16 and nip-57
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayEndpoint {
+    callback: String,
+    min_sendable: u64,
+    max_sendable: u64,
+    #[serde(default)]
+    allows_nostr: bool,
+    nostr_pubkey: Option<XOnlyPublicKey>,
+}
+
+#[derive(Deserialize)]
+struct PayInvoice {
+    pr: String,
+}
+
+// premium skins are unlocked by zapping the maintainer's lightning address. the zap request
+// names the skin, and the zap receipt the address's lnurl server publishes is the proof:
+// the receipts are fetched whenever the menu or the settings open and again every few
+// seconds after a zap
+pub struct CosmeticsPlugin;
+
+impl Plugin for CosmeticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtocolConfig>()
+            .init_resource::<Cosmetics>()
+            .init_resource::<ZapTasks>()
+            .add_systems(OnEnter(AppState::Menu), fetch_receipts)
+            .add_systems(OnEnter(AppState::Settings), fetch_receipts)
+            .add_systems(
+                Update,
+                (
+                    request_unlock,
+                    receive_invoice,
+                    recheck_receipts,
+                    receive_receipts,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn fetch_receipts(
+    config: Res<ProtocolConfig>,
+    game_state: Res<GameState>,
+    mut tasks: ResMut<ZapTasks>,
+) {
+    fetch(&config, game_state.nostr_keys.public_key(), &mut tasks);
+}
+
+fn fetch(config: &ProtocolConfig, own: XOnlyPublicKey, tasks: &mut ZapTasks) {
+    let (Some(maintainer), Some(address)) = (config.maintainer, config.lightning_address.clone())
+    else {
+        return;
+    };
+    if tasks.receipts.is_some() {
+        return;
+    }
+
+    let (mut receipts_tx, receipts_rx) = futures::channel::mpsc::channel::<Vec<Skin>>(1);
+    tasks.receipts = Some(receipts_rx);
+
+    platform::spawn(async move {
+        let skins = zapped_skins(&address, maintainer, own).await;
+
+        if let Err(e) = receipts_tx.try_send(skins) {
+            error!("Error sending zap receipts: {}", e);
+        }
+    });
+}
+
+// the receipts are only trusted from the zapper key the address itself names
+async fn zapped_skins(address: &str, maintainer: XOnlyPublicKey, own: XOnlyPublicKey) -> Vec<Skin> {
+    let Some(zapper) = pay_endpoint(address)
+        .await
+        .and_then(|endpoint| endpoint.nostr_pubkey)
+    else {
+        return Vec::new();
+    };
+
+    let filter = Filter::new().kind(Kind::ZapReceipt).pubkey(maintainer);
+    let receipts = fetch_events(vec![filter], Vec::new()).await;
+    receipts
+        .iter()
+        .filter_map(|receipt| zapped_skin(receipt, zapper, own))
+        .collect()
+}
+
+// the skin a zap receipt unlocks: signed by the zapper, for a zap request the player signed
+// that names the skin and brings at least its price
+fn zapped_skin(receipt: &NostrEvent, zapper: XOnlyPublicKey, own: XOnlyPublicKey) -> Option<Skin> {
+    if receipt.pubkey != zapper || receipt.verify().is_err() {
+        return None;
+    }
+
+    let request = NostrEvent::from_json(tag_value(receipt, "description")?).ok()?;
+    if request.kind != Kind::ZapRequest || request.pubkey != own || request.verify().is_err() {
+        return None;
+    }
+
+    let skin = Skin::from_id(&tag_value(&request, SKIN_TAG)?)?;
+    let millisats: u64 = tag_value(&request, "amount")?.parse().ok()?;
+    (millisats >= skin.price()? * 1000).then_some(skin)
+}
+
+fn tag_value(event: &NostrEvent, name: &str) -> Option<String> {
+    event.tags.iter().find_map(|tag| {
+        let fields = tag.as_vec();
+        if fields.first()? == name {
+            fields.get(1).cloned()
+        } else {
+            None
+        }
+    })
+}
+
+async fn pay_endpoint(address: &str) -> Option<PayEndpoint> {
+    let (name, domain) = address.split_once('@')?;
+    let url = format!("https://{}/.well-known/lnurlp/{}", domain, name);
+    let (_, body) = platform::fetch(&url).await?;
+    serde_json::from_slice(&body)
+        .map_err(|e| error!("Error reading {}: {}", url, e))
+        .ok()
+}
+
+// the invoice of a zap for the skin's price, with the zap request the player signed
+async fn zap_invoice(
+    address: String,
+    maintainer: XOnlyPublicKey,
+    keys: Keys,
+    skin: Skin,
+    locale: Locale,
+) -> Result<String, String> {
+    let price = skin
+        .price()
+        .ok_or_else(|| locale.get(Phrase::SkinIsFree).to_string())?;
+    let endpoint = pay_endpoint(&address)
+        .await
+        .ok_or_else(|| locale.fill(Phrase::ZapUnreachable, &[&address]))?;
+    if !endpoint.allows_nostr || endpoint.nostr_pubkey.is_none() {
+        return Err(locale.fill(Phrase::NoZaps, &[&address]));
+    }
+    let millisats = price * 1000;
+    if millisats < endpoint.min_sendable || millisats > endpoint.max_sendable {
+        return Err(locale.fill(Phrase::ZapAmount, &[&address, &price.to_string()]));
+    }
+
+    let tag =
+        |name: &str, values: Vec<String>| Tag::Generic(TagKind::Custom(name.to_string()), values);
+    let request = EventBuilder::new(
+        Kind::ZapRequest,
+        format!("{} skin for unite4", skin.name()),
+        [
+            Tag::PubKey(maintainer, None),
+            tag("relays", relay_pool(stored_relays(), false)),
+            tag("amount", vec![millisats.to_string()]),
+            tag(SKIN_TAG, vec![skin.id().to_string()]),
+        ],
+    )
+    .to_event(&keys)
+    .map_err(|e| locale.fill(Phrase::ZapSigning, &[&e.to_string()]))?;
+
+    let separator = if endpoint.callback.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let url = format!(
+        "{}{}amount={}&nostr={}",
+        endpoint.callback,
+        separator,
+        millisats,
+        url_encode(&request.as_json())
+    );
+    let (_, body) = platform::fetch(&url)
+        .await
+        .ok_or_else(|| locale.fill(Phrase::ZapNoAnswer, &[&address]))?;
+    serde_json::from_slice::<PayInvoice>(&body)
+        .map(|invoice| invoice.pr)
+        .map_err(|_| locale.fill(Phrase::ZapRefused, &[&address]))
+}
+
+// the zap request goes into the callback's query
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn request_unlock(
+    config: Res<ProtocolConfig>,
+    cosmetics: Res<Cosmetics>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut tasks: ResMut<ZapTasks>,
+) {
+    let Some(skin) = UNLOCK_REQUESTED
+        .lock()
+        .ok()
+        .and_then(|mut skin| skin.take())
+    else {
+        return;
+    };
+    if cosmetics.is_unlocked(skin) || tasks.invoice.is_some() {
+        return;
+    }
+    let (Some(maintainer), Some(address)) = (config.maintainer, config.lightning_address.clone())
+    else {
+        platform::alert(locale.get(Phrase::NoZapAddress));
+        return;
+    };
+
+    let (mut invoice_tx, invoice_rx) = futures::channel::mpsc::channel::<Result<String, String>>(1);
+    tasks.invoice = Some(invoice_rx);
+    tasks.pending = Some((
+        skin,
+        Timer::from_seconds(RECHECK_SECONDS, TimerMode::Repeating),
+        0,
+    ));
+
+    let keys = game_state.nostr_keys.clone();
+    let locale = *locale;
+    platform::spawn(async move {
+        let invoice = zap_invoice(address, maintainer, keys, skin, locale).await;
+
+        if let Err(e) = invoice_tx.try_send(invoice) {
+            error!("Error sending zap invoice: {}", e);
+        }
+    });
+}
+
+// the page pays the invoice with the browser's wallet or opens it in a lightning app
+fn receive_invoice(mut tasks: ResMut<ZapTasks>) {
+    let Some(ref mut invoice_rx) = tasks.invoice else {
+        return;
+    };
+    let Ok(Some(invoice)) = invoice_rx.try_next() else {
+        return;
+    };
+    tasks.invoice = None;
+
+    match invoice {
+        Ok(invoice) => {
+            let Some((skin, _, ref mut checks)) = tasks.pending else {
+                return;
+            };
+            *checks = RECHECKS;
+            let detail = serde_json::to_string(&ZapInvoice {
+                skin: skin.id(),
+                invoice: &invoice,
+            })
+            .unwrap();
+            platform::dispatch_event("zapInvoice", Some(&detail));
+        }
+        Err(e) => {
+            tasks.pending = None;
+            platform::alert(&e);
+        }
+    }
+}
+
+fn recheck_receipts(
+    time: Res<Time>,
+    config: Res<ProtocolConfig>,
+    game_state: Res<GameState>,
+    mut tasks: ResMut<ZapTasks>,
+) {
+    let Some((_, ref mut timer, ref mut checks)) = tasks.pending else {
+        return;
+    };
+    if *checks == 0 || !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    *checks -= 1;
+
+    fetch(&config, game_state.nostr_keys.public_key(), &mut tasks);
+}
+
+fn receive_receipts(
+    mut tasks: ResMut<ZapTasks>,
+    mut cosmetics: ResMut<Cosmetics>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(ref mut receipts_rx) = tasks.receipts else {
+        return;
+    };
+    let Ok(Some(skins)) = receipts_rx.try_next() else {
+        return;
+    };
+    tasks.receipts = None;
+
+    if let Some((skin, _, checks)) = tasks.pending {
+        if skins.contains(&skin) {
+            info!("the {} skin is unlocked", skin.name());
+            tasks.pending = None;
+            settings.skin = skin;
+            settings.save();
+        } else if checks == 0 && tasks.invoice.is_none() {
+            info!("no zap receipt for the {} skin", skin.name());
+            tasks.pending = None;
+        }
+    }
+
+    if skins.iter().all(|skin| cosmetics.is_unlocked(*skin)) {
+        return;
+    }
+    for skin in skins {
+        if !cosmetics.is_unlocked(skin) {
+            cosmetics.unlocked.push(skin);
+        }
+    }
+    storage::set_item(
+        UNLOCKED_KEY,
+        &serde_json::to_string(&cosmetics.unlocked).unwrap(),
+    );
+}
+
+// zaps the maintainer the price of the skin, it's picked once the receipt comes
+#[wasm_bindgen]
+pub fn unlock_skin(skin: &str) {
+    let Some(skin) = Skin::from_id(skin) else {
+        return;
+    };
+    if let Ok(mut requested) = UNLOCK_REQUESTED.lock() {
+        *requested = Some(skin);
+    }
+}
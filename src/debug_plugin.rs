@@ -0,0 +1,131 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use nostr_sdk::{secp256k1::XOnlyPublicKey, serde_json};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    latency,
+    nostr_plugin::{EVENT_DELIVERY, RELAY_NOTICES, RELAY_STATUS},
+    platform, profiling_plugin,
+    resources::{Board, GameState, PlayerMove},
+    AppState,
+};
+
+static DEBUG_SNAPSHOT: Mutex<Option<serde_json::Value>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct BoardSnapshot {
+    moves: Vec<PlayerMove>,
+    player_turn: usize,
+    winner: Option<usize>,
+    in_progress: bool,
+    draw: bool,
+    resigned: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GameStateSnapshot {
+    app_state: String,
+    start: bool,
+    pubkey: XOnlyPublicKey,
+    game_tag: Vec<String>,
+    player_type: usize,
+    local_ln_address: Option<String>,
+    p2_ln_address: Option<String>,
+    connected: bool,
+    transport: Option<&'static str>,
+}
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_debug_snapshot);
+
+        #[cfg(feature = "alloc_audit")]
+        app.add_plugins(crate::alloc_audit::AllocAuditPlugin);
+    }
+}
+
+fn update_debug_snapshot(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    app_state: Res<State<AppState>>,
+) {
+    if !board.is_changed() && !game_state.is_changed() && !app_state.is_changed() {
+        return;
+    }
+
+    let board_snapshot = BoardSnapshot {
+        moves: board.moves.clone(),
+        player_turn: board.player_turn,
+        winner: board.winner,
+        in_progress: board.in_progress,
+        draw: board.draw,
+        resigned: board.resigned,
+    };
+
+    let game_state_snapshot = GameStateSnapshot {
+        app_state: format!("{:?}", app_state.get()),
+        start: game_state.start,
+        pubkey: game_state.nostr_keys.public_key(),
+        game_tag: game_state.game_tag.as_vec(),
+        player_type: game_state.player_type,
+        local_ln_address: game_state.local_ln_address.clone(),
+        p2_ln_address: game_state.p2_ln_address.clone(),
+        connected: game_state.transport.is_some(),
+        transport: game_state
+            .transport
+            .as_ref()
+            .map(|transport| transport.name()),
+    };
+
+    let snapshot = serde_json::json!({
+        "board": board_snapshot,
+        "game_state": game_state_snapshot,
+    });
+
+    match DEBUG_SNAPSHOT.lock() {
+        Ok(mut debug_snapshot) => *debug_snapshot = Some(snapshot),
+        Err(e) => error!("Error updating debug snapshot: {}", e),
+    }
+}
+
+#[wasm_bindgen(js_name = debugState)]
+pub fn debug_state() -> String {
+    let mut snapshot = match DEBUG_SNAPSHOT.lock() {
+        Ok(debug_snapshot) => debug_snapshot
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({})),
+        Err(_) => serde_json::json!({}),
+    };
+
+    if let Ok(relay_status) = RELAY_STATUS.lock() {
+        snapshot["relays"] = serde_json::json!(*relay_status);
+    }
+
+    if let Ok(delivery) = EVENT_DELIVERY.lock() {
+        snapshot["delivery"] = serde_json::json!(*delivery);
+    }
+
+    if let Ok(notices) = RELAY_NOTICES.lock() {
+        snapshot["notices"] = serde_json::json!(*notices);
+    }
+
+    snapshot["latency"] = latency::traces();
+    snapshot["safe_mode"] = serde_json::json!(platform::safe_mode());
+
+    if let Some(profile) = profiling_plugin::profile() {
+        snapshot["profile"] = profile;
+    }
+
+    #[cfg(feature = "alloc_audit")]
+    {
+        snapshot["allocations_per_frame"] =
+            serde_json::json!(crate::alloc_audit::average_allocations());
+    }
+
+    snapshot.to_string()
+}
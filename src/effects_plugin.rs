@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{Confetti, LossVeil, ScreenFlash},
+    events::{GameEnded, GameSwitched},
+    performance_plugin::ReducedMode,
+    platform,
+    resources::{Board, GameState},
+    AppState,
+};
+
+const CONFETTI_PIECES: usize = 120;
+const CONFETTI_COLORS: [Color; 5] = [
+    Color::rgb(0.85, 0.1, 0.1),
+    Color::rgb(1.0, 0.85, 0.0),
+    Color::rgb(0.1, 0.45, 0.9),
+    Color::rgb(0.2, 0.75, 0.3),
+    Color::rgb(0.9, 0.4, 0.8),
+];
+const CONFETTI_GRAVITY: f32 = 300.0;
+const CONFETTI_SECONDS: f32 = 3.0;
+// the flash fades from this alpha in FLASH_SECONDS
+const FLASH_ALPHA: f32 = 0.6;
+const FLASH_SECONDS: f32 = 0.4;
+// the grey veil over a lost board, reached in VEIL_SECONDS
+const VEIL_ALPHA: f32 = 0.3;
+const VEIL_SECONDS: f32 = 1.0;
+
+// a win of the local player bursts confetti over a white flash, a loss greys the board
+// out a little until the next game
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                start_effects,
+                fall_confetti,
+                fade_flash,
+                fade_in_veil,
+                lift_veil,
+            )
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Update, clear_effects.run_if(on_event::<GameSwitched>()))
+        .add_systems(OnExit(AppState::InGame), clear_effects);
+    }
+}
+
+fn start_effects(
+    mut commands: Commands,
+    mut game_ended: EventReader<GameEnded>,
+    game_state: Res<GameState>,
+    reduced: Res<ReducedMode>,
+    mut shown: Local<Option<String>>,
+) {
+    let Some(ended) = game_ended.read().last() else {
+        return;
+    };
+    let player = game_state.player_type;
+    // the end of a game is sent again by a resignation after the win or a replay
+    if (player != 1 && player != 2) || shown.as_ref() == Some(&game_state.game_id) {
+        return;
+    }
+    *shown = Some(game_state.game_id.clone());
+    if reduced.0 {
+        return;
+    }
+
+    match ended.winner {
+        Some(winner) if winner == player => {
+            spawn_flash(&mut commands);
+            spawn_confetti(&mut commands);
+        }
+        Some(_) => spawn_veil(&mut commands),
+        None => {}
+    }
+}
+
+fn full_screen(color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        background_color: color.into(),
+        z_index: ZIndex::Global(4),
+        ..default()
+    }
+}
+
+fn spawn_flash(commands: &mut Commands) {
+    commands
+        .spawn(full_screen(Color::WHITE.with_a(FLASH_ALPHA)))
+        .insert(ScreenFlash);
+}
+
+fn spawn_veil(commands: &mut Commands) {
+    commands
+        .spawn(full_screen(Color::GRAY.with_a(0.0)))
+        .insert(LossVeil);
+}
+
+// the pieces start above the board and are thrown up and sideways
+fn spawn_confetti(commands: &mut Commands) {
+    let random = |min: f32, max: f32| min + platform::random() as f32 * (max - min);
+
+    for piece in 0..CONFETTI_PIECES {
+        commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(random(5.0, 9.0), random(3.0, 5.0))),
+                    color: CONFETTI_COLORS[piece % CONFETTI_COLORS.len()],
+                    ..default()
+                },
+                transform: Transform::from_xyz(random(-200.0, 200.0), random(150.0, 230.0), 5.0)
+                    .with_rotation(Quat::from_rotation_z(random(0.0, std::f32::consts::TAU))),
+                ..default()
+            })
+            .insert(Confetti {
+                velocity: Vec2::new(random(-120.0, 120.0), random(50.0, 250.0)),
+                spin: random(-8.0, 8.0),
+                age: 0.0,
+            });
+    }
+}
+
+fn fall_confetti(
+    mut commands: Commands,
+    mut confetti: Query<(Entity, &mut Confetti, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut piece, mut transform, mut sprite) in confetti.iter_mut() {
+        piece.age += delta;
+        if piece.age >= CONFETTI_SECONDS {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        piece.velocity.y -= CONFETTI_GRAVITY * delta;
+        transform.translation += (piece.velocity * delta).extend(0.0);
+        transform.rotate_z(piece.spin * delta);
+        // the last second fades them out
+        sprite.color.set_a((CONFETTI_SECONDS - piece.age).min(1.0));
+    }
+}
+
+fn fade_flash(
+    mut commands: Commands,
+    mut flash: Query<(Entity, &mut BackgroundColor), With<ScreenFlash>>,
+    time: Res<Time>,
+) {
+    for (entity, mut color) in flash.iter_mut() {
+        let alpha = color.0.a() - FLASH_ALPHA / FLASH_SECONDS * time.delta_seconds();
+        if alpha <= 0.0 {
+            commands.entity(entity).despawn();
+        } else {
+            color.0.set_a(alpha);
+        }
+    }
+}
+
+fn fade_in_veil(mut veil: Query<&mut BackgroundColor, With<LossVeil>>, time: Res<Time>) {
+    for mut color in veil.iter_mut() {
+        let alpha = color.0.a();
+        if alpha < VEIL_ALPHA {
+            color
+                .0
+                .set_a((alpha + VEIL_ALPHA / VEIL_SECONDS * time.delta_seconds()).min(VEIL_ALPHA));
+        }
+    }
+}
+
+// the next game of a series starts on a clear board
+fn lift_veil(mut commands: Commands, board: Res<Board>, veil: Query<Entity, With<LossVeil>>) {
+    if !board.is_changed() || board.winner.is_some() || board.draw {
+        return;
+    }
+
+    for entity in veil.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn clear_effects(
+    mut commands: Commands,
+    effects: Query<Entity, Or<(With<Confetti>, With<ScreenFlash>, With<LossVeil>)>>,
+) {
+    for entity in effects.iter() {
+        commands.entity(entity).despawn();
+    }
+}
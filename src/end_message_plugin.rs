@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{EndMessageButton, EndMessageNotice, EndMessageUi},
+    locale_plugin::{Locale, Phrase},
+    messages::MessagePack,
+    resources::{Board, GameState},
+    settings_plugin::Settings,
+    theme_plugin::Theme,
+    AppState,
+};
+
+// the messages of each pack, sent by their index so a message is shown in the loser's
+// language
+pub fn presets(pack: MessagePack) -> [Phrase; 3] {
+    match pack {
+        MessagePack::Friendly => [Phrase::GoodGame, Phrase::WellPlayed, Phrase::ThatWasFun],
+        MessagePack::Competitive => [Phrase::BetterLuck, Phrase::AsPlanned, Phrase::TryAgain],
+        MessagePack::Silly => [Phrase::LolLoser, Phrase::GotFourd, Phrase::CoinsHaveSpoken],
+    }
+}
+
+// after a win against another player the winner can send one message of their pack, the
+// loser sees it under the board. a loser on the friendly pack gets a plain good game
+// instead of a competitive or silly message
+pub struct EndMessagePlugin;
+
+impl Plugin for EndMessagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (offer_end_messages, send_end_message, show_end_message)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(OnExit(AppState::InGame), cleanup_end_messages);
+    }
+}
+
+// offered once per game, a reload of the finished game offers them again
+#[allow(clippy::too_many_arguments)]
+fn offer_end_messages(
+    mut commands: Commands,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    ui: Query<Entity, With<EndMessageUi>>,
+    mut offered: Local<Option<String>>,
+) {
+    if board.winner.is_none() {
+        for entity in ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if offered.as_ref() == Some(&game_state.game_id) {
+        return;
+    }
+    *offered = Some(game_state.game_id.clone());
+
+    let won = matches!(game_state.player_type, 1 | 2)
+        && board.winner == Some(game_state.player_type)
+        && game_state.ghost.is_none()
+        && game_state.p2_pubkey.is_some()
+        && game_state.opponent_supports("end_message");
+    if !won {
+        return;
+    }
+
+    let text_style = |font_size: f32| TextStyle {
+        color: theme.ui.text,
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                flex_wrap: FlexWrap::Wrap,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: theme.ui.panel.into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(EndMessageUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                locale.get(Phrase::SendMessage),
+                text_style(16.0),
+            ));
+
+            for (index, phrase) in presets(settings.message_pack).into_iter().enumerate() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: theme.ui.button.into(),
+                        ..default()
+                    })
+                    .insert(EndMessageButton(index))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            locale.get(phrase),
+                            text_style(16.0),
+                        ));
+                    });
+            }
+        });
+}
+
+// one message per game, the row goes away once it's sent
+fn send_end_message(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    buttons: Query<(&Interaction, &EndMessageButton), Changed<Interaction>>,
+    ui: Query<Entity, With<EndMessageUi>>,
+) {
+    for (interaction, EndMessageButton(index)) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        info!(
+            "sending end message {} of {:?}",
+            index, settings.message_pack
+        );
+        game_state
+            .clone()
+            .send_end_message(settings.message_pack, *index);
+        for entity in ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        break;
+    }
+}
+
+fn show_end_message(
+    mut commands: Commands,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    notices: Query<(Entity, &Children), With<EndMessageNotice>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !game_state.is_changed() && !board.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    let message = game_state
+        .end_message
+        .filter(|_| board.winner.is_some())
+        .and_then(|(pack, index)| {
+            let phrase = presets(pack).get(index).copied()?;
+            if settings.message_pack == MessagePack::Friendly && pack != MessagePack::Friendly {
+                return Some(Phrase::GoodGame);
+            }
+            Some(phrase)
+        });
+
+    let Some(phrase) = message else {
+        for (entity, _) in notices.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+
+    let opponent = game_state
+        .p2_ln_address
+        .clone()
+        .unwrap_or_else(|| locale.get(Phrase::Player2).to_string());
+    let value = locale.fill(Phrase::OpponentSays, &[&opponent, locale.get(phrase)]);
+
+    if let Ok((_, children)) = notices.get_single() {
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            if text.sections[0].value != value {
+                text.sections[0].value = value.clone();
+            }
+        }
+        return;
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: theme.ui.panel.into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(EndMessageNotice)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                value,
+                TextStyle {
+                    color: theme.ui.text,
+                    font_size: 18.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn cleanup_end_messages(
+    mut commands: Commands,
+    ui: Query<Entity, Or<(With<EndMessageUi>, With<EndMessageNotice>)>>,
+) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
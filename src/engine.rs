@@ -0,0 +1,621 @@
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitboard::{Bitboard, COLUMNS, ROWS},
+    game::GameRng,
+    opening_book,
+    resources::PlayerMove,
+};
+
+pub const SEARCH_DEPTH: usize = 6;
+const SHALLOW_DEPTH: usize = 2;
+const DEEP_DEPTH: usize = 10;
+const WIN_SCORE: i32 = 1000;
+// center columns take part in the most lines, searching them first prunes more
+const MOVE_ORDER: [usize; COLUMNS] = [3, 2, 4, 1, 5, 0, 6];
+const COLUMN_WEIGHTS: [i32; COLUMNS] = [1, 2, 3, 4, 3, 2, 1];
+// the positions the table keeps, a position takes the slot of the one before it
+const TABLE_SIZE: usize = 1 << 16;
+// a random key per cell and player, a position's hash is the xor of the keys of its coins
+const ZOBRIST_KEYS: [[u64; COLUMNS * ROWS]; 2] = zobrist_keys();
+// xored in when player 2 is to move
+const ZOBRIST_SIDE: u64 = splitmix(u64::MAX);
+
+// how the engine picks its moves against a person, chosen in the menu before a game
+// against it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    // any column that isn't full
+    Random,
+    // looks two moves ahead, it takes a win and blocks one
+    Shallow,
+    // looks ten moves ahead, a position searched before is looked up
+    #[default]
+    Deep,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Random, Difficulty::Shallow, Difficulty::Deep];
+}
+
+// what a searched position is known to score, the exact score or a bound of it when the
+// search was cut off
+#[derive(Clone, Copy)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    hash: u64,
+    depth: usize,
+    score: i32,
+    bound: Bound,
+}
+
+// the positions searched so far by their hash, a slot keeps the latest one
+struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    fn new() -> Self {
+        Self {
+            entries: vec![None; TABLE_SIZE],
+        }
+    }
+
+    fn slot(hash: u64) -> usize {
+        (hash % TABLE_SIZE as u64) as usize
+    }
+
+    // only a score searched to the same depth is taken, so a search gets the scores it
+    // would have got with an empty table whatever was searched before
+    fn get(&self, hash: u64, depth: usize) -> Option<Entry> {
+        self.entries[Self::slot(hash)].filter(|entry| entry.hash == hash && entry.depth == depth)
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.entries[Self::slot(entry.hash)] = Some(entry);
+    }
+}
+
+thread_local! {
+    // kept from one search to the next, so the engine's moves, the hint, the analysis
+    // board, the review and the evaluation bar look up what any of them searched before
+    static TABLE: RefCell<TranspositionTable> = RefCell::new(TranspositionTable::new());
+}
+
+// empties the table of this thread, so the next search starts cold. only the engine
+// benchmark does this, the app keeps the table
+#[allow(dead_code)]
+pub fn clear_table() {
+    TABLE.with(|table| *table.borrow_mut() = TranspositionTable::new());
+}
+
+// a splitmix64 step, in a const fn so the zobrist keys are built at compile time
+const fn splitmix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn zobrist_keys() -> [[u64; COLUMNS * ROWS]; 2] {
+    let mut keys = [[0; COLUMNS * ROWS]; 2];
+    let mut index = 0;
+    while index < COLUMNS * ROWS * 2 {
+        keys[index % 2][index / 2] = splitmix(index as u64);
+        index += 1;
+    }
+    keys
+}
+
+fn zobrist_key(column: usize, row: usize, player: usize) -> u64 {
+    ZOBRIST_KEYS[usize::from(player != 1)][column * ROWS + row]
+}
+
+struct Position {
+    bitboard: Bitboard,
+    // updated with every coin played or taken back, see `ZOBRIST_KEYS`
+    hash: u64,
+}
+
+impl Position {
+    fn from_moves(moves: &[PlayerMove]) -> Self {
+        let mut position = Self {
+            bitboard: Bitboard::default(),
+            hash: 0,
+        };
+        for player_move in moves {
+            position.play(player_move.column, player_move.player);
+        }
+        position
+    }
+
+    // the same position with the other player to move is another entry
+    fn hash(&self, player: usize) -> u64 {
+        if player == 1 {
+            self.hash
+        } else {
+            self.hash ^ ZOBRIST_SIDE
+        }
+    }
+
+    fn playable(&self, column: usize) -> bool {
+        self.bitboard.playable(column)
+    }
+
+    // a full column is left as it is
+    fn play(&mut self, column: usize, player: usize) {
+        if let Some(row) = self.bitboard.play(column, player) {
+            self.hash ^= zobrist_key(column, row, player);
+        }
+    }
+
+    fn undo(&mut self, column: usize, player: usize) {
+        if let Some(row) = self.bitboard.undo(column) {
+            self.hash ^= zobrist_key(column, row, player);
+        }
+    }
+
+    fn is_winner(&self, player: usize) -> bool {
+        self.bitboard.wins(player)
+    }
+
+    fn evaluate(&self, player: usize) -> i32 {
+        (0..COLUMNS)
+            .map(|column| {
+                let own = self.bitboard.column_coins(column, player) as i32;
+                let other = self.bitboard.column_coins(column, opponent(player)) as i32;
+                (own - other) * COLUMN_WEIGHTS[column]
+            })
+            .sum()
+    }
+}
+
+// depth limited negamax with alpha-beta pruning, None when the board is full. of equal
+// scores the column searched first is played
+pub fn best_move(moves: &[PlayerMove], player: usize) -> Option<usize> {
+    highest(&scored_moves(moves, player))
+}
+
+// the engine's move at the difficulty, the seed and the number of moves pick the random
+// ones so a replayed game gets the same
+pub fn choose_move(
+    moves: &[PlayerMove],
+    player: usize,
+    difficulty: Difficulty,
+    seed: u64,
+) -> Option<usize> {
+    match difficulty {
+        Difficulty::Random => {
+            let position = Position::from_moves(moves);
+            let playable: Vec<usize> = (0..COLUMNS)
+                .filter(|&column| position.playable(column))
+                .collect();
+            if playable.is_empty() {
+                return None;
+            }
+            let mut rng = GameRng::at(seed, moves.len() as u64);
+            Some(playable[rng.below(playable.len())])
+        }
+        Difficulty::Shallow => highest(&search(moves, player, SHALLOW_DEPTH)),
+        Difficulty::Deep => {
+            let mut search = ColumnSearch::deep(moves, player);
+            while search.step() {}
+            search.best_column()
+        }
+    }
+}
+
+// a score the search only gives a forced win within its depth
+pub fn is_win(score: i32) -> bool {
+    score >= WIN_SCORE
+}
+
+pub fn is_loss(score: i32) -> bool {
+    score <= -WIN_SCORE
+}
+
+// the first of the columns with the highest score
+fn highest(scored: &[(usize, i32)]) -> Option<usize> {
+    let mut best_column = None;
+    let mut best_score = i32::MIN;
+
+    for &(column, score) in scored {
+        if score > best_score {
+            best_column = Some(column);
+            best_score = score;
+        }
+    }
+
+    best_column
+}
+
+// every playable column with its score for the player, in search order. the self-play
+// tool samples from them
+pub fn scored_moves(moves: &[PlayerMove], player: usize) -> Vec<(usize, i32)> {
+    search(moves, player, SEARCH_DEPTH)
+}
+
+fn search(moves: &[PlayerMove], player: usize, depth: usize) -> Vec<(usize, i32)> {
+    TABLE.with(|table| search_with(moves, player, depth, &mut table.borrow_mut()))
+}
+
+fn search_with(
+    moves: &[PlayerMove],
+    player: usize,
+    depth: usize,
+    table: &mut TranspositionTable,
+) -> Vec<(usize, i32)> {
+    let mut position = Position::from_moves(moves);
+    let mut scored = Vec::with_capacity(COLUMNS);
+
+    for column in MOVE_ORDER {
+        if position.playable(column) {
+            scored.push((
+                column,
+                score_column(&mut position, player, column, depth, table),
+            ));
+        }
+    }
+
+    scored
+}
+
+// the score of one playable column searched to the depth, the position is left as it was
+fn score_column(
+    position: &mut Position,
+    player: usize,
+    column: usize,
+    depth: usize,
+    table: &mut TranspositionTable,
+) -> i32 {
+    position.play(column, player);
+    let score = if position.is_winner(player) {
+        WIN_SCORE + depth as i32
+    } else {
+        -negamax(
+            position,
+            opponent(player),
+            depth - 1,
+            -WIN_SCORE * 2,
+            WIN_SCORE * 2,
+            table,
+        )
+    };
+    position.undo(column, player);
+    score
+}
+
+// the search of `scored_moves` one column per step, so the app can spread it over frames
+pub struct ColumnSearch {
+    position: Position,
+    player: usize,
+    depth: usize,
+    // the move of the opening book, which leaves nothing to search
+    book: Option<usize>,
+    // the columns of `MOVE_ORDER` looked at so far
+    searched: usize,
+    pub scored: Vec<(usize, i32)>,
+}
+
+impl ColumnSearch {
+    pub fn new(moves: &[PlayerMove], player: usize) -> Self {
+        Self {
+            position: Position::from_moves(moves),
+            player,
+            depth: SEARCH_DEPTH,
+            book: None,
+            searched: 0,
+            scored: Vec::with_capacity(COLUMNS),
+        }
+    }
+
+    // the search of `Difficulty::Deep`. the opening is played from the book, where the
+    // search can't see the end
+    pub fn deep(moves: &[PlayerMove], player: usize) -> Self {
+        let columns: Vec<usize> = moves.iter().map(|m| m.column).collect();
+        Self {
+            depth: DEEP_DEPTH,
+            book: opening_book::best_move(&columns),
+            ..Self::new(moves, player)
+        }
+    }
+
+    // scores the next playable column, false once every column is scored
+    pub fn step(&mut self) -> bool {
+        if self.book.is_some() {
+            return false;
+        }
+        while let Some(&column) = MOVE_ORDER.get(self.searched) {
+            self.searched += 1;
+            if !self.position.playable(column) {
+                continue;
+            }
+            let score = TABLE.with(|table| {
+                score_column(
+                    &mut self.position,
+                    self.player,
+                    column,
+                    self.depth,
+                    &mut table.borrow_mut(),
+                )
+            });
+            self.scored.push((column, score));
+            return true;
+        }
+        false
+    }
+
+    // the score of the side to move's best column, None when the board is full
+    pub fn best_score(&self) -> Option<i32> {
+        self.scored.iter().map(|&(_, score)| score).max()
+    }
+
+    // the column to play once the search is done, as `best_move` picks it
+    pub fn best_column(&self) -> Option<usize> {
+        self.book.or_else(|| highest(&self.scored))
+    }
+}
+
+// the columns that win by force within `max_moves` of the player's own moves whatever the
+// opponent answers, with the own moves the quickest win takes. puzzles are mined with it
+pub fn forced_wins(moves: &[PlayerMove], player: usize, max_moves: usize) -> Vec<(usize, usize)> {
+    let mut position = Position::from_moves(moves);
+    let mut wins = Vec::new();
+
+    for column in MOVE_ORDER {
+        if !position.playable(column) {
+            continue;
+        }
+
+        position.play(column, player);
+        let won = position.is_winner(player);
+        let quickest = (1..=max_moves)
+            .find(|&own_moves| won || no_escape(&mut position, player, own_moves - 1));
+        position.undo(column, player);
+
+        if let Some(own_moves) = quickest {
+            wins.push((column, own_moves));
+        }
+    }
+
+    wins
+}
+
+// the player to move wins within `own_moves` of their moves
+fn wins_within(position: &mut Position, player: usize, own_moves: usize) -> bool {
+    if own_moves == 0 {
+        return false;
+    }
+
+    MOVE_ORDER.iter().any(|&column| {
+        if !position.playable(column) {
+            return false;
+        }
+        position.play(column, player);
+        let won = position.is_winner(player) || no_escape(position, player, own_moves - 1);
+        position.undo(column, player);
+        won
+    })
+}
+
+// the opponent is to move and every answer still loses within `own_moves` of the
+// player's moves. a full board is a draw
+fn no_escape(position: &mut Position, player: usize, own_moves: usize) -> bool {
+    let opponent = opponent(player);
+    let mut answers = 0;
+
+    for column in MOVE_ORDER {
+        if !position.playable(column) {
+            continue;
+        }
+        answers += 1;
+
+        position.play(column, opponent);
+        let escaped = position.is_winner(opponent) || !wins_within(position, player, own_moves);
+        position.undo(column, opponent);
+
+        if escaped {
+            return false;
+        }
+    }
+
+    answers > 0
+}
+
+// the positions searched before are looked up, inside the window their score is taken as
+// it is
+fn negamax(
+    position: &mut Position,
+    player: usize,
+    depth: usize,
+    mut alpha: i32,
+    beta: i32,
+    table: &mut TranspositionTable,
+) -> i32 {
+    if depth == 0 {
+        return position.evaluate(player);
+    }
+
+    let hash = position.hash(player);
+    if let Some(entry) = table.get(hash, depth) {
+        match entry.bound {
+            Bound::Exact => return entry.score,
+            Bound::Lower if entry.score >= beta => return entry.score,
+            Bound::Upper if entry.score <= alpha => return entry.score,
+            _ => {}
+        }
+    }
+
+    let window_start = alpha;
+    let mut playable = false;
+
+    for column in MOVE_ORDER {
+        if !position.playable(column) {
+            continue;
+        }
+        playable = true;
+
+        position.play(column, player);
+        // quicker wins score higher
+        let score = if position.is_winner(player) {
+            WIN_SCORE + depth as i32
+        } else {
+            -negamax(position, opponent(player), depth - 1, -beta, -alpha, table)
+        };
+        position.undo(column, player);
+
+        if score >= beta {
+            table.insert(Entry {
+                hash,
+                depth,
+                score,
+                bound: Bound::Lower,
+            });
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+
+    let score = if playable { alpha } else { 0 };
+    let bound = if playable && score <= window_start {
+        Bound::Upper
+    } else {
+        Bound::Exact
+    };
+    table.insert(Entry {
+        hash,
+        depth,
+        score,
+        bound,
+    });
+    score
+}
+
+fn opponent(player: usize) -> usize {
+    if player == 1 {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, Outcome};
+
+    fn play(columns: &[usize]) -> Game {
+        let mut game = Game::new();
+        for &column in columns {
+            game.apply_move(column).unwrap();
+        }
+        game
+    }
+
+    // a game of random moves that is still going after `plies` moves
+    fn random_position(seed: u64, plies: usize) -> Option<Game> {
+        let mut game = Game::new();
+        let mut rng = GameRng::at(seed, 0);
+        for _ in 0..plies {
+            let open: Vec<usize> = (0..COLUMNS)
+                .filter(|&column| game.landing_row(column).is_some())
+                .collect();
+            let column = open[rng.below(open.len())];
+            if game.apply_move(column).ok()? != Outcome::Ongoing {
+                return None;
+            }
+        }
+        Some(game)
+    }
+
+    #[test]
+    fn a_warm_table_gives_the_scores_of_a_fresh_one() {
+        let mut warm = TranspositionTable::new();
+        let mut positions = 0;
+        for seed in 0..40 {
+            let Some(game) = random_position(seed, 4 + seed as usize % 16) else {
+                continue;
+            };
+            positions += 1;
+            let player = game.player_turn();
+            // the warm table holds what the other depths and positions searched
+            for depth in [SHALLOW_DEPTH, 4, SEARCH_DEPTH] {
+                let fresh =
+                    search_with(game.moves(), player, depth, &mut TranspositionTable::new());
+                let cached = search_with(game.moves(), player, depth, &mut warm);
+                assert_eq!(fresh, cached, "seed {} depth {}", seed, depth);
+                assert_eq!(search_with(game.moves(), player, depth, &mut warm), fresh);
+            }
+        }
+        assert!(positions >= 20);
+    }
+
+    #[test]
+    fn every_depth_takes_a_win() {
+        // red to move after yellow's third coin in column 6 and red's in 0 to 2
+        let game = play(&[0, 6, 1, 6, 2, 6]);
+        for depth in [SHALLOW_DEPTH, SEARCH_DEPTH, DEEP_DEPTH] {
+            let scored = search_with(game.moves(), 1, depth, &mut TranspositionTable::new());
+            assert_eq!(highest(&scored), Some(3), "depth {}", depth);
+            assert!(is_win(
+                scored.iter().map(|&(_, score)| score).max().unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    fn every_depth_blocks_a_win() {
+        // yellow to move, red has three on the bottom row in columns 0 to 2
+        let game = play(&[0, 6, 1, 6, 2]);
+        for depth in [SHALLOW_DEPTH, SEARCH_DEPTH, DEEP_DEPTH] {
+            let scored = search_with(game.moves(), 2, depth, &mut TranspositionTable::new());
+            assert_eq!(highest(&scored), Some(3), "depth {}", depth);
+        }
+        for difficulty in [Difficulty::Shallow, Difficulty::Deep] {
+            assert_eq!(choose_move(game.moves(), 2, difficulty, 0), Some(3));
+        }
+    }
+
+    #[test]
+    fn the_opening_is_played_from_the_book() {
+        assert_eq!(choose_move(&[], 1, Difficulty::Deep, 0), Some(3));
+        for column in 0..COLUMNS {
+            let game = play(&[column]);
+            let mut search = ColumnSearch::deep(game.moves(), 2);
+            // nothing to search
+            assert!(!search.step());
+            assert_eq!(search.best_column(), opening_book::best_move(&[column]));
+            assert_eq!(
+                choose_move(game.moves(), 2, Difficulty::Deep, 0),
+                search.best_column()
+            );
+        }
+
+        // past the book the deep search goes column by column
+        let game = play(&[3, 3]);
+        let mut search = ColumnSearch::deep(game.moves(), 1);
+        let mut steps = 0;
+        while search.step() {
+            steps += 1;
+        }
+        assert_eq!(steps, COLUMNS);
+        assert_eq!(
+            search.best_column(),
+            highest(&search_with(
+                game.moves(),
+                1,
+                DEEP_DEPTH,
+                &mut TranspositionTable::new()
+            ))
+        );
+    }
+}
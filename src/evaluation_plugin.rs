@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+use crate::{
+    analysis_plugin::analysis_allowed,
+    components::{EvaluationBar, EvaluationText, EvaluationUi},
+    game::Game,
+    locale_plugin::{Locale, Phrase},
+    opening_book,
+    resources::{Board, GameState},
+    review::PositionEvaluator,
+    settings_plugin::Settings,
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+const BAR_WIDTH: f32 = 240.0;
+// a share of the bar this far from the middle favors a side
+const FAVORED: f32 = 0.05;
+
+// the moves of the position last sent to the engine, its search while it goes on and
+// red's share of the bar
+#[derive(Resource, Default)]
+struct LiveEvaluation {
+    requested: Option<usize>,
+    evaluator: Option<PositionEvaluator>,
+    share: Option<f32>,
+}
+
+// with the evaluation bar on in the settings, a bar above the board shows which side is
+// favored. the opening book gives the first plies, the engine evaluates the rest after
+// each move a column per frame, so a frame never waits for the whole search
+pub struct EvaluationPlugin;
+
+impl Plugin for EvaluationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LiveEvaluation>()
+            .add_systems(
+                Update,
+                (request_evaluation, step_evaluation, update_evaluation_bar)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), hide_evaluation_bar);
+    }
+}
+
+// the engine only knows the classic rules, and like the analysis board it keeps out of
+// rated games until they're over
+fn shown(settings: &Settings, board: &Board, game_state: &GameState) -> bool {
+    settings.evaluation_bar
+        && game_state.start
+        && board.classic()
+        && analysis_allowed(board, game_state)
+}
+
+fn request_evaluation(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    mut live: ResMut<LiveEvaluation>,
+) {
+    if !shown(&settings, &board, &game_state) {
+        if live.requested.is_some() {
+            live.requested = None;
+            live.evaluator = None;
+        }
+        return;
+    }
+    let moves = board.moves.len();
+    if live.requested == Some(moves) {
+        return;
+    }
+
+    let game = match Game::from_board(&board) {
+        Ok(game) => game,
+        Err(e) => {
+            error!("Error replaying board for the evaluation: {}", e);
+            return;
+        }
+    };
+    live.requested = Some(moves);
+    // the search of a position already left is dropped, the bar keeps the last position's
+    // evaluation until the next one comes
+    live.evaluator = Some(PositionEvaluator::new(&game));
+}
+
+// a search going on leaves the bar alone, only its share changes it
+fn step_evaluation(mut live: ResMut<LiveEvaluation>) {
+    let Some(evaluator) = live.bypass_change_detection().evaluator.as_mut() else {
+        return;
+    };
+    if evaluator.step() {
+        return;
+    }
+    let share = evaluator.share();
+    live.share = share;
+    live.evaluator = None;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_evaluation_bar(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut live: ResMut<LiveEvaluation>,
+    ui: Query<Entity, With<EvaluationUi>>,
+    mut bar: Query<&mut Style, With<EvaluationBar>>,
+    mut text: Query<&mut Text, With<EvaluationText>>,
+) {
+    if !shown(&settings, &board, &game_state) {
+        for entity in ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        if live.share.is_some() {
+            live.share = None;
+        }
+        return;
+    }
+
+    // the bar is only touched when a move was made or its evaluation came, an idle board
+    // builds no label
+    if !ui.is_empty() && !live.is_changed() && !locale.is_changed() {
+        return;
+    }
+    let share = live.share.unwrap_or(0.5);
+    let value = label(&board, live.share, &locale);
+    if ui.is_empty() {
+        spawn_evaluation_bar(&mut commands, theme.ui, share, value);
+        return;
+    }
+
+    for mut style in bar.iter_mut() {
+        let width = Val::Percent(share * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn label(board: &Board, share: Option<f32>, locale: &Locale) -> String {
+    let Some(share) = share else {
+        return locale.get(Phrase::Evaluating).to_string();
+    };
+    let red = locale.get(Phrase::Red);
+    let yellow = locale.get(Phrase::Yellow);
+
+    let columns: Vec<usize> = board.moves.iter().map(|m| m.column).collect();
+    if let Some(score) = opening_book::score(&columns) {
+        let to_move = columns.len() % 2 + 1;
+        return match (score.signum(), to_move) {
+            (0, _) => locale.get(Phrase::BookDraw).to_string(),
+            (1, 1) | (-1, 2) => locale.fill(Phrase::BookWins, &[red]),
+            _ => locale.fill(Phrase::BookWins, &[yellow]),
+        };
+    }
+
+    if share > 0.5 + FAVORED {
+        locale.fill(Phrase::EngineFavors, &[red])
+    } else if share < 0.5 - FAVORED {
+        locale.fill(Phrase::EngineFavors, &[yellow])
+    } else {
+        locale.get(Phrase::EngineEven).to_string()
+    }
+}
+
+fn spawn_evaluation_bar(commands: &mut Commands, ui: UiColors, share: f32, value: String) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(4.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            z_index: ZIndex::Global(2),
+            ..default()
+        })
+        .insert(EvaluationUi)
+        .with_children(|parent| {
+            // red's share grows from the left, yellow's is what's left of it
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(10.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.97, 0.8, 0.08).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(share * 100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.87, 0.19, 0.19).into(),
+                        ..default()
+                    })
+                    .insert(EvaluationBar);
+                });
+
+            parent
+                .spawn(TextBundle::from_section(
+                    value,
+                    TextStyle {
+                        color: ui.text,
+                        font_size: 12.0,
+                        ..default()
+                    },
+                ))
+                .insert(EvaluationText);
+        });
+}
+
+fn hide_evaluation_bar(
+    mut commands: Commands,
+    mut live: ResMut<LiveEvaluation>,
+    ui: Query<Entity, With<EvaluationUi>>,
+) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *live = LiveEvaluation::default();
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+
+use crate::{
+    messages::{NetworkMessage, Players},
+    resources::PlayerMove,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveSource {
+    Local,
+    Network,
+    Ghost,
+}
+
+// a move for the board, applied by the gui which spawns its coin. index is the number
+// of moves on the board when it was made, so stale or repeated moves are dropped
+#[derive(Event, Debug, Clone)]
+pub struct MoveMade {
+    pub index: usize,
+    pub player_move: PlayerMove,
+    pub source: MoveSource,
+    pub texture: Handle<Image>,
+}
+
+// winner is None for a draw
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GameEnded {
+    pub winner: Option<usize>,
+    pub resigned: Option<usize>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct OpponentJoined {
+    pub player_type: usize,
+    pub opponent_name: Option<String>,
+    pub series_length: usize,
+}
+
+// systems sending MoveMade, the board applies the moves after all of them
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MoveInput;
+
+// a study message of the game, handled by the study plugin
+#[derive(Event, Debug)]
+pub struct StudyReceived {
+    pub from: XOnlyPublicKey,
+    pub message: NetworkMessage,
+}
+
+// another game was put on the board, what was shown for the previous one goes
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GameSwitched;
+
+// player 1 turned down the join, the game is between the players named
+#[derive(Event, Debug)]
+pub struct GameFull {
+    pub players: Players,
+}
@@ -0,0 +1,180 @@
+use bevy::{prelude::*, ui::UiSystem, window::CursorMoved};
+
+use crate::{game::COLUMNS, menu_plugin::setting_edited, username_plugin::entry_closed, AppState};
+
+const FOCUS_RING: Color = Color::rgb(0.1, 0.4, 0.9);
+
+// the button the keyboard is on. tab and the arrow keys move it in reading order, enter or
+// space press it like a click. nothing has focus until tab is pressed, a click ends the
+// keyboard navigation so mouse players never see the ring
+#[derive(Resource, Default)]
+pub struct Focus {
+    entity: Option<Entity>,
+    // the next screen's first button gets focus while the keyboard is in use
+    keyboard: bool,
+    // bevy only releases a pressed button when the mouse is released
+    pressed: Option<Entity>,
+}
+
+// the column the keyboard aims at on the board. left and right move it, enter, space or
+// down drop a coin there and up pops the bottom coin in pop out
+#[derive(Resource, Default, PartialEq)]
+pub struct BoardCursor {
+    pub column: Option<usize>,
+    pub drop: bool,
+    pub pop: bool,
+}
+
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>()
+            .init_resource::<BoardCursor>()
+            .add_systems(
+                PreUpdate,
+                (
+                    release_pressed,
+                    (move_focus, press_focused, steer_board)
+                        .chain()
+                        // screens that are typing into keep the keyboard to themselves
+                        .run_if(not(in_state(AppState::JoinGame)))
+                        .run_if(entry_closed)
+                        .run_if(not(setting_edited)),
+                )
+                    .chain()
+                    .after(UiSystem::Focus),
+            )
+            .add_systems(PostUpdate, show_focus_ring);
+    }
+}
+
+fn release_pressed(mut focus: ResMut<Focus>, mut interactions: Query<&mut Interaction>) {
+    let Some(entity) = focus.pressed else {
+        return;
+    };
+    focus.pressed = None;
+
+    if let Ok(mut interaction) = interactions.get_mut(entity) {
+        interaction.set_if_neq(Interaction::None);
+    }
+}
+
+fn move_focus(
+    mut keys: ResMut<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    buttons: Query<(Entity, &GlobalTransform, &InheritedVisibility), With<Button>>,
+    mut focus: ResMut<Focus>,
+) {
+    if mouse.get_just_pressed().next().is_some() || touches.any_just_pressed() {
+        if focus.keyboard {
+            focus.keyboard = false;
+            focus.entity = None;
+        }
+        return;
+    }
+
+    let lost = focus
+        .entity
+        .is_some_and(|entity| !buttons.get(entity).is_ok_and(|(_, _, shown)| shown.get()));
+    let step = if keys.clear_just_pressed(KeyCode::Tab) {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            -1
+        } else {
+            1
+        }
+    } else if focus.entity.is_some() && keys.clear_just_pressed(KeyCode::Down) {
+        1
+    } else if focus.entity.is_some() && keys.clear_just_pressed(KeyCode::Up) {
+        -1
+    } else if lost || (focus.keyboard && focus.entity.is_none()) {
+        0
+    } else {
+        return;
+    };
+
+    // reading order, top to bottom then left to right
+    let mut order: Vec<(Entity, Vec3)> = buttons
+        .iter()
+        .filter(|(_, _, shown)| shown.get())
+        .map(|(entity, transform, _)| (entity, transform.translation()))
+        .collect();
+    order.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let current = focus
+        .entity
+        .and_then(|entity| order.iter().position(|(button, _)| *button == entity));
+    let next = match (current, step) {
+        (Some(index), step) => (index as isize + step).rem_euclid(order.len() as isize) as usize,
+        (None, -1) => order.len().saturating_sub(1),
+        (None, _) => 0,
+    };
+
+    focus.keyboard = true;
+    focus.entity = order.get(next).map(|(entity, _)| *entity);
+}
+
+fn press_focused(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut focus: ResMut<Focus>,
+    mut interactions: Query<&mut Interaction>,
+) {
+    let Some(entity) = focus.entity else {
+        return;
+    };
+    let enter = keys.clear_just_pressed(KeyCode::Return);
+    let space = keys.clear_just_pressed(KeyCode::Space);
+    if !enter && !space {
+        return;
+    }
+
+    if let Ok(mut interaction) = interactions.get_mut(entity) {
+        *interaction = Interaction::Pressed;
+        focus.pressed = Some(entity);
+    }
+}
+
+// the board is played with the arrow keys while no button has focus
+fn steer_board(
+    keys: Res<Input<KeyCode>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    focus: Res<Focus>,
+    state: Res<State<AppState>>,
+    mut cursor: ResMut<BoardCursor>,
+) {
+    let mouse_moved = cursor_moved.read().last().is_some();
+    if *state.get() != AppState::InGame || focus.entity.is_some() || mouse_moved {
+        cursor.set_if_neq(BoardCursor::default());
+        return;
+    }
+
+    let mut column = cursor.column;
+    if keys.just_pressed(KeyCode::Left) {
+        column = Some(column.map_or(COLUMNS / 2, |column| column.saturating_sub(1)));
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        column = Some(column.map_or(COLUMNS / 2, |column| (column + 1).min(COLUMNS - 1)));
+    }
+
+    cursor.set_if_neq(BoardCursor {
+        column,
+        drop: column.is_some()
+            && keys.any_just_pressed([KeyCode::Return, KeyCode::Space, KeyCode::Down]),
+        pop: column.is_some() && keys.just_pressed(KeyCode::Up),
+    });
+}
+
+fn show_focus_ring(mut commands: Commands, focus: Res<Focus>, mut ringed: Local<Option<Entity>>) {
+    if !focus.is_changed() || *ringed == focus.entity {
+        return;
+    }
+
+    if let Some(mut entity) = ringed.and_then(|entity| commands.get_entity(entity)) {
+        entity.remove::<Outline>();
+    }
+    if let Some(mut entity) = focus.entity.and_then(|entity| commands.get_entity(entity)) {
+        entity.insert(Outline::new(Val::Px(3.0), Val::Px(2.0), FOCUS_RING));
+    }
+    *ringed = focus.entity;
+}
@@ -0,0 +1,923 @@
+use std::fmt;
+
+use crate::{
+    bitboard::Bitboard,
+    messages::Special,
+    resources::{Board, PlayerMove},
+};
+
+pub use crate::bitboard::{COLUMNS, ROWS};
+// columns of fortune spins a modifier after this many moves
+pub const MODIFIER_EVERY: usize = 4;
+// gravity shift turns the board over after this many moves
+pub const TURN_EVERY: usize = 8;
+// "unite4" in ascii, the seed every client draws its fingerprint from
+const RNG_FINGERPRINT_SEED: u64 = 0x756E_6974_6534;
+
+// the state of the game after a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ongoing,
+    Win(usize),
+    Draw,
+}
+
+// classic players take turns, in blitz drop either player can claim the next drop.
+// columns of fortune plays classic turns with random modifiers drawn from the seed, in
+// pop out a player can remove one of their coins from the bottom row instead of dropping,
+// in power up each player can drop an anvil and a bomb once, on a cylinder lines wrap
+// around the left and right edge, in gravity shift the board turns upside down every few
+// moves and the coins fall to the new bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rules {
+    #[default]
+    Classic,
+    BlitzDrop,
+    Fortune(u64),
+    PopOut,
+    PowerUp,
+    Cylinder,
+    GravityShift,
+}
+
+impl Rules {
+    pub fn of(board: &Board) -> Self {
+        match board.fortune_seed {
+            Some(seed) => Rules::Fortune(seed),
+            None if board.pop_out => Rules::PopOut,
+            None if board.power_up => Rules::PowerUp,
+            None if board.cylinder => Rules::Cylinder,
+            None if board.gravity_shift => Rules::GravityShift,
+            None => Rules::Classic,
+        }
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rules::Classic => write!(f, "Classic"),
+            Rules::BlitzDrop => write!(f, "Blitz Drop"),
+            Rules::Fortune(_) => write!(f, "Columns of Fortune"),
+            Rules::PopOut => write!(f, "Pop Out"),
+            Rules::PowerUp => write!(f, "Power Up"),
+            Rules::Cylinder => write!(f, "Cylinder"),
+            Rules::GravityShift => write!(f, "Gravity Shift"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    // the column can't be played on the next move
+    LockColumn(usize),
+    // the bottom coin of the column is removed and the coins above drop down
+    PopCoin(usize),
+    // gravity shift, the board turned upside down and its coins fell to the new bottom
+    Turn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    GameOver,
+    InvalidColumn(usize),
+    ColumnFull(usize),
+    ColumnLocked(usize),
+    NotYourCoin(usize),
+    NotYourTurn(usize),
+    SpecialUsed(Special),
+    WrongRules(Rules),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "the game is over"),
+            MoveError::InvalidColumn(column) => write!(f, "column {} does not exist", column),
+            MoveError::ColumnFull(column) => write!(f, "column {} is full", column),
+            MoveError::ColumnLocked(column) => write!(f, "column {} is locked", column),
+            MoveError::NotYourCoin(column) => {
+                write!(f, "the bottom coin of column {} is not yours", column)
+            }
+            MoveError::NotYourTurn(player) => write!(f, "it is not player {}'s turn", player),
+            MoveError::SpecialUsed(special) => write!(f, "the {} was already used", special),
+            MoveError::WrongRules(rules) => write!(f, "not allowed with {:?} rules", rules),
+        }
+    }
+}
+
+// the rules of connect 4 without any rendering or networking, player 1 moves first.
+// moves is the history as played, coins what is on the board, they only differ once a
+// coin was removed, by a modifier, a pop out move or a power up. the bitboard holds the
+// same coins for the wins and landing rows
+#[derive(Debug, Clone)]
+pub struct Game {
+    rules: Rules,
+    moves: Vec<PlayerMove>,
+    coins: Vec<PlayerMove>,
+    bitboard: Bitboard,
+    player_turn: usize,
+    outcome: Outcome,
+    locked: Option<usize>,
+    modifier: Option<Modifier>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::with_rules(Rules::Classic)
+    }
+
+    pub fn with_rules(rules: Rules) -> Self {
+        Self {
+            rules,
+            moves: Vec::new(),
+            coins: Vec::new(),
+            bitboard: Bitboard::default(),
+            player_turn: 1,
+            outcome: Outcome::Ongoing,
+            locked: None,
+            modifier: None,
+        }
+    }
+
+    // moves are replayed by column and kind, the players and rows are taken from the rules
+    pub fn replay(rules: Rules, moves: &[PlayerMove]) -> Result<Self, MoveError> {
+        let mut game = Self::with_rules(rules);
+        for player_move in moves {
+            game.play(player_move)?;
+        }
+        Ok(game)
+    }
+
+    pub fn from_board(board: &Board) -> Result<Self, MoveError> {
+        Self::replay(Rules::of(board), &board.moves)
+    }
+
+    pub fn moves(&self) -> &[PlayerMove] {
+        &self.moves
+    }
+
+    pub fn coins(&self) -> &[PlayerMove] {
+        &self.coins
+    }
+
+    // the modifier spun by the last move, if any
+    pub fn modifier(&self) -> Option<Modifier> {
+        self.modifier
+    }
+
+    pub fn player_turn(&self) -> usize {
+        self.player_turn
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    // columns that still take a coin, empty once the game is over
+    pub fn open_columns(&self) -> Vec<usize> {
+        if self.outcome != Outcome::Ongoing {
+            return Vec::new();
+        }
+
+        (0..COLUMNS)
+            .filter(|&column| self.landing_row(column).is_some())
+            .collect()
+    }
+
+    pub fn landing_row(&self, column: usize) -> Option<usize> {
+        self.bitboard.landing_row(column)
+    }
+
+    // plays a move of any kind for the player to move, its player and row are ignored
+    pub fn play(&mut self, player_move: &PlayerMove) -> Result<Outcome, MoveError> {
+        match player_move.special {
+            Some(special) => self.play_special(special, player_move.column),
+            None if player_move.pop => self.pop_out(player_move.column),
+            None => self.apply_move(player_move.column),
+        }
+    }
+
+    // checks a move of any kind by a given player, returns the row it lands in
+    pub fn check(&self, player_move: &PlayerMove) -> Result<usize, MoveError> {
+        let (player, column) = (player_move.player, player_move.column);
+        match player_move.special {
+            Some(special) => self.check_special(player, special, column),
+            None if player_move.pop => self.check_pop(player, column).map(|()| 0),
+            None => self.check_move(player, column),
+        }
+    }
+
+    pub fn apply_move(&mut self, column: usize) -> Result<Outcome, MoveError> {
+        if self.rules == Rules::BlitzDrop {
+            return Err(MoveError::WrongRules(self.rules));
+        }
+
+        if self.locked == Some(column) {
+            return Err(MoveError::ColumnLocked(column));
+        }
+
+        self.drop_coin(self.player_turn, column)?;
+
+        self.locked = None;
+        self.modifier = None;
+        match self.rules {
+            Rules::Fortune(seed) => self.spin(seed, column),
+            Rules::GravityShift => self.shift_gravity(),
+            _ => {}
+        }
+
+        Ok(self.outcome)
+    }
+
+    // pop out: the player to move takes their coin from the bottom of the column
+    pub fn pop_out(&mut self, column: usize) -> Result<Outcome, MoveError> {
+        let player = self.player_turn;
+        self.check_pop(player, column)?;
+
+        self.moves.push(PlayerMove::popped(player, column));
+        self.player_turn = other(player);
+        self.pop_coin(column);
+
+        Ok(self.outcome)
+    }
+
+    pub fn check_pop(&self, player: usize, column: usize) -> Result<(), MoveError> {
+        if self.rules != Rules::PopOut {
+            return Err(MoveError::WrongRules(self.rules));
+        }
+
+        if player != self.player_turn {
+            return Err(MoveError::NotYourTurn(player));
+        }
+
+        if self.outcome != Outcome::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+
+        if column >= COLUMNS {
+            return Err(MoveError::InvalidColumn(column));
+        }
+
+        if self.bitboard.player_at(column, 0) != Some(player) {
+            return Err(MoveError::NotYourCoin(column));
+        }
+
+        Ok(())
+    }
+
+    // power up coins the player hasn't used yet
+    pub fn specials_left(&self, player: usize) -> Vec<Special> {
+        if self.rules != Rules::PowerUp {
+            return Vec::new();
+        }
+
+        [Special::Anvil, Special::Bomb]
+            .into_iter()
+            .filter(|&special| {
+                !self.moves.iter().any(|player_move| {
+                    player_move.player == player && player_move.special == Some(special)
+                })
+            })
+            .collect()
+    }
+
+    // an anvil can go into a full column, it crushes the coins and lands at the bottom
+    pub fn check_special(
+        &self,
+        player: usize,
+        special: Special,
+        column: usize,
+    ) -> Result<usize, MoveError> {
+        if self.rules != Rules::PowerUp {
+            return Err(MoveError::WrongRules(self.rules));
+        }
+
+        if player != self.player_turn {
+            return Err(MoveError::NotYourTurn(player));
+        }
+
+        if !self.specials_left(player).contains(&special) {
+            return Err(MoveError::SpecialUsed(special));
+        }
+
+        match special {
+            Special::Anvil => {
+                if self.outcome != Outcome::Ongoing {
+                    return Err(MoveError::GameOver);
+                }
+                if column >= COLUMNS {
+                    return Err(MoveError::InvalidColumn(column));
+                }
+                Ok(0)
+            }
+            Special::Bomb => self.check_drop(column),
+        }
+    }
+
+    pub fn play_special(&mut self, special: Special, column: usize) -> Result<Outcome, MoveError> {
+        let player = self.player_turn;
+        let row = self.check_special(player, special, column)?;
+        let player_move = PlayerMove::power_up(player, column, row, special);
+
+        self.moves.push(player_move);
+        self.player_turn = other(player);
+
+        match special {
+            Special::Anvil => {
+                // the anvil stays on the board as the player's coin
+                self.coins.retain(|coin| coin.column != column);
+                self.coins.push(player_move);
+                self.bitboard = Bitboard::from_coins(&self.coins);
+                self.outcome = self.outcome_after(&player_move);
+            }
+            Special::Bomb => {
+                // the bomb goes off where it lands, taking the coins around it
+                self.coins
+                    .retain(|coin| coin.column.abs_diff(column) > 1 || coin.row.abs_diff(row) > 1);
+                let columns = column.saturating_sub(1)..=(column + 1).min(COLUMNS - 1);
+                for blasted in columns {
+                    self.settle(blasted);
+                }
+                self.check_lines();
+            }
+        }
+
+        Ok(self.outcome)
+    }
+
+    // a cylinder win that needs a line across the left and right edge
+    pub fn wrapped_win(&self) -> bool {
+        let Outcome::Win(winner) = self.outcome else {
+            return false;
+        };
+        self.rules == Rules::Cylinder
+            && self.bitboard.wins_wrapped(winner)
+            && !self.bitboard.wins(winner)
+    }
+
+    fn can_pop(&self, player: usize) -> bool {
+        self.rules == Rules::PopOut
+            && (0..COLUMNS).any(|column| self.bitboard.player_at(column, 0) == Some(player))
+    }
+
+    // the player who was quickest drops a coin, turns don't alternate
+    pub fn claim_drop(&mut self, player: usize, column: usize) -> Result<Outcome, MoveError> {
+        if self.rules != Rules::BlitzDrop {
+            return Err(MoveError::WrongRules(self.rules));
+        }
+
+        self.drop_coin(player, column)
+    }
+
+    // checks a move by a given player without playing it, used for moves from the network
+    pub fn check_move(&self, player: usize, column: usize) -> Result<usize, MoveError> {
+        if self.rules != Rules::BlitzDrop && player != self.player_turn {
+            return Err(MoveError::NotYourTurn(player));
+        }
+
+        let row = self.check_drop(column)?;
+        if self.locked == Some(column) {
+            return Err(MoveError::ColumnLocked(column));
+        }
+
+        Ok(row)
+    }
+
+    fn check_drop(&self, column: usize) -> Result<usize, MoveError> {
+        if self.outcome != Outcome::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+
+        if column >= COLUMNS {
+            return Err(MoveError::InvalidColumn(column));
+        }
+
+        self.landing_row(column)
+            .ok_or(MoveError::ColumnFull(column))
+    }
+
+    fn drop_coin(&mut self, player: usize, column: usize) -> Result<Outcome, MoveError> {
+        let row = self.check_drop(column)?;
+
+        let player_move = PlayerMove::new(player, column, row);
+        self.moves.push(player_move);
+        self.coins.push(player_move);
+        self.bitboard.play(column, player);
+        self.player_turn = other(player);
+        self.outcome = self.outcome_after(&player_move);
+
+        Ok(self.outcome)
+    }
+
+    // only the player who moved can have made a line. in pop out a full board is only a
+    // draw when the next player has no coin to pop
+    fn outcome_after(&self, player_move: &PlayerMove) -> Outcome {
+        if self.wins(player_move.player) {
+            Outcome::Win(player_move.player)
+        } else if self.bitboard.is_full() && !self.can_pop(self.player_turn) {
+            Outcome::Draw
+        } else {
+            Outcome::Ongoing
+        }
+    }
+
+    fn wins(&self, player: usize) -> bool {
+        if self.rules == Rules::Cylinder {
+            self.bitboard.wins_wrapped(player)
+        } else {
+            self.bitboard.wins(player)
+        }
+    }
+
+    // the modifier only depends on the seed and the number of moves, so both players
+    // spin the same one. a coin never pops out of the column just played
+    fn spin(&mut self, seed: u64, played: usize) {
+        if self.outcome != Outcome::Ongoing || !self.moves.len().is_multiple_of(MODIFIER_EVERY) {
+            return;
+        }
+
+        let roll = GameRng::at(seed, self.moves.len() as u64).next_u64();
+        let pick = (roll >> 8) as usize;
+
+        self.modifier = if roll & 1 == 0 {
+            // locking the last open column would leave no move
+            let open = self.open_columns();
+            (open.len() > 1).then(|| Modifier::LockColumn(open[pick % open.len()]))
+        } else {
+            let filled: Vec<usize> = (0..COLUMNS)
+                .filter(|&column| column != played && self.landing_row(column) != Some(0))
+                .collect();
+            (!filled.is_empty()).then(|| Modifier::PopCoin(filled[pick % filled.len()]))
+        };
+
+        match self.modifier {
+            Some(Modifier::LockColumn(column)) => self.locked = Some(column),
+            Some(Modifier::PopCoin(column)) => self.pop_coin(column),
+            Some(Modifier::Turn) | None => {}
+        }
+    }
+
+    // every TURN_EVERY moves the board turns upside down, unless the move won. the coins
+    // fall to the new bottom before any line counts, a full board can still end in a win
+    fn shift_gravity(&mut self) {
+        if matches!(self.outcome, Outcome::Win(_)) || !self.moves.len().is_multiple_of(TURN_EVERY) {
+            return;
+        }
+
+        self.transform(|column, row| (COLUMNS - 1 - column, ROWS - 1 - row));
+        self.modifier = Some(Modifier::Turn);
+        self.outcome = Outcome::Ongoing;
+        self.check_lines();
+        if self.outcome == Outcome::Ongoing && self.bitboard.is_full() {
+            self.outcome = Outcome::Draw;
+        }
+    }
+
+    // moves every coin to the slot the map gives it, then settles the columns. the map
+    // has to keep the coins on the board and apart
+    pub fn transform(&mut self, map: impl Fn(usize, usize) -> (usize, usize)) {
+        for coin in self.coins.iter_mut() {
+            (coin.column, coin.row) = map(coin.column, coin.row);
+        }
+        for column in 0..COLUMNS {
+            self.settle(column);
+        }
+    }
+
+    fn pop_coin(&mut self, column: usize) {
+        self.coins
+            .retain(|coin| coin.column != column || coin.row != 0);
+        self.settle(column);
+        self.check_lines();
+    }
+
+    // the coins left in the column drop into the gaps below them
+    fn settle(&mut self, column: usize) {
+        let mut rows: Vec<usize> = self
+            .coins
+            .iter()
+            .filter(|coin| coin.column == column)
+            .map(|coin| coin.row)
+            .collect();
+        rows.sort_unstable();
+
+        for coin in self.coins.iter_mut().filter(|coin| coin.column == column) {
+            coin.row = rows
+                .iter()
+                .position(|&row| row == coin.row)
+                .unwrap_or(coin.row);
+        }
+        self.bitboard = Bitboard::from_coins(&self.coins);
+    }
+
+    // the coins dropping down can complete a line for either player, the player who just
+    // moved is checked first. the game was still on before, so any line is a new one
+    fn check_lines(&mut self) {
+        let mover = other(self.player_turn);
+        for player in [mover, self.player_turn] {
+            if self.wins(player) {
+                self.outcome = Outcome::Win(player);
+                return;
+            }
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<PlayerMove> {
+        // a popped coin can't be taken back, the game is replayed without the last move
+        if let Rules::Fortune(_) | Rules::PopOut | Rules::PowerUp | Rules::GravityShift = self.rules
+        {
+            let player_move = *self.moves.last()?;
+            let moves = &self.moves[..self.moves.len() - 1];
+            *self = Self::replay(self.rules, moves).ok()?;
+            return Some(player_move);
+        }
+
+        let player_move = self.moves.pop()?;
+        self.coins.pop();
+        self.bitboard.undo(player_move.column);
+        self.player_turn = player_move.player;
+        self.outcome = Outcome::Ongoing;
+        Some(player_move)
+    }
+}
+
+fn other(player: usize) -> usize {
+    if player == 1 {
+        2
+    } else {
+        1
+    }
+}
+
+// the one source of randomness for the rules. both clients start it from the seed in the
+// new game message and the number of moves played, so they draw the same numbers in the
+// same order. a splitmix64 stream, the first draw at index n is the old splitmix(seed ^ n)
+#[derive(Debug, Clone)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn at(seed: u64, index: u64) -> Self {
+        GameRng {
+            state: seed ^ index,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut z = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.state = z;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // a number below n, the bias is negligible for board sized n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+// sent in the handshake, a client whose rng draws differently would spin other modifiers
+// from the same seed and desync a random game
+pub fn rng_fingerprint() -> u64 {
+    let mut rng = GameRng::at(RNG_FINGERPRINT_SEED, 0);
+    (0..8).fold(0, |fingerprint, draw| {
+        let value = rng.next_u64() ^ rng.below(COLUMNS + draw) as u64;
+        fingerprint.rotate_left(7) ^ value
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a full board (42 moves) with no four in a row for either player
+    const DRAWN_GAME: [usize; 42] = [
+        0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 2, 3, 2, 3, 2, 3, 3, 2, 3, 2, 3, 2, 4, 5, 4, 5, 4, 5,
+        5, 4, 5, 4, 5, 4, 6, 6, 6, 6, 6, 6,
+    ];
+
+    fn play(columns: &[usize]) -> Result<Outcome, MoveError> {
+        let mut game = Game::new();
+        let mut outcome = Outcome::Ongoing;
+        for &column in columns {
+            outcome = game.apply_move(column)?;
+        }
+        Ok(outcome)
+    }
+
+    #[test]
+    fn wins_in_every_direction() {
+        // red's bottom row 0 to 3
+        assert_eq!(play(&[0, 0, 1, 1, 2, 2, 3]), Ok(Outcome::Win(1)));
+        // red's column 3
+        assert_eq!(play(&[3, 4, 3, 4, 3, 4, 3]), Ok(Outcome::Win(1)));
+        // red rising from column 0 to 3, then falling from column 6 to 3
+        assert_eq!(
+            play(&[0, 1, 1, 2, 2, 3, 2, 3, 3, 5, 3]),
+            Ok(Outcome::Win(1))
+        );
+        assert_eq!(
+            play(&[6, 5, 5, 4, 4, 3, 4, 3, 3, 1, 3]),
+            Ok(Outcome::Win(1))
+        );
+        // yellow's column 3
+        assert_eq!(play(&[0, 3, 0, 3, 1, 3, 6, 3]), Ok(Outcome::Win(2)));
+        // three in a row is no win
+        assert_eq!(play(&[0, 0, 1, 1, 2, 2]), Ok(Outcome::Ongoing));
+    }
+
+    #[test]
+    fn full_board_without_a_line_is_a_draw() {
+        assert_eq!(play(&DRAWN_GAME[..41]), Ok(Outcome::Ongoing));
+        assert_eq!(play(&DRAWN_GAME), Ok(Outcome::Draw));
+    }
+
+    #[test]
+    fn rejects_full_and_missing_columns() {
+        assert_eq!(play(&[3; 7]), Err(MoveError::ColumnFull(3)));
+        assert_eq!(play(&[COLUMNS]), Err(MoveError::InvalidColumn(COLUMNS)));
+
+        let mut game = Game::new();
+        for _ in 0..ROWS {
+            game.apply_move(3).unwrap();
+        }
+        assert_eq!(game.landing_row(3), None);
+        assert!(!game.open_columns().contains(&3));
+        assert_eq!(game.apply_move(3), Err(MoveError::ColumnFull(3)));
+        // a rejected move changes nothing
+        assert_eq!(game.moves().len(), ROWS);
+        assert_eq!(game.player_turn(), 1);
+    }
+
+    #[test]
+    fn players_take_turns() {
+        let mut game = Game::new();
+        assert_eq!(game.player_turn(), 1);
+        assert_eq!(game.check_move(1, 3), Ok(0));
+        assert_eq!(game.check_move(2, 3), Err(MoveError::NotYourTurn(2)));
+        assert!(game.moves().is_empty());
+
+        game.apply_move(3).unwrap();
+        assert_eq!(game.player_turn(), 2);
+        assert_eq!(game.check_move(1, 3), Err(MoveError::NotYourTurn(1)));
+        assert_eq!(game.check_move(2, 3), Ok(1));
+
+        game.apply_move(3).unwrap();
+        let players: Vec<usize> = game.moves().iter().map(|coin| coin.player).collect();
+        assert_eq!(players, [1, 2]);
+    }
+
+    #[test]
+    fn no_moves_after_the_game_is_over() {
+        assert_eq!(play(&[3, 4, 3, 4, 3, 4, 3, 4]), Err(MoveError::GameOver));
+        assert_eq!(
+            play(&[DRAWN_GAME.as_slice(), &[0]].concat()),
+            Err(MoveError::GameOver)
+        );
+
+        let mut game = Game::new();
+        for column in [3, 4, 3, 4, 3, 4, 3] {
+            game.apply_move(column).unwrap();
+        }
+        assert!(game.open_columns().is_empty());
+        assert_eq!(game.check_move(2, 0), Err(MoveError::GameOver));
+        assert_eq!(game.apply_move(0), Err(MoveError::GameOver));
+        assert_eq!(game.outcome(), Outcome::Win(1));
+    }
+
+    #[test]
+    fn undo_takes_back_the_last_move() {
+        let mut game = Game::new();
+        assert!(game.undo().is_none());
+
+        game.apply_move(3).unwrap();
+        game.apply_move(4).unwrap();
+        let undone = game.undo().unwrap();
+        assert_eq!((undone.player, undone.column, undone.row), (2, 4, 0));
+        assert_eq!(game.player_turn(), 2);
+        assert_eq!(game.landing_row(4), Some(0));
+        assert_eq!(game.moves().len(), 1);
+        assert_eq!(game.coins().len(), 1);
+
+        // undoing the winning move reopens the game
+        for column in [4, 3, 4, 3, 4, 3] {
+            game.apply_move(column).unwrap();
+        }
+        assert_eq!(game.outcome(), Outcome::Win(1));
+        game.undo().unwrap();
+        assert_eq!(game.outcome(), Outcome::Ongoing);
+        assert_eq!(game.player_turn(), 1);
+        assert_eq!(game.landing_row(3), Some(3));
+        assert_eq!(game.apply_move(0), Ok(Outcome::Ongoing));
+    }
+
+    // every client has to draw these, they're splitmix64's outputs
+    #[test]
+    fn rng_draws_splitmix64() {
+        let draws = |seed, index| {
+            let mut rng = GameRng::at(seed, index);
+            [rng.next_u64(), rng.next_u64(), rng.next_u64()]
+        };
+        assert_eq!(
+            draws(0, 0),
+            [
+                0xe220_a839_7b1d_cdaf,
+                0x6e78_9e6a_a1b9_65f4,
+                0x06c4_5d18_8009_454f
+            ]
+        );
+        assert_eq!(
+            draws(7, 0),
+            [
+                0x63cb_e1e4_5932_0dd7,
+                0x044c_3cd7_f43c_661c,
+                0xe698_4080_bab1_2a02
+            ]
+        );
+        assert_eq!(
+            draws(7, 4),
+            [
+                0x1d0b_14e4_db01_8fed,
+                0xb346_6f8a_7b81_a989,
+                0x9ceb_e8a6_d050_dd01
+            ]
+        );
+        assert_ne!(draws(7, 4), draws(8, 4));
+
+        let mut rng = GameRng::at(7, 0);
+        assert!((0..100).all(|_| rng.below(COLUMNS) < COLUMNS));
+        assert_eq!(rng.below(0), 0);
+    }
+
+    // the value every client sends in the handshake, a change here desyncs random games
+    // with older clients
+    #[test]
+    fn rng_fingerprint_is_fixed() {
+        assert_eq!(rng_fingerprint(), 0xcddb_6d50_e72f_3e42);
+    }
+
+    #[test]
+    fn fortune_spins_from_the_seed() {
+        let spin = |seed| {
+            let mut fortune = Game::with_rules(Rules::Fortune(seed));
+            for column in 0..MODIFIER_EVERY {
+                fortune.apply_move(column).unwrap();
+                if fortune.moves().len() < MODIFIER_EVERY {
+                    assert_eq!(fortune.modifier(), None);
+                }
+            }
+            fortune
+        };
+
+        // the fourth move drops into column 3, the bottom coin of column 0 pops out and
+        // the one of column 2 in another game
+        let fortune = spin(7);
+        assert_eq!(fortune.modifier(), Some(Modifier::PopCoin(0)));
+        assert_eq!(fortune.landing_row(0), Some(0));
+        assert_eq!(fortune.coins().len(), MODIFIER_EVERY - 1);
+        assert_eq!(spin(42).modifier(), Some(Modifier::PopCoin(2)));
+    }
+
+    // either player claims the next drop, turns don't alternate
+    #[test]
+    fn blitz_drop_takes_claims() {
+        let mut blitz = Game::with_rules(Rules::BlitzDrop);
+        assert_eq!(
+            blitz.apply_move(3),
+            Err(MoveError::WrongRules(Rules::BlitzDrop))
+        );
+        assert_eq!(blitz.check_move(2, 0), Ok(0));
+        for column in 0..3 {
+            assert_eq!(blitz.claim_drop(2, column), Ok(Outcome::Ongoing));
+        }
+        assert_eq!(blitz.claim_drop(1, 0), Ok(Outcome::Ongoing));
+        assert_eq!(blitz.claim_drop(2, 3), Ok(Outcome::Win(2)));
+        assert_eq!(blitz.claim_drop(1, 4), Err(MoveError::GameOver));
+
+        assert_eq!(
+            Game::new().claim_drop(1, 3),
+            Err(MoveError::WrongRules(Rules::Classic))
+        );
+    }
+
+    // yellow pops their coin under red's, red's coin drops and completes the bottom row
+    #[test]
+    fn pop_out_drops_the_coins_above() {
+        let mut pop_out = Game::with_rules(Rules::PopOut);
+        for column in [0, 3, 1, 5, 3, 5, 2] {
+            assert_eq!(pop_out.apply_move(column), Ok(Outcome::Ongoing));
+        }
+        assert_eq!(pop_out.check_pop(2, 0), Err(MoveError::NotYourCoin(0)));
+        assert_eq!(pop_out.check_pop(1, 3), Err(MoveError::NotYourTurn(1)));
+        assert_eq!(
+            pop_out.check_pop(2, COLUMNS),
+            Err(MoveError::InvalidColumn(COLUMNS))
+        );
+        assert_eq!(pop_out.pop_out(3), Ok(Outcome::Win(1)));
+        assert_eq!(pop_out.coins().len(), pop_out.moves().len() - 2);
+        assert_eq!(pop_out.check_pop(1, 0), Err(MoveError::GameOver));
+
+        let replayed = Game::replay(Rules::PopOut, pop_out.moves()).unwrap();
+        assert_eq!(replayed.outcome(), Outcome::Win(1));
+
+        // a pop is taken back by replaying the moves before it
+        let popped = pop_out.undo().unwrap();
+        assert!(popped.pop);
+        assert_eq!(pop_out.outcome(), Outcome::Ongoing);
+        assert_eq!(pop_out.coins().len(), pop_out.moves().len());
+
+        assert_eq!(
+            Game::new().pop_out(3),
+            Err(MoveError::WrongRules(Rules::Classic))
+        );
+    }
+
+    // the anvil crushes red's column, the bomb takes the coins around it
+    #[test]
+    fn power_up_specials_clear_coins() {
+        let mut power_up = Game::with_rules(Rules::PowerUp);
+        for column in [3, 4, 3, 4, 3] {
+            assert_eq!(power_up.apply_move(column), Ok(Outcome::Ongoing));
+        }
+        assert_eq!(
+            power_up.check_special(1, Special::Anvil, 3),
+            Err(MoveError::NotYourTurn(1))
+        );
+        assert_eq!(
+            power_up.play_special(Special::Anvil, 3),
+            Ok(Outcome::Ongoing)
+        );
+        assert_eq!(power_up.coins().len(), 3);
+        assert_eq!(power_up.landing_row(3), Some(1));
+        assert_eq!(
+            power_up.play_special(Special::Bomb, 4),
+            Ok(Outcome::Ongoing)
+        );
+        assert_eq!(power_up.coins().len(), 2);
+        assert_eq!(
+            power_up.check_special(2, Special::Anvil, 0),
+            Err(MoveError::SpecialUsed(Special::Anvil))
+        );
+        assert_eq!(power_up.specials_left(1), vec![Special::Anvil]);
+        assert_eq!(power_up.specials_left(2), vec![Special::Bomb]);
+
+        let replayed = Game::replay(Rules::PowerUp, power_up.moves()).unwrap();
+        assert_eq!(replayed.coins().len(), 2);
+
+        assert_eq!(
+            Game::new().play_special(Special::Bomb, 3),
+            Err(MoveError::WrongRules(Rules::Classic))
+        );
+    }
+
+    // red's bottom row 5, 6, 0, 1 only joins across the edge
+    #[test]
+    fn cylinder_lines_wrap_around() {
+        let wrap = [5, 3, 6, 3, 0, 3, 1];
+        let mut cylinder = Game::with_rules(Rules::Cylinder);
+        for &column in &wrap {
+            cylinder.apply_move(column).unwrap();
+        }
+        assert_eq!(cylinder.outcome(), Outcome::Win(1));
+        assert!(cylinder.wrapped_win());
+        assert_eq!(play(&wrap), Ok(Outcome::Ongoing));
+
+        // a line that doesn't cross the edge wins without wrapping
+        let mut cylinder = Game::with_rules(Rules::Cylinder);
+        for column in [3, 4, 3, 4, 3, 4, 3] {
+            cylinder.apply_move(column).unwrap();
+        }
+        assert_eq!(cylinder.outcome(), Outcome::Win(1));
+        assert!(!cylinder.wrapped_win());
+    }
+
+    // yellow's eighth move turns the board over, red's coins on top of columns 0 to 2 and
+    // the one in column 3 fall into a bottom row
+    #[test]
+    fn gravity_shift_turns_the_board() {
+        let turn = [3, 0, 0, 1, 1, 2, 2, 5];
+        let mut gravity = Game::with_rules(Rules::GravityShift);
+        for &column in &turn[..TURN_EVERY - 1] {
+            gravity.apply_move(column).unwrap();
+            assert_eq!(gravity.modifier(), None);
+        }
+        gravity.apply_move(turn[TURN_EVERY - 1]).unwrap();
+        assert_eq!(gravity.modifier(), Some(Modifier::Turn));
+        assert_eq!(gravity.outcome(), Outcome::Win(1));
+        assert_eq!(gravity.landing_row(6), Some(2));
+        assert_eq!(gravity.landing_row(0), Some(0));
+        assert_eq!(play(&turn), Ok(Outcome::Ongoing));
+
+        let replayed = Game::replay(Rules::GravityShift, gravity.moves()).unwrap();
+        assert_eq!(replayed.outcome(), Outcome::Win(1));
+    }
+}
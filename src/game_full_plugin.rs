@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::{
+    components::{GameFullAction, GameFullUi},
+    events::GameFull,
+    leaderboard_plugin::short_pubkey,
+    locale_plugin::{Locale, Phrase},
+    resources::GameState,
+    theme_plugin::Theme,
+    AppState,
+};
+
+// a third player opening the game's link answered the new game after the opponent did.
+// player 1 turns the join down and the latecomer chooses between watching and the menu
+pub struct GameFullPlugin;
+
+impl Plugin for GameFullPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (show_game_full, game_full_actions).run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(OnExit(AppState::InGame), cleanup_game_full);
+    }
+}
+
+fn show_game_full(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut game_full: EventReader<GameFull>,
+    ui: Query<(), With<GameFullUi>>,
+) {
+    let Some(GameFull { players }) = game_full.read().last() else {
+        return;
+    };
+    if !ui.is_empty() {
+        return;
+    }
+
+    let p1 = players
+        .p1_name
+        .clone()
+        .unwrap_or_else(|| short_pubkey(&players.p1_pubkey));
+    let p2 = players
+        .p2_name
+        .clone()
+        .unwrap_or_else(|| short_pubkey(&players.p2_pubkey));
+    let text_style = |font_size: f32| TextStyle {
+        color: theme.ui.text,
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: theme.ui.background.with_a(0.95).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(GameFullUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                locale.get(Phrase::GameIsFull),
+                text_style(24.0),
+            ));
+            parent.spawn(TextBundle::from_section(
+                locale.fill(Phrase::SpectateInstead, &[&p1, &p2]),
+                text_style(16.0),
+            ));
+
+            for (phrase, action) in [
+                (Phrase::Spectate, GameFullAction::Spectate),
+                (Phrase::BackToMenu, GameFullAction::Menu),
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: theme.ui.button.into(),
+                        ..default()
+                    })
+                    .insert(action)
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            locale.get(phrase),
+                            text_style(16.0),
+                        ));
+                    });
+            }
+        });
+}
+
+fn game_full_actions(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    actions: Query<(&Interaction, &GameFullAction), Changed<Interaction>>,
+    ui: Query<Entity, With<GameFullUi>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            // the moves of the player it didn't follow are asked for
+            GameFullAction::Spectate => {
+                for entity in ui.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                game_state.clone().send_sync_request();
+            }
+            GameFullAction::Menu => next_state.set(AppState::Menu),
+        }
+    }
+}
+
+fn cleanup_game_full(mut commands: Commands, ui: Query<Entity, With<GameFullUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
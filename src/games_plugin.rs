@@ -0,0 +1,478 @@
+use std::{mem, sync::Mutex};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use nostr_sdk::serde_json;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{CoinMove, GameSwitcherUi, GameTab},
+    events::GameSwitched,
+    locale_plugin::{Locale, Phrase},
+    messages::NetworkMessage,
+    nostr_plugin::{connect_game, redraw_coins},
+    platform,
+    resources::{
+        Board, Desync, GameState, MovePreload, NetworkStuff, PendingAck, Processed, UndoState,
+    },
+    settings_plugin::Settings,
+    storage::{self, OngoingGame},
+    theme_plugin::Theme,
+    AppState,
+};
+
+// parked games beyond it are closed, the one switched away from longest ago first
+const MAX_PARKED_GAMES: usize = 8;
+
+// the game to switch to, asked for outside of the bevy systems
+static SWITCH_TO: Mutex<Option<String>> = Mutex::new(None);
+
+// several games can be open at once, correspondence games mostly. the game on the board
+// lives in the usual resources, every other one is parked here with its own connection
+// and the events it gets wait in its backlog until it is switched back to
+pub struct GamesPlugin;
+
+impl Plugin for GamesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenGames>()
+            .add_event::<GameSwitched>()
+            .add_systems(OnEnter(AppState::InGame), (open_url_game, spawn_switcher))
+            .add_systems(Update, check_page_switch)
+            .add_systems(
+                Update,
+                (
+                    click_game_tab,
+                    switch_requested_game,
+                    watch_parked_games,
+                    update_switcher,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), cleanup_switcher);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct OpenGames {
+    // the most recently parked last
+    parked: Vec<ParkedGame>,
+    requested: Option<String>,
+}
+
+impl OpenGames {
+    fn take(&mut self, game_id: &str) -> Option<ParkedGame> {
+        let index = self
+            .parked
+            .iter()
+            .position(|parked| parked.game_state.game_id == game_id)?;
+        Some(self.parked.remove(index))
+    }
+
+    fn park(&mut self, game: ParkedGame) {
+        self.parked.push(game);
+        if self.parked.len() > MAX_PARKED_GAMES {
+            let closed = self.parked.remove(0);
+            info!("closing game {}", closed.game_state.game_id);
+        }
+    }
+}
+
+struct ParkedGame {
+    game_state: GameState,
+    processed: Processed,
+    board: Board,
+    network_stuff: NetworkStuff,
+    pending_ack: PendingAck,
+    undo: UndoState,
+    desync: Desync,
+    // the opponent moved since the game was parked
+    moved: bool,
+}
+
+impl ParkedGame {
+    // a game not opened yet, played with the same keys
+    fn fresh(game_state: &GameState) -> Self {
+        Self {
+            game_state: GameState {
+                nostr_keys: game_state.nostr_keys.clone(),
+                ..GameState::new()
+            },
+            processed: Processed::default(),
+            board: Board::new(),
+            network_stuff: NetworkStuff::new(),
+            pending_ack: PendingAck::new(),
+            undo: UndoState::new(),
+            desync: Desync::default(),
+            moved: false,
+        }
+    }
+
+    fn your_turn(&self) -> bool {
+        let player = self.game_state.player_type;
+        let finished = self.board.winner.is_some() || self.board.draw;
+        !finished && (self.moved || (self.game_state.start && self.board.player_turn == player))
+    }
+}
+
+// the resources of the game on the board
+#[derive(SystemParam)]
+struct ActiveGame<'w> {
+    game_state: ResMut<'w, GameState>,
+    processed: ResMut<'w, Processed>,
+    board: ResMut<'w, Board>,
+    network_stuff: ResMut<'w, NetworkStuff>,
+    pending_ack: ResMut<'w, PendingAck>,
+    undo: ResMut<'w, UndoState>,
+    desync: ResMut<'w, Desync>,
+    preload: ResMut<'w, MovePreload>,
+}
+
+impl ActiveGame<'_> {
+    // puts the game on the board and returns the one taken off
+    fn swap(&mut self, game: ParkedGame) -> ParkedGame {
+        *self.preload = MovePreload::new();
+        ParkedGame {
+            game_state: mem::replace(&mut *self.game_state, game.game_state),
+            processed: mem::replace(&mut *self.processed, game.processed),
+            board: mem::replace(&mut *self.board, game.board),
+            network_stuff: mem::replace(&mut *self.network_stuff, game.network_stuff),
+            pending_ack: mem::replace(&mut *self.pending_ack, game.pending_ack),
+            undo: mem::replace(&mut *self.undo, game.undo),
+            desync: mem::replace(&mut *self.desync, game.desync),
+            moved: false,
+        }
+    }
+
+    fn open(&self) -> bool {
+        self.game_state.transport.is_some()
+    }
+}
+
+// the page's menu and the desktop menu open a game through its url
+#[allow(clippy::too_many_arguments)]
+fn open_url_game(
+    mut commands: Commands,
+    mut active: ActiveGame,
+    mut open_games: ResMut<OpenGames>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+    coins: Query<(Entity, &CoinMove)>,
+    mut switched: EventWriter<GameSwitched>,
+) {
+    let game_id = platform::game_id();
+
+    // the first game, or a practice game which never connects
+    if !active.open() || active.game_state.ghost.is_some() {
+        connect_game(
+            &mut active.network_stuff,
+            &mut active.game_state,
+            &mut active.processed,
+            &mut active.board,
+            &settings,
+        );
+        return;
+    }
+
+    // back from the menu to the game on the board
+    if active.game_state.game_id == game_id {
+        return;
+    }
+
+    switch_game(&game_id, &mut active, &mut open_games, &settings, &time);
+    redraw_coins(&active.board, &mut commands, &theme, &coins);
+    switched.send(GameSwitched);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn switch_requested_game(
+    mut commands: Commands,
+    mut active: ActiveGame,
+    mut open_games: ResMut<OpenGames>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+    coins: Query<(Entity, &CoinMove)>,
+    mut switched: EventWriter<GameSwitched>,
+) {
+    if open_games.requested.is_none() {
+        return;
+    }
+    let Some(game_id) = open_games.requested.take() else {
+        return;
+    };
+    if game_id == active.game_state.game_id {
+        return;
+    }
+
+    info!("switching to game {}", game_id);
+    platform::push_url(&format!("{}{}", platform::origin(), game_id));
+    switch_game(&game_id, &mut active, &mut open_games, &settings, &time);
+    redraw_coins(&active.board, &mut commands, &theme, &coins);
+    switched.send(GameSwitched);
+}
+
+// the game on the board is parked unless it's a practice game, the other one is taken
+// out of the parked games or connected
+fn switch_game(
+    game_id: &str,
+    active: &mut ActiveGame,
+    open_games: &mut OpenGames,
+    settings: &Settings,
+    time: &Time,
+) {
+    let previous = match open_games.take(game_id) {
+        Some(parked) => active.swap(parked),
+        None => {
+            let fresh = ParkedGame::fresh(&active.game_state);
+            let previous = active.swap(fresh);
+            connect_game(
+                &mut active.network_stuff,
+                &mut active.game_state,
+                &mut active.processed,
+                &mut active.board,
+                settings,
+            );
+            previous
+        }
+    };
+
+    if previous.game_state.ghost.is_none() && previous.game_state.transport.is_some() {
+        open_games.park(previous);
+    }
+
+    // the coins are redrawn in their slots, and a move that waited for its
+    // acknowledgement gets its retries again
+    active.board.in_progress = false;
+    active.pending_ack.restart(time.elapsed_seconds_f64());
+}
+
+// the moves of a parked game wait in its backlog, the switcher shows whose turn it is
+fn watch_parked_games(mut open_games: ResMut<OpenGames>) {
+    let mut moved = false;
+
+    for parked in open_games.bypass_change_detection().parked.iter_mut() {
+        let Some(ref mut receive_rx) = parked.network_stuff.read else {
+            continue;
+        };
+
+        while let Ok(Some(event)) = receive_rx.try_next() {
+            let opponent_move = event.pubkey != parked.game_state.nostr_keys.public_key()
+                && matches!(
+                    serde_json::from_str::<NetworkMessage>(&event.content),
+                    Ok(NetworkMessage::Input(_)
+                        | NetworkMessage::Pop(_)
+                        | NetworkMessage::Anvil(_)
+                        | NetworkMessage::Bomb(_))
+                );
+
+            if opponent_move && !parked.moved {
+                parked.moved = true;
+                moved = true;
+
+                if parked.game_state.correspondence {
+                    storage::save_ongoing_game(OngoingGame {
+                        game_id: parked.game_state.game_id.clone(),
+                        opponent: parked.game_state.p2_ln_address.clone(),
+                        your_turn: true,
+                        last_move_at: Some(event.created_at.as_u64()),
+                    });
+                }
+            }
+
+            parked.network_stuff.backlog.push_back(event);
+        }
+    }
+
+    if moved {
+        open_games.set_changed();
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Tab {
+    game_id: String,
+    label: String,
+    active: bool,
+}
+
+fn tab_label(opponent: Option<&str>, your_turn: bool, locale: &Locale) -> String {
+    let marker = if your_turn { " ●" } else { "" };
+    let opponent = opponent.unwrap_or(locale.get(Phrase::Player2));
+    format!("{}{}", locale.fill(Phrase::Versus, &[opponent]), marker)
+}
+
+// the game on the board, the parked games and the correspondence games not open yet
+fn tabs(
+    open_games: &OpenGames,
+    game_state: &GameState,
+    board: &Board,
+    locale: &Locale,
+) -> Vec<Tab> {
+    let player = game_state.player_type;
+    let mut tabs = vec![Tab {
+        game_id: game_state.game_id.clone(),
+        label: tab_label(
+            game_state.p2_ln_address.as_deref(),
+            game_state.start
+                && board.player_turn == player
+                && board.winner.is_none()
+                && !board.draw,
+            locale,
+        ),
+        active: true,
+    }];
+
+    for parked in open_games.parked.iter().rev() {
+        tabs.push(Tab {
+            game_id: parked.game_state.game_id.clone(),
+            label: tab_label(
+                parked.game_state.p2_ln_address.as_deref(),
+                parked.your_turn(),
+                locale,
+            ),
+            active: false,
+        });
+    }
+
+    for ongoing in storage::ongoing_games() {
+        if tabs.iter().all(|tab| tab.game_id != ongoing.game_id) {
+            tabs.push(Tab {
+                label: tab_label(ongoing.opponent.as_deref(), ongoing.your_turn, locale),
+                game_id: ongoing.game_id,
+                active: false,
+            });
+        }
+    }
+
+    tabs
+}
+
+fn spawn_switcher(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                flex_wrap: FlexWrap::Wrap,
+                column_gap: Val::Px(6.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(GameSwitcherUi);
+}
+
+// the row is only shown with more than one game to switch between
+fn update_switcher(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    open_games: Res<OpenGames>,
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    locale: Res<Locale>,
+    switcher: Query<(Entity, Ref<GameSwitcherUi>)>,
+    mut shown: Local<Vec<Tab>>,
+) {
+    let Ok((switcher, spawned)) = switcher.get_single() else {
+        return;
+    };
+    if !spawned.is_added()
+        && !open_games.is_changed()
+        && !game_state.is_changed()
+        && !board.is_changed()
+        && !locale.is_changed()
+    {
+        return;
+    }
+
+    let mut tabs = tabs(&open_games, &game_state, &board, &locale);
+    if tabs.len() < 2 {
+        tabs.clear();
+    }
+    if *shown == tabs && !spawned.is_added() {
+        return;
+    }
+
+    commands.entity(switcher).despawn_descendants();
+    commands.entity(switcher).with_children(|parent| {
+        for tab in tabs.iter() {
+            let background = if tab.active {
+                theme.ui.highlight
+            } else {
+                theme.ui.button
+            };
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: background.into(),
+                    ..default()
+                })
+                .insert(GameTab(tab.game_id.clone()))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        tab.label.clone(),
+                        TextStyle {
+                            color: theme.ui.text,
+                            font_size: 16.0,
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+    *shown = tabs;
+}
+
+fn click_game_tab(
+    tabs: Query<(&Interaction, &GameTab), Changed<Interaction>>,
+    mut open_games: ResMut<OpenGames>,
+) {
+    if let Some((_, GameTab(game_id))) = tabs
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    {
+        open_games.requested = Some(game_id.clone());
+    }
+}
+
+fn cleanup_switcher(mut commands: Commands, switcher: Query<Entity, With<GameSwitcherUi>>) {
+    for entity in switcher.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// in a game the switch happens on the board, from the menu the game is opened
+fn check_page_switch(
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut open_games: ResMut<OpenGames>,
+) {
+    let Some(game_id) = SWITCH_TO
+        .lock()
+        .ok()
+        .and_then(|mut switch_to| switch_to.take())
+    else {
+        return;
+    };
+
+    if *state.get() == AppState::InGame {
+        open_games.requested = Some(game_id);
+    } else {
+        platform::push_url(&format!("{}{}", platform::origin(), game_id));
+        next_state.set(AppState::InGame);
+    }
+}
+
+// the page's list of ongoing games and the next game of a series switch through it
+#[wasm_bindgen(js_name = switchGame)]
+pub fn switch_to_game(game_id: String) {
+    if let Ok(mut switch_to) = SWITCH_TO.lock() {
+        *switch_to = Some(game_id);
+    }
+}
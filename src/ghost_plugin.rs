@@ -0,0 +1,251 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use bevy::prelude::*;
+use nostr_sdk::serde_json;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    engine::{self, ColumnSearch, Difficulty},
+    events::{MoveInput, MoveMade, MoveSource},
+    locale_plugin::{Locale, Phrase},
+    platform,
+    resources::{Board, GameState, Ghost, PlayerMove},
+    settings_plugin::Settings,
+    storage::{self, ArchivedMoves},
+    theme_plugin::Theme,
+    AppState,
+};
+
+const GHOST_THINK_TIME: f64 = 0.6;
+
+static GHOST_CALLED: Mutex<Option<String>> = Mutex::new(None);
+static ENGINE_CALLED: AtomicBool = AtomicBool::new(false);
+
+// the difficulty of a game against the engine alone, from the settings when it started,
+// and the seed of its random moves. a ghost past its recorded games and a puzzle's
+// defender play the engine's usual search
+#[derive(Resource, Default)]
+struct EngineLevel {
+    difficulty: Option<Difficulty>,
+    seed: u64,
+}
+
+// the deep search of the engine's move at that many moves, one column a frame
+#[derive(Resource, Default)]
+struct DeepSearch(Option<(usize, ColumnSearch)>);
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EngineLevel>()
+            .init_resource::<DeepSearch>()
+            .add_systems(Update, check_ghost_system.run_if(in_state(AppState::Menu)))
+            .add_systems(
+                Update,
+                ghost_move
+                    .in_set(MoveInput)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), reset_engine_level);
+    }
+}
+
+fn check_ghost_system(
+    mut game_state: ResMut<GameState>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut level: ResMut<EngineLevel>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+) {
+    let name = match GHOST_CALLED.lock() {
+        Ok(mut called) => called.take(),
+        Err(e) => {
+            error!("Error reading ghost request: {}", e);
+            None
+        }
+    };
+
+    // against the engine alone the ghost has no recorded games
+    if ENGINE_CALLED.swap(false, Ordering::SeqCst) {
+        info!("practicing against the engine, {:?}", settings.difficulty);
+        *level = EngineLevel {
+            difficulty: Some(settings.difficulty),
+            seed: (platform::random() * u32::MAX as f64) as u64,
+        };
+        game_state.player_type = 1;
+        let name = locale.fill(
+            Phrase::EngineName,
+            &[locale.difficulty(settings.difficulty)],
+        );
+        game_state.p2_ln_address = Some(name.clone());
+        game_state.start = true;
+        game_state.ghost = Some(Ghost {
+            name,
+            player: 2,
+            games: Vec::new(),
+        });
+
+        next_state.set(AppState::InGame);
+        return;
+    }
+
+    let Some(name) = name else {
+        return;
+    };
+
+    let records: Vec<ArchivedMoves> = archived_moves()
+        .into_iter()
+        .filter(|record| record.opponent == name)
+        .collect();
+
+    // the ghost keeps the color it had in your latest game against it
+    let Some(player) = records.last().map(|record| record.opponent_player) else {
+        error!("no archived games against {}", name);
+        return;
+    };
+
+    let games: Vec<Vec<usize>> = records
+        .into_iter()
+        .filter(|record| record.opponent_player == player)
+        .map(|record| record.columns)
+        .collect();
+
+    info!(
+        "practicing against {} as player {} from {} games",
+        name,
+        player,
+        games.len()
+    );
+
+    game_state.player_type = if player == 1 { 2 } else { 1 };
+    game_state.p2_ln_address = Some(name.clone());
+    game_state.start = true;
+    game_state.ghost = Some(Ghost {
+        name,
+        player,
+        games,
+    });
+
+    next_state.set(AppState::InGame);
+}
+
+fn reset_engine_level(mut level: ResMut<EngineLevel>, mut deep_search: ResMut<DeepSearch>) {
+    *level = EngineLevel::default();
+    deep_search.0 = None;
+}
+
+fn archived_moves() -> Vec<ArchivedMoves> {
+    storage::archived_games()
+        .iter()
+        .filter_map(|game_id| storage::load_moves(game_id))
+        .collect()
+}
+
+// the ghost repeats its recorded choice while the game follows one of its games,
+// afterwards the engine takes over
+fn ghost_move(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    theme: Res<Theme>,
+    time: Res<Time>,
+    level: Res<EngineLevel>,
+    mut moves: EventWriter<MoveMade>,
+    mut deep_search: ResMut<DeepSearch>,
+    mut thinking_since: Local<Option<f64>>,
+) {
+    let Some(ref ghost) = game_state.ghost else {
+        return;
+    };
+
+    if board.player_turn != ghost.player
+        || board.in_progress
+        || board.winner.is_some()
+        || board.draw
+    {
+        *thinking_since = None;
+        deep_search.0 = None;
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    if now - *thinking_since.get_or_insert(now) < GHOST_THINK_TIME {
+        return;
+    }
+
+    let recorded_move = ghost.recorded_move(&board.moves);
+    let column = match (recorded_move, level.difficulty) {
+        (Some(column), _) => {
+            info!("{} replays column {}", ghost.name, column);
+            Some(column)
+        }
+        // the deep search goes one column a frame, the ghost thinks until it's done
+        (None, Some(Difficulty::Deep)) => {
+            let moves_searched = board.moves.len();
+            let search = match deep_search.0 {
+                Some((searched, ref mut search)) if searched == moves_searched => search,
+                _ => {
+                    let search = ColumnSearch::deep(&board.moves, ghost.player);
+                    &mut deep_search.0.insert((moves_searched, search)).1
+                }
+            };
+            if search.step() {
+                return;
+            }
+            let column = search.best_column();
+            deep_search.0 = None;
+            column
+        }
+        (None, Some(difficulty)) => {
+            engine::choose_move(&board.moves, ghost.player, difficulty, level.seed)
+        }
+        (None, None) => engine::best_move(&board.moves, ghost.player),
+    };
+    *thinking_since = None;
+
+    let Some(column) = column else {
+        error!("ghost has no legal move");
+        return;
+    };
+
+    let Some(row) = board.landing_row(column) else {
+        error!("ghost played full column {}", column);
+        return;
+    };
+
+    moves.send(MoveMade {
+        index: board.moves.len(),
+        player_move: PlayerMove::new(ghost.player, column, row),
+        source: MoveSource::Ghost,
+        texture: theme.coin(ghost.player).clone(),
+    });
+}
+
+// opponents from archived games, most recent first
+#[wasm_bindgen(js_name = ghostOpponents)]
+pub fn ghost_opponents() -> String {
+    let mut opponents: Vec<String> = Vec::new();
+    for record in archived_moves().into_iter().rev() {
+        if !opponents.contains(&record.opponent) {
+            opponents.push(record.opponent);
+        }
+    }
+
+    serde_json::to_string(&opponents).unwrap()
+}
+
+#[wasm_bindgen]
+pub fn practice_engine() {
+    ENGINE_CALLED.store(true, Ordering::SeqCst);
+}
+
+#[wasm_bindgen]
+pub fn practice_ghost(name: String) {
+    match GHOST_CALLED.lock() {
+        Ok(mut called) => *called = Some(name),
+        Err(e) => error!("Error requesting ghost game: {}", e),
+    }
+}
@@ -1,34 +1,154 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 
-use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
+use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*, sprite::Anchor};
 
-use nostr_sdk::serde_json;
+use nostr_sdk::{serde_json, Timestamp, ToBech32};
 use serde::Serialize;
 
-extern crate js_sys;
-
 use crate::{
-    components::{CoinMove, CoinSlot, DisplayTurn, TextChanges, TopRow},
-    resources::{Board, GameState, PlayerMove},
+    analysis_plugin::analysis_closed,
+    audio_plugin::GameSound,
+    club_plugin,
+    components::{
+        BoardFrame, CoinMove, CoinSlot, DisplayTurn, DropGhost, EdgeGlow, ExplodingCoin,
+        OpponentAvatar, OpponentBadges, OpponentHover, OpponentName, OpponentPresence, PoppedCoin,
+        TextChanges, TimeLapseCoin, TopRow, TurningCoin,
+    },
+    correspondence_plugin::time_ago,
+    events::{GameEnded, MoveInput, MoveMade, MoveSource},
+    focus_plugin::BoardCursor,
+    game::{rng_fingerprint, Game, Modifier, Outcome, ROWS as BOARD_ROWS, TURN_EVERY},
+    invite::Invitation,
+    latency,
+    layout_plugin::Layout,
+    locale_plugin::{Locale, Phrase},
+    messages::{Special, Teams, GAME_ID_ALPHABET},
+    nostr_plugin,
+    performance_plugin::ReducedMode,
+    platform,
+    profiling_plugin::{self, Span},
+    rating::RatingChange,
+    resources::{
+        Board, Desync, GameSession, GameState, NetworkStuff, PendingAck, PlayerMove, Series,
+        UndoState,
+    },
+    settings_plugin::Settings,
+    storage,
+    theme_plugin::{frame_image, Theme},
+    tween_plugin::{Tween, Tweening},
+    username_plugin::entry_closed,
     AppState,
 };
 
 use nanoid::nanoid;
 
 use wasm_bindgen::prelude::*;
-use web_sys::{window, History};
 
-const COIN_SIZE: Vec2 = Vec2::new(40.0, 40.0);
+pub const COIN_SIZE: Vec2 = Vec2::new(40.0, 40.0);
 const COLUMNS: usize = 7;
 const ROWS: usize = 7;
 const SPACING: f32 = 5.0;
+const DROP_SPEED: f32 = 250.0;
+// a spectator's time-lapse of the moves so far takes about this long
+const TIME_LAPSE_SECONDS: f64 = 2.5;
+const MAX_TIME_LAPSE_INTERVAL: f64 = 0.3;
+const TIME_LAPSE_SPEED: f32 = 4.0;
+const TIME_LAPSE_IDLE_FRAMES: usize = 3;
+// a gravity shift turn of the board
+const TURN_SECONDS: f32 = 0.8;
+const GHOST_ALPHA: f32 = 0.35;
+// the frame covers the edge of the coins behind its holes
+const FRAME_OVERLAP: f32 = 1.5;
+const FRAME_MARGIN: usize = 4;
 
 static CEATE_GAME_CALLED: AtomicBool = AtomicBool::new(false);
 static JOIN_GAME_CALLED: AtomicBool = AtomicBool::new(false);
+static RESIGN_CALLED: AtomicBool = AtomicBool::new(false);
+static UNDO_CALLED: AtomicBool = AtomicBool::new(false);
+static SERIES_LENGTH: AtomicUsize = AtomicUsize::new(1);
+static FORTUNE_MODE: AtomicBool = AtomicBool::new(false);
+static POP_OUT_MODE: AtomicBool = AtomicBool::new(false);
+static POWER_UP_MODE: AtomicBool = AtomicBool::new(false);
+static CYLINDER_MODE: AtomicBool = AtomicBool::new(false);
+static GRAVITY_SHIFT_MODE: AtomicBool = AtomicBool::new(false);
+static CORRESPONDENCE_MODE: AtomicBool = AtomicBool::new(false);
+static TEAMS_MODE: AtomicBool = AtomicBool::new(false);
+// the terms of the next invitation link, 0 for none
+static WAGER_SATS: AtomicUsize = AtomicUsize::new(0);
+static SECONDS_PER_MOVE: AtomicUsize = AtomicUsize::new(0);
+// the power up coin the next click plays: 0 none, 1 anvil, 2 bomb
+static ARMED_SPECIAL: AtomicUsize = AtomicUsize::new(0);
+
+// client features worth a badge next to the opponent's name
+const FEATURE_BADGES: [(&str, &str); 4] = [
+    ("timer", "timer"),
+    ("encryption", "encrypted"),
+    ("variants", "variants"),
+    ("series", "series"),
+];
+
+// spectators who open a game under way watch the moves so far drop one after another
+// before the live moves. the stored moves are applied one per frame when the game opens,
+// a few frames without one end the backlog
+// the column under the mouse, a touch or the keyboard cursor
+#[derive(Resource, Default, PartialEq)]
+pub struct HoveredColumn(pub Option<usize>);
+
+#[derive(Resource, Default)]
+struct TimeLapse {
+    waiting: VecDeque<Entity>,
+    queued: usize,
+    next_drop: f64,
+    idle_frames: usize,
+    backlog_over: bool,
+}
+
+impl TimeLapse {
+    fn takes(&self, source: MoveSource, player_type: usize) -> bool {
+        !self.backlog_over && source == MoveSource::Network && player_type == 3
+    }
+
+    fn queue(&mut self, commands: &mut Commands, coin: Entity) {
+        commands
+            .entity(coin)
+            .insert((TimeLapseCoin { waiting: true }, Visibility::Hidden));
+        self.waiting.push_back(coin);
+        self.queued += 1;
+    }
+
+    fn release(commands: &mut Commands, coin: Entity) {
+        if let Some(mut entity) = commands.get_entity(coin) {
+            entity.insert((TimeLapseCoin { waiting: false }, Visibility::Inherited));
+        }
+    }
+
+    // the waiting coins drop at once, before a move that acts on the coins on screen
+    fn finish(&mut self, commands: &mut Commands) {
+        for coin in self.waiting.drain(..) {
+            Self::release(commands, coin);
+        }
+        self.backlog_over = true;
+    }
+}
+
+// a gravity shift turn waits for the coin that caused it to land, then the coins go round
+// with the board for TURN_SECONDS and fall to the new bottom. the result is theirs to show
+#[derive(Resource, Default)]
+struct BoardTurn {
+    coin: Option<Entity>,
+    outcome: Option<Outcome>,
+    // the moves on the board when it turned, any other count drops the turn
+    moves: usize,
+    elapsed: Option<f32>,
+}
 
 #[derive(Serialize)]
 struct ShareData {
     msg: String,
+    post: String,
     moves: Vec<PlayerMove>,
 }
 
@@ -38,52 +158,163 @@ impl Plugin for Connect4GuiPlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<AppState>()
             .insert_resource(Board::new())
+            .init_resource::<TimeLapse>()
+            .init_resource::<BoardTurn>()
+            .init_resource::<HoveredColumn>()
             .add_systems(Startup, (setup, setup_game))
             .add_systems(
                 Update,
                 (check_new_game_system.run_if(in_state(AppState::Menu)),),
             )
+            .add_event::<MoveMade>()
+            .add_event::<GameEnded>()
             .add_systems(
                 Update,
-                (place, move_coin, update_text).run_if(in_state(AppState::InGame)),
+                place
+                    .run_if(analysis_closed)
+                    .run_if(entry_closed)
+                    .in_set(MoveInput)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (show_drop_ghost, show_opponent_hover)
+                    .after(MoveInput)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_moves,
+                    play_time_lapse,
+                    move_coin,
+                    turn_board,
+                    slide_popped_coins,
+                    burst_exploding_coins,
+                    check_resign_system,
+                    end_game,
+                    glow_wrapped_edges,
+                    apply_coin_settings,
+                )
+                    .chain()
+                    .after(MoveInput)
+                    .after(Tweening)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_text,
+                    check_undo_system,
+                    update_opponent_profile,
+                    update_opponent_badges,
+                    update_power_ups,
+                )
+                    .run_if(in_state(AppState::InGame)),
             );
     }
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, theme: Res<Theme>) {
     commands.spawn(Camera2dBundle {
         camera_2d: Camera2d {
-            clear_color: ClearColorConfig::Custom(Color::WHITE),
+            clear_color: ClearColorConfig::Custom(theme.ui.background),
         },
         ..Default::default()
     });
 }
 
-fn check_new_game_system(mut next_state: ResMut<NextState<AppState>>) {
-    if CEATE_GAME_CALLED.load(Ordering::SeqCst) {
-        let alphabet: [char; 31] = [
-            '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j',
-            'k', 'm', 'n', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-        ];
+fn check_new_game_system(
+    mut next_state: ResMut<NextState<AppState>>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    mut resume_checked: Local<bool>,
+) {
+    if !*resume_checked {
+        *resume_checked = true;
+
+        if nostr_plugin::open_handler_link() {
+            return;
+        }
 
-        let game_id = nanoid!(6, &alphabet);
+        let game_id = platform::game_id();
+        if game_id.len() > 1 && GameSession::load(&game_id).is_some() {
+            info!("saved session found for {}, resuming", game_id);
+            next_state.set(AppState::InGame);
+            return;
+        }
+    }
 
-        let location = web_sys::window().unwrap().location();
+    if CEATE_GAME_CALLED.load(Ordering::SeqCst) {
+        let game_id = new_game_id();
+
+        // team games are single games, the seats would have to be taken again every game
+        let teams = TEAMS_MODE.swap(false, Ordering::SeqCst);
+        let series_length = SERIES_LENGTH.swap(1, Ordering::SeqCst);
+        let series_length = if teams { 1 } else { series_length };
+        if teams {
+            let teams = Teams::new(game_state.nostr_keys.public_key());
+            storage::save_teams(&format!("/{}", game_id), &teams);
+        }
+        if series_length > 1 {
+            let series_id = format!("/{}", game_id);
+            Series::new(&series_id, series_length).save(&series_id);
+        }
 
-        let host = location.host().unwrap();
+        // one variant is picked from the list, they don't combine
+        let variant = if POP_OUT_MODE.swap(false, Ordering::SeqCst) {
+            storage::save_pop_out(&format!("/{}", game_id));
+            "popout"
+        } else if POWER_UP_MODE.swap(false, Ordering::SeqCst) {
+            storage::save_power_up(&format!("/{}", game_id));
+            "powerup"
+        } else if CYLINDER_MODE.swap(false, Ordering::SeqCst) {
+            storage::save_cylinder(&format!("/{}", game_id));
+            "cylinder"
+        } else if GRAVITY_SHIFT_MODE.swap(false, Ordering::SeqCst) {
+            storage::save_gravity_shift(&format!("/{}", game_id));
+            "gravity"
+        } else if FORTUNE_MODE.swap(false, Ordering::SeqCst) {
+            storage::save_fortune_seed(&format!("/{}", game_id), new_fortune_seed());
+            "fortune"
+        } else {
+            "classic"
+        };
 
-        let protocol = location.protocol().unwrap();
+        if let Some(club_tag) = club_plugin::take_lobby_club() {
+            storage::save_club(&format!("/{}", game_id), &club_tag);
+        }
 
-        let full_url = format!("{protocol}//{host}/{game_id}");
+        // moves hours apart don't fit a clock
+        let correspondence = CORRESPONDENCE_MODE.swap(false, Ordering::SeqCst);
+        if correspondence {
+            storage::save_correspondence(&format!("/{}", game_id));
+        }
 
-        let history: History = window().unwrap().history().unwrap();
-        history
-            .push_state_with_url(&JsValue::from_str("Create New Game"), "", Some(&full_url))
-            .expect("pushState failed");
+        let invitation = Invitation {
+            variant: variant.to_string(),
+            series_length,
+            wager: Some(WAGER_SATS.swap(0, Ordering::SeqCst) as u64).filter(|&sats| sats > 0),
+            time_control: Some(SECONDS_PER_MOVE.swap(0, Ordering::SeqCst) as u64)
+                .filter(|&seconds| seconds > 0)
+                .or(settings.time_control)
+                .filter(|_| !correspondence),
+        };
+        storage::save_invitation(&format!("/{}", game_id), &invitation);
+
+        let origin = platform::origin();
+
+        // challenge link, the joining player only accepts a game created by this pubkey
+        // with the terms of the invitation
+        let full_url = match game_state.nostr_keys.public_key().to_bech32() {
+            Ok(npub) => format!(
+                "{origin}/{game_id}?challenger={npub}&{}",
+                invitation.query()
+            ),
+            Err(_) => format!("{origin}/{game_id}?{}", invitation.query()),
+        };
 
-        let window = web_sys::window().unwrap();
-        let event = web_sys::CustomEvent::new("urlChanged").unwrap();
-        window.dispatch_event(&event).unwrap();
+        platform::push_url(&full_url);
         next_state.set(AppState::InGame);
 
         CEATE_GAME_CALLED.store(false, Ordering::SeqCst);
@@ -96,10 +327,161 @@ fn check_new_game_system(mut next_state: ResMut<NextState<AppState>>) {
     }
 }
 
-fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn update_opponent_profile(
+    mut network_stuff: ResMut<NetworkStuff>,
+    mut game_state: ResMut<GameState>,
+    mut images: ResMut<Assets<Image>>,
+    mut avatar: Query<(&mut Handle<Image>, &mut Visibility), With<OpponentAvatar>>,
+    mut name: Query<&mut Text, With<OpponentName>>,
+) {
+    let Some(ref mut profile_rx) = network_stuff.profile else {
+        return;
+    };
+
+    while let Ok(Some(profile)) = profile_rx.try_next() {
+        if game_state.player_type == 3 {
+            continue;
+        }
+
+        game_state.p2_pubkey = Some(profile.pubkey);
+
+        if let Some(image) = profile.avatar {
+            let handle = images.add(image);
+            for (mut texture, mut visibility) in avatar.iter_mut() {
+                *texture = handle.clone();
+                *visibility = Visibility::Visible;
+            }
+        }
+
+        if let Some(profile_name) = profile.name {
+            for mut text in name.iter_mut() {
+                text.sections[0].value = profile_name.clone();
+            }
+        }
+    }
+}
+
+fn update_opponent_badges(
+    game_state: Res<GameState>,
+    mut badges: Query<&mut Text, With<OpponentBadges>>,
+) {
+    if !game_state.is_changed() {
+        return;
+    }
+
+    let Some(ref client_info) = game_state.p2_client else {
+        return;
+    };
+
+    let mut value = format!("{} v{}", client_info.name, client_info.version);
+    for (feature, badge) in FEATURE_BADGES {
+        if client_info
+            .features
+            .iter()
+            .any(|supported| supported == feature)
+        {
+            value.push_str(&format!(" [{}]", badge));
+        }
+    }
+
+    for mut text in badges.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn check_resign_system(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut game_ended: EventWriter<GameEnded>,
+) {
+    if RESIGN_CALLED.load(Ordering::SeqCst) {
+        if game_state.start
+            && (game_state.player_type == 1 || game_state.player_type == 2)
+            && board.winner.is_none()
+            && !board.draw
+        {
+            game_ended.send(GameEnded {
+                winner: if game_state.player_type == 1 {
+                    Some(2)
+                } else {
+                    Some(1)
+                },
+                resigned: Some(game_state.player_type),
+            });
+            game_state.clone().send_resign();
+        }
+
+        RESIGN_CALLED.store(false, Ordering::SeqCst);
+    }
+}
+
+// only your own last move can be taken back, and only while the game is running
+fn check_undo_system(board: Res<Board>, game_state: Res<GameState>, mut undo: ResMut<UndoState>) {
+    if !UNDO_CALLED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    let index = board.moves.len().saturating_sub(1);
+    let own_last_move = board
+        .moves
+        .last()
+        .is_some_and(|player_move| player_move.player == game_state.player_type);
+
+    // popped coins can't be taken back, a teammate's move isn't the player's to take back
+    if !game_state.start
+        || !board.classic()
+        || game_state.teams.is_some()
+        || !own_last_move
+        || board.winner.is_some()
+        || board.draw
+        || undo.sent == Some(index)
+    {
+        info!("nothing to undo");
+        return;
+    }
+
+    info!("requesting undo of move {}", index);
+    undo.sent = Some(index);
+    game_state.clone().send_undo_request(index);
+}
+
+fn setup_game(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    layout: Res<Layout>,
+    mut images: ResMut<Assets<Image>>,
+) {
     let offset_x = -COIN_SIZE.x * (COLUMNS as f32) / 2.0;
     let offset_y = -COIN_SIZE.y * (ROWS as f32) / 2.0;
 
+    // the front of the board covers the rows coins land in, between the coins and the
+    // explosions, the row above it keeps the hover coin
+    let cell = COIN_SIZE.x + SPACING;
+    let frame = frame_image(
+        COLUMNS,
+        ROWS - 1,
+        cell as usize,
+        COIN_SIZE.x / 2.0 - FRAME_OVERLAP,
+        FRAME_MARGIN,
+    );
+    let frame_size = Vec2::new(COLUMNS as f32 * cell, (ROWS - 1) as f32 * cell)
+        + Vec2::splat(2.0 * FRAME_MARGIN as f32);
+    let frame_center = (slot_position(0, 0) + slot_position(COLUMNS - 1, ROWS - 2)) / 2.0;
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: theme.board,
+                custom_size: Some(frame_size),
+                ..default()
+            },
+            texture: images.add(frame),
+            transform: Transform::from_translation(frame_center.truncate().extend(1.5)),
+            ..default()
+        })
+        .insert(BoardFrame);
+
     for column in 0..COLUMNS {
         for row in 0..ROWS {
             if row != 6 {
@@ -109,7 +491,7 @@ fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
                             custom_size: Some(COIN_SIZE),
                             ..default()
                         },
-                        texture: asset_server.load("white_circle.png"),
+                        texture: theme.empty.clone(),
                         transform: Transform::from_xyz(
                             offset_x + column as f32 * (COIN_SIZE.x + SPACING),
                             offset_y + row as f32 * (COIN_SIZE.y + SPACING),
@@ -125,7 +507,7 @@ fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
                             custom_size: Some(COIN_SIZE),
                             ..default()
                         },
-                        texture: asset_server.load("white_circle.png"),
+                        texture: theme.empty.clone(),
                         transform: Transform::from_xyz(
                             offset_x + column as f32 * (COIN_SIZE.x + SPACING),
                             offset_y + row as f32 * (COIN_SIZE.y + SPACING),
@@ -140,10 +522,36 @@ fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
         }
     }
 
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(COIN_SIZE),
+                color: Color::WHITE.with_a(GHOST_ALPHA),
+                ..default()
+            },
+            texture: theme.empty.clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(DropGhost);
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(COIN_SIZE),
+                color: Color::WHITE.with_a(GHOST_ALPHA),
+                ..default()
+            },
+            texture: theme.empty.clone(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(OpponentHover);
+
     let game_text = Text::from_sections([TextSection::new(
         String::new(),
         TextStyle {
-            color: Color::BLACK,
+            color: theme.ui.text,
             font_size: 18.0,
             ..Default::default()
         },
@@ -173,9 +581,71 @@ fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
                 .insert(TextChanges);
         });
 
-    let window = web_sys::window().unwrap();
-    let event = web_sys::CustomEvent::new("wasmLoaded").unwrap();
-    window.dispatch_event(&event).unwrap();
+    let name_text = Text::from_sections([TextSection::new(
+        String::new(),
+        TextStyle {
+            color: theme.ui.muted,
+            font_size: 14.0,
+            ..Default::default()
+        },
+    )]);
+
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(24.0, 24.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(layout.opponent_anchor().extend(1.0)),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(OpponentAvatar);
+
+    commands
+        .spawn(Text2dBundle {
+            text: name_text.with_alignment(TextAlignment::Left),
+            text_anchor: Anchor::CenterLeft,
+            transform: Transform::from_translation(layout.opponent_name().extend(1.0)),
+            ..Default::default()
+        })
+        .insert(OpponentName);
+
+    let badges_text = Text::from_sections([TextSection::new(
+        String::new(),
+        TextStyle {
+            color: Color::GRAY,
+            font_size: 10.0,
+            ..Default::default()
+        },
+    )]);
+
+    commands
+        .spawn(Text2dBundle {
+            text: badges_text.with_alignment(TextAlignment::Left),
+            text_anchor: Anchor::CenterLeft,
+            transform: Transform::from_translation(layout.opponent_badges().extend(1.0)),
+            ..Default::default()
+        })
+        .insert(OpponentBadges);
+
+    let presence_text = Text::from_sections([TextSection::new(
+        String::new(),
+        TextStyle {
+            color: theme.ui.muted,
+            font_size: 10.0,
+            ..Default::default()
+        },
+    )]);
+
+    commands
+        .spawn(Text2dBundle {
+            text: presence_text.with_alignment(TextAlignment::Left),
+            text_anchor: Anchor::CenterLeft,
+            transform: Transform::from_translation(layout.opponent_presence().extend(1.0)),
+            ..Default::default()
+        })
+        .insert(OpponentPresence);
 }
 
 #[allow(clippy::too_many_arguments, clippy::type_complexity)]
@@ -185,12 +655,17 @@ fn place(
     mut board_pos: Query<(&CoinSlot, &mut Sprite, &Transform, &mut Visibility)>,
     windows: Query<&Window>,
     camera_query: Query<(&Camera, &GlobalTransform)>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
     mut update_sprite: Query<&mut Handle<Image>, (With<TopRow>, Without<DisplayTurn>)>,
-    mut board: ResMut<Board>,
-    game_state: ResMut<GameState>,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut moves: EventWriter<MoveMade>,
+    cursor: Res<BoardCursor>,
+    mut hovered: ResMut<HoveredColumn>,
+    locale: Res<Locale>,
+    mut result_shared: Local<HashSet<String>>,
 ) {
+    let _span = profiling_plugin::span(Span::Place);
     let (camera, camera_transform) = camera_query.single();
 
     let get_position = |cursor_position: Vec2, window: &Window| {
@@ -210,6 +685,7 @@ fn place(
     };
 
     let mut hovered_column: Option<usize> = None;
+    let mut hovered_row: Option<usize> = None;
 
     if let Some(window) = windows.iter().next() {
         if let Some(cursor) = window.cursor_position() {
@@ -218,6 +694,7 @@ fn place(
             for (coin, _, transform, _) in board_pos.iter() {
                 if position.distance(transform.translation.truncate()) < 20.0 {
                     hovered_column = Some(coin.c);
+                    hovered_row = Some(coin.r);
                     break;
                 }
             }
@@ -230,330 +707,1180 @@ fn place(
             for (coin, _, transform, _) in board_pos.iter() {
                 if position.distance(transform.translation.truncate()) < 20.0 {
                     hovered_column = Some(coin.c);
+                    hovered_row = Some(coin.r);
                     break;
                 }
             }
         }
     }
 
-    #[allow(clippy::collapsible_if)]
-    if (board.winner.is_some() || board.draw) && game_state.player_type != 3 {
-        let location = web_sys::window().unwrap().location();
-        let full_url = location.href().unwrap();
-
-        if board.winner == Some(game_state.player_type) {
-            let msg = if let Some(ref address) = game_state.p2_ln_address {
-                format!("I beat {} at #connect4\n\n\n{}\n\n", address, full_url)
-            } else {
-                format!(
-                    "I beat an unknown player at #connect4\n\n\n{}\n\n",
-                    full_url
-                )
-            };
-
-            let share_data = ShareData {
-                msg,
-                moves: board.moves.clone(),
-            };
-
-            let send_board = serde_json::to_string(&share_data).unwrap();
-
-            let mut event_init = web_sys::CustomEventInit::new();
+    // the keyboard aims while the mouse and touches don't, up aims at the bottom coin
+    if hovered_column.is_none() {
+        hovered_column = cursor.column;
+        if cursor.pop {
+            hovered_row = Some(0);
+        }
+    }
+    hovered.set_if_neq(HoveredColumn(hovered_column));
 
-            event_init.detail(&JsValue::from_str(&send_board));
+    // the result is shared with the page once per finished game, not every frame
+    let finished = board.winner.is_some() || board.draw;
+    if !finished {
+        result_shared.remove(&game_state.game_id);
+    }
 
-            let event =
-                web_sys::CustomEvent::new_with_event_init_dict("send_board", &event_init).unwrap();
+    if finished && game_state.player_type != 3 && !result_shared.contains(&game_state.game_id) {
+        result_shared.insert(game_state.game_id.clone());
+        let full_url = platform::current_url();
 
-            web_sys::window().unwrap().dispatch_event(&event).unwrap();
+        let opponent = game_state
+            .p2_ln_address
+            .as_deref()
+            .unwrap_or(locale.get(Phrase::UnknownPlayer));
+        let shared = if board.winner == Some(game_state.player_type) {
+            Phrase::SharedWin
         } else {
-            let msg = if let Some(ref address) = game_state.p2_ln_address {
-                format!("I lost to {} at #connect4\n\n{}\n\n", address, full_url)
-            } else {
-                format!(
-                    "I lost to an unknown player at #connect4\n\n{}\n\n",
-                    full_url
-                )
-            };
-
-            let share_data = ShareData {
-                msg,
-                moves: board.moves.clone(),
-            };
-
-            let send_board = serde_json::to_string(&share_data).unwrap();
-
-            let mut event_init = web_sys::CustomEventInit::new();
+            Phrase::SharedLoss
+        };
 
-            event_init.detail(&JsValue::from_str(&send_board));
+        let share_data = ShareData {
+            msg: locale.fill(shared, &[opponent, &full_url]),
+            post: result_post(&board, &game_state, &full_url, &locale),
+            moves: board.moves.clone(),
+        };
 
-            let event =
-                web_sys::CustomEvent::new_with_event_init_dict("send_board", &event_init).unwrap();
+        let send_board = serde_json::to_string(&share_data).unwrap();
 
-            web_sys::window().unwrap().dispatch_event(&event).unwrap();
-        }
+        platform::dispatch_event("send_board", Some(&send_board));
     }
 
     for (coin, mut sprite, _, mut visibility) in board_pos.iter_mut() {
         if Some(coin.c) == hovered_column && board.winner.is_none() {
             if coin.r == 6 && !board.in_progress {
-                *visibility = Visibility::Visible;
+                visibility.set_if_neq(Visibility::Visible);
 
-                if game_state.player_type == 1 {
-                    for mut handle in &mut update_sprite.iter_mut() {
-                        *handle = asset_server.load("red_circle.png");
-                    }
-                } else if game_state.player_type == 2 {
-                    for mut handle in &mut update_sprite.iter_mut() {
-                        *handle = asset_server.load("yellow_circle.png");
-                    }
-                } else {
-                    for mut handle in &mut update_sprite.iter_mut() {
-                        *handle = asset_server.load("white_circle.png");
-                    }
+                let texture = theme.hover(game_state.player_type);
+                for mut handle in update_sprite.iter_mut() {
+                    handle.set_if_neq(texture.clone());
                 }
             } else if coin.r == 6 {
-                *visibility = Visibility::Hidden;
+                visibility.set_if_neq(Visibility::Hidden);
             } else {
-                sprite.color = Color::rgb(0.9, 0.9, 0.9);
+                set_color(&mut sprite, Color::rgb(0.9, 0.9, 0.9));
             }
 
             if board.in_progress {
                 continue;
             }
-            if board.player_turn == game_state.player_type
+            if game_state.own_turn(&board)
                 && (mouse.just_pressed(MouseButton::Left)
                     || mouse.just_pressed(MouseButton::Right)
-                    || touches.iter_just_pressed().any(|_| true))
+                    || touches.iter_just_pressed().any(|_| true)
+                    || cursor.drop
+                    || cursor.pop)
             {
-                let row_pos = board.moves.iter().filter(|m| m.column == coin.c).count();
-                if row_pos <= 5 {
-                    let player_move = PlayerMove::new(game_state.player_type, coin.c, row_pos);
-                    board.moves.push(player_move);
-
-                    game_state.clone().send_input(coin.c);
-
-                    let offset_x = -COIN_SIZE.x * (COLUMNS as f32) / 2.0;
-                    let offset_y = -COIN_SIZE.y * (ROWS as f32) / 2.0;
-
-                    if board.player_turn == 1 {
-                        commands
-                            .spawn(SpriteBundle {
-                                sprite: Sprite {
-                                    custom_size: Some(COIN_SIZE),
-                                    ..Default::default()
-                                },
-                                texture: asset_server.load("red_circle.png"),
-                                transform: Transform::from_xyz(
-                                    offset_x + coin.c as f32 * (COIN_SIZE.x + SPACING),
-                                    offset_y + 6_f32 * (COIN_SIZE.y + SPACING),
-                                    1.0,
-                                ),
-                                ..Default::default()
-                            })
-                            .insert(CoinMove::new(player_move));
-                    } else {
-                        commands
-                            .spawn(SpriteBundle {
-                                sprite: Sprite {
-                                    custom_size: Some(COIN_SIZE),
-                                    ..Default::default()
-                                },
-                                texture: asset_server.load("yellow_circle.png"),
-                                transform: Transform::from_xyz(
-                                    offset_x + coin.c as f32 * (COIN_SIZE.x + SPACING),
-                                    offset_y + 6_f32 * (COIN_SIZE.y + SPACING),
-                                    1.0,
-                                ),
-                                ..Default::default()
-                            })
-                            .insert(CoinMove::new(player_move));
+                let Ok(game) = Game::from_board(&board) else {
+                    break;
+                };
+
+                // an armed power up is played instead of a coin, and disarmed either way
+                if let Some(special) = armed_special() {
+                    ARMED_SPECIAL.store(0, Ordering::SeqCst);
+                    match game.check_special(game_state.player_type, special, coin.c) {
+                        Ok(row_pos) => moves.send(MoveMade {
+                            index: board.moves.len(),
+                            player_move: PlayerMove::power_up(
+                                game_state.player_type,
+                                coin.c,
+                                row_pos,
+                                special,
+                            ),
+                            source: MoveSource::Local,
+                            texture: theme.coin(game_state.player_type).clone(),
+                        }),
+                        Err(e) => info!("can't play the {}: {}", special, e),
                     }
-                    board.player_turn = if board.player_turn == 1 { 2 } else { 1 };
+
+                    break;
+                }
+
+                // in pop out, pressing your own bottom coin pops it
+                let pop = hovered_row == Some(0)
+                    && game.check_pop(game_state.player_type, coin.c).is_ok();
+                if pop {
+                    moves.send(MoveMade {
+                        index: board.moves.len(),
+                        player_move: PlayerMove::popped(game_state.player_type, coin.c),
+                        source: MoveSource::Local,
+                        texture: theme.coin(game_state.player_type).clone(),
+                    });
+
+                    break;
+                }
+
+                if let Ok(row_pos) = game.check_move(game_state.player_type, coin.c) {
+                    moves.send(MoveMade {
+                        index: board.moves.len(),
+                        player_move: PlayerMove::new(game_state.player_type, coin.c, row_pos),
+                        source: MoveSource::Local,
+                        texture: theme.coin(game_state.player_type).clone(),
+                    });
 
                     break;
                 }
             }
         } else if coin.r == 6 {
-            *visibility = Visibility::Hidden;
+            visibility.set_if_neq(Visibility::Hidden);
         } else {
-            sprite.color = Color::WHITE;
+            set_color(&mut sprite, Color::WHITE);
         }
     }
 }
 
+// only written when it changes, so change detection stays quiet
+fn set_color(sprite: &mut Mut<Sprite>, color: Color) {
+    if sprite.color != color {
+        sprite.color = color;
+    }
+}
+
+// each drop is a tween to the coin's slot, the move counts once the coin first lands
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn move_coin(
-    mut coin_query: Query<(&mut CoinMove, &mut Transform)>,
-    board_pos: Query<(&CoinSlot, &Transform), Without<CoinMove>>,
+    mut commands: Commands,
+    mut coin_query: Query<
+        (
+            Entity,
+            &mut CoinMove,
+            &mut Transform,
+            Option<&Tween>,
+            Option<&TimeLapseCoin>,
+        ),
+        Without<TurningCoin>,
+    >,
     mut board: ResMut<Board>,
-    time: Res<Time>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    reduced: Res<ReducedMode>,
+    mut sounds: EventWriter<GameSound>,
+    mut game_ended: EventWriter<GameEnded>,
 ) {
-    for (mut coin, mut coin_transform) in coin_query.iter_mut() {
-        for (coin_pos, board_transform) in board_pos.iter() {
-            if coin.player_move.column == coin_pos.c && coin.player_move.row == coin_pos.r {
-                let target = Vec3::new(
-                    board_transform.translation.x,
-                    board_transform.translation.y,
-                    1.,
-                );
-                let mut current = Vec3::new(
-                    coin_transform.translation.x,
-                    coin_transform.translation.y,
-                    1.,
-                );
-
-                if current.y > target.y {
-                    current.y -= 1.0 * 250.0 * time.delta_seconds();
-                    board.in_progress = true;
-                } else if !coin.reached_target {
-                    check_win(&mut board);
-                    if board.winner.is_none() && is_draw(&board) {
-                        board.draw = true;
-                    }
+    let _span = profiling_plugin::span(Span::MoveCoin);
+    for (entity, mut coin, mut transform, tween, time_lapse) in coin_query.iter_mut() {
+        let speed = match time_lapse {
+            Some(TimeLapseCoin { waiting: true }) => continue,
+            Some(TimeLapseCoin { waiting: false }) => TIME_LAPSE_SPEED,
+            None => 1.0,
+        };
+        let target = slot_position(coin.player_move.column, coin.player_move.row);
 
-                    current.y = target.y;
-                    board.in_progress = false;
+        match tween {
+            Some(tween) if tween.to == target => {
+                if tween.finished() {
+                    commands.entity(entity).remove::<Tween>();
+                }
+                if !tween.landed() {
+                    board.in_progress = true;
+                    continue;
+                }
+            }
+            _ if coin.reached_target => continue,
+            // a new coin, or one a pop or a bomb sent further down
+            _ if transform.translation.y > target.y => {
+                let seconds = (transform.translation.y - target.y)
+                    / (speed * settings.animation_speed * reduced.drop_speed() * DROP_SPEED);
+                commands.entity(entity).insert(Tween::new(
+                    transform.translation,
+                    target,
+                    seconds,
+                    reduced.easing(settings.drop_style),
+                ));
+                board.in_progress = true;
+                continue;
+            }
+            _ => transform.translation = target,
+        }
 
-                    coin.reached_target = true;
+        if coin.reached_target {
+            continue;
+        }
+        board.in_progress = false;
+        coin.reached_target = true;
+
+        sounds.send(GameSound::CoinDrop);
+
+        match coin.outcome {
+            Outcome::Win(winner) => game_ended.send(GameEnded {
+                winner: Some(winner),
+                resigned: None,
+            }),
+            Outcome::Draw => game_ended.send(GameEnded {
+                winner: None,
+                resigned: None,
+            }),
+            Outcome::Ongoing => {
+                if game_state.own_turn(&board) && coin.player_move.player != game_state.player_type
+                {
+                    sounds.send(GameSound::YourTurn);
                 }
+            }
+        }
+    }
+}
+
+// a faint coin in the slot the hovered column would drop into
+fn show_drop_ghost(
+    hovered: Res<HoveredColumn>,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut ghost: Query<(&mut Transform, &mut Visibility, &mut Handle<Image>), With<DropGhost>>,
+) {
+    if !hovered.is_changed() && !board.is_changed() && !settings.is_changed() && !theme.is_changed()
+    {
+        return;
+    }
+    let Ok((mut transform, mut visibility, mut texture)) = ghost.get_single_mut() else {
+        return;
+    };
+
+    let player = game_state.player_type;
+    let landing = match hovered.0 {
+        Some(column)
+            if settings.drop_preview
+                && (player == 1 || player == 2)
+                && !board.in_progress
+                && board.winner.is_none() =>
+        {
+            Game::from_board(&board)
+                .ok()
+                .and_then(|game| game.check_move(player, column).ok())
+                .map(|row| slot_position(column, row))
+        }
+        _ => None,
+    };
+
+    let Some(landing) = landing else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    transform.translation = landing.truncate().extend(0.5);
+    texture.set_if_neq(theme.coin(player).clone());
+    visibility.set_if_neq(Visibility::Visible);
+}
+
+// a faint coin of the opponent's above the column they aim at during their turn
+fn show_opponent_hover(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    theme: Res<Theme>,
+    mut hover: Query<(&mut Transform, &mut Visibility, &mut Handle<Image>), With<OpponentHover>>,
+) {
+    if !board.is_changed() && !game_state.is_changed() && !theme.is_changed() {
+        return;
+    }
+    let Ok((mut transform, mut visibility, mut texture)) = hover.get_single_mut() else {
+        return;
+    };
+
+    let player = game_state.player_type;
+    let opponent = if player == 1 { 2 } else { 1 };
+    let column = game_state.opponent_presence.hover.filter(|_| {
+        (player == 1 || player == 2)
+            && board.player_turn == opponent
+            && !board.in_progress
+            && board.winner.is_none()
+            && !board.draw
+    });
+
+    let Some(column) = column else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    transform.translation = slot_position(column, ROWS - 1).truncate().extend(0.5);
+    texture.set_if_neq(theme.coin(opponent).clone());
+    visibility.set_if_neq(Visibility::Visible);
+}
+
+// the only place moves reach the board, whoever made them
+fn apply_moves(
+    mut commands: Commands,
+    mut board: ResMut<Board>,
+    game_state: Res<GameState>,
+    mut time_lapse: ResMut<TimeLapse>,
+    mut turn: ResMut<BoardTurn>,
+    mut moves: EventReader<MoveMade>,
+    mut coins: Query<(Entity, &mut CoinMove)>,
+) {
+    if moves.is_empty() && !time_lapse.backlog_over && time_lapse.queued > 0 {
+        time_lapse.idle_frames += 1;
+        time_lapse.backlog_over = time_lapse.idle_frames >= TIME_LAPSE_IDLE_FRAMES;
+    }
+
+    for move_made in moves.read() {
+        if move_made.index != board.moves.len() || board.winner.is_some() || board.draw {
+            info!("dropping stale move {}", move_made.index);
+            continue;
+        }
+
+        let mut game = match Game::from_board(&board) {
+            Ok(game) => game,
+            Err(e) => {
+                error!("Error replaying board: {}", e);
+                continue;
+            }
+        };
+
+        if move_made.player_move.player != game.player_turn() {
+            info!("dropping move {} out of turn", move_made.index);
+            continue;
+        }
+
+        let column = move_made.player_move.column;
+        let outcome = match game.play(&move_made.player_move) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                info!("dropping move {}: {}", move_made.index, e);
+                continue;
+            }
+        };
+
+        let player_move = game.moves()[move_made.index];
+        board.moves.push(player_move);
+        latency::rendered(move_made.index);
+        board.player_turn = game.player_turn();
+
+        // pops, specials and modifiers move coins that have to be on screen
+        let lapsed = time_lapse.takes(move_made.source, game_state.player_type)
+            && player_move.special.is_none()
+            && !player_move.pop
+            && game.modifier().is_none();
+        if lapsed {
+            time_lapse.idle_frames = 0;
+        } else if !time_lapse.waiting.is_empty() {
+            time_lapse.finish(&mut commands);
+        }
 
-                coin_transform.translation = current;
+        // a pop spawns no coin, the result is shown once the coins above have dropped
+        if let Some(Special::Anvil) = player_move.special {
+            crush_column(&mut commands, &mut coins, column);
+            spawn_coin(
+                &mut commands,
+                move_made.texture.clone(),
+                player_move,
+                outcome,
+                6,
+            );
+        } else if let Some(Special::Bomb) = player_move.special {
+            explode_bomb(
+                &mut commands,
+                &mut coins,
+                move_made.texture.clone(),
+                player_move,
+                outcome,
+            );
+        } else if player_move.pop {
+            pop_coin(&mut commands, &mut coins, column, outcome);
+        } else if game.modifier() == Some(Modifier::Turn) {
+            let coin = spawn_coin(
+                &mut commands,
+                move_made.texture.clone(),
+                player_move,
+                Outcome::Ongoing,
+                6,
+            );
+            *turn = BoardTurn {
+                coin: Some(coin),
+                outcome: Some(outcome),
+                moves: board.moves.len(),
+                elapsed: None,
+            };
+        } else {
+            let coin = spawn_coin(
+                &mut commands,
+                move_made.texture.clone(),
+                player_move,
+                outcome,
+                6,
+            );
+            if lapsed {
+                time_lapse.queue(&mut commands, coin);
             }
         }
+
+        if let Some(Modifier::PopCoin(column)) = game.modifier() {
+            pop_coin(&mut commands, &mut coins, column, outcome);
+        }
+        board.last_modifier = game.modifier();
     }
 }
 
-fn check_win(board: &mut ResMut<Board>) {
-    if has_winning_move(&board.moves) {
-        board.winner = if board.player_turn == 1 {
-            Some(2)
+// the bottom coin of the column slides out and the coins above fall one slot
+fn pop_coin(
+    commands: &mut Commands,
+    coins: &mut Query<(Entity, &mut CoinMove)>,
+    column: usize,
+    outcome: Outcome,
+) {
+    for (entity, mut coin) in coins.iter_mut() {
+        if coin.player_move.column != column {
+            continue;
+        }
+
+        if coin.player_move.row == 0 {
+            commands
+                .entity(entity)
+                .remove::<(CoinMove, Tween)>()
+                .insert(PoppedCoin);
         } else {
-            Some(1)
-        };
+            coin.player_move.row -= 1;
+            coin.reached_target = false;
+            coin.outcome = outcome;
+        }
+    }
+}
+
+// the anvil crushes every coin in its column, they slide out like popped coins
+fn crush_column(
+    commands: &mut Commands,
+    coins: &mut Query<(Entity, &mut CoinMove)>,
+    column: usize,
+) {
+    for (entity, coin) in coins.iter() {
+        if coin.player_move.column == column {
+            commands
+                .entity(entity)
+                .remove::<(CoinMove, Tween)>()
+                .insert(PoppedCoin);
+        }
+    }
+}
+
+// the bomb goes off in its slot with the coins around it, the coins left in those
+// columns fall down and show the result once they land
+fn explode_bomb(
+    commands: &mut Commands,
+    coins: &mut Query<(Entity, &mut CoinMove)>,
+    texture: Handle<Image>,
+    bomb: PlayerMove,
+    outcome: Outcome,
+) {
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(COIN_SIZE),
+                color: special_tint(bomb.special),
+                ..Default::default()
+            },
+            texture,
+            transform: Transform::from_translation(slot_position(bomb.column, bomb.row)),
+            ..Default::default()
+        })
+        .insert(ExplodingCoin);
+
+    let mut survivors: Vec<(usize, usize, Entity)> = Vec::new();
+    for (entity, coin) in coins.iter() {
+        let (column, row) = (coin.player_move.column, coin.player_move.row);
+        if column.abs_diff(bomb.column) > 1 {
+            continue;
+        }
+
+        if row.abs_diff(bomb.row) <= 1 {
+            commands
+                .entity(entity)
+                .remove::<(CoinMove, Tween)>()
+                .insert(ExplodingCoin);
+        } else {
+            survivors.push((column, row, entity));
+        }
+    }
+
+    // rows by rank in the column, like the rules settle them
+    survivors.sort_unstable();
+    let mut settled = (usize::MAX, 0);
+    for (column, row, entity) in survivors {
+        if settled.0 != column {
+            settled = (column, 0);
+        }
+
+        if let Ok((_, mut coin)) = coins.get_mut(entity) {
+            if row != settled.1 {
+                coin.player_move.row = settled.1;
+                coin.reached_target = false;
+                coin.outcome = outcome;
+            }
+        }
+        settled.1 += 1;
+    }
+}
+
+// the coins go round the board's center, then fall by rank in their new column like the
+// rules settle them. a board that turns full shows its result at once
+fn turn_board(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut turn: ResMut<BoardTurn>,
+    mut board: ResMut<Board>,
+    mut coins: Query<(Entity, &mut CoinMove, &mut Transform, Option<&TurningCoin>)>,
+    mut game_ended: EventWriter<GameEnded>,
+) {
+    let (Some(coin), Some(outcome)) = (turn.coin, turn.outcome) else {
+        return;
+    };
+    // undone, rebuilt or another game, the coins are redrawn where the rules put them
+    if board.moves.len() != turn.moves {
+        *turn = BoardTurn::default();
+        return;
+    }
+
+    let Some(elapsed) = turn.elapsed else {
+        let landed = coins
+            .get(coin)
+            .is_ok_and(|(_, coin, ..)| coin.reached_target);
+        if landed && !board.in_progress {
+            for (entity, _, transform, _) in coins.iter() {
+                commands
+                    .entity(entity)
+                    .remove::<Tween>()
+                    .insert(TurningCoin {
+                        from: transform.translation,
+                    });
+            }
+            turn.elapsed = Some(0.0);
+            board.in_progress = true;
+        }
+        return;
+    };
+
+    let elapsed = elapsed + time.delta_seconds();
+    let progress = (elapsed / TURN_SECONDS).min(1.0);
+    let center = (slot_position(0, 0) + slot_position(COLUMNS - 1, BOARD_ROWS - 1)) / 2.0;
+    let rotation = Quat::from_rotation_z(std::f32::consts::PI * progress);
+    for (_, _, mut transform, turning) in coins.iter_mut() {
+        if let Some(turning) = turning {
+            transform.translation = center + rotation * (turning.from - center);
+        }
+    }
+    if progress < 1.0 {
+        turn.elapsed = Some(elapsed);
+        return;
+    }
+
+    let mut turned: Vec<(usize, usize, Entity)> = coins
+        .iter()
+        .filter(|(.., turning)| turning.is_some())
+        .map(|(entity, coin, ..)| {
+            (
+                COLUMNS - 1 - coin.player_move.column,
+                BOARD_ROWS - 1 - coin.player_move.row,
+                entity,
+            )
+        })
+        .collect();
+    turned.sort_unstable();
+
+    let mut falling = false;
+    let mut settled = (usize::MAX, 0);
+    for (column, row, entity) in turned {
+        if settled.0 != column {
+            settled = (column, 0);
+        }
+
+        commands.entity(entity).remove::<TurningCoin>();
+        if let Ok((_, mut coin, mut transform, _)) = coins.get_mut(entity) {
+            transform.translation = slot_position(column, row);
+            coin.player_move.column = column;
+            coin.player_move.row = settled.1;
+            if row != settled.1 {
+                coin.reached_target = false;
+                coin.outcome = outcome;
+                falling = true;
+            }
+        }
+        settled.1 += 1;
+    }
+
+    board.in_progress = falling;
+    if !falling {
+        match outcome {
+            Outcome::Win(winner) => game_ended.send(GameEnded {
+                winner: Some(winner),
+                resigned: None,
+            }),
+            Outcome::Draw => game_ended.send(GameEnded {
+                winner: None,
+                resigned: None,
+            }),
+            Outcome::Ongoing => {}
+        }
+    }
+    *turn = BoardTurn::default();
+}
+
+// the time-lapse drops its coins one after another, spread over TIME_LAPSE_SECONDS
+fn play_time_lapse(mut commands: Commands, time: Res<Time>, mut time_lapse: ResMut<TimeLapse>) {
+    let now = time.elapsed_seconds_f64();
+    if now < time_lapse.next_drop {
+        return;
+    }
+
+    let Some(coin) = time_lapse.waiting.pop_front() else {
+        return;
+    };
+    TimeLapse::release(&mut commands, coin);
+
+    let interval = TIME_LAPSE_SECONDS / time_lapse.queued as f64;
+    time_lapse.next_drop = now + interval.min(MAX_TIME_LAPSE_INTERVAL);
+}
+
+fn slide_popped_coins(
+    mut commands: Commands,
+    mut popped: Query<(Entity, &mut Transform, &mut Sprite), With<PoppedCoin>>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut sprite) in popped.iter_mut() {
+        transform.translation.y -= 250.0 * time.delta_seconds();
+
+        let alpha = sprite.color.a() - 2.0 * time.delta_seconds();
+        if alpha <= 0.0 {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+fn burst_exploding_coins(
+    mut commands: Commands,
+    mut exploding: Query<(Entity, &mut Transform, &mut Sprite), With<ExplodingCoin>>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut sprite) in exploding.iter_mut() {
+        transform.scale += Vec3::splat(3.0 * time.delta_seconds());
+        transform.translation.z = 2.0;
+
+        let alpha = sprite.color.a() - 2.0 * time.delta_seconds();
+        if alpha <= 0.0 {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
+
+fn end_game(
+    mut board: ResMut<Board>,
+    game_state: Res<GameState>,
+    mut game_ended: EventReader<GameEnded>,
+    mut sounds: EventWriter<GameSound>,
+) {
+    for ended in game_ended.read() {
+        if board.winner.is_some() || board.draw {
+            continue;
+        }
+
+        board.winner = ended.winner;
+        board.resigned = ended.resigned;
+        board.draw = ended.winner.is_none();
+
+        sounds.send(GameSound::result(board.winner, game_state.player_type));
+    }
+}
+
+// both edges pulse while the winning line wraps around the board, a new game clears them
+fn glow_wrapped_edges(
+    mut commands: Commands,
+    board: Res<Board>,
+    mut glows: Query<(Entity, &mut Sprite), With<EdgeGlow>>,
+    time: Res<Time>,
+) {
+    if !board.cylinder || board.winner.is_none() {
+        for (entity, _) in glows.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    if glows.is_empty() && board.is_changed() {
+        let wrapped = Game::from_board(&board).is_ok_and(|game| game.wrapped_win());
+        if wrapped {
+            let (bottom_left, top_right) =
+                (slot_position(0, 0), slot_position(COLUMNS - 1, ROWS - 2));
+            let edge = COIN_SIZE.x / 2.0 + SPACING;
+            for x in [bottom_left.x - edge, top_right.x + edge] {
+                commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(
+                                SPACING * 2.0,
+                                top_right.y - bottom_left.y + COIN_SIZE.y,
+                            )),
+                            color: Color::GOLD,
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(x, (bottom_left.y + top_right.y) / 2.0, 2.0),
+                        ..Default::default()
+                    })
+                    .insert(EdgeGlow);
+            }
+        }
+    }
+
+    let alpha = 0.6 + 0.4 * (time.elapsed_seconds() * 4.0).sin();
+    for (_, mut sprite) in glows.iter_mut() {
+        sprite.color.set_a(alpha);
+    }
+}
+
+// power up coins are darker than the player's coins
+fn special_tint(special: Option<Special>) -> Color {
+    match special {
+        Some(Special::Anvil) => Color::GRAY,
+        Some(Special::Bomb) => Color::rgb(0.3, 0.3, 0.3),
+        None => Color::WHITE,
+    }
+}
+
+// the coin theme tints every coin, new coins get the tint as they are spawned and coins
+// on their way out of the board keep their look. patterns are in the coin textures
+#[allow(clippy::type_complexity)]
+fn apply_coin_settings(
+    settings: Res<Settings>,
+    mut coins: Query<(Ref<CoinMove>, &mut Sprite), (Without<PoppedCoin>, Without<ExplodingCoin>)>,
+) {
+    for (coin, mut sprite) in coins.iter_mut() {
+        if !settings.is_changed() && !coin.is_added() {
+            continue;
+        }
+
+        let alpha = sprite.color.a();
+        sprite.color = settings
+            .coin_theme
+            .tint(special_tint(coin.player_move.special))
+            .with_a(alpha);
+    }
+}
+
+// the centre of a slot, coins are drawn above the board
+pub fn slot_position(column: usize, row: usize) -> Vec3 {
+    let offset_x = -COIN_SIZE.x * (COLUMNS as f32) / 2.0;
+    let offset_y = -COIN_SIZE.y * (ROWS as f32) / 2.0;
+
+    Vec3::new(
+        offset_x + column as f32 * (COIN_SIZE.x + SPACING),
+        offset_y + row as f32 * (COIN_SIZE.y + SPACING),
+        1.0,
+    )
+}
+
+pub fn spawn_coin(
+    commands: &mut Commands,
+    texture: Handle<Image>,
+    player_move: PlayerMove,
+    outcome: Outcome,
+    start_row: usize,
+) -> Entity {
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(COIN_SIZE),
+                color: special_tint(player_move.special),
+                ..Default::default()
+            },
+            texture,
+            transform: Transform::from_translation(slot_position(player_move.column, start_row)),
+            ..Default::default()
+        })
+        .insert(CoinMove::new(player_move, outcome))
+        .id()
+}
+
+// what the last columns of fortune modifier did, in the player's language
+fn modifier_note(modifier: Modifier, locale: &Locale) -> String {
+    match modifier {
+        Modifier::LockColumn(column) => {
+            locale.fill(Phrase::ColumnLocked, &[&(column + 1).to_string()])
+        }
+        Modifier::PopCoin(column) => locale.fill(Phrase::CoinPopped, &[&(column + 1).to_string()]),
+        Modifier::Turn => locale.get(Phrase::BoardTurned).to_string(),
     }
 }
 
-fn is_draw(board: &Board) -> bool {
-    board.moves.len() == 42
+// default text for the kind-1 result note, editable before posting
+fn result_post(board: &Board, game_state: &GameState, url: &str, locale: &Locale) -> String {
+    let opponent = game_state
+        .p2_ln_address
+        .as_deref()
+        .unwrap_or(locale.get(Phrase::UnknownPlayer));
+
+    let result = if board.draw {
+        locale.fill(Phrase::PostDrew, &[opponent])
+    } else if board.winner == Some(game_state.player_type) {
+        locale.fill(Phrase::PostBeat, &[opponent])
+    } else {
+        locale.fill(Phrase::PostLost, &[opponent])
+    };
+
+    locale.fill(
+        Phrase::ResultPost,
+        &[&result, &board.moves.len().to_string(), url],
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_text(
     mut display_turn: Query<&mut Handle<Image>, With<DisplayTurn>>,
-    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
     mut text: Query<&mut Text, With<TextChanges>>,
     board: Res<Board>,
     game_state: Res<GameState>,
+    pending_ack: Res<PendingAck>,
+    desync: Res<Desync>,
+    locale: Res<Locale>,
+    mut shown_special: Local<Option<Special>>,
+    mut shown_minute: Local<Option<u64>>,
 ) {
+    // the hud only depends on these, on most frames none changed and nothing is rebuilt.
+    // the time since the last move of a correspondence game is updated every minute
+    let special = armed_special();
+    let minute = game_state
+        .correspondence
+        .then(|| Timestamp::now().as_u64() / 60);
+    if !board.is_changed()
+        && !game_state.is_changed()
+        && !pending_ack.is_changed()
+        && !desync.is_changed()
+        && !theme.is_changed()
+        && !locale.is_changed()
+        && special == *shown_special
+        && minute == *shown_minute
+    {
+        return;
+    }
+    *shown_special = special;
+    *shown_minute = minute;
+
     if game_state.start {
         check_player_connection_and_hide_button();
     } else {
         hide_new_game_button();
     }
 
-    let new_image: Option<&str>;
+    if game_state.start
+        && (game_state.player_type == 1 || game_state.player_type == 2)
+        && board.winner.is_none()
+        && !board.draw
+    {
+        show_resign_button();
+    } else {
+        hide_resign_button();
+    }
+
+    let turn_coin: Option<usize>;
     let mut new_text_value: String;
 
-    if board.winner.is_some() {
+    if let Some(resigned) = board.resigned {
+        new_text_value = if game_state.player_type == 3 {
+            locale.fill(Phrase::PlayerResigned, &[&resigned.to_string()])
+        } else if resigned == game_state.player_type {
+            locale.get(Phrase::YouResigned).to_string()
+        } else {
+            locale.get(Phrase::OpponentResigned).to_string()
+        };
+        turn_coin = match board.winner {
+            Some(1) => Some(1),
+            Some(2) => Some(2),
+            _ => None,
+        };
+    } else if board.winner.is_some() {
         if board.winner == Some(game_state.player_type) {
             let address_display = match &game_state.local_ln_address {
                 Some(address) => address.clone(),
-                None => "You".to_string(),
+                None => locale.get(Phrase::You).to_string(),
             };
             let enemy_display = match &game_state.p2_ln_address {
                 Some(enemy) => enemy.clone(),
-                None => "Player 2".to_string(),
+                None => locale.get(Phrase::Player2).to_string(),
             };
-            new_text_value = format!("{} beat {}", address_display, enemy_display);
+            new_text_value = locale.fill(Phrase::Beat, &[&address_display, &enemy_display]);
         } else {
             let address_display = match &game_state.local_ln_address {
                 Some(address) => address.clone(),
-                None => "You".to_string(),
+                None => locale.get(Phrase::You).to_string(),
             };
             let enemy_display = match &game_state.p2_ln_address {
                 Some(enemy) => enemy.clone(),
-                None => "Player 2".to_string(),
+                None => locale.get(Phrase::Player2).to_string(),
             };
-            new_text_value = format!("{} lost to {}", address_display, enemy_display);
+            new_text_value = locale.fill(Phrase::LostTo, &[&address_display, &enemy_display]);
         }
 
         if game_state.player_type == 3 {
-            new_text_value = "Game Over!!".to_string();
-            new_image = match board.winner {
-                Some(1) => Some("red_circle.png"),
-                _ => Some("yellow_circle.png"),
+            new_text_value = locale.get(Phrase::GameOver).to_string();
+            turn_coin = match board.winner {
+                Some(1) => Some(1),
+                _ => Some(2),
             };
         } else {
-            new_image = match game_state.player_type {
-                1 => Some("red_circle.png"),
-                2 => Some("yellow_circle.png"),
+            turn_coin = match game_state.player_type {
+                1 => Some(1),
+                2 => Some(2),
                 _ => None,
             };
         }
     } else if board.draw {
         let address_display = match &game_state.local_ln_address {
             Some(address) => address.clone(),
-            None => "You".to_string(),
+            None => locale.get(Phrase::You).to_string(),
         };
         let enemy_display = match &game_state.p2_ln_address {
             Some(enemy) => enemy.clone(),
-            None => "Player 2".to_string(),
+            None => locale.get(Phrase::Player2).to_string(),
         };
-        new_text_value = format!("{} drew against {}", address_display, enemy_display);
-        new_image = None;
+        new_text_value = locale.fill(Phrase::DrewAgainst, &[&address_display, &enemy_display]);
+        turn_coin = None;
     } else if game_state.player_type == 0 {
-        new_text_value = "Waiting for player to join...".to_string();
-        new_image = None;
+        new_text_value = locale.get(Phrase::WaitingForPlayer).to_string();
+        turn_coin = None;
     } else {
-        new_image = match board.player_turn {
-            1 => Some("red_circle.png"),
-            2 => Some("yellow_circle.png"),
+        turn_coin = match board.player_turn {
+            1 => Some(1),
+            2 => Some(2),
             _ => None,
         };
 
         new_text_value = match game_state.player_type {
-            3 => "Spectating".to_string(),
-            _ if board.player_turn == game_state.player_type => {
+            3 => locale.get(Phrase::Spectating).to_string(),
+            _ if game_state.teams.as_ref().is_some_and(|teams| !teams.full()) => {
+                let seated = game_state
+                    .teams
+                    .as_ref()
+                    .map_or(0, |teams| teams.seats.len());
+                locale.fill(
+                    Phrase::WaitingForTeammates,
+                    &[&seated.to_string(), &Teams::SEATS.to_string()],
+                )
+            }
+            _ if game_state.own_turn(&board) => {
                 let address_display = match &game_state.local_ln_address {
                     Some(address) => address.clone(),
                     None => "".to_string(),
                 };
-                format!("Its your turn {}", address_display)
+                locale.fill(Phrase::YourTurn, &[&address_display])
+            }
+            _ if board.player_turn == game_state.player_type => {
+                locale.get(Phrase::TeammateTurn).to_string()
             }
+            _ if pending_ack.overdue => locale.get(Phrase::WaitingForConfirmation).to_string(),
             _ => {
                 let address_display = match &game_state.p2_ln_address {
                     Some(address) => address.clone(),
-                    None => "Player 2".to_string(),
+                    None => locale.get(Phrase::Player2).to_string(),
                 };
-                format!("{}'s turn", address_display)
+                locale.fill(Phrase::TheirTurn, &[&address_display])
             }
         };
+
+        if let Some(p2_rating) = game_state.p2_rating {
+            let change = RatingChange::new(game_state.local_rating, p2_rating);
+            let stakes = locale.fill(
+                Phrase::RatingStakes,
+                &[
+                    &format!("{:+}", change.win),
+                    &format!("{:+}", change.draw),
+                    &format!("{:+}", change.loss),
+                ],
+            );
+            new_text_value = format!("{}\n{}", new_text_value, stakes);
+        }
+
+        if pending_ack.low_delivery {
+            let warning = locale.fill(
+                Phrase::LowDelivery,
+                &[&pending_ack.confirmations.to_string()],
+            );
+            new_text_value = format!("{}\n{}", new_text_value, warning);
+        } else if pending_ack.confirmations > 0 {
+            let confirmed = locale.fill(
+                Phrase::MoveConfirmed,
+                &[&pending_ack.confirmations.to_string()],
+            );
+            new_text_value = format!("{}\n{}", new_text_value, confirmed);
+        }
+    }
+
+    if board.pop_out && board.winner.is_none() && !board.draw && game_state.own_turn(&board) {
+        new_text_value = format!("{}\n{}", new_text_value, locale.get(Phrase::PopOutHint));
+    }
+
+    if board.power_up
+        && board.winner.is_none()
+        && !board.draw
+        && (game_state.player_type == 1 || game_state.player_type == 2)
+    {
+        let left = Game::from_board(&board)
+            .map(|game| game.specials_left(game_state.player_type))
+            .unwrap_or_default();
+        let left: Vec<String> = left.iter().map(|special| special.to_string()).collect();
+        let power_up = match special {
+            Some(special) => locale.fill(Phrase::PowerUpArmed, &[&special.to_string()]),
+            None if left.is_empty() => locale.get(Phrase::PowerUpNoneLeft).to_string(),
+            None => locale.fill(Phrase::PowerUpLeft, &[&left.join(", ")]),
+        };
+        new_text_value = format!("{}\n{}", new_text_value, power_up);
+    }
+
+    if let Some(terms) = game_state
+        .invitation
+        .as_ref()
+        .and_then(|invitation| invitation.terms())
+    {
+        new_text_value = format!("{}\n{}", new_text_value, terms);
+    }
+
+    if board.cylinder && board.winner.is_none() && !board.draw {
+        new_text_value = format!("{}\n{}", new_text_value, locale.get(Phrase::CylinderHint));
+    }
+
+    if board.gravity_shift && board.winner.is_none() && !board.draw {
+        let left = TURN_EVERY - board.moves.len() % TURN_EVERY;
+        let gravity = match left {
+            1 => locale.get(Phrase::GravityNextMove).to_string(),
+            left => locale.fill(Phrase::GravityInMoves, &[&left.to_string()]),
+        };
+        new_text_value = format!("{}\n{}", new_text_value, gravity);
+    }
+
+    if game_state.correspondence {
+        let correspondence = match game_state.last_move_at {
+            Some(at) => locale.fill(Phrase::CorrespondenceLastMove, &[&time_ago(at, &locale)]),
+            None => locale.get(Phrase::CorrespondenceGame).to_string(),
+        };
+        new_text_value = format!("{}\n{}", new_text_value, correspondence);
+    }
+
+    // an older client would reject the variant's moves
+    if !board.classic() {
+        if let Some(ref client_info) = game_state.p2_client {
+            if !client_info
+                .features
+                .iter()
+                .any(|feature| feature == "variants")
+            {
+                new_text_value = format!("{}\n{}", new_text_value, locale.get(Phrase::NoVariants));
+            }
+        }
+    }
+
+    // the same seed would spin other modifiers on the opponent's side
+    if board.fortune_seed.is_some() {
+        let fingerprint = game_state
+            .p2_client
+            .as_ref()
+            .and_then(|client_info| client_info.rng);
+        if fingerprint.is_some_and(|fingerprint| fingerprint != rng_fingerprint()) {
+            new_text_value = format!(
+                "{}\n{}",
+                new_text_value,
+                locale.get(Phrase::DifferentModifiers)
+            );
+        }
+    }
+
+    if let Some(modifier) = board.last_modifier {
+        new_text_value = format!(
+            "{}\n{}",
+            new_text_value,
+            locale.fill(Phrase::Fortune, &[&modifier_note(modifier, &locale)])
+        );
+    }
+
+    if let Some(ref tampering) = desync.tampering {
+        new_text_value = format!(
+            "{}\n{}",
+            new_text_value,
+            locale.fill(Phrase::Tampered, &[tampering])
+        );
     }
 
-    if let Some(image) = new_image {
+    if desync.divergent {
+        new_text_value = format!("{}\n{}", new_text_value, locale.get(Phrase::BoardsDisagree));
+    } else if let Some(ref reason) = desync.reason {
+        new_text_value = format!(
+            "{}\n{}",
+            new_text_value,
+            locale.fill(Phrase::Desynced, &[reason])
+        );
+    }
+
+    if let Some(ref series) = game_state.series {
+        if game_state.player_type == 1 || game_state.player_type == 2 {
+            let score = locale.fill(
+                Phrase::SeriesScore,
+                &[
+                    &series.game.to_string(),
+                    &series.length.to_string(),
+                    &series.local_wins.to_string(),
+                    &series.opponent_wins.to_string(),
+                ],
+            );
+            new_text_value = format!("{}\n{}", new_text_value, score);
+        }
+    }
+
+    if let Some(player) = turn_coin {
+        let texture = theme.coin(player);
         for mut handle in display_turn.iter_mut() {
-            *handle = asset_server.load(image);
+            handle.set_if_neq(texture.clone());
         }
     }
 
     for mut txt in text.iter_mut() {
         if txt.sections[0].value != new_text_value {
-            txt.sections[0].value = new_text_value.to_string();
+            txt.sections[0].value.clone_from(&new_text_value);
         }
     }
 }
 
-fn has_winning_move(moves: &[PlayerMove]) -> bool {
-    moves.iter().any(|move_| move_.is_winner(moves))
-}
-
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     fn hideNewGameButton();
 }
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     fn hideCopyButton();
 }
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn showResignButton();
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn hideResignButton();
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn showPowerUps(anvil: bool, bomb: bool, armed: &str);
+}
+
+// the desktop build has no page buttons to show or hide
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(non_snake_case)]
+mod page {
+    pub fn hideNewGameButton() {}
+    pub fn hideCopyButton() {}
+    pub fn showResignButton() {}
+    pub fn hideResignButton() {}
+    pub fn showPowerUps(_anvil: bool, _bomb: bool, _armed: &str) {}
+}
+#[cfg(not(target_arch = "wasm32"))]
+use page::*;
 
 #[wasm_bindgen]
 pub fn check_player_connection_and_hide_button() {
@@ -565,11 +1892,123 @@ pub fn hide_new_game_button() {
     hideNewGameButton();
 }
 
+#[wasm_bindgen]
+pub fn show_resign_button() {
+    showResignButton();
+}
+
+#[wasm_bindgen]
+pub fn hide_resign_button() {
+    hideResignButton();
+}
+
+// without the leading slash
+pub fn new_game_id() -> String {
+    nanoid!(6, &GAME_ID_ALPHABET)
+}
+
 #[wasm_bindgen]
 pub fn new_game() {
+    new_series(1, "classic".to_string(), 0, 0, false, false);
+}
+// the variant is one of classic, fortune, popout, powerup, cylinder or gravity, a wager of
+// 0 sats or 0 seconds per move leaves them out of the invitation. a team game is a single
+// 2v2 game
+#[wasm_bindgen]
+pub fn new_series(
+    length: usize,
+    variant: String,
+    wager_sats: u32,
+    seconds_per_move: u32,
+    correspondence: bool,
+    teams: bool,
+) {
+    CORRESPONDENCE_MODE.store(correspondence, Ordering::SeqCst);
+    TEAMS_MODE.store(teams, Ordering::SeqCst);
+    SERIES_LENGTH.store(length.max(1), Ordering::SeqCst);
+    WAGER_SATS.store(wager_sats as usize, Ordering::SeqCst);
+    SECONDS_PER_MOVE.store(seconds_per_move as usize, Ordering::SeqCst);
+    FORTUNE_MODE.store(variant == "fortune", Ordering::SeqCst);
+    POP_OUT_MODE.store(variant == "popout", Ordering::SeqCst);
+    POWER_UP_MODE.store(variant == "powerup", Ordering::SeqCst);
+    CYLINDER_MODE.store(variant == "cylinder", Ordering::SeqCst);
+    GRAVITY_SHIFT_MODE.store(variant == "gravity", Ordering::SeqCst);
     CEATE_GAME_CALLED.store(true, Ordering::SeqCst);
 }
+
+fn armed_special() -> Option<Special> {
+    match ARMED_SPECIAL.load(Ordering::SeqCst) {
+        1 => Some(Special::Anvil),
+        2 => Some(Special::Bomb),
+        _ => None,
+    }
+}
+
+// arms a power up coin for the next move, arming it again disarms it
+#[wasm_bindgen]
+pub fn arm_special(special: &str) {
+    let armed = match special {
+        "anvil" => 1,
+        "bomb" => 2,
+        _ => 0,
+    };
+    let current = ARMED_SPECIAL.load(Ordering::SeqCst);
+    ARMED_SPECIAL.store(if current == armed { 0 } else { armed }, Ordering::SeqCst);
+}
+
+// the page shows a button per unused power up coin while it's your turn
+fn update_power_ups(
+    keys: Res<Input<KeyCode>>,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut shown: Local<Option<(bool, bool, Option<Special>)>>,
+) {
+    if keys.just_pressed(KeyCode::Key1) {
+        arm_special("anvil");
+    } else if keys.just_pressed(KeyCode::Key2) {
+        arm_special("bomb");
+    }
+
+    let left = match Game::from_board(&board) {
+        Ok(game) if game_state.own_turn(&board) && !board.in_progress => {
+            game.specials_left(game_state.player_type)
+        }
+        _ => Vec::new(),
+    };
+    if left.is_empty() {
+        ARMED_SPECIAL.store(0, Ordering::SeqCst);
+    }
+
+    let state = (
+        left.contains(&Special::Anvil),
+        left.contains(&Special::Bomb),
+        armed_special(),
+    );
+    if *shown != Some(state) {
+        *shown = Some(state);
+        let armed = state
+            .2
+            .map(|special| special.to_string())
+            .unwrap_or_default();
+        showPowerUps(state.0, state.1, &armed);
+    }
+}
+
+// two random halves, shared with the opponent in the new game event
+pub fn new_fortune_seed() -> u64 {
+    let high = (platform::random() * u32::MAX as f64) as u64;
+    let low = (platform::random() * u32::MAX as f64) as u64;
+    (high << 32) | low
+}
 #[wasm_bindgen]
 pub fn join_game() {
     JOIN_GAME_CALLED.store(true, Ordering::SeqCst);
 }
+#[wasm_bindgen]
+pub fn resign() {
+    RESIGN_CALLED.store(true, Ordering::SeqCst);
+}
+#[wasm_bindgen]
+pub fn request_undo() {
+    UNDO_CALLED.store(true, Ordering::SeqCst);
+}
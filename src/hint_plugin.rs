@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::HintGlow,
+    engine::ColumnSearch,
+    gui_plugin::{slot_position, COIN_SIZE},
+    resources::{Board, GameState},
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+static HINT_CALLED: AtomicBool = AtomicBool::new(false);
+
+// the number of moves when the hint was given, it goes once a move is made
+#[derive(Resource, Default)]
+struct Hint {
+    moves: Option<usize>,
+    // the deep search for the hint asked at that many moves, one column a frame
+    search: Option<(usize, ColumnSearch)>,
+}
+
+// the engine's pick for the local player, glowing in its column until they move. the deep
+// search suggests it, so a hint in a game against another player keeps the game off the
+// leaderboard
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hint>()
+            .add_systems(
+                Update,
+                (show_hint_button, give_hint, update_hint)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), clear_hint);
+    }
+}
+
+// on the player's turn of a classic game, the engine doesn't know the variants
+fn hint_allowed(board: &Board, game_state: &GameState) -> bool {
+    game_state.start
+        && (game_state.player_type == 1 || game_state.player_type == 2)
+        && game_state.own_turn(board)
+        && board.classic()
+        && !board.in_progress
+        && board.winner.is_none()
+        && !board.draw
+}
+
+fn show_hint_button(board: Res<Board>, game_state: Res<GameState>) {
+    if hint_allowed(&board, &game_state) {
+        showHintButton();
+    } else {
+        hideHintButton();
+    }
+}
+
+fn give_hint(
+    mut commands: Commands,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    theme: Res<Theme>,
+    keys: Res<Input<KeyCode>>,
+    mut hint: ResMut<Hint>,
+) {
+    let player = game_state.player_type;
+    let called = HINT_CALLED.swap(false, Ordering::SeqCst) || keys.just_pressed(KeyCode::H);
+    if called
+        && hint.moves != Some(board.moves.len())
+        && hint.search.is_none()
+        && hint_allowed(&board, &game_state)
+    {
+        hint.search = Some((board.moves.len(), ColumnSearch::deep(&board.moves, player)));
+    }
+
+    let Some((moves, search)) = hint.search.as_mut() else {
+        return;
+    };
+    if *moves != board.moves.len() || !hint_allowed(&board, &game_state) {
+        hint.search = None;
+        return;
+    }
+    if search.step() {
+        return;
+    }
+    let column = search.best_column();
+    hint.search = None;
+
+    let Some(column) = column else {
+        return;
+    };
+    let Some(row) = board.landing_row(column) else {
+        return;
+    };
+
+    info!("hint: column {}", column + 1);
+    if game_state.ghost.is_none() {
+        storage::mark_hinted(&game_state.game_id);
+    }
+
+    let mut position = slot_position(column, row);
+    position.z = 1.5;
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(COIN_SIZE),
+                color: Color::WHITE.with_a(0.0),
+                ..Default::default()
+            },
+            texture: theme.coin(player).clone(),
+            transform: Transform::from_translation(position),
+            ..Default::default()
+        })
+        .insert(HintGlow);
+    hint.moves = Some(board.moves.len());
+}
+
+// the hinted coin pulses where it would land
+fn update_hint(
+    mut commands: Commands,
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    time: Res<Time>,
+    mut hint: ResMut<Hint>,
+    mut glows: Query<(Entity, &mut Sprite), With<HintGlow>>,
+) {
+    let Some(moves) = hint.moves else {
+        return;
+    };
+
+    if moves != board.moves.len() || !hint_allowed(&board, &game_state) {
+        for (entity, _) in glows.iter() {
+            commands.entity(entity).despawn();
+        }
+        hint.moves = None;
+        return;
+    }
+
+    let alpha = 0.45 + 0.3 * (time.elapsed_seconds() * 5.0).sin();
+    for (_, mut sprite) in glows.iter_mut() {
+        sprite.color.set_a(alpha);
+    }
+}
+
+fn clear_hint(
+    mut commands: Commands,
+    mut hint: ResMut<Hint>,
+    glows: Query<Entity, With<HintGlow>>,
+) {
+    for entity in glows.iter() {
+        commands.entity(entity).despawn();
+    }
+    hint.moves = None;
+    hint.search = None;
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn showHintButton();
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn hideHintButton();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(non_snake_case)]
+mod page {
+    pub fn showHintButton() {}
+    pub fn hideHintButton() {}
+}
+#[cfg(not(target_arch = "wasm32"))]
+use page::*;
+
+#[wasm_bindgen]
+pub fn request_hint() {
+    HINT_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent, Filter, Kind, Timestamp,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{HistoryGame, HistoryText, HistoryUi},
+    locale_plugin::{Locale, Phrase},
+    messages::{GameResult, NetworkMessage},
+    nostr_plugin::{fetch_events, game_id_from_tags, open_game},
+    platform,
+    resources::{GameState, RESULT_KIND},
+    theme_plugin::Theme,
+    AppState,
+};
+
+const HISTORY_SIZE: usize = 20;
+
+static HISTORY_CALLED: AtomicBool = AtomicBool::new(false);
+
+struct GameSummary {
+    game_id: String,
+    opponent: Option<String>,
+    result: Phrase,
+    played_at: Timestamp,
+}
+
+#[derive(Resource, Default)]
+struct HistoryResults {
+    read: Option<Receiver<Vec<GameSummary>>>,
+}
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HistoryResults>()
+            .add_systems(
+                Update,
+                check_history_system.run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(OnEnter(AppState::History), (setup_history, fetch_history))
+            .add_systems(
+                Update,
+                (update_history, open_history_game).run_if(in_state(AppState::History)),
+            )
+            .add_systems(OnExit(AppState::History), cleanup_history);
+    }
+}
+
+fn check_history_system(mut next_state: ResMut<NextState<AppState>>) {
+    if HISTORY_CALLED.swap(false, Ordering::SeqCst) {
+        next_state.set(AppState::History);
+    }
+}
+
+fn setup_history(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::top(Val::Px(60.0)),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(HistoryUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    locale.get(Phrase::MyGamesLoading),
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(HistoryText);
+        });
+}
+
+fn fetch_history(mut results: ResMut<HistoryResults>, game_state: Res<GameState>) {
+    let (mut history_tx, history_rx) = futures::channel::mpsc::channel::<Vec<GameSummary>>(1);
+    results.read = Some(history_rx);
+
+    let pubkey = game_state.nostr_keys.public_key();
+
+    platform::spawn(async move {
+        let filters = vec![
+            Filter::new().kind(Kind::Regular(4444)).author(pubkey),
+            Filter::new()
+                .kind(Kind::ParameterizedReplaceable(RESULT_KIND))
+                .author(pubkey),
+        ];
+
+        let events = fetch_events(filters, Vec::new()).await;
+        let games = summarize_games(&events, pubkey);
+
+        info!("found {} games", games.len());
+
+        if let Err(e) = history_tx.try_send(games) {
+            error!("Error sending game history: {}", e);
+        }
+    });
+}
+
+// groups our own game and result events by game id, newest game first
+fn summarize_games(events: &[NostrEvent], pubkey: XOnlyPublicKey) -> Vec<GameSummary> {
+    let mut games: BTreeMap<String, GameSummary> = BTreeMap::new();
+
+    for event in events {
+        let game_id = if event.kind == Kind::ParameterizedReplaceable(RESULT_KIND) {
+            serde_json::from_str::<GameResult>(&event.content)
+                .ok()
+                .map(|result| result.game_id)
+        } else {
+            game_id_from_tags(event)
+        };
+
+        let Some(game_id) = game_id else {
+            continue;
+        };
+
+        let summary = games.entry(game_id.clone()).or_insert(GameSummary {
+            game_id,
+            opponent: None,
+            result: Phrase::Unfinished,
+            played_at: event.created_at,
+        });
+        summary.played_at = summary.played_at.max(event.created_at);
+
+        if let Ok(result) = serde_json::from_str::<GameResult>(&event.content) {
+            let (local_player, opponent) = if result.p1_pubkey == pubkey {
+                (1, result.p2_name)
+            } else {
+                (2, result.p1_name)
+            };
+
+            summary.opponent = opponent.or(summary.opponent.take());
+            summary.result = match result.winner {
+                None => Phrase::Drawn,
+                Some(winner) if winner == local_player => Phrase::Won,
+                Some(_) => Phrase::Lost,
+            };
+        } else if let Ok(NetworkMessage::JoinGame(players)) =
+            serde_json::from_str::<NetworkMessage>(&event.content)
+        {
+            summary.opponent = summary.opponent.take().or(players.p1_name);
+        }
+    }
+
+    let mut games: Vec<GameSummary> = games.into_values().collect();
+    games.sort_by_key(|game| std::cmp::Reverse(game.played_at));
+    games
+}
+
+fn update_history(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut results: ResMut<HistoryResults>,
+    ui: Query<Entity, With<HistoryUi>>,
+    mut text: Query<&mut Text, With<HistoryText>>,
+) {
+    let Some(ref mut history_rx) = results.read else {
+        return;
+    };
+
+    let Ok(Some(games)) = history_rx.try_next() else {
+        return;
+    };
+
+    let title = if games.is_empty() {
+        locale.get(Phrase::NoGamesFound)
+    } else {
+        locale.get(Phrase::MyGames)
+    };
+
+    for mut txt in text.iter_mut() {
+        txt.sections[0].value = title.to_string();
+    }
+
+    let Ok(root) = ui.get_single() else {
+        return;
+    };
+
+    commands.entity(root).with_children(|parent| {
+        for game in games.iter().take(HISTORY_SIZE) {
+            let label = locale.fill(
+                Phrase::HistoryEntry,
+                &[
+                    &format_date(game.played_at),
+                    game.opponent
+                        .as_deref()
+                        .unwrap_or(locale.get(Phrase::Unknown)),
+                    locale.get(game.result),
+                ],
+            );
+
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: theme.ui.button.into(),
+                    ..default()
+                })
+                .insert(HistoryGame {
+                    game_id: game.game_id.clone(),
+                })
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            color: theme.ui.text,
+                            font_size: 14.0,
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+fn format_date(timestamp: Timestamp) -> String {
+    platform::format_date(timestamp.as_u64())
+}
+
+// games open at their url, which replays the stored events
+fn open_history_game(games: Query<(&Interaction, &HistoryGame), Changed<Interaction>>) {
+    for (interaction, game) in games.iter() {
+        if *interaction == Interaction::Pressed {
+            open_game(&game.game_id);
+        }
+    }
+}
+
+fn cleanup_history(mut commands: Commands, ui: Query<Entity, With<HistoryUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[wasm_bindgen]
+pub fn show_history() {
+    HISTORY_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use nostr_sdk::{key::SecretKey, FromBech32, Keys, ToBech32};
+use wasm_bindgen::prelude::*;
+
+use crate::{resources::GameState, AppState};
+
+static IMPORTED_KEY: Mutex<Option<Keys>> = Mutex::new(None);
+static CURRENT_KEY: Mutex<Option<Keys>> = Mutex::new(None);
+
+pub struct IdentityPlugin;
+
+impl Plugin for IdentityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                check_identity_system.run_if(in_state(AppState::Menu)),
+                update_current_identity,
+            ),
+        );
+    }
+}
+
+// keys can only change from the menu, a running game keeps the keys it started with
+fn check_identity_system(mut game_state: ResMut<GameState>) {
+    let imported = match IMPORTED_KEY.lock() {
+        Ok(mut imported) => imported.take(),
+        Err(e) => {
+            error!("Error reading imported key: {}", e);
+            return;
+        }
+    };
+
+    if let Some(nostr_keys) = imported {
+        info!("identity changed to {:?}", nostr_keys.public_key());
+        game_state.nostr_keys = nostr_keys;
+    }
+}
+
+fn update_current_identity(game_state: Res<GameState>) {
+    if !game_state.is_changed() {
+        return;
+    }
+
+    match CURRENT_KEY.lock() {
+        Ok(mut current) => *current = Some(game_state.nostr_keys.clone()),
+        Err(e) => error!("Error updating identity: {}", e),
+    }
+}
+
+#[wasm_bindgen(js_name = setIdentity)]
+pub fn set_identity(nsec: String) -> bool {
+    let secret_key = match SecretKey::from_bech32(nsec.trim()) {
+        Ok(secret_key) => secret_key,
+        Err(e) => {
+            error!("invalid nsec: {}", e);
+            return false;
+        }
+    };
+
+    match IMPORTED_KEY.lock() {
+        Ok(mut imported) => {
+            *imported = Some(Keys::new(secret_key));
+            true
+        }
+        Err(e) => {
+            error!("Error importing key: {}", e);
+            false
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = exportIdentity)]
+pub fn export_identity() -> String {
+    match CURRENT_KEY.lock() {
+        Ok(current) => current
+            .as_ref()
+            .and_then(|keys| keys.secret_key().ok())
+            .and_then(|secret_key| secret_key.to_bech32().ok())
+            .unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
+
+#[wasm_bindgen(js_name = identityPubkey)]
+pub fn identity_pubkey() -> String {
+    match CURRENT_KEY.lock() {
+        Ok(current) => current
+            .as_ref()
+            .and_then(|keys| keys.public_key().to_bech32().ok())
+            .unwrap_or_default(),
+        Err(_) => String::new(),
+    }
+}
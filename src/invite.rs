@@ -0,0 +1,146 @@
+use std::{fmt, ops::RangeInclusive};
+
+use crate::messages::{GameConfig, NetworkMessage};
+
+pub const VARIANTS: [&str; 6] = [
+    "classic", "fortune", "popout", "powerup", "cylinder", "gravity",
+];
+pub const SERIES_LENGTHS: [usize; 4] = [1, 3, 5, 7];
+// up to a bitcoin
+pub const WAGER_SATS: RangeInclusive<u64> = 1..=100_000_000;
+// seconds per move, up to a day
+pub const TIME_CONTROL: RangeInclusive<u64> = 5..=86_400;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InviteError {
+    UnknownVariant(String),
+    InvalidNumber(&'static str, String),
+    SeriesLength(usize),
+    Wager(u64),
+    TimeControl(u64),
+}
+
+impl fmt::Display for InviteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InviteError::UnknownVariant(variant) => write!(f, "unknown variant {}", variant),
+            InviteError::InvalidNumber(name, value) => {
+                write!(f, "{} is not a number: {}", name, value)
+            }
+            InviteError::SeriesLength(length) => write!(f, "a series can't be {} games", length),
+            InviteError::Wager(sats) => write!(f, "a wager of {} sats is out of range", sats),
+            InviteError::TimeControl(seconds) => {
+                write!(f, "{} seconds per move is out of range", seconds)
+            }
+        }
+    }
+}
+
+// the terms of a game carried by its invitation link,
+// `/{game_id}?challenger=npub...&variant=popout&series=3&wager=1000&time=30`.
+// the variant and series length are checked against the new game event before joining,
+// the wager and time control are shown to both players
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitation {
+    pub variant: String,
+    pub series_length: usize,
+    pub wager: Option<u64>,
+    pub time_control: Option<u64>,
+}
+
+impl Invitation {
+    pub fn query(&self) -> String {
+        let mut query = format!("variant={}&series={}", self.variant, self.series_length);
+        if let Some(wager) = self.wager {
+            query.push_str(&format!("&wager={}", wager));
+        }
+        if let Some(time_control) = self.time_control {
+            query.push_str(&format!("&time={}", time_control));
+        }
+        query
+    }
+
+    // none for a link without terms, a plain game link or a challenge link
+    pub fn parse(query: &str) -> Result<Option<Invitation>, InviteError> {
+        let param = |name: &str| {
+            query
+                .trim_start_matches('?')
+                .split('&')
+                .find_map(|param| param.strip_prefix(name)?.strip_prefix('='))
+        };
+        let number = |name: &'static str| {
+            param(name)
+                .map(|value| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| InviteError::InvalidNumber(name, value.to_string()))
+                })
+                .transpose()
+        };
+
+        let Some(variant) = param("variant") else {
+            return Ok(None);
+        };
+        if !VARIANTS.contains(&variant) {
+            return Err(InviteError::UnknownVariant(variant.to_string()));
+        }
+
+        let series_length = number("series")?.unwrap_or(1) as usize;
+        if !SERIES_LENGTHS.contains(&series_length) {
+            return Err(InviteError::SeriesLength(series_length));
+        }
+
+        let wager = number("wager")?;
+        if let Some(sats) = wager.filter(|sats| !WAGER_SATS.contains(sats)) {
+            return Err(InviteError::Wager(sats));
+        }
+
+        let time_control = number("time")?;
+        if let Some(seconds) = time_control.filter(|seconds| !TIME_CONTROL.contains(seconds)) {
+            return Err(InviteError::TimeControl(seconds));
+        }
+
+        Ok(Some(Invitation {
+            variant: variant.to_string(),
+            series_length,
+            wager,
+            time_control,
+        }))
+    }
+
+    // whether player 1 created the game this link promises
+    pub fn accepts(&self, new_game: &NetworkMessage) -> bool {
+        let NetworkMessage::NewGame(_, series_length, config) = new_game else {
+            return false;
+        };
+
+        let variant = match config {
+            GameConfig {
+                fortune_seed: Some(_),
+                ..
+            } => "fortune",
+            GameConfig { pop_out: true, .. } => "popout",
+            GameConfig { power_up: true, .. } => "powerup",
+            GameConfig { cylinder: true, .. } => "cylinder",
+            GameConfig {
+                gravity_shift: true,
+                ..
+            } => "gravity",
+            _ => "classic",
+        };
+
+        self.variant == variant && self.series_length == *series_length
+    }
+
+    // the agreed terms for the hud, none when there is neither a wager nor a clock
+    pub fn terms(&self) -> Option<String> {
+        match (self.wager, self.time_control) {
+            (Some(sats), Some(seconds)) => {
+                Some(format!("Wager {} sats, {}s per move", sats, seconds))
+            }
+            (Some(sats), None) => Some(format!("Wager {} sats", sats)),
+            (None, Some(seconds)) => Some(format!("{}s per move", seconds)),
+            (None, None) => None,
+        }
+    }
+}
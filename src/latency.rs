@@ -0,0 +1,173 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use bevy::prelude::*;
+use nostr_sdk::{serde_json, EventId};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::platform;
+
+// the traces of the last moves of the open game
+const MAX_TRACES: usize = 100;
+
+static MOVE_TRACES: Mutex<VecDeque<MoveTrace>> = Mutex::new(VecDeque::new());
+
+// the wall clock milliseconds a move passed each stage at. an own move is clicked,
+// published to the relays, accepted by the first relay, received by the opponent, whose
+// clock tells in the ack, and acknowledged. an opponent's move is received and rendered
+#[derive(Serialize, Clone, Default)]
+struct MoveTrace {
+    index: usize,
+    own: bool,
+    #[serde(skip)]
+    event_id: Option<EventId>,
+    clicked: Option<f64>,
+    published: Option<f64>,
+    relay_ok: Option<f64>,
+    received: Option<f64>,
+    acked: Option<f64>,
+    rendered: Option<f64>,
+}
+
+impl MoveTrace {
+    // the stages as durations. the opponent's receive time is on their clock, a skew between
+    // the clocks shows there and not in the round trip
+    fn breakdown(&self) -> serde_json::Value {
+        let between = |from: Option<f64>, to: Option<f64>| match (from, to) {
+            (Some(from), Some(to)) => Some(to - from),
+            _ => None,
+        };
+        let mut breakdown = serde_json::json!(self);
+        if self.own {
+            breakdown["click_to_publish_ms"] =
+                serde_json::json!(between(self.clicked, self.published));
+            breakdown["publish_to_relay_ok_ms"] =
+                serde_json::json!(between(self.published, self.relay_ok));
+            breakdown["publish_to_opponent_ms"] =
+                serde_json::json!(between(self.published, self.received));
+            breakdown["round_trip_ms"] = serde_json::json!(between(self.published, self.acked));
+        }
+        breakdown["receive_to_render_ms"] = serde_json::json!(between(
+            if self.own {
+                self.clicked
+            } else {
+                self.received
+            },
+            self.rendered
+        ));
+        breakdown
+    }
+}
+
+fn update(find: impl Fn(&MoveTrace) -> bool, stage: impl FnOnce(&mut MoveTrace)) {
+    match MOVE_TRACES.lock() {
+        Ok(mut traces) => {
+            if let Some(trace) = traces.iter_mut().rev().find(|trace| find(trace)) {
+                stage(trace);
+            }
+        }
+        Err(e) => error!("Error tracing move: {}", e),
+    }
+}
+
+fn start(trace: MoveTrace) {
+    match MOVE_TRACES.lock() {
+        Ok(mut traces) => {
+            traces.retain(|traced| traced.index != trace.index);
+            traces.push_back(trace);
+            while traces.len() > MAX_TRACES {
+                traces.pop_front();
+            }
+        }
+        Err(e) => error!("Error tracing move: {}", e),
+    }
+}
+
+// a game was opened, its moves start over from index 0
+pub fn clear() {
+    if let Ok(mut traces) = MOVE_TRACES.lock() {
+        traces.clear();
+    }
+}
+
+pub fn clicked(index: usize) {
+    start(MoveTrace {
+        index,
+        own: true,
+        clicked: Some(platform::now_millis()),
+        ..default()
+    });
+}
+
+pub fn signed(index: usize, event_id: EventId) {
+    update(
+        |trace| trace.own && trace.index == index,
+        |trace| trace.event_id = Some(event_id),
+    );
+}
+
+// handed to the relays, resends keep the first time
+pub fn published(event_id: &EventId) {
+    update(
+        |trace| trace.event_id.as_ref() == Some(event_id),
+        |trace| {
+            trace.published.get_or_insert_with(platform::now_millis);
+        },
+    );
+}
+
+pub fn relay_ok(event_id: &EventId) {
+    update(
+        |trace| trace.event_id.as_ref() == Some(event_id),
+        |trace| {
+            trace.relay_ok.get_or_insert_with(platform::now_millis);
+        },
+    );
+}
+
+// the opponent's time of receipt, when their ack carries it
+pub fn acked(index: usize, received: Option<f64>) {
+    update(
+        |trace| trace.own && trace.index == index,
+        |trace| {
+            trace.received = trace.received.or(received);
+            if trace.acked.is_none() {
+                trace.acked = Some(platform::now_millis());
+                info!("move {} latency {}", index, trace.breakdown());
+            }
+        },
+    );
+}
+
+pub fn received(index: usize, event_id: EventId) {
+    start(MoveTrace {
+        index,
+        own: false,
+        event_id: Some(event_id),
+        received: Some(platform::now_millis()),
+        ..default()
+    });
+}
+
+// the move is on the board, its coin shows from the next frame
+pub fn rendered(index: usize) {
+    update(
+        |trace| trace.index == index,
+        |trace| {
+            trace.rendered.get_or_insert_with(platform::now_millis);
+        },
+    );
+}
+
+pub fn traces() -> serde_json::Value {
+    match MOVE_TRACES.lock() {
+        Ok(traces) => traces.iter().map(MoveTrace::breakdown).collect(),
+        Err(_) => serde_json::json!([]),
+    }
+}
+
+// the traces with the duration of each stage, for the debug console
+#[wasm_bindgen(js_name = moveLatency)]
+pub fn move_latency() -> String {
+    traces().to_string()
+}
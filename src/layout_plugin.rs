@@ -0,0 +1,148 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use crate::components::{OpponentAvatar, OpponentBadges, OpponentName, OpponentPresence};
+
+// the room the board and the hud need at full size, in world units
+const WIDE_ROOM: Vec2 = Vec2::new(460.0, 440.0);
+// with the opponent moved above the turn display the hud is no wider than the board
+const NARROW_WIDTH: f32 = 340.0;
+const MIN_SCALE: f32 = 0.4;
+
+// the opponent's name and badges start right of their avatar
+const NAME_OFFSET: Vec2 = Vec2::new(18.0, 0.0);
+const BADGES_OFFSET: Vec2 = Vec2::new(18.0, -14.0);
+const PRESENCE_OFFSET: Vec2 = Vec2::new(18.0, -26.0);
+
+// how the board fits the window. the camera zooms out by the scale, so a 40 unit coin is
+// drawn at 40 * scale pixels, and narrow windows move the opponent's name above the turn
+// display where it doesn't overflow
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub scale: f32,
+    pub narrow: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            scale: 1.0,
+            narrow: false,
+        }
+    }
+}
+
+impl Layout {
+    pub fn for_window(width: f32, height: f32) -> Self {
+        let narrow = width < WIDE_ROOM.x;
+        let room_width = if narrow { NARROW_WIDTH } else { WIDE_ROOM.x };
+        let scale = (width / room_width)
+            .min(height / WIDE_ROOM.y)
+            .clamp(MIN_SCALE, 1.0);
+
+        Layout { scale, narrow }
+    }
+
+    // where the opponent's avatar sits, beside the turn display or above the board's left edge
+    pub fn opponent_anchor(&self) -> Vec2 {
+        if self.narrow {
+            Vec2::new(-130.0, 215.0)
+        } else {
+            Vec2::new(40.0, 180.0)
+        }
+    }
+
+    pub fn opponent_name(&self) -> Vec2 {
+        self.opponent_anchor() + NAME_OFFSET
+    }
+
+    pub fn opponent_badges(&self) -> Vec2 {
+        self.opponent_anchor() + BADGES_OFFSET
+    }
+
+    pub fn opponent_presence(&self) -> Vec2 {
+        self.opponent_anchor() + PRESENCE_OFFSET
+    }
+}
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Layout>()
+            .add_systems(PreUpdate, (update_layout, apply_layout).chain());
+    }
+}
+
+fn update_layout(
+    mut resized: EventReader<WindowResized>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut layout: ResMut<Layout>,
+    mut measured: Local<bool>,
+) {
+    let resized = resized.read().last().is_some();
+    if *measured && !resized {
+        return;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    *measured = true;
+
+    layout.set_if_neq(Layout::for_window(window.width(), window.height()));
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_layout(
+    layout: Res<Layout>,
+    mut ui_scale: ResMut<UiScale>,
+    mut projections: Query<&mut OrthographicProjection>,
+    mut avatar: Query<
+        &mut Transform,
+        (
+            With<OpponentAvatar>,
+            Without<OpponentName>,
+            Without<OpponentBadges>,
+        ),
+    >,
+    mut name: Query<&mut Transform, (With<OpponentName>, Without<OpponentBadges>)>,
+    mut badges: Query<&mut Transform, (With<OpponentBadges>, Without<OpponentAvatar>)>,
+    mut presence: Query<
+        &mut Transform,
+        (
+            With<OpponentPresence>,
+            Without<OpponentAvatar>,
+            Without<OpponentName>,
+            Without<OpponentBadges>,
+        ),
+    >,
+) {
+    if !layout.is_changed() {
+        return;
+    }
+
+    for mut projection in projections.iter_mut() {
+        projection.scale = 1.0 / layout.scale;
+    }
+    // menus and overlays with fixed sizes shrink along with the board
+    ui_scale.0 = layout.scale as f64;
+
+    let place = |transform: &mut Transform, position: Vec2| {
+        transform.translation = position.extend(transform.translation.z);
+    };
+    for mut transform in avatar.iter_mut() {
+        place(&mut transform, layout.opponent_anchor());
+    }
+    for mut transform in name.iter_mut() {
+        place(&mut transform, layout.opponent_name());
+    }
+    for mut transform in badges.iter_mut() {
+        place(&mut transform, layout.opponent_badges());
+    }
+    for mut transform in presence.iter_mut() {
+        place(&mut transform, layout.opponent_presence());
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{secp256k1::XOnlyPublicKey, serde_json, Filter, Kind, ToBech32};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{LeaderboardText, LeaderboardUi},
+    locale_plugin::{Locale, Phrase},
+    messages::{moves_hash, GameResult},
+    nostr_plugin::{fetch_events, game_duration, sync_column},
+    platform,
+    rating::{leaderboard, LeaderboardEntry},
+    resources::{Board, GameState, RESULT_KIND, RESULT_TAG},
+    settings_plugin::Settings,
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+const LEADERBOARD_SIZE: usize = 20;
+
+static LEADERBOARD_CALLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Resource, Default)]
+struct LeaderboardResults {
+    read: Option<Receiver<Vec<GameResult>>>,
+}
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LeaderboardResults>()
+            .add_systems(
+                Update,
+                check_leaderboard_system.run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(
+                Update,
+                publish_game_result.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                OnEnter(AppState::Leaderboard),
+                (setup_leaderboard, fetch_results),
+            )
+            .add_systems(
+                Update,
+                update_leaderboard.run_if(in_state(AppState::Leaderboard)),
+            )
+            .add_systems(OnExit(AppState::Leaderboard), cleanup_leaderboard);
+    }
+}
+
+fn check_leaderboard_system(mut next_state: ResMut<NextState<AppState>>) {
+    if LEADERBOARD_CALLED.swap(false, Ordering::SeqCst) {
+        next_state.set(AppState::Leaderboard);
+    }
+}
+
+// both players publish the result, the event is replaceable per author and game.
+// only classic games are rated, columns of fortune, pop out and team games are never
+// published, nor a game the player took a hint in
+fn publish_game_result(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    settings: Res<Settings>,
+    mut published: Local<HashSet<String>>,
+) {
+    if board.in_progress
+        || (board.winner.is_none() && !board.draw)
+        || (game_state.player_type != 1 && game_state.player_type != 2)
+        || published.contains(&game_state.game_id)
+        || !board.classic()
+        || game_state.teams.is_some()
+    {
+        return;
+    }
+
+    let Some(p2_pubkey) = game_state.p2_pubkey else {
+        return;
+    };
+
+    // storage is read once per game, a hinted game is never published
+    published.insert(game_state.game_id.clone());
+    if storage::hinted(&game_state.game_id) {
+        return;
+    }
+
+    let local_pubkey = game_state.nostr_keys.public_key();
+    let (p1_pubkey, p2_pubkey, p1_name, p2_name) = if game_state.player_type == 1 {
+        (
+            local_pubkey,
+            p2_pubkey,
+            game_state.local_ln_address.clone(),
+            game_state.p2_ln_address.clone(),
+        )
+    } else {
+        (
+            p2_pubkey,
+            local_pubkey,
+            game_state.p2_ln_address.clone(),
+            game_state.local_ln_address.clone(),
+        )
+    };
+
+    let result = GameResult {
+        game_id: game_state.game_id.clone(),
+        p1_pubkey,
+        p2_pubkey,
+        p1_name,
+        p2_name,
+        winner: board.winner,
+        moves: board.moves.len(),
+        duration: (publish_speed() && !settings.privacy)
+            .then(game_duration)
+            .flatten(),
+        resigned: board.resigned.is_some(),
+        moves_hash: Some(moves_hash(board.moves.iter().map(sync_column))),
+        author: None,
+    };
+
+    info!("publishing result {:?}", result);
+    game_state.clone().send_result(result);
+}
+
+fn setup_leaderboard(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::top(Val::Px(60.0)),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(LeaderboardUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    locale.get(Phrase::LeaderboardLoading),
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(LeaderboardText);
+        });
+}
+
+// the game duration is left out of results when the player turned it off in the settings
+pub fn publish_speed() -> bool {
+    storage::get_item("publish_speed").as_deref() != Some("false")
+}
+
+fn fetch_results(mut results: ResMut<LeaderboardResults>) {
+    let (mut results_tx, results_rx) = futures::channel::mpsc::channel::<Vec<GameResult>>(1);
+    results.read = Some(results_rx);
+
+    platform::spawn(async move {
+        let game_results = fetch_game_results().await;
+
+        if let Err(e) = results_tx.try_send(game_results) {
+            error!("Error sending results: {}", e);
+        }
+    });
+}
+
+pub async fn fetch_game_results() -> Vec<GameResult> {
+    let filter = Filter::new()
+        .kind(Kind::ParameterizedReplaceable(RESULT_KIND))
+        .hashtag(RESULT_TAG);
+
+    let events = fetch_events(vec![filter], Vec::new()).await;
+
+    // only results signed by one of the players count, see `rating::confirmed` for whose
+    let game_results: Vec<GameResult> = events
+        .iter()
+        .filter_map(|event| {
+            let mut result: GameResult = serde_json::from_str(&event.content).ok()?;
+            result.author = Some(event.pubkey);
+            (event.pubkey == result.p1_pubkey || event.pubkey == result.p2_pubkey).then_some(result)
+        })
+        .collect();
+
+    info!("fetched {} results", game_results.len());
+    game_results
+}
+
+fn update_leaderboard(
+    mut results: ResMut<LeaderboardResults>,
+    mut game_state: ResMut<GameState>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<LeaderboardText>>,
+) {
+    let Some(ref mut results_rx) = results.read else {
+        return;
+    };
+
+    let Ok(Some(game_results)) = results_rx.try_next() else {
+        return;
+    };
+
+    let entries = leaderboard(&game_results);
+    let local_pubkey = game_state.nostr_keys.public_key();
+
+    let mut value = format!("{}\n\n", locale.get(Phrase::Leaderboard));
+    if entries.is_empty() {
+        value.push_str(locale.get(Phrase::NoRatedGames));
+    }
+
+    for (rank, entry) in entries.iter().enumerate() {
+        if entry.pubkey == local_pubkey && entry.rating != game_state.local_rating {
+            game_state.local_rating = entry.rating;
+            storage::set_item("rating", &entry.rating.to_string());
+        }
+
+        if rank >= LEADERBOARD_SIZE {
+            continue;
+        }
+
+        let name = match entry.name {
+            Some(ref name) => name.clone(),
+            None => short_npub(entry),
+        };
+
+        value.push_str(&locale.fill(
+            Phrase::LeaderboardEntry,
+            &[
+                &(rank + 1).to_string(),
+                &name,
+                &entry.rating.to_string(),
+                &entry.games.to_string(),
+            ],
+        ));
+        value.push('\n');
+    }
+
+    for mut txt in text.iter_mut() {
+        txt.sections[0].value = value.clone();
+    }
+}
+
+fn short_npub(entry: &LeaderboardEntry) -> String {
+    short_pubkey(&entry.pubkey)
+}
+
+pub fn short_pubkey(pubkey: &XOnlyPublicKey) -> String {
+    match pubkey.to_bech32() {
+        Ok(npub) => format!("{}…", &npub[..12]),
+        Err(_) => pubkey.to_string(),
+    }
+}
+
+fn cleanup_leaderboard(mut commands: Commands, ui: Query<Entity, With<LeaderboardUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[wasm_bindgen]
+pub fn show_leaderboard() {
+    LEADERBOARD_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use nostr_sdk::{
+    nips::nip01::Coordinate, secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent,
+    EventBuilder, FromBech32, Kind, Tag, ToBech32,
+};
+
+use crate::{
+    game::GameRng,
+    messages::{state_hash, GameResult, LeagueInfo, GAME_ID_ALPHABET, LEAGUE_KIND, LEAGUE_TAG},
+    rating::{confirmed, disputed},
+};
+
+// a round is played in a week
+pub const LEAGUE_ROUND: u64 = 7 * 24 * 60 * 60;
+const WIN_POINTS: usize = 3;
+const DRAW_POINTS: usize = 1;
+
+// a league as read from the relays, or as just created
+#[derive(Debug, Clone)]
+pub struct League {
+    pub author: XOnlyPublicKey,
+    pub identifier: String,
+    pub info: LeagueInfo,
+    pub players: Vec<XOnlyPublicKey>,
+}
+
+// a game of a round between two players, on a game id both derive from the league
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub round: usize,
+    pub players: [XOnlyPublicKey; 2],
+    pub game_id: String,
+}
+
+impl Fixture {
+    pub fn plays(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.players.contains(pubkey)
+    }
+
+    pub fn opponent(&self, pubkey: &XOnlyPublicKey) -> XOnlyPublicKey {
+        if self.players[0] == *pubkey {
+            self.players[1]
+        } else {
+            self.players[0]
+        }
+    }
+
+    // either player may have created the game, so either may be player 1
+    fn result<'a>(&self, results: &'a [GameResult]) -> Option<&'a GameResult> {
+        results.iter().find(|result| {
+            result.game_id == self.game_id
+                && self.plays(&result.p1_pubkey)
+                && self.plays(&result.p2_pubkey)
+                && result.p1_pubkey != result.p2_pubkey
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Standing {
+    pub pubkey: XOnlyPublicKey,
+    pub name: Option<String>,
+    pub played: usize,
+    pub won: usize,
+    pub drawn: usize,
+    pub lost: usize,
+    pub points: usize,
+}
+
+impl League {
+    pub fn new(
+        author: XOnlyPublicKey,
+        identifier: String,
+        info: LeagueInfo,
+        players: Vec<XOnlyPublicKey>,
+    ) -> Self {
+        Self {
+            author,
+            identifier,
+            info,
+            players,
+        }
+    }
+
+    // relays may keep older versions of the league, the newest one counts
+    pub fn from_events(events: &[NostrEvent], coordinate: &Coordinate) -> Option<Self> {
+        let event = events
+            .iter()
+            .filter(|event| {
+                event.kind == Kind::ParameterizedReplaceable(LEAGUE_KIND)
+                    && event.pubkey == coordinate.pubkey
+                    && event.tags.iter().any(|tag| match tag {
+                        Tag::Identifier(identifier) => *identifier == coordinate.identifier,
+                        _ => false,
+                    })
+            })
+            .max_by_key(|event| event.created_at)?;
+
+        let mut players: Vec<XOnlyPublicKey> = Vec::new();
+        for tag in event.tags.iter() {
+            if let Tag::PubKey(pubkey, _) = tag {
+                if !players.contains(pubkey) {
+                    players.push(*pubkey);
+                }
+            }
+        }
+
+        Some(Self {
+            author: event.pubkey,
+            identifier: coordinate.identifier.clone(),
+            info: serde_json::from_str(&event.content).ok()?,
+            players,
+        })
+    }
+
+    // the naddr of the league event, shared with the players
+    pub fn address(&self) -> String {
+        let coordinate = Coordinate {
+            kind: Kind::ParameterizedReplaceable(LEAGUE_KIND),
+            pubkey: self.author,
+            identifier: self.identifier.clone(),
+            relays: Vec::new(),
+        };
+        coordinate.to_bech32().unwrap_or_default()
+    }
+
+    // signed by the author, a new version replaces the last
+    pub fn event(&self) -> EventBuilder {
+        let tags = [
+            Tag::Identifier(self.identifier.clone()),
+            Tag::Hashtag(LEAGUE_TAG.to_string()),
+        ]
+        .into_iter()
+        .chain(self.players.iter().map(|player| Tag::PubKey(*player, None)));
+
+        EventBuilder::new(
+            Kind::ParameterizedReplaceable(LEAGUE_KIND),
+            serde_json::to_string(&self.info).unwrap(),
+            tags,
+        )
+    }
+
+    // the pairings follow from the league's address, every client draws the same ones
+    fn seed(&self) -> u64 {
+        let address = format!("{}:{}", self.author, self.identifier);
+        state_hash(address.bytes().map(usize::from))
+    }
+
+    // everyone plays everyone once, an odd player sits a round out
+    pub fn rounds(&self) -> usize {
+        let players = self.players.len();
+        if players < 2 {
+            0
+        } else {
+            players - 1 + players % 2
+        }
+    }
+
+    // the round under way, none before the start or after the last deadline
+    pub fn round_at(&self, now: u64) -> Option<usize> {
+        let round = (now.checked_sub(self.info.starts_at)? / LEAGUE_ROUND) as usize;
+        (round < self.rounds()).then_some(round)
+    }
+
+    pub fn deadline(&self, round: usize) -> u64 {
+        self.info.starts_at + (round as u64 + 1) * LEAGUE_ROUND
+    }
+
+    // a round robin by the circle method over the players in a seeded order
+    pub fn fixtures(&self) -> Vec<Fixture> {
+        let seed = self.seed();
+        let mut order: Vec<Option<XOnlyPublicKey>> =
+            self.players.iter().copied().map(Some).collect();
+        let mut rng = GameRng::at(seed, 0);
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+        if order.len() % 2 == 1 {
+            order.push(None);
+        }
+
+        let mut fixtures = Vec::new();
+        for round in 0..self.rounds() {
+            let mut rest = order[1..].to_vec();
+            rest.rotate_right(round);
+            let seating: Vec<Option<XOnlyPublicKey>> =
+                std::iter::once(order[0]).chain(rest).collect();
+
+            for table in 0..seating.len() / 2 {
+                let (Some(a), Some(b)) = (seating[table], seating[seating.len() - 1 - table])
+                else {
+                    continue;
+                };
+                let mut rng = GameRng::at(seed, ((round as u64) << 16) | (table as u64 + 1));
+                let game_id: String = (0..8)
+                    .map(|_| GAME_ID_ALPHABET[rng.below(GAME_ID_ALPHABET.len())])
+                    .collect();
+                fixtures.push(Fixture {
+                    round,
+                    players: [a, b],
+                    game_id: format!("/{}", game_id),
+                });
+            }
+        }
+        fixtures
+    }
+
+    // the result of a fixture both players agree on and the loser signed, if any was
+    // published
+    pub fn result<'a>(
+        &self,
+        fixture: &Fixture,
+        results: &'a [GameResult],
+    ) -> Option<&'a GameResult> {
+        let disputed = disputed(results);
+        let confirmed = confirmed(results);
+        fixture.result(results).filter(|result| {
+            !disputed.contains(result.game_id.as_str())
+                && confirmed.contains(result.game_id.as_str())
+        })
+    }
+
+    // three points for a win and one for a draw, the most points and then wins first.
+    // fixtures without a result count as not played
+    pub fn standings(&self, results: &[GameResult]) -> Vec<Standing> {
+        let mut table: HashMap<XOnlyPublicKey, Standing> = self
+            .players
+            .iter()
+            .map(|pubkey| {
+                (
+                    *pubkey,
+                    Standing {
+                        pubkey: *pubkey,
+                        name: None,
+                        played: 0,
+                        won: 0,
+                        drawn: 0,
+                        lost: 0,
+                        points: 0,
+                    },
+                )
+            })
+            .collect();
+
+        for fixture in self.fixtures() {
+            let Some(result) = self.result(&fixture, results) else {
+                continue;
+            };
+            for (player, pubkey, name) in [
+                (1, result.p1_pubkey, &result.p1_name),
+                (2, result.p2_pubkey, &result.p2_name),
+            ] {
+                let Some(standing) = table.get_mut(&pubkey) else {
+                    continue;
+                };
+                standing.played += 1;
+                match result.winner {
+                    Some(winner) if winner == player => {
+                        standing.won += 1;
+                        standing.points += WIN_POINTS;
+                    }
+                    Some(_) => standing.lost += 1,
+                    None => {
+                        standing.drawn += 1;
+                        standing.points += DRAW_POINTS;
+                    }
+                }
+                if name.is_some() {
+                    standing.name = name.clone();
+                }
+            }
+        }
+
+        let mut standings: Vec<Standing> = table.into_values().collect();
+        standings.sort_by(|a, b| {
+            b.points
+                .cmp(&a.points)
+                .then(b.won.cmp(&a.won))
+                .then(a.played.cmp(&b.played))
+                .then(a.pubkey.cmp(&b.pubkey))
+        });
+        standings
+    }
+}
+
+// the league event an address points to
+pub fn coordinate(address: &str) -> Option<Coordinate> {
+    Coordinate::from_bech32(address)
+        .ok()
+        .filter(|coordinate| coordinate.kind == Kind::ParameterizedReplaceable(LEAGUE_KIND))
+}
@@ -0,0 +1,462 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nanoid::nanoid;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, Event as NostrEvent, EventBuilder, Filter, FromBech32, Kind,
+    Timestamp,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{LeagueAction, LeagueText, LeagueUi},
+    leaderboard_plugin::{fetch_game_results, short_pubkey},
+    league::{self, League, LEAGUE_ROUND},
+    locale_plugin::{Locale, Phrase},
+    messages::{GameResult, LeagueInfo, LEAGUE_KIND},
+    nostr_plugin::{fetch_events, publish_events},
+    platform,
+    resources::GameState,
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+// the league to open and the name, the players' npubs and the days until the start of a
+// league to create, from the page
+static OPEN_LEAGUE: Mutex<Option<String>> = Mutex::new(None);
+static NEW_LEAGUE: Mutex<Option<(String, String, u32)>> = Mutex::new(None);
+
+// the league on the page and the results of its games
+#[derive(Resource, Default)]
+struct LeaguePage {
+    address: String,
+    read: Option<Receiver<(Vec<NostrEvent>, Vec<GameResult>)>>,
+    league: Option<League>,
+    results: Vec<GameResult>,
+}
+
+// a league pairs its players every week from the league event alone, everyone plays
+// everyone once. its page lists the player's fixtures with their deadlines and the table
+pub struct LeaguePlugin;
+
+impl Plugin for LeaguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LeaguePage>()
+            .add_systems(Update, check_league_system.run_if(in_state(AppState::Menu)))
+            .add_systems(OnEnter(AppState::League), (setup_league, fetch_league))
+            .add_systems(
+                Update,
+                (receive_league, league_actions, update_league_text)
+                    .run_if(in_state(AppState::League)),
+            )
+            .add_systems(OnExit(AppState::League), cleanup_league);
+    }
+}
+
+// the author plays in the league too
+fn check_league_system(
+    mut page: ResMut<LeaguePage>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if let Some((name, npubs, days)) = NEW_LEAGUE.lock().ok().and_then(|mut new| new.take()) {
+        let own = game_state.nostr_keys.public_key();
+        let mut players = vec![own];
+        for npub in npubs.split(|c: char| c == ',' || c.is_whitespace()) {
+            if npub.is_empty() {
+                continue;
+            }
+            match XOnlyPublicKey::from_bech32(npub) {
+                Ok(player) if !players.contains(&player) => players.push(player),
+                Ok(_) => {}
+                Err(e) => {
+                    platform::alert(&locale.fill(Phrase::NamedNotAnNpub, &[npub, &e.to_string()]));
+                    return;
+                }
+            }
+        }
+
+        let info = LeagueInfo {
+            name,
+            starts_at: Timestamp::now().as_u64() + days as u64 * 24 * 60 * 60,
+        };
+        let league = League::new(own, nanoid!(10), info, players);
+        info!("creating league {}", league.address());
+        publish(league.event(), &game_state);
+
+        *page = LeaguePage {
+            address: league.address(),
+            league: Some(league),
+            ..default()
+        };
+        storage::set_current_league(&page.address);
+        next_state.set(AppState::League);
+        return;
+    }
+
+    if let Some(address) = OPEN_LEAGUE.lock().ok().and_then(|mut open| open.take()) {
+        if league::coordinate(&address).is_none() {
+            platform::alert(locale.get(Phrase::NotLeagueAddress));
+            return;
+        }
+        if address != page.address {
+            *page = LeaguePage {
+                address,
+                ..default()
+            };
+        }
+        storage::set_current_league(&page.address);
+        next_state.set(AppState::League);
+    }
+}
+
+fn publish(event: EventBuilder, game_state: &GameState) {
+    match event.to_event(&game_state.nostr_keys) {
+        Ok(event) => platform::spawn(publish_events(vec![event])),
+        Err(e) => error!("Error signing league event: {}", e),
+    }
+}
+
+fn fetch_league(mut page: ResMut<LeaguePage>) {
+    let Some(coordinate) = league::coordinate(&page.address) else {
+        return;
+    };
+
+    let (mut league_tx, league_rx) =
+        futures::channel::mpsc::channel::<(Vec<NostrEvent>, Vec<GameResult>)>(1);
+    page.read = Some(league_rx);
+
+    platform::spawn(async move {
+        let filter = Filter::new()
+            .kind(Kind::ParameterizedReplaceable(LEAGUE_KIND))
+            .author(coordinate.pubkey)
+            .identifier(coordinate.identifier.clone());
+        let events = fetch_events(vec![filter], Vec::new()).await;
+        let results = fetch_game_results().await;
+
+        if let Err(e) = league_tx.try_send((events, results)) {
+            error!("Error sending league: {}", e);
+        }
+    });
+}
+
+fn receive_league(mut page: ResMut<LeaguePage>) {
+    let Some(ref mut league_rx) = page.read else {
+        return;
+    };
+    let Ok(Some((events, results))) = league_rx.try_next() else {
+        return;
+    };
+    page.read = None;
+
+    // a league just created may not have reached the relays yet
+    let fetched = league::coordinate(&page.address)
+        .and_then(|coordinate| League::from_events(&events, &coordinate));
+    if let Some(league) = fetched {
+        page.league = Some(league);
+    }
+    page.results = results;
+}
+
+fn league_actions(
+    actions: Query<(&Interaction, &LeagueAction), Changed<Interaction>>,
+    mut page: ResMut<LeaguePage>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let own = game_state.nostr_keys.public_key();
+
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(league) = page.league.clone() else {
+            continue;
+        };
+
+        match action {
+            // whoever opens the fixture first creates the game, the other one joins
+            LeagueAction::Play => {
+                let fixture = league
+                    .round_at(Timestamp::now().as_u64())
+                    .and_then(|round| {
+                        league.fixtures().into_iter().find(|fixture| {
+                            fixture.round == round
+                                && fixture.plays(&own)
+                                && league.result(fixture, &page.results).is_none()
+                        })
+                    });
+                let Some(fixture) = fixture else {
+                    platform::alert(locale.get(Phrase::NoLeagueGame));
+                    continue;
+                };
+                info!("playing league fixture {:?}", fixture);
+                platform::push_url(&format!("{}{}", platform::origin(), fixture.game_id));
+                next_state.set(AppState::InGame);
+            }
+            LeagueAction::Share => {
+                platform::prompt(locale.get(Phrase::ShareLeague), Some(&league.address()));
+            }
+            LeagueAction::AddPlayer => {
+                let Some(npub) = platform::prompt(locale.get(Phrase::NewPlayerNpub), None) else {
+                    continue;
+                };
+                let player = match XOnlyPublicKey::from_bech32(npub.trim()) {
+                    Ok(player) => player,
+                    Err(e) => {
+                        platform::alert(&locale.fill(Phrase::NotAnNpub, &[&e.to_string()]));
+                        continue;
+                    }
+                };
+                let mut league = league;
+                if !league.players.contains(&player) {
+                    league.players.push(player);
+                    publish(league.event(), &game_state);
+                }
+                page.league = Some(league);
+            }
+        }
+    }
+}
+
+fn setup_league(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::top(Val::Px(60.0)),
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(LeagueUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    locale.get(Phrase::LeagueLoading),
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(LeagueText);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (action, label) in [
+                        (LeagueAction::Play, Phrase::PlayThisRound),
+                        (LeagueAction::Share, Phrase::Share),
+                        (LeagueAction::AddPlayer, Phrase::AddPlayer),
+                    ] {
+                        row.spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: theme.ui.button.into(),
+                            ..default()
+                        })
+                        .insert(action)
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(
+                                locale.get(label),
+                                TextStyle {
+                                    color: theme.ui.text,
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+// players join before the start, a new player later would change every pairing
+fn update_league_text(
+    page: Res<LeaguePage>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<LeagueText>>,
+    mut buttons: Query<(&LeagueAction, &mut Visibility)>,
+    spawned: Query<(), Added<LeagueText>>,
+) {
+    if !page.is_changed() && !locale.is_changed() && spawned.is_empty() {
+        return;
+    }
+
+    let own = game_state.nostr_keys.public_key();
+    let now = Timestamp::now().as_u64();
+    for (action, mut visibility) in buttons.iter_mut() {
+        let shown = match action {
+            LeagueAction::Play => page
+                .league
+                .as_ref()
+                .is_some_and(|league| league.players.contains(&own)),
+            LeagueAction::Share => page.league.is_some(),
+            LeagueAction::AddPlayer => page
+                .league
+                .as_ref()
+                .is_some_and(|league| league.author == own && league.info.starts_at > now),
+        };
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    let value = league_text(&page, own, &locale);
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn league_text(page: &LeaguePage, own: XOnlyPublicKey, locale: &Locale) -> String {
+    let Some(league) = page.league.as_ref() else {
+        return locale
+            .get(if page.read.is_some() {
+                Phrase::LeagueLoading
+            } else {
+                Phrase::LeagueNotFound
+            })
+            .to_string();
+    };
+
+    let now = Timestamp::now().as_u64();
+    let rounds = league.rounds();
+    let season = match league.round_at(now) {
+        Some(round) => locale.fill(
+            Phrase::LeagueRound,
+            &[
+                &(round + 1).to_string(),
+                &rounds.to_string(),
+                &platform::format_date(league.deadline(round)),
+            ],
+        ),
+        None if now < league.info.starts_at => locale.fill(
+            Phrase::LeagueRounds,
+            &[
+                &rounds.to_string(),
+                &platform::format_date(league.info.starts_at),
+            ],
+        ),
+        None => locale.get(Phrase::SeasonOver).to_string(),
+    };
+    let mut value = locale.fill(
+        Phrase::LeagueHeader,
+        &[
+            &league.info.name,
+            &league.players.len().to_string(),
+            &season,
+        ],
+    );
+    value.push('\n');
+
+    let fixtures = league.fixtures();
+    if league.players.contains(&own) {
+        value.push_str(&format!("\n{}\n", locale.get(Phrase::YourFixtures)));
+        for fixture in fixtures.iter().filter(|fixture| fixture.plays(&own)) {
+            let deadline = league.deadline(fixture.round);
+            let state = match league.result(fixture, &page.results) {
+                Some(result) => match result.winner {
+                    None => locale.get(Phrase::Drawn).to_string(),
+                    Some(winner) => {
+                        let winner_pubkey = if winner == 1 {
+                            result.p1_pubkey
+                        } else {
+                            result.p2_pubkey
+                        };
+                        let won = winner_pubkey == own;
+                        locale
+                            .get(if won { Phrase::Won } else { Phrase::Lost })
+                            .to_string()
+                    }
+                },
+                None if deadline <= now => locale.get(Phrase::NotPlayed).to_string(),
+                None if deadline - LEAGUE_ROUND > now => locale.fill(
+                    Phrase::FromDate,
+                    &[&platform::format_date(deadline - LEAGUE_ROUND)],
+                ),
+                None => locale.fill(Phrase::PlayBy, &[&platform::format_date(deadline)]),
+            };
+            value.push_str(&locale.fill(
+                Phrase::FixtureRound,
+                &[
+                    &(fixture.round + 1).to_string(),
+                    &short_pubkey(&fixture.opponent(&own)),
+                    &state,
+                ],
+            ));
+            value.push('\n');
+        }
+        if fixtures.len() < rounds * league.players.len() / 2 {
+            value.push_str(&format!("{}\n", locale.get(Phrase::SitsOut)));
+        }
+    } else {
+        value.push_str(&format!("\n{}\n", locale.get(Phrase::NotInLeague)));
+    }
+
+    value.push_str(&format!("\n{}\n", locale.get(Phrase::LeagueTable)));
+    for (rank, standing) in league.standings(&page.results).iter().enumerate() {
+        let name = standing
+            .name
+            .clone()
+            .unwrap_or_else(|| short_pubkey(&standing.pubkey));
+        value.push_str(&format!(
+            "{}. {}  {} {} {} {} {}\n",
+            rank + 1,
+            name,
+            standing.points,
+            standing.played,
+            standing.won,
+            standing.drawn,
+            standing.lost
+        ));
+    }
+
+    value
+}
+
+fn cleanup_league(mut commands: Commands, ui: Query<Entity, With<LeagueUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// opens the league page of a league address, an naddr
+#[wasm_bindgen]
+pub fn show_league(address: String) {
+    if let Ok(mut open) = OPEN_LEAGUE.lock() {
+        *open = Some(address.trim().to_string());
+    }
+}
+
+// the players' npubs separated by commas or spaces, the season starts in the given days
+#[wasm_bindgen]
+pub fn create_league(name: String, npubs: String, days: u32) {
+    if let Ok(mut new) = NEW_LEAGUE.lock() {
+        *new = Some((name.trim().to_string(), npubs, days));
+    }
+}
@@ -0,0 +1,177 @@
+use bevy::{
+    asset::{LoadState, UntypedAssetId},
+    prelude::*,
+};
+
+use crate::{
+    audio_plugin::GameSound,
+    components::{LoadingBar, LoadingUi},
+    locale_plugin::{Locale, Phrase},
+    platform,
+    theme_plugin::Theme,
+    AppState,
+};
+
+const BAR_WIDTH: f32 = 240.0;
+
+// every file the game loads, requested once at startup. the rest of the game takes its
+// handles from here instead of loading by path. only the sounds and the font are files,
+// the coins and the board are drawn by the theme
+#[derive(Resource)]
+pub struct GameAssets {
+    coin_drop: Handle<AudioSource>,
+    win: Handle<AudioSource>,
+    loss: Handle<AudioSource>,
+    draw: Handle<AudioSource>,
+    your_turn: Handle<AudioSource>,
+    // noto sans jp, bevy's built-in font has no japanese
+    pub font: Handle<Font>,
+}
+
+impl GameAssets {
+    pub fn sound(&self, sound: GameSound) -> Handle<AudioSource> {
+        match sound {
+            GameSound::CoinDrop => self.coin_drop.clone(),
+            GameSound::Win => self.win.clone(),
+            GameSound::Loss => self.loss.clone(),
+            GameSound::Draw => self.draw.clone(),
+            GameSound::YourTurn => self.your_turn.clone(),
+        }
+    }
+
+    fn ids(&self) -> [UntypedAssetId; 6] {
+        [
+            self.coin_drop.id().untyped(),
+            self.win.id().untyped(),
+            self.loss.id().untyped(),
+            self.draw.id().untyped(),
+            self.your_turn.id().untyped(),
+            self.font.id().untyped(),
+        ]
+    }
+}
+
+impl FromWorld for GameAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        GameAssets {
+            coin_drop: asset_server.load("sounds/coin_drop.wav"),
+            win: asset_server.load("sounds/win.wav"),
+            loss: asset_server.load("sounds/loss.wav"),
+            draw: asset_server.load("sounds/draw.wav"),
+            your_turn: asset_server.load("sounds/your_turn.wav"),
+            font: asset_server.load("fonts/NotoSansJP-Regular.ttf"),
+        }
+    }
+}
+
+// the app starts in the loading state with a progress bar and goes on to the menu once
+// every file loaded. a file that failed is logged and doesn't hold up the game, its
+// sound stays silent and without the font the texts keep bevy's
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameAssets>()
+            .add_systems(OnEnter(AppState::Loading), setup_loading)
+            .add_systems(Update, check_loading.run_if(in_state(AppState::Loading)))
+            .add_systems(OnExit(AppState::Loading), cleanup_loading);
+    }
+}
+
+fn setup_loading(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(LoadingUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                locale.get(Phrase::Loading),
+                TextStyle {
+                    color: theme.ui.text,
+                    font_size: 20.0,
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(10.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.85, 0.85, 0.85).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.11, 0.31, 0.77).into(),
+                        ..default()
+                    })
+                    .insert(LoadingBar);
+                });
+        });
+}
+
+fn check_loading(
+    asset_server: Res<AssetServer>,
+    assets: Res<GameAssets>,
+    mut bar: Query<&mut Style, With<LoadingBar>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut failed: Local<Vec<UntypedAssetId>>,
+) {
+    let ids = assets.ids();
+    let mut done = 0;
+    for id in ids {
+        match asset_server.get_load_state(id) {
+            Some(LoadState::Loaded) => done += 1,
+            Some(LoadState::Failed) => {
+                done += 1;
+                if !failed.contains(&id) {
+                    failed.push(id);
+                    error!("Error loading {:?}", asset_server.get_path(id));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let width = Val::Percent(done as f32 * 100.0 / ids.len() as f32);
+    for mut style in bar.iter_mut() {
+        if style.width != width {
+            style.width = width;
+        }
+    }
+
+    if done == ids.len() {
+        next_state.set(AppState::Menu);
+    }
+}
+
+// the page shows its menu once the game is ready
+fn cleanup_loading(mut commands: Commands, ui: Query<Entity, With<LoadingUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    platform::dispatch_event("wasmLoaded", None);
+}
@@ -0,0 +1,1526 @@
+use bevy::{asset::LoadState, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{engine::Difficulty, loading_plugin::GameAssets, platform, settings_plugin::Settings};
+
+// the languages the game talks in, by their two letter code
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Es,
+    Pt,
+    Ja,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [Language::En, Language::Es, Language::Pt, Language::Ja];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Es => "es",
+            Language::Pt => "pt",
+            Language::Ja => "ja",
+        }
+    }
+
+    // the name of the language in itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Es => "Español",
+            Language::Pt => "Português",
+            Language::Ja => "日本語",
+        }
+    }
+
+    // a tag like `pt-BR`, or `ja_JP.UTF-8` on the desktop, by its primary language
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let primary = tag.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        Language::ALL
+            .into_iter()
+            .find(|language| language.code() == primary)
+    }
+
+    // the browser's language, english when the game isn't translated to it
+    pub fn detect() -> Language {
+        platform::language()
+            .and_then(|tag| Language::from_tag(&tag))
+            .unwrap_or_default()
+    }
+}
+
+// every text the game shows, `{}` is filled in by `Locale::fill`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phrase {
+    Loading,
+    // the line above the board
+    You,
+    Player2,
+    PlayerResigned,
+    YouResigned,
+    OpponentResigned,
+    Beat,
+    LostTo,
+    GameOver,
+    DrewAgainst,
+    WaitingForPlayer,
+    Spectating,
+    WaitingForTeammates,
+    YourTurn,
+    TeammateTurn,
+    WaitingForConfirmation,
+    TheirTurn,
+    RatingStakes,
+    LowDelivery,
+    MoveConfirmed,
+    PopOutHint,
+    PowerUpArmed,
+    PowerUpNoneLeft,
+    PowerUpLeft,
+    CylinderHint,
+    GravityNextMove,
+    GravityInMoves,
+    CorrespondenceLastMove,
+    CorrespondenceGame,
+    NoVariants,
+    DifferentModifiers,
+    Fortune,
+    Tampered,
+    BoardsDisagree,
+    Desynced,
+    SeriesScore,
+    // the menu of the desktop app
+    CreateGame,
+    JoinById,
+    QuickMatch,
+    VsAi,
+    Puzzles,
+    Club,
+    League,
+    Settings,
+    Join,
+    Back,
+    TypeGameId,
+    LookingForGame,
+    OngoingGame,
+    OwnTurn,
+    OpponentTurn,
+    // the settings screen
+    On,
+    Off,
+    Auto,
+    Sound,
+    AnimationSpeed,
+    CoinDrop,
+    DropPreview,
+    Coins,
+    Skin,
+    ZapToUnlock,
+    Unlocked,
+    Free,
+    Colorblind,
+    HighContrast,
+    Appearance,
+    BoardColor,
+    SkinColor,
+    BoardColorInvalid,
+    TimePerMove,
+    NoClock,
+    PublishSpeed,
+    NameOrAddress,
+    Privacy,
+    Performance,
+    Difficulty,
+    EvaluationBar,
+    Relays,
+    RelaysInvalid,
+    Language,
+    EndMessages,
+    // the winner's message to the loser
+    SendMessage,
+    OpponentSays,
+    GoodGame,
+    WellPlayed,
+    ThatWasFun,
+    BetterLuck,
+    AsPlanned,
+    TryAgain,
+    LolLoser,
+    GotFourd,
+    CoinsHaveSpoken,
+    // series
+    NextGameIn,
+    WonSeries,
+    LostSeries,
+    SeriesDrawn,
+    SeriesOver,
+    // the game review and the analysis board
+    Red,
+    Yellow,
+    ShowMissedWin,
+    Close,
+    ReviewSummary,
+    ReviewingMove,
+    StartOfGame,
+    WinMissed,
+    PlaysBest,
+    PlaysVerdict,
+    Inaccuracy,
+    Blunder,
+    GameReview,
+    StepBack,
+    ResetToGame,
+    ShareAsChallenge,
+    SideWins,
+    Drawn,
+    ToMove,
+    ToMoveSuggestion,
+    AnalysisBoard,
+    // studies on the analysis board
+    FollowStudy,
+    ShareAsStudy,
+    Note,
+    TakeControl,
+    RequestControl,
+    HandOverTo,
+    EndStudy,
+    LeaveStudy,
+    StudyLeadOne,
+    StudyLead,
+    StudyYouControl,
+    StudyHasControl,
+    StudyNote,
+    NotePrompt,
+    // leagues and clubs
+    NotAnNpub,
+    NamedNotAnNpub,
+    NotLeagueAddress,
+    NoLeagueGame,
+    ShareLeague,
+    NewPlayerNpub,
+    LeagueLoading,
+    LeagueNotFound,
+    PlayThisRound,
+    Share,
+    AddPlayer,
+    LeagueHeader,
+    LeagueRound,
+    LeagueRounds,
+    SeasonOver,
+    YourFixtures,
+    Won,
+    Lost,
+    NotPlayed,
+    FromDate,
+    PlayBy,
+    FixtureRound,
+    SitsOut,
+    NotInLeague,
+    LeagueTable,
+    NotClubAddress,
+    ShareClub,
+    NewMemberNpub,
+    TournamentName,
+    TournamentHours,
+    TournamentHoursInvalid,
+    ClubLoading,
+    ClubNotFound,
+    ClubLobby,
+    Invite,
+    AddMember,
+    ScheduleTournament,
+    ClubMembers,
+    NotMember,
+    LookingForClubGame,
+    Tournaments,
+    NoneScheduled,
+    UnderWay,
+    StartsIn,
+    PerMove,
+    ClubLeaderboard,
+    NoMemberGames,
+    LeaderboardEntry,
+    // scheduled games
+    ScheduleWithOther,
+    GameStartsNow,
+    GameStartsIn,
+    StartNowVs,
+    ScheduledVs,
+    // a game joined by a third player and the speed stats
+    GameIsFull,
+    SpectateInstead,
+    Spectate,
+    BackToMenu,
+    StatsLoading,
+    SpeedStats,
+    YourBest,
+    FastestWin,
+    FewestMovesToWin,
+    MovesEntry,
+    FastestWins,
+    NoTimedGames,
+    // the evaluation bar and the engine
+    Evaluating,
+    BookDraw,
+    BookWins,
+    EngineFavors,
+    EngineEven,
+    Random,
+    Shallow,
+    Deep,
+    EngineName,
+    UndoDeclined,
+    AllowUndo,
+    // presence, notices, links, challenges, blitz drop, history and shared results
+    LastSeen,
+    OfflineLastSeen,
+    OpponentThinking,
+    OpponentOnline,
+    JustNow,
+    MinutesAgo,
+    HoursAgo,
+    DaysAgo,
+    LeaderboardLoading,
+    Leaderboard,
+    NoRatedGames,
+    RedPlayer,
+    YellowPlayer,
+    BlitzDrop,
+    BlitzWins,
+    BlitzDraw,
+    BlitzGo,
+    BlitzClaimed,
+    BlitzWait,
+    JumpedTheGun,
+    SlowDevice,
+    NoForcedWinToShare,
+    OneMove,
+    ManyMoves,
+    FindTheWin,
+    ChallengePosted,
+    ChallengeNoWin,
+    SolvedChallenge,
+    MissedChallenge,
+    Maintenance,
+    NewVersion,
+    Tournament,
+    InvitationInvalid,
+    UnknownVariant,
+    NotANumber,
+    BadSeriesLength,
+    BadWager,
+    BadTimeControl,
+    NostrError,
+    ChallengerMismatch,
+    InvitationMismatch,
+    NotAGameLink,
+    YourTurnTitle,
+    YourOpponent,
+    OpponentPlayed,
+    Versus,
+    MyGames,
+    MyGamesLoading,
+    NoGamesFound,
+    Unfinished,
+    Unknown,
+    HistoryEntry,
+    UnknownPlayer,
+    SharedWin,
+    SharedLoss,
+    PostDrew,
+    PostBeat,
+    PostLost,
+    ResultPost,
+    // puzzles as the opponent's name
+    PuzzleWinIn,
+    ChallengeWinIn,
+    PuzzleMissed,
+    // the name entry, skin zaps and columns of fortune modifiers
+    NameOrLightningAddress,
+    UsernameHint,
+    UsernameEmpty,
+    UsernameTooLong,
+    NotLightningAddress,
+    Save,
+    Cancel,
+    NoZapAddress,
+    SkinIsFree,
+    ZapUnreachable,
+    NoZaps,
+    ZapAmount,
+    ZapSigning,
+    ZapNoAnswer,
+    ZapRefused,
+    ColumnLocked,
+    CoinPopped,
+    BoardTurned,
+}
+
+impl Phrase {
+    // english, spanish, portuguese and japanese, in the order of `Language::ALL`
+    fn texts(self) -> [&'static str; 4] {
+        match self {
+            Phrase::Loading => [
+                "Loading...",
+                "Cargando...",
+                "Carregando...",
+                "読み込み中...",
+            ],
+            Phrase::You => ["You", "Tú", "Você", "あなた"],
+            Phrase::Player2 => ["Player 2", "Jugador 2", "Jogador 2", "プレイヤー2"],
+            Phrase::PlayerResigned => [
+                "Player {} resigned",
+                "El jugador {} se rindió",
+                "O jogador {} desistiu",
+                "プレイヤー{}が投了しました",
+            ],
+            Phrase::YouResigned => [
+                "You resigned",
+                "Te rendiste",
+                "Você desistiu",
+                "投了しました",
+            ],
+            Phrase::OpponentResigned => [
+                "Opponent resigned - you win!",
+                "El rival se rindió - ¡ganaste!",
+                "O adversário desistiu - você venceu!",
+                "相手が投了 - あなたの勝ち!",
+            ],
+            Phrase::Beat => [
+                "{} beat {}",
+                "{} le ganó a {}",
+                "{} venceu {}",
+                "{}が{}に勝利",
+            ],
+            Phrase::LostTo => [
+                "{} lost to {}",
+                "{} perdió contra {}",
+                "{} perdeu para {}",
+                "{}は{}に敗北",
+            ],
+            Phrase::GameOver => [
+                "Game Over!!",
+                "¡¡Fin de la partida!!",
+                "Fim de jogo!!",
+                "ゲーム終了!!",
+            ],
+            Phrase::DrewAgainst => [
+                "{} drew against {}",
+                "{} empató con {}",
+                "{} empatou com {}",
+                "{}と{}は引き分け",
+            ],
+            Phrase::WaitingForPlayer => [
+                "Waiting for player to join...",
+                "Esperando a que se una un jugador...",
+                "Esperando um jogador entrar...",
+                "対戦相手の参加を待っています...",
+            ],
+            Phrase::Spectating => ["Spectating", "Observando", "Assistindo", "観戦中"],
+            Phrase::WaitingForTeammates => [
+                "Waiting for teammates, {} of {} seated",
+                "Esperando al equipo, {} de {} sentados",
+                "Esperando a equipe, {} de {} sentados",
+                "チームメイトを待っています、{}/{}人",
+            ],
+            Phrase::YourTurn => [
+                "Its your turn {}",
+                "Es tu turno {}",
+                "É a sua vez {}",
+                "あなたの番です {}",
+            ],
+            Phrase::TeammateTurn => [
+                "Your teammate's turn",
+                "Turno de tu compañero",
+                "Vez do seu colega de equipe",
+                "チームメイトの番です",
+            ],
+            Phrase::WaitingForConfirmation => [
+                "Waiting for opponent confirmation...",
+                "Esperando la confirmación del rival...",
+                "Esperando a confirmação do adversário...",
+                "相手の確認を待っています...",
+            ],
+            Phrase::TheirTurn => ["{}'s turn", "Turno de {}", "Vez de {}", "{}の番です"],
+            Phrase::RatingStakes => [
+                "Win {} / Draw {} / Loss {}",
+                "Victoria {} / Empate {} / Derrota {}",
+                "Vitória {} / Empate {} / Derrota {}",
+                "勝ち {} / 引き分け {} / 負け {}",
+            ],
+            Phrase::LowDelivery => [
+                "Warning: last move reached only {} relay(s)",
+                "Aviso: la última jugada llegó solo a {} relay(s)",
+                "Aviso: a última jogada chegou a só {} relay(s)",
+                "警告: 最後の手は{}個のリレーにしか届いていません",
+            ],
+            Phrase::MoveConfirmed => [
+                "Move confirmed by {} relay(s)",
+                "Jugada confirmada por {} relay(s)",
+                "Jogada confirmada por {} relay(s)",
+                "{}個のリレーが手を確認しました",
+            ],
+            Phrase::PopOutHint => [
+                "Pop Out: press one of your bottom coins to pop it",
+                "Pop Out: pulsa una de tus fichas de abajo para sacarla",
+                "Pop Out: toque uma das suas fichas de baixo para tirá-la",
+                "Pop Out: 一番下の自分のコインを押して抜きます",
+            ],
+            Phrase::PowerUpArmed => [
+                "Power Up: {} armed, press a column to play it",
+                "Power Up: {} listo, pulsa una columna para jugarlo",
+                "Power Up: {} pronto, toque uma coluna para jogá-lo",
+                "Power Up: {}を準備、列を押して使います",
+            ],
+            Phrase::PowerUpNoneLeft => [
+                "Power Up: no specials left",
+                "Power Up: no quedan especiales",
+                "Power Up: nenhum especial restante",
+                "Power Up: 特殊コインは残っていません",
+            ],
+            Phrase::PowerUpLeft => [
+                "Power Up: {} left (1 anvil, 2 bomb)",
+                "Power Up: quedan {} (1 yunque, 2 bomba)",
+                "Power Up: restam {} (1 bigorna, 2 bomba)",
+                "Power Up: 残り {} (1 金床、2 爆弾)",
+            ],
+            Phrase::CylinderHint => [
+                "Cylinder: lines wrap around the left and right edge",
+                "Cilindro: las líneas siguen del borde izquierdo al derecho",
+                "Cilindro: as linhas continuam da borda esquerda à direita",
+                "シリンダー: 列は左右の端でつながります",
+            ],
+            Phrase::GravityNextMove => [
+                "Gravity Shift: the board turns over after the next move",
+                "Gravity Shift: el tablero gira tras la próxima jugada",
+                "Gravity Shift: o tabuleiro vira após a próxima jogada",
+                "Gravity Shift: 次の手の後に盤面が回転します",
+            ],
+            Phrase::GravityInMoves => [
+                "Gravity Shift: the board turns over in {} moves",
+                "Gravity Shift: el tablero gira en {} jugadas",
+                "Gravity Shift: o tabuleiro vira em {} jogadas",
+                "Gravity Shift: {}手後に盤面が回転します",
+            ],
+            Phrase::CorrespondenceLastMove => [
+                "Correspondence, last move {}",
+                "Por correspondencia, última jugada {}",
+                "Por correspondência, última jogada {}",
+                "通信対局、最後の手 {}",
+            ],
+            Phrase::CorrespondenceGame => [
+                "Correspondence game",
+                "Partida por correspondencia",
+                "Jogo por correspondência",
+                "通信対局",
+            ],
+            Phrase::NoVariants => [
+                "Warning: the opponent's client doesn't support variants",
+                "Aviso: el cliente del rival no admite variantes",
+                "Aviso: o cliente do adversário não suporta variantes",
+                "警告: 相手のクライアントはバリアントに対応していません",
+            ],
+            Phrase::DifferentModifiers => [
+                "Warning: the opponent's client draws different random modifiers",
+                "Aviso: el cliente del rival sortea otros modificadores",
+                "Aviso: o cliente do adversário sorteia outros modificadores",
+                "警告: 相手のクライアントは別のランダム修飾を引きます",
+            ],
+            Phrase::Fortune => [
+                "Fortune: {}",
+                "Fortuna: {}",
+                "Sorte: {}",
+                "フォーチュン: {}",
+            ],
+            Phrase::Tampered => [
+                "Warning: the move history was tampered with, {}",
+                "Aviso: el historial de jugadas fue alterado, {}",
+                "Aviso: o histórico de jogadas foi adulterado, {}",
+                "警告: 手の履歴が改ざんされています、{}",
+            ],
+            Phrase::BoardsDisagree => [
+                "Boards disagree, reload the game to rebuild it from the relays",
+                "Los tableros no coinciden, recarga para reconstruirlo desde los relays",
+                "Os tabuleiros não batem, recarregue para reconstruí-lo dos relays",
+                "盤面が一致しません、再読み込みしてリレーから復元してください",
+            ],
+            Phrase::Desynced => [
+                "Desync detected: {}, resyncing...",
+                "Desincronización: {}, resincronizando...",
+                "Dessincronização: {}, ressincronizando...",
+                "同期ずれを検出: {}、再同期中...",
+            ],
+            Phrase::SeriesScore => [
+                "Game {} of {}: You {} - {} Opponent",
+                "Partida {} de {}: Tú {} - {} Rival",
+                "Jogo {} de {}: Você {} - {} Adversário",
+                "{}/{}局目: あなた {} - {} 相手",
+            ],
+            Phrase::CreateGame => ["Create game", "Crear partida", "Criar jogo", "対局を作成"],
+            Phrase::JoinById => ["Join by ID", "Unirse por ID", "Entrar por ID", "IDで参加"],
+            Phrase::QuickMatch => [
+                "Quick match",
+                "Partida rápida",
+                "Partida rápida",
+                "クイックマッチ",
+            ],
+            Phrase::VsAi => ["vs AI", "contra la IA", "contra a IA", "AIと対戦"],
+            Phrase::Puzzles => [
+                "Puzzle ({} mined)",
+                "Problema ({} encontrados)",
+                "Quebra-cabeça ({} encontrados)",
+                "パズル ({}問)",
+            ],
+            Phrase::Club => ["Club", "Club", "Clube", "クラブ"],
+            Phrase::League => ["League", "Liga", "Liga", "リーグ"],
+            Phrase::Settings => ["Settings", "Ajustes", "Configurações", "設定"],
+            Phrase::Join => ["Join", "Unirse", "Entrar", "参加"],
+            Phrase::Back => ["Back", "Volver", "Voltar", "戻る"],
+            Phrase::TypeGameId => [
+                "Type the id of the game",
+                "Escribe el id de la partida",
+                "Digite o id do jogo",
+                "対局のIDを入力してください",
+            ],
+            Phrase::LookingForGame => [
+                "Looking for a game...",
+                "Buscando una partida...",
+                "Procurando um jogo...",
+                "対局を探しています...",
+            ],
+            Phrase::OngoingGame => ["vs {}, {}", "contra {}, {}", "contra {}, {}", "{}戦、{}"],
+            Phrase::OwnTurn => ["your turn", "tu turno", "sua vez", "あなたの番"],
+            Phrase::OpponentTurn => ["their turn", "su turno", "vez dele", "相手の番"],
+            Phrase::On => ["on", "sí", "sim", "オン"],
+            Phrase::Off => ["off", "no", "não", "オフ"],
+            Phrase::Auto => ["Auto", "Auto", "Auto", "自動"],
+            Phrase::Sound => ["Sound", "Sonido", "Som", "サウンド"],
+            Phrase::AnimationSpeed => [
+                "Animation speed",
+                "Velocidad de animación",
+                "Velocidade da animação",
+                "アニメーション速度",
+            ],
+            Phrase::CoinDrop => [
+                "Coin drop",
+                "Caída de fichas",
+                "Queda das fichas",
+                "コインの落下",
+            ],
+            Phrase::DropPreview => [
+                "Drop preview",
+                "Vista previa de caída",
+                "Prévia da queda",
+                "落下プレビュー",
+            ],
+            Phrase::Coins => ["Coins", "Fichas", "Fichas", "コイン"],
+            Phrase::Skin => ["Skin", "Aspecto", "Visual", "スキン"],
+            Phrase::ZapToUnlock => [
+                "zap {} sats to unlock",
+                "zapea {} sats para desbloquear",
+                "zap de {} sats para desbloquear",
+                "{} satsのzapで解除",
+            ],
+            Phrase::Unlocked => ["unlocked", "desbloqueado", "desbloqueado", "解除済み"],
+            Phrase::Free => ["free", "gratis", "grátis", "無料"],
+            Phrase::Colorblind => [
+                "Colorblind mode",
+                "Modo daltónico",
+                "Modo daltônico",
+                "色覚サポート",
+            ],
+            Phrase::HighContrast => [
+                "High contrast board",
+                "Tablero de alto contraste",
+                "Tabuleiro de alto contraste",
+                "高コントラストの盤面",
+            ],
+            Phrase::Appearance => ["Appearance", "Apariencia", "Aparência", "外観"],
+            Phrase::BoardColor => [
+                "Board color",
+                "Color del tablero",
+                "Cor do tabuleiro",
+                "盤面の色",
+            ],
+            Phrase::SkinColor => ["skin", "del aspecto", "do visual", "スキン"],
+            Phrase::BoardColorInvalid => [
+                "Board color must be a hex color like #3366ff",
+                "El color del tablero debe ser hexadecimal, como #3366ff",
+                "A cor do tabuleiro deve ser hexadecimal, como #3366ff",
+                "盤面の色は#3366ffのような16進数で指定してください",
+            ],
+            Phrase::TimePerMove => [
+                "Time per move",
+                "Tiempo por jugada",
+                "Tempo por jogada",
+                "1手の持ち時間",
+            ],
+            Phrase::NoClock => ["no clock", "sin reloj", "sem relógio", "時計なし"],
+            Phrase::PublishSpeed => [
+                "Publish game speed",
+                "Publicar la velocidad de juego",
+                "Publicar a velocidade de jogo",
+                "対局速度を公開",
+            ],
+            Phrase::NameOrAddress => [
+                "Name / LN address",
+                "Nombre / dirección LN",
+                "Nome / endereço LN",
+                "名前 / LNアドレス",
+            ],
+            Phrase::Privacy => [
+                "Privacy mode",
+                "Modo privado",
+                "Modo privado",
+                "プライバシーモード",
+            ],
+            Phrase::Performance => ["Performance", "Rendimiento", "Desempenho", "パフォーマンス"],
+            Phrase::Difficulty => [
+                "AI difficulty",
+                "Dificultad de la IA",
+                "Dificuldade da IA",
+                "AIの強さ",
+            ],
+            Phrase::EvaluationBar => [
+                "Evaluation bar",
+                "Barra de evaluación",
+                "Barra de avaliação",
+                "評価バー",
+            ],
+            Phrase::Relays => ["Relays", "Relays", "Relays", "リレー"],
+            Phrase::RelaysInvalid => [
+                "Relay urls must start with wss:// or ws://",
+                "Las urls de los relays deben empezar con wss:// o ws://",
+                "As urls dos relays devem começar com wss:// ou ws://",
+                "リレーのURLはwss://かws://で始めてください",
+            ],
+            Phrase::Language => ["Language", "Idioma", "Idioma", "言語"],
+            Phrase::EndMessages => [
+                "End messages",
+                "Mensajes finales",
+                "Mensagens finais",
+                "終局メッセージ",
+            ],
+            Phrase::SendMessage => [
+                "Send a message:",
+                "Envía un mensaje:",
+                "Envie uma mensagem:",
+                "メッセージを送る:",
+            ],
+            Phrase::OpponentSays => ["{}: {}", "{}: {}", "{}: {}", "{}: {}"],
+            Phrase::GoodGame => [
+                "Good game!",
+                "¡Buena partida!",
+                "Boa partida!",
+                "いい試合でした!",
+            ],
+            Phrase::WellPlayed => [
+                "Well played, thanks for the game",
+                "Bien jugado, gracias por la partida",
+                "Bem jogado, obrigado pela partida",
+                "お見事、対局ありがとう",
+            ],
+            Phrase::ThatWasFun => [
+                "That was fun, rematch?",
+                "Fue divertido, ¿revancha?",
+                "Foi divertido, revanche?",
+                "楽しかった、もう一局?",
+            ],
+            Phrase::BetterLuck => [
+                "GG, better luck next time",
+                "GG, más suerte la próxima",
+                "GG, mais sorte na próxima",
+                "GG、次は頑張って",
+            ],
+            Phrase::AsPlanned => [
+                "Four in a row, as planned",
+                "Cuatro en línea, como estaba previsto",
+                "Quatro em linha, como planejado",
+                "計画通りの四目並べ",
+            ],
+            Phrase::TryAgain => [
+                "Want to try again?",
+                "¿Quieres intentarlo otra vez?",
+                "Quer tentar de novo?",
+                "もう一度挑戦する?",
+            ],
+            Phrase::LolLoser => ["lol loser", "jaja perdiste", "kkk perdeu", "笑 負けたね"],
+            Phrase::GotFourd => [
+                "You got four'd!",
+                "¡Te cayeron cuatro!",
+                "Levou um quatro!",
+                "四つ並べられたね!",
+            ],
+            Phrase::CoinsHaveSpoken => [
+                "The coins have spoken",
+                "Las fichas han hablado",
+                "As fichas falaram",
+                "コインがそう言っている",
+            ],
+            Phrase::NextGameIn => [
+                "Next game in {}s",
+                "Siguiente partida en {}s",
+                "Próximo jogo em {}s",
+                "次の対局まで{}秒",
+            ],
+            Phrase::WonSeries => [
+                "You won the series",
+                "Ganaste la serie",
+                "Você venceu a série",
+                "シリーズに勝ちました",
+            ],
+            Phrase::LostSeries => [
+                "You lost the series",
+                "Perdiste la serie",
+                "Você perdeu a série",
+                "シリーズに負けました",
+            ],
+            Phrase::SeriesDrawn => [
+                "The series is drawn",
+                "La serie terminó en empate",
+                "A série terminou empatada",
+                "シリーズは引き分けです",
+            ],
+            Phrase::SeriesOver => [
+                "Series over\n\n{} {} - {}",
+                "Serie terminada\n\n{} {} - {}",
+                "Série encerrada\n\n{} {} - {}",
+                "シリーズ終了\n\n{} {} - {}",
+            ],
+            Phrase::Red => ["red", "rojo", "vermelho", "赤"],
+            Phrase::Yellow => ["yellow", "amarillo", "amarelo", "黄"],
+            Phrase::ShowMissedWin => [
+                "Show missed win",
+                "Ver la victoria perdida",
+                "Ver a vitória perdida",
+                "逃した勝ちを見る",
+            ],
+            Phrase::Close => ["Close", "Cerrar", "Fechar", "閉じる"],
+            Phrase::ReviewSummary => [
+                "{}: {} blunders, {} inaccuracies",
+                "{}: {} errores graves, {} imprecisiones",
+                "{}: {} erros graves, {} imprecisões",
+                "{}: 悪手{}、疑問手{}",
+            ],
+            Phrase::ReviewingMove => [
+                "Reviewing move {} of {}",
+                "Revisando la jugada {} de {}",
+                "Revisando a jogada {} de {}",
+                "{}/{}手目を検討中",
+            ],
+            Phrase::StartOfGame => [
+                "Start of the game",
+                "Inicio de la partida",
+                "Início do jogo",
+                "対局開始",
+            ],
+            Phrase::WinMissed => [
+                "The win {} missed: column {} instead of {}",
+                "La victoria que {} perdió: columna {} en lugar de {}",
+                "A vitória que {} perdeu: coluna {} em vez de {}",
+                "{}が逃した勝ち: {}列目 ({}列目ではなく)",
+            ],
+            Phrase::PlaysBest => [
+                "{} plays column {}, best",
+                "{} juega la columna {}, la mejor",
+                "{} joga a coluna {}, a melhor",
+                "{}は{}列目、最善手",
+            ],
+            Phrase::PlaysVerdict => [
+                "{} plays column {}, {} (best was column {})",
+                "{} juega la columna {}, {} (la mejor era la columna {})",
+                "{} joga a coluna {}, {} (a melhor era a coluna {})",
+                "{}は{}列目、{} (最善手は{}列目)",
+            ],
+            Phrase::Inaccuracy => ["inaccuracy", "imprecisión", "imprecisão", "疑問手"],
+            Phrase::Blunder => ["blunder", "error grave", "erro grave", "悪手"],
+            Phrase::GameReview => [
+                "Game review - move {} of {}\n{}",
+                "Revisión de la partida - jugada {} de {}\n{}",
+                "Revisão do jogo - jogada {} de {}\n{}",
+                "対局の検討 - {}/{}手目\n{}",
+            ],
+            Phrase::StepBack => ["Back", "Atrás", "Voltar", "一手戻る"],
+            Phrase::ResetToGame => [
+                "Reset to game",
+                "Volver a la partida",
+                "Voltar ao jogo",
+                "対局に戻す",
+            ],
+            Phrase::ShareAsChallenge => [
+                "Share as challenge",
+                "Compartir como reto",
+                "Compartilhar como desafio",
+                "チャレンジとして共有",
+            ],
+            Phrase::SideWins => ["{} wins", "gana {}", "{} vence", "{}の勝ち"],
+            Phrase::Drawn => ["draw", "empate", "empate", "引き分け"],
+            Phrase::ToMove => ["{} to move", "juega {}", "{} joga", "{}の手番"],
+            Phrase::ToMoveSuggestion => [
+                "{} to move, engine suggests column {}",
+                "juega {}, el motor sugiere la columna {}",
+                "{} joga, o motor sugere a coluna {}",
+                "{}の手番、エンジンの推奨は{}列目",
+            ],
+            Phrase::AnalysisBoard => [
+                "Analysis board - not the live game ({} moves)\n{}",
+                "Tablero de análisis - no es la partida en curso ({} jugadas)\n{}",
+                "Tabuleiro de análise - não é o jogo ao vivo ({} jogadas)\n{}",
+                "解析ボード - 実際の対局ではありません ({}手)\n{}",
+            ],
+            Phrase::FollowStudy => [
+                "Follow {}'s study",
+                "Seguir el estudio de {}",
+                "Seguir o estudo de {}",
+                "{}の研究を見る",
+            ],
+            Phrase::ShareAsStudy => [
+                "Share as study",
+                "Compartir como estudio",
+                "Compartilhar como estudo",
+                "研究として共有",
+            ],
+            Phrase::Note => ["Note", "Nota", "Nota", "メモ"],
+            Phrase::TakeControl => [
+                "Take control",
+                "Tomar el control",
+                "Assumir o controle",
+                "操作を取り戻す",
+            ],
+            Phrase::RequestControl => [
+                "Request control",
+                "Pedir el control",
+                "Pedir o controle",
+                "操作をリクエスト",
+            ],
+            Phrase::HandOverTo => [
+                "Hand over to {}",
+                "Ceder a {}",
+                "Passar para {}",
+                "{}に操作を渡す",
+            ],
+            Phrase::EndStudy => [
+                "End study",
+                "Terminar el estudio",
+                "Encerrar o estudo",
+                "研究を終了",
+            ],
+            Phrase::LeaveStudy => [
+                "Leave study",
+                "Salir del estudio",
+                "Sair do estudo",
+                "研究から抜ける",
+            ],
+            Phrase::StudyLeadOne => [
+                "Study: you lead, 1 following",
+                "Estudio: diriges, 1 siguiendo",
+                "Estudo: você conduz, 1 seguindo",
+                "研究: あなたが操作中、1人が参加",
+            ],
+            Phrase::StudyLead => [
+                "Study: you lead, {} following",
+                "Estudio: diriges, {} siguiendo",
+                "Estudo: você conduz, {} seguindo",
+                "研究: あなたが操作中、{}人が参加",
+            ],
+            Phrase::StudyYouControl => [
+                "Study: you have control",
+                "Estudio: tienes el control",
+                "Estudo: você tem o controle",
+                "研究: あなたが操作中",
+            ],
+            Phrase::StudyHasControl => [
+                "Study: {} has control",
+                "Estudio: {} tiene el control",
+                "Estudo: {} tem o controle",
+                "研究: {}が操作中",
+            ],
+            Phrase::StudyNote => [
+                "{}\nNote: {}",
+                "{}\nNota: {}",
+                "{}\nNota: {}",
+                "{}\nメモ: {}",
+            ],
+            Phrase::NotePrompt => [
+                "Note on this position",
+                "Nota sobre esta posición",
+                "Nota sobre esta posição",
+                "この局面のメモ",
+            ],
+            Phrase::NotAnNpub => [
+                "This is not an npub: {}.",
+                "Esto no es un npub: {}.",
+                "Isto não é um npub: {}.",
+                "npubではありません: {}。",
+            ],
+            Phrase::NamedNotAnNpub => [
+                "{} is not an npub: {}.",
+                "{} no es un npub: {}.",
+                "{} não é um npub: {}.",
+                "{}はnpubではありません: {}。",
+            ],
+            Phrase::NotLeagueAddress => [
+                "This is not a league address.",
+                "Esta no es una dirección de liga.",
+                "Este não é um endereço de liga.",
+                "リーグのアドレスではありません。",
+            ],
+            Phrase::NoLeagueGame => [
+                "You have no league game to play this round.",
+                "No tienes partida de liga en esta ronda.",
+                "Você não tem jogo da liga nesta rodada.",
+                "このラウンドに対局するリーグ戦はありません。",
+            ],
+            Phrase::ShareLeague => [
+                "Share the league address with its players",
+                "Comparte la dirección de la liga con sus jugadores",
+                "Compartilhe o endereço da liga com os jogadores",
+                "リーグのアドレスを参加者に共有してください",
+            ],
+            Phrase::NewPlayerNpub => [
+                "npub of the new player",
+                "npub del nuevo jugador",
+                "npub do novo jogador",
+                "新しいプレイヤーのnpub",
+            ],
+            Phrase::LeagueLoading => [
+                "League\n\nloading...",
+                "Liga\n\ncargando...",
+                "Liga\n\ncarregando...",
+                "リーグ\n\n読み込み中...",
+            ],
+            Phrase::LeagueNotFound => [
+                "League\n\nnot found on the relays",
+                "Liga\n\nno encontrada en los relays",
+                "Liga\n\nnão encontrada nos relays",
+                "リーグ\n\nリレーに見つかりません",
+            ],
+            Phrase::PlayThisRound => [
+                "Play this round",
+                "Jugar esta ronda",
+                "Jogar esta rodada",
+                "このラウンドを対局",
+            ],
+            Phrase::Share => ["Share", "Compartir", "Compartilhar", "共有"],
+            Phrase::AddPlayer => [
+                "Add player",
+                "Añadir jugador",
+                "Adicionar jogador",
+                "プレイヤーを追加",
+            ],
+            Phrase::LeagueHeader => [
+                "{}\n{} players, {}",
+                "{}\n{} jugadores, {}",
+                "{}\n{} jogadores, {}",
+                "{}\n{}人、{}",
+            ],
+            Phrase::LeagueRound => [
+                "round {} of {}, ends {}",
+                "ronda {} de {}, termina el {}",
+                "rodada {} de {}, termina em {}",
+                "第{}/{}ラウンド、{}まで",
+            ],
+            Phrase::LeagueRounds => [
+                "{} weekly rounds from {}",
+                "{} rondas semanales desde el {}",
+                "{} rodadas semanais a partir de {}",
+                "週1回、全{}ラウンド ({}から)",
+            ],
+            Phrase::SeasonOver => [
+                "the season is over",
+                "la temporada terminó",
+                "a temporada terminou",
+                "シーズンは終了しました",
+            ],
+            Phrase::YourFixtures => [
+                "Your fixtures",
+                "Tus partidas",
+                "Seus jogos",
+                "あなたの対戦",
+            ],
+            Phrase::Won => ["won", "ganada", "vencido", "勝ち"],
+            Phrase::Lost => ["lost", "perdida", "perdido", "負け"],
+            Phrase::NotPlayed => ["not played", "no jugada", "não jogado", "未対局"],
+            Phrase::FromDate => ["from {}", "desde el {}", "a partir de {}", "{}から"],
+            Phrase::PlayBy => [
+                "play by {}",
+                "jugar antes del {}",
+                "jogar até {}",
+                "{}までに対局",
+            ],
+            Phrase::FixtureRound => [
+                "Round {}: vs {}, {}",
+                "Ronda {}: vs {}, {}",
+                "Rodada {}: vs {}, {}",
+                "第{}ラウンド: vs {}、{}",
+            ],
+            Phrase::SitsOut => [
+                "one player sits out each round",
+                "un jugador descansa cada ronda",
+                "um jogador folga a cada rodada",
+                "各ラウンドで1人が休みになります",
+            ],
+            Phrase::NotInLeague => [
+                "you're not playing in this league",
+                "no juegas en esta liga",
+                "você não joga nesta liga",
+                "あなたはこのリーグに参加していません",
+            ],
+            Phrase::LeagueTable => [
+                "Table  pts P W D L",
+                "Tabla  pts PJ G E P",
+                "Tabela  pts J V E D",
+                "順位表  勝点 試 勝 分 負",
+            ],
+            Phrase::NotClubAddress => [
+                "This is not a club address.",
+                "Esta no es una dirección de club.",
+                "Este não é um endereço de clube.",
+                "クラブのアドレスではありません。",
+            ],
+            Phrase::ShareClub => [
+                "Share the club address to invite members",
+                "Comparte la dirección del club para invitar miembros",
+                "Compartilhe o endereço do clube para convidar membros",
+                "クラブのアドレスを共有してメンバーを招待してください",
+            ],
+            Phrase::NewMemberNpub => [
+                "npub of the new member",
+                "npub del nuevo miembro",
+                "npub do novo membro",
+                "新しいメンバーのnpub",
+            ],
+            Phrase::TournamentName => [
+                "Name of the tournament",
+                "Nombre del torneo",
+                "Nome do torneio",
+                "大会の名前",
+            ],
+            Phrase::TournamentHours => [
+                "Starts in how many hours?",
+                "¿En cuántas horas empieza?",
+                "Começa em quantas horas?",
+                "何時間後に始めますか?",
+            ],
+            Phrase::TournamentHoursInvalid => [
+                "Type the hours until the tournament starts.",
+                "Escribe las horas que faltan para el torneo.",
+                "Digite as horas até o início do torneio.",
+                "大会開始までの時間を入力してください。",
+            ],
+            Phrase::ClubLoading => [
+                "Club\n\nloading...",
+                "Club\n\ncargando...",
+                "Clube\n\ncarregando...",
+                "クラブ\n\n読み込み中...",
+            ],
+            Phrase::ClubNotFound => [
+                "Club\n\nnot found on the relays",
+                "Club\n\nno encontrado en los relays",
+                "Clube\n\nnão encontrado nos relays",
+                "クラブ\n\nリレーに見つかりません",
+            ],
+            Phrase::ClubLobby => [
+                "Club lobby",
+                "Sala del club",
+                "Sala do clube",
+                "クラブのロビー",
+            ],
+            Phrase::Invite => ["Invite", "Invitar", "Convidar", "招待"],
+            Phrase::AddMember => [
+                "Add member",
+                "Añadir miembro",
+                "Adicionar membro",
+                "メンバーを追加",
+            ],
+            Phrase::ScheduleTournament => [
+                "Schedule tournament",
+                "Programar torneo",
+                "Agendar torneio",
+                "大会を予定",
+            ],
+            Phrase::ClubMembers => [
+                "{} members, admins: {}",
+                "{} miembros, administradores: {}",
+                "{} membros, administradores: {}",
+                "メンバー{}人、管理者: {}",
+            ],
+            Phrase::NotMember => [
+                "you're not a member, ask an admin to add you",
+                "no eres miembro, pide a un administrador que te añada",
+                "você não é membro, peça a um administrador para adicioná-lo",
+                "メンバーではありません。管理者に追加を頼んでください",
+            ],
+            Phrase::LookingForClubGame => [
+                "Looking for a club game...",
+                "Buscando una partida del club...",
+                "Procurando um jogo do clube...",
+                "クラブの対局を探しています...",
+            ],
+            Phrase::Tournaments => ["Tournaments", "Torneos", "Torneios", "大会"],
+            Phrase::NoneScheduled => [
+                "none scheduled",
+                "ninguno programado",
+                "nenhum agendado",
+                "予定はありません",
+            ],
+            Phrase::UnderWay => [
+                "under way, meet in the club lobby",
+                "en curso, nos vemos en la sala del club",
+                "em andamento, encontre-se na sala do clube",
+                "開催中、クラブのロビーに集合",
+            ],
+            Phrase::StartsIn => [
+                "{}, in {}h {}m",
+                "{}, en {}h {}m",
+                "{}, em {}h {}m",
+                "{}、{}時間{}分後",
+            ],
+            Phrase::PerMove => [
+                ", {}s per move",
+                ", {}s por jugada",
+                ", {}s por jogada",
+                "、1手{}秒",
+            ],
+            Phrase::ClubLeaderboard => [
+                "Club leaderboard",
+                "Clasificación del club",
+                "Classificação do clube",
+                "クラブのランキング",
+            ],
+            Phrase::NoMemberGames => [
+                "no rated games between members yet",
+                "aún no hay partidas puntuadas entre miembros",
+                "ainda não há jogos ranqueados entre membros",
+                "メンバー同士のレーティング対局はまだありません",
+            ],
+            Phrase::LeaderboardEntry => [
+                "{}. {}  {} ({} games)",
+                "{}. {}  {} ({} partidas)",
+                "{}. {}  {} ({} jogos)",
+                "{}. {}  {} ({}局)",
+            ],
+            Phrase::ScheduleWithOther => [
+                "Schedule the game with another player.",
+                "Programa la partida con otro jugador.",
+                "Agende o jogo com outro jogador.",
+                "別のプレイヤーと対局を予定してください。",
+            ],
+            Phrase::GameStartsNow => [
+                "Your game with {} starts now",
+                "Tu partida con {} empieza ahora",
+                "Seu jogo com {} começa agora",
+                "{}との対局が始まります",
+            ],
+            Phrase::GameStartsIn => [
+                "Your game with {} starts in {} min",
+                "Tu partida con {} empieza en {} min",
+                "Seu jogo com {} começa em {} min",
+                "{}との対局は{}分後に始まります",
+            ],
+            Phrase::StartNowVs => [
+                "Start now: vs {}",
+                "Empezar ahora: vs {}",
+                "Começar agora: vs {}",
+                "今すぐ開始: vs {}",
+            ],
+            Phrase::ScheduledVs => [
+                "vs {} in {}h {}m",
+                "vs {} en {}h {}m",
+                "vs {} em {}h {}m",
+                "vs {} {}時間{}分後",
+            ],
+            Phrase::GameIsFull => [
+                "This game is full",
+                "Esta partida está completa",
+                "Este jogo está cheio",
+                "この対局は満員です",
+            ],
+            Phrase::SpectateInstead => [
+                "{} and {} are playing it. Spectate instead?",
+                "{} y {} la están jugando. ¿Quieres mirarla?",
+                "{} e {} estão jogando. Quer assistir?",
+                "{}と{}が対局中です。観戦しますか?",
+            ],
+            Phrase::Spectate => ["Spectate", "Mirar", "Assistir", "観戦"],
+            Phrase::BackToMenu => [
+                "Back to menu",
+                "Volver al menú",
+                "Voltar ao menu",
+                "メニューに戻る",
+            ],
+            Phrase::StatsLoading => [
+                "Speed Stats\n\nloading...",
+                "Estadísticas de velocidad\n\ncargando...",
+                "Estatísticas de velocidade\n\ncarregando...",
+                "スピード記録\n\n読み込み中...",
+            ],
+            Phrase::SpeedStats => [
+                "Speed Stats",
+                "Estadísticas de velocidad",
+                "Estatísticas de velocidade",
+                "スピード記録",
+            ],
+            Phrase::YourBest => [
+                "Your best: win in {} moves",
+                "Tu mejor marca: victoria en {} jugadas",
+                "Seu recorde: vitória em {} jogadas",
+                "自己ベスト: {}手で勝利",
+            ],
+            Phrase::FastestWin => [
+                ", fastest win {}",
+                ", victoria más rápida {}",
+                ", vitória mais rápida {}",
+                "、最速の勝利 {}",
+            ],
+            Phrase::FewestMovesToWin => [
+                "Fewest moves to win",
+                "Menos jugadas para ganar",
+                "Menos jogadas para vencer",
+                "最少手数の勝利",
+            ],
+            Phrase::MovesEntry => [
+                "{}. {}  {} moves",
+                "{}. {}  {} jugadas",
+                "{}. {}  {} jogadas",
+                "{}. {}  {}手",
+            ],
+            Phrase::FastestWins => [
+                "Fastest wins",
+                "Victorias más rápidas",
+                "Vitórias mais rápidas",
+                "最速の勝利",
+            ],
+            Phrase::NoTimedGames => [
+                "no timed games yet",
+                "aún no hay partidas con reloj",
+                "ainda não há jogos com relógio",
+                "時間計測された対局はまだありません",
+            ],
+            Phrase::Evaluating => ["Evaluating...", "Evaluando...", "Avaliando...", "評価中..."],
+            Phrase::BookDraw => [
+                "Book: a draw with perfect play",
+                "Libro: tablas con juego perfecto",
+                "Livro: empate com jogo perfeito",
+                "定跡: 最善手順で引き分け",
+            ],
+            Phrase::BookWins => [
+                "Book: {} wins with perfect play",
+                "Libro: gana {} con juego perfecto",
+                "Livro: {} vence com jogo perfeito",
+                "定跡: 最善手順で{}の勝ち",
+            ],
+            Phrase::EngineFavors => [
+                "Engine: {} is favored",
+                "Motor: ventaja para {}",
+                "Motor: vantagem para {}",
+                "エンジン: {}が優勢",
+            ],
+            Phrase::EngineEven => [
+                "Engine: even",
+                "Motor: igualado",
+                "Motor: equilibrado",
+                "エンジン: 互角",
+            ],
+            Phrase::Random => ["Random", "Aleatorio", "Aleatório", "ランダム"],
+            Phrase::Shallow => ["Shallow", "Superficial", "Raso", "浅い"],
+            Phrase::Deep => ["Deep", "Profundo", "Profundo", "深い"],
+            Phrase::EngineName => ["Engine ({})", "Motor ({})", "Motor ({})", "エンジン ({})"],
+            Phrase::UndoDeclined => [
+                "Your opponent declined the undo.",
+                "Tu rival rechazó deshacer la jugada.",
+                "Seu adversário recusou desfazer a jogada.",
+                "相手が待ったを断りました。",
+            ],
+            Phrase::AllowUndo => [
+                "Your opponent asks to take back their last move. Allow it?",
+                "Tu rival pide deshacer su última jugada. ¿Lo permites?",
+                "Seu adversário pede para desfazer a última jogada. Permitir?",
+                "相手が最後の手の待ったを求めています。許可しますか?",
+            ],
+            Phrase::LastSeen => ["last seen {}", "visto {}", "visto {}", "最終確認 {}"],
+            Phrase::OfflineLastSeen => ["offline, last seen {}", "desconectado, visto {}", "offline, visto {}", "オフライン、最終確認 {}"],
+            Phrase::OpponentThinking => ["● thinking…", "● pensando…", "● pensando…", "● 考慮中…"],
+            Phrase::OpponentOnline => ["● online", "● en línea", "● online", "● オンライン"],
+            Phrase::JustNow => ["just now", "justo ahora", "agora mesmo", "たった今"],
+            Phrase::MinutesAgo => ["{}m ago", "hace {} min", "há {} min", "{}分前"],
+            Phrase::HoursAgo => ["{}h ago", "hace {} h", "há {} h", "{}時間前"],
+            Phrase::DaysAgo => ["{}d ago", "hace {} d", "há {} d", "{}日前"],
+            Phrase::LeaderboardLoading => ["Leaderboard\n\nloading...", "Clasificación\n\ncargando...", "Classificação\n\ncarregando...", "ランキング\n\n読み込み中..."],
+            Phrase::Leaderboard => ["Leaderboard", "Clasificación", "Classificação", "ランキング"],
+            Phrase::NoRatedGames => ["no rated games yet", "aún no hay partidas puntuadas", "ainda não há jogos valendo rating", "レーティング対局はまだありません"],
+            Phrase::RedPlayer => ["Red", "Rojo", "Vermelho", "赤"],
+            Phrase::YellowPlayer => ["Yellow", "Amarillo", "Amarelo", "黄"],
+            Phrase::BlitzDrop => ["Blitz Drop\nRed: A or tap left - Yellow: L or tap right", "Blitz Drop\nRojo: A o toca a la izquierda - Amarillo: L o toca a la derecha", "Blitz Drop\nVermelho: A ou toque à esquerda - Amarelo: L ou toque à direita", "ブリッツドロップ\n赤: Aか左をタップ - 黄: Lか右をタップ"],
+            Phrase::BlitzWins => ["{} wins! Space or tap to play again", "¡Gana {}! Espacio o toca para jugar otra vez", "{} venceu! Espaço ou toque para jogar de novo", "{}の勝ち! スペースかタップでもう一度"],
+            Phrase::BlitzDraw => ["Draw! Space or tap to play again", "¡Empate! Espacio o toca para jugar otra vez", "Empate! Espaço ou toque para jogar de novo", "引き分け! スペースかタップでもう一度"],
+            Phrase::BlitzGo => ["GO! Column {}", "¡YA! Columna {}", "JÁ! Coluna {}", "今だ! {}列目"],
+            Phrase::BlitzClaimed => ["{} claimed column {}, wait for the light...", "{} tomó la columna {}, espera la luz...", "{} pegou a coluna {}, espere a luz...", "{}が{}列目を取りました、ライトを待って..."],
+            Phrase::BlitzWait => ["Wait for the light...", "Espera la luz...", "Espere a luz...", "ライトを待って..."],
+            Phrase::JumpedTheGun => ["{} jumped the gun!", "¡{} se adelantó!", "{} queimou a largada!", "{}のフライング!"],
+            Phrase::SlowDevice => ["Slow device, switched to reduced mode. Settings > Performance changes it.", "Dispositivo lento, se pasó al modo reducido. Ajustes > Rendimiento lo cambia.", "Dispositivo lento, mudou para o modo reduzido. Configurações > Desempenho altera isso.", "端末が遅いため軽量モードにしました。設定 > パフォーマンスで変更できます。"],
+            Phrase::NoForcedWinToShare => ["There is no forced win in this position, play on to one first.", "No hay victoria forzada en esta posición, juega hasta llegar a una.", "Não há vitória forçada nesta posição, jogue até chegar a uma.", "この局面に必勝手順はありません。必勝になるまで進めてください。"],
+            Phrase::OneMove => ["1 move", "1 jugada", "1 jogada", "1手"],
+            Phrase::ManyMoves => ["{} moves", "{} jugadas", "{} jogadas", "{}手"],
+            Phrase::FindTheWin => ["Find the win! {} to move wins in {} in this Connect 4 position: {}", "¡Encuentra la victoria! Juega {} y gana en {} en esta posición de Conecta 4: {}", "Encontre a vitória! {} joga e vence em {} nesta posição de Lig 4: {}", "勝ちを見つけよう! この四目並べの局面で{}の手番、{}で勝ち: {}"],
+            Phrase::ChallengePosted => ["Challenge posted, share the link", "Reto publicado, comparte el enlace", "Desafio publicado, compartilhe o link", "チャレンジを投稿しました。リンクを共有してください"],
+            Phrase::ChallengeNoWin => ["This challenge has no forced win to find.", "Este reto no tiene una victoria forzada que encontrar.", "Este desafio não tem vitória forçada para encontrar.", "このチャレンジには必勝手順がありません。"],
+            Phrase::SolvedChallenge => ["Solved your Connect 4 challenge, the win in {} ✅", "Resolví tu reto de Conecta 4, la victoria en {} ✅", "Resolvi seu desafio de Lig 4, a vitória em {} ✅", "四目並べのチャレンジを解きました、{}で勝ち ✅"],
+            Phrase::MissedChallenge => ["Missed the win in your Connect 4 challenge ❌", "No encontré la victoria de tu reto de Conecta 4 ❌", "Não achei a vitória do seu desafio de Lig 4 ❌", "四目並べのチャレンジの勝ちを逃しました ❌"],
+            Phrase::Maintenance => ["Maintenance", "Mantenimiento", "Manutenção", "メンテナンス"],
+            Phrase::NewVersion => ["New version", "Nueva versión", "Nova versão", "新バージョン"],
+            Phrase::Tournament => ["Tournament", "Torneo", "Torneio", "トーナメント"],
+            Phrase::InvitationInvalid => ["This invitation link is invalid: {}.", "Este enlace de invitación no es válido: {}.", "Este link de convite é inválido: {}.", "この招待リンクは無効です: {}。"],
+            Phrase::UnknownVariant => ["unknown variant {}", "variante desconocida {}", "variante desconhecida {}", "不明なバリアント {}"],
+            Phrase::NotANumber => ["{} is not a number: {}", "{} no es un número: {}", "{} não é um número: {}", "{}が数値ではありません: {}"],
+            Phrase::BadSeriesLength => ["a series can't be {} games", "una serie no puede ser de {} partidas", "uma série não pode ter {} partidas", "{}局のシリーズはできません"],
+            Phrase::BadWager => ["a wager of {} sats is out of range", "una apuesta de {} sats está fuera de rango", "uma aposta de {} sats está fora do limite", "{} satsの賭けは範囲外です"],
+            Phrase::BadTimeControl => ["{} seconds per move is out of range", "{} segundos por jugada está fuera de rango", "{} segundos por jogada está fora do limite", "1手{}秒は範囲外です"],
+            Phrase::NostrError => ["Error connecting to nostr: {}", "Error al conectar con nostr: {}", "Erro ao conectar ao nostr: {}", "nostrへの接続エラー: {}"],
+            Phrase::ChallengerMismatch => ["This challenge link does not match the player who created the game.", "Este enlace de desafío no coincide con el jugador que creó la partida.", "Este link de desafio não corresponde ao jogador que criou o jogo.", "このチャレンジリンクは対局を作成したプレイヤーと一致しません。"],
+            Phrase::InvitationMismatch => ["This invitation link does not match the game that was created.", "Este enlace de invitación no coincide con la partida creada.", "Este link de convite não corresponde ao jogo criado.", "この招待リンクは作成された対局と一致しません。"],
+            Phrase::NotAGameLink => ["This link does not point to a Connect 4 game.", "Este enlace no lleva a una partida de Conecta 4.", "Este link não leva a um jogo de Lig 4.", "このリンクは四目並べの対局ではありません。"],
+            Phrase::YourTurnTitle => ["● Your turn — unite4", "● Tu turno — unite4", "● Sua vez — unite4", "● あなたの手番 — unite4"],
+            Phrase::YourOpponent => ["Your opponent", "Tu rival", "Seu adversário", "相手"],
+            Phrase::OpponentPlayed => ["{} played, it's your turn", "{} jugó, es tu turno", "{} jogou, é sua vez", "{}が指しました。あなたの手番です"],
+            Phrase::Versus => ["vs {}", "vs {}", "vs {}", "vs {}"],
+            Phrase::MyGames => ["My Games", "Mis partidas", "Meus jogos", "自分の対局"],
+            Phrase::MyGamesLoading => ["My Games\n\nloading...", "Mis partidas\n\ncargando...", "Meus jogos\n\ncarregando...", "自分の対局\n\n読み込み中..."],
+            Phrase::NoGamesFound => ["My Games\n\nno games found", "Mis partidas\n\nno se encontraron partidas", "Meus jogos\n\nnenhum jogo encontrado", "自分の対局\n\n対局が見つかりません"],
+            Phrase::Unfinished => ["unfinished", "sin terminar", "não terminado", "未終了"],
+            Phrase::Unknown => ["unknown", "desconocido", "desconhecido", "不明"],
+            Phrase::HistoryEntry => ["{}  vs {}  {}", "{}  vs {}  {}", "{}  vs {}  {}", "{}  vs {}  {}"],
+            Phrase::UnknownPlayer => ["an unknown player", "un jugador desconocido", "um jogador desconhecido", "不明なプレイヤー"],
+            Phrase::SharedWin => ["I beat {} at #connect4\n\n\n{}\n\n", "Le gané a {} en #connect4\n\n\n{}\n\n", "Venci {} no #connect4\n\n\n{}\n\n", "#connect4 で{}に勝ちました\n\n\n{}\n\n"],
+            Phrase::SharedLoss => ["I lost to {} at #connect4\n\n{}\n\n", "Perdí contra {} en #connect4\n\n{}\n\n", "Perdi para {} no #connect4\n\n{}\n\n", "#connect4 で{}に負けました\n\n{}\n\n"],
+            Phrase::PostDrew => ["I drew against {}", "Empaté contra {}", "Empatei com {}", "{}と引き分けました"],
+            Phrase::PostBeat => ["I beat {}", "Le gané a {}", "Venci {}", "{}に勝ちました"],
+            Phrase::PostLost => ["I lost to {}", "Perdí contra {}", "Perdi para {}", "{}に負けました"],
+            Phrase::ResultPost => ["{} in {} moves at #connect4 — replay: {}", "{} en {} jugadas en #connect4 — repetición: {}", "{} em {} jogadas no #connect4 — replay: {}", "{}({}手、#connect4) — 再生: {}"],
+            Phrase::PuzzleWinIn => ["Puzzle, win in {}", "Problema, gana en {}", "Problema, vença em {}", "詰め問題、{}で勝ち"],
+            Phrase::ChallengeWinIn => ["Challenge, win in {}", "Reto, gana en {}", "Desafio, vença em {}", "チャレンジ、{}で勝ち"],
+            Phrase::PuzzleMissed => ["Puzzle missed, the engine plays on", "Problema fallado, el motor sigue jugando", "Problema perdido, o motor continua jogando", "詰め問題失敗、エンジンが続けます"],
+            Phrase::NameOrLightningAddress => ["Name / lightning address", "Nombre / dirección lightning", "Nome / endereço lightning", "名前 / ライトニングアドレス"],
+            Phrase::UsernameHint => ["A name, or a lightning address like you@getalby.com to receive zaps", "Un nombre, o una dirección lightning como tu@getalby.com para recibir zaps", "Um nome, ou um endereço lightning como voce@getalby.com para receber zaps", "名前、またはzapを受け取るyou@getalby.comのようなライトニングアドレス"],
+            Phrase::UsernameEmpty => ["Type a name or a lightning address", "Escribe un nombre o una dirección lightning", "Digite um nome ou um endereço lightning", "名前かライトニングアドレスを入力してください"],
+            Phrase::UsernameTooLong => ["{} characters is too long, at most {}", "{} caracteres es demasiado largo, como máximo {}", "{} caracteres é longo demais, no máximo {}", "{}文字は長すぎます。最大{}文字です"],
+            Phrase::NotLightningAddress => ["{} is not a lightning address, they look like name@domain.com", "{} no es una dirección lightning, son como nombre@dominio.com", "{} não é um endereço lightning, eles são como nome@dominio.com", "{}はライトニングアドレスではありません。name@domain.comの形式です"],
+            Phrase::Save => ["Save", "Guardar", "Salvar", "保存"],
+            Phrase::Cancel => ["Cancel", "Cancelar", "Cancelar", "キャンセル"],
+            Phrase::NoZapAddress => ["This version has no lightning address to zap, only the free skins.", "Esta versión no tiene dirección lightning para zaps, solo las skins gratis.", "Esta versão não tem endereço lightning para zaps, só as skins grátis.", "このバージョンにはzap先のライトニングアドレスがありません。無料のスキンのみです。"],
+            Phrase::SkinIsFree => ["This skin is free.", "Esta skin es gratis.", "Esta skin é grátis.", "このスキンは無料です。"],
+            Phrase::ZapUnreachable => ["{} can't be reached, zaps need the browser.", "No se puede contactar con {}, los zaps necesitan el navegador.", "Não foi possível contatar {}, zaps precisam do navegador.", "{}に接続できません。zapにはブラウザが必要です。"],
+            Phrase::NoZaps => ["{} doesn't take zaps.", "{} no acepta zaps.", "{} não aceita zaps.", "{}はzapを受け付けていません。"],
+            Phrase::ZapAmount => ["{} doesn't take {} sats.", "{} no acepta {} sats.", "{} não aceita {} sats.", "{}は{} satsを受け付けていません。"],
+            Phrase::ZapSigning => ["Error signing the zap request: {}", "Error al firmar la solicitud de zap: {}", "Erro ao assinar o pedido de zap: {}", "zapリクエストの署名エラー: {}"],
+            Phrase::ZapNoAnswer => ["{} didn't answer.", "{} no respondió.", "{} não respondeu.", "{}から応答がありません。"],
+            Phrase::ZapRefused => ["{} refused the zap.", "{} rechazó el zap.", "{} recusou o zap.", "{}がzapを拒否しました。"],
+            Phrase::ColumnLocked => ["column {} is locked for one turn", "la columna {} está bloqueada un turno", "a coluna {} está bloqueada por um turno", "{}列目は1手の間ロックされています"],
+            Phrase::CoinPopped => ["a coin popped out of column {}", "salió una ficha de la columna {}", "uma peça saiu da coluna {}", "{}列目からコインが飛び出しました"],
+            Phrase::BoardTurned => ["the board turned over", "el tablero se dio la vuelta", "o tabuleiro virou", "盤面がひっくり返りました"],
+        }
+    }
+}
+
+// the language the texts are shown in, the one picked in the settings or else the
+// browser's. a system that shows text rebuilds it when this changes
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub language: Language,
+}
+
+impl Locale {
+    pub fn of(settings: &Settings) -> Locale {
+        Locale {
+            language: settings.language.unwrap_or_else(Language::detect),
+        }
+    }
+
+    // the language of the stored settings, for the texts of network tasks and page calls
+    // that run outside the systems
+    pub fn saved() -> Locale {
+        Locale::of(&Settings::load())
+    }
+
+    pub fn get(&self, phrase: Phrase) -> &'static str {
+        let index = Language::ALL
+            .iter()
+            .position(|language| *language == self.language)
+            .unwrap_or(0);
+        phrase.texts()[index]
+    }
+
+    // the phrase with each `{}` filled in by the next value
+    pub fn fill(&self, phrase: Phrase, values: &[&str]) -> String {
+        let mut parts = self.get(phrase).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for (part, value) in parts.zip(values.iter().chain(std::iter::repeat(&""))) {
+            text.push_str(value);
+            text.push_str(part);
+        }
+        text
+    }
+
+    pub fn on_off(&self, on: bool) -> &'static str {
+        self.get(if on { Phrase::On } else { Phrase::Off })
+    }
+
+    pub fn difficulty(&self, difficulty: Difficulty) -> &'static str {
+        self.get(match difficulty {
+            Difficulty::Random => Phrase::Random,
+            Difficulty::Shallow => Phrase::Shallow,
+            Difficulty::Deep => Phrase::Deep,
+        })
+    }
+}
+
+impl FromWorld for Locale {
+    fn from_world(world: &mut World) -> Self {
+        Locale::of(world.resource::<Settings>())
+    }
+}
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Locale>()
+            .add_systems(Update, (follow_settings, use_bundled_font));
+    }
+}
+
+fn follow_settings(settings: Res<Settings>, mut locale: ResMut<Locale>) {
+    if settings.is_changed() {
+        locale.set_if_neq(Locale::of(&settings));
+    }
+}
+
+// every text is spawned in the built-in font and drawn in the bundled one once it's
+// loaded, so the screens don't wait for it. a font that failed leaves the built-in one
+fn use_bundled_font(
+    asset_server: Res<AssetServer>,
+    assets: Res<GameAssets>,
+    mut texts: Query<&mut Text>,
+) {
+    if asset_server.get_load_state(assets.font.id()) != Some(LoadState::Loaded) {
+        return;
+    }
+
+    for mut text in texts.iter_mut() {
+        if text
+            .sections
+            .iter()
+            .all(|section| section.font != Handle::default())
+        {
+            continue;
+        }
+        for section in text.sections.iter_mut() {
+            if section.font == Handle::default() {
+                section.font = assets.font.clone();
+            }
+        }
+    }
+}
@@ -1,14 +1,120 @@
+use analysis_plugin::AnalysisPlugin;
+use announcement_plugin::AnnouncementPlugin;
+use archive_plugin::ArchivePlugin;
+use audio_plugin::GameAudioPlugin;
 use bevy::{asset::AssetMetaCheck, prelude::*};
+use blitz_plugin::BlitzPlugin;
+use challenge_plugin::ChallengePlugin;
+use club_plugin::ClubPlugin;
+use correspondence_plugin::CorrespondencePlugin;
+use cosmetics_plugin::CosmeticsPlugin;
+use debug_plugin::DebugPlugin;
+use effects_plugin::EffectsPlugin;
+use end_message_plugin::EndMessagePlugin;
+use evaluation_plugin::EvaluationPlugin;
+use focus_plugin::FocusPlugin;
+use game_full_plugin::GameFullPlugin;
+use games_plugin::GamesPlugin;
+use ghost_plugin::GhostPlugin;
 use gui_plugin::Connect4GuiPlugin;
+use hint_plugin::HintPlugin;
+use history_plugin::HistoryPlugin;
+use identity_plugin::IdentityPlugin;
+use layout_plugin::LayoutPlugin;
+use leaderboard_plugin::LeaderboardPlugin;
+use league_plugin::LeaguePlugin;
+use loading_plugin::LoadingPlugin;
+use locale_plugin::LocalePlugin;
+use menu_plugin::MenuPlugin;
+use mini_board::MiniBoardPlugin;
 use nostr_plugin::NostrPlugin;
+use notify_plugin::NotifyPlugin;
+use performance_plugin::PerformancePlugin;
+use presence_plugin::PresencePlugin;
+use profiling_plugin::ProfilingPlugin;
+use puzzle_plugin::PuzzlePlugin;
+use resume_plugin::ResumePlugin;
+use review_plugin::ReviewPlugin;
+use schedule_plugin::SchedulePlugin;
+use series_plugin::SeriesPlugin;
+use settings_plugin::SettingsPlugin;
+use stats_plugin::StatsPlugin;
+use study_plugin::StudyPlugin;
+use theme_plugin::ThemePlugin;
+use tween_plugin::TweenPlugin;
+use username_plugin::UsernamePlugin;
 
+#[cfg(feature = "alloc_audit")]
+mod alloc_audit;
+mod analysis_plugin;
+mod announcement_plugin;
+mod archive_plugin;
+mod audio_plugin;
+mod bitboard;
+mod blitz_plugin;
+mod challenge_plugin;
+mod club;
+mod club_plugin;
 mod components;
+mod correspondence_plugin;
+mod cosmetics_plugin;
+mod debug_plugin;
+mod effects_plugin;
+mod end_message_plugin;
+mod engine;
+mod evaluation_plugin;
+mod events;
+mod focus_plugin;
+mod game;
+mod game_full_plugin;
+mod games_plugin;
+mod ghost_plugin;
 mod gui_plugin;
+mod hint_plugin;
+mod history_plugin;
+mod identity_plugin;
+mod invite;
+mod latency;
+mod layout_plugin;
+mod leaderboard_plugin;
+mod league;
+mod league_plugin;
+mod loading_plugin;
+mod locale_plugin;
+mod menu_plugin;
 mod messages;
+mod mini_board;
 mod nostr_plugin;
+mod notify_plugin;
+mod opening_book;
+mod performance_plugin;
+mod platform;
+mod presence_plugin;
+mod profiling_plugin;
+mod puzzle;
+mod puzzle_plugin;
+mod rating;
 mod resources;
+mod resume_plugin;
+mod review;
+mod review_plugin;
+mod schedule_plugin;
+mod series_plugin;
+mod settings_plugin;
+mod stats_plugin;
+mod storage;
+mod study_plugin;
+mod theme_plugin;
+mod training;
+mod transport;
+mod tween_plugin;
+mod username_plugin;
+mod webrtc;
 
 fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    launch_from_args();
+
     App::new()
         .insert_resource(AssetMetaCheck::Never)
         .add_plugins((
@@ -25,14 +131,124 @@ fn main() {
                 .set(ImagePlugin::default_nearest()),
             Connect4GuiPlugin,
             NostrPlugin,
+            DebugPlugin,
+            MiniBoardPlugin,
+            ArchivePlugin,
+            IdentityPlugin,
+            LeaderboardPlugin,
+            HistoryPlugin,
+            SeriesPlugin,
+            GhostPlugin,
+            GameAudioPlugin,
+            AnalysisPlugin,
+            StatsPlugin,
+            BlitzPlugin,
+        ))
+        .add_plugins((
+            MenuPlugin,
+            SettingsPlugin,
+            UsernamePlugin,
+            ThemePlugin,
+            ResumePlugin,
+            AnnouncementPlugin,
+            LayoutPlugin,
+            FocusPlugin,
+            TweenPlugin,
+            EffectsPlugin,
+            NotifyPlugin,
+            CorrespondencePlugin,
+            GamesPlugin,
+            PresencePlugin,
+            PuzzlePlugin,
+        ))
+        .add_plugins((
+            StudyPlugin,
+            ClubPlugin,
+            SchedulePlugin,
+            LeaguePlugin,
+            ChallengePlugin,
+            ProfilingPlugin,
+            GameFullPlugin,
+            PerformancePlugin,
+            HintPlugin,
+            ReviewPlugin,
+            EvaluationPlugin,
+            LoadingPlugin,
+            CosmeticsPlugin,
+            LocalePlugin,
+            EndMessagePlugin,
         ))
         .run();
 }
 
+// the desktop build has no html menu, `connect4xyz <game_id>` joins or resumes a game and
+// without a game id the menu opens. UNITE4_NAME sets the player name
+#[cfg(not(target_arch = "wasm32"))]
+fn launch_from_args() {
+    // `--safe-mode` starts without the stored state, see `platform::safe_mode`
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--safe-mode") {
+        platform::start_safe_mode();
+        args.remove(0);
+    }
+
+    if let Ok(name) = std::env::var("UNITE4_NAME") {
+        let mut settings = settings_plugin::Settings::load();
+        settings.username = Some(name.trim().to_string());
+        settings.save();
+    }
+
+    // `--export-training games.npz` writes the archived games as training data and quits,
+    // the extension picks json or npz
+    if args.first().map(String::as_str) == Some("--export-training") {
+        let path = args.get(1).map_or("training.json", String::as_str);
+        let format = if path.ends_with(".npz") {
+            "npz"
+        } else {
+            "json"
+        };
+        match archive_plugin::training_export(format).map(|data| std::fs::write(path, data)) {
+            Some(Ok(())) => {
+                println!("training data written to {}", path);
+                std::process::exit(0);
+            }
+            Some(Err(e)) => eprintln!("Error writing {}: {}", path, e),
+            None => eprintln!("Error exporting training data"),
+        }
+        std::process::exit(1);
+    }
+
+    // `--mine-puzzles` searches the archived games not searched yet for puzzles and quits
+    if args.first().map(String::as_str) == Some("--mine-puzzles") {
+        let (games, added) = puzzle_plugin::mine_archive();
+        println!(
+            "{} puzzles added from {} games, {} in the pool",
+            added,
+            games,
+            storage::puzzle_pool().len()
+        );
+        std::process::exit(0);
+    }
+
+    if let Some(game_id) = args.first() {
+        platform::set_game_id(game_id);
+        gui_plugin::join_game();
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum AppState {
+    // the assets load before anything else shows, see the loading plugin
     #[default]
+    Loading,
     Menu,
     InGame,
     JoinGame,
+    Leaderboard,
+    History,
+    Stats,
+    Blitz,
+    Settings,
+    Club,
+    League,
 }
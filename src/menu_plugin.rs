@@ -0,0 +1,793 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent, Filter, Kind, Timestamp,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    club, club_plugin,
+    components::{
+        GameIdText, MenuAction, MenuText, MenuUi, OngoingGameButton, ScheduledGameButton,
+        SettingToggle,
+    },
+    correspondence_plugin::time_ago,
+    cosmetics_plugin::{self, Cosmetics},
+    engine::Difficulty,
+    ghost_plugin,
+    gui_plugin::{new_game, new_series},
+    leaderboard_plugin::publish_speed,
+    league_plugin,
+    locale_plugin::{Language, Locale, Phrase},
+    messages::{GameConfig, MessagePack, NetworkMessage, Skin},
+    nostr_plugin::{self, fetch_events, game_id_from_tags},
+    platform, puzzle_plugin,
+    resources::GameState,
+    schedule_plugin::{scheduled_label, upcoming_games},
+    settings_plugin::{
+        Appearance, CoinTheme, Performance, Settings, ANIMATION_SPEEDS, TIME_CONTROLS,
+    },
+    storage::{self, OngoingGame},
+    theme_plugin::{Theme, UiColors},
+    tween_plugin::Easing,
+    username_plugin::UsernameEntry,
+    AppState,
+};
+
+// how far back quick match looks for a game waiting for a second player
+pub const QUICK_MATCH_WINDOW: Duration = Duration::from_secs(600);
+const MAX_GAME_ID_LENGTH: usize = 32;
+// the languages the settings cycle through, none follows the browser
+const LANGUAGES: [Option<Language>; 5] = [
+    None,
+    Some(Language::En),
+    Some(Language::Es),
+    Some(Language::Pt),
+    Some(Language::Ja),
+];
+
+static QUICK_MATCH_CALLED: AtomicBool = AtomicBool::new(false);
+
+// the open game quick match found, none to create one
+#[derive(Resource, Default)]
+struct QuickMatch {
+    read: Option<Receiver<Option<String>>>,
+}
+
+#[derive(Resource, Default)]
+struct TypedGameId(String);
+
+// the text setting being typed on the settings screen and its new value
+#[derive(Resource, Default)]
+pub struct EditedSetting(Option<(SettingToggle, String)>);
+
+pub fn setting_edited(edited: Res<EditedSetting>) -> bool {
+    edited.0.is_some()
+}
+
+// the title, join by id and settings screens of the desktop app. in the browser the html
+// page around the canvas has the menu, its buttons call the same functions
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuickMatch>()
+            .init_resource::<TypedGameId>()
+            .init_resource::<EditedSetting>()
+            .add_systems(OnEnter(AppState::Menu), setup_menu.run_if(engine_menu))
+            .add_systems(
+                Update,
+                (
+                    menu_actions,
+                    open_ongoing_game,
+                    check_quick_match_system,
+                    update_quick_match,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(OnExit(AppState::Menu), cleanup_menu)
+            .add_systems(OnEnter(AppState::JoinGame), setup_join_game)
+            .add_systems(
+                Update,
+                (menu_actions, type_game_id).run_if(in_state(AppState::JoinGame)),
+            )
+            .add_systems(OnExit(AppState::JoinGame), cleanup_menu)
+            .add_systems(OnEnter(AppState::Settings), setup_settings)
+            .add_systems(
+                Update,
+                (
+                    menu_actions,
+                    toggle_settings,
+                    type_setting,
+                    update_setting_labels,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Settings)),
+            )
+            .add_systems(OnExit(AppState::Settings), cleanup_menu);
+    }
+}
+
+fn engine_menu() -> bool {
+    !platform::page_menu()
+}
+
+fn menu_screen(
+    commands: &mut Commands,
+    ui: UiColors,
+    title: &str,
+    spawn_children: impl FnOnce(&mut ChildBuilder),
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: ui.background.into(),
+            ..default()
+        })
+        .insert(MenuUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    color: ui.text,
+                    font_size: 28.0,
+                    ..default()
+                },
+            ));
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: ui.muted,
+                        font_size: 16.0,
+                        ..default()
+                    },
+                ))
+                .insert(MenuText);
+            spawn_children(parent);
+        });
+}
+
+fn spawn_button(parent: &mut ChildBuilder, ui: UiColors, label: &str, action: impl Component) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                width: Val::Px(220.0),
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: ui.button.into(),
+            ..default()
+        })
+        .insert(action)
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    color: ui.text,
+                    font_size: 18.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn setup_menu(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    let ui = theme.ui;
+    let text = |phrase| locale.get(phrase);
+    menu_screen(&mut commands, ui, "Unite4", |parent| {
+        spawn_button(parent, ui, text(Phrase::CreateGame), MenuAction::CreateGame);
+        spawn_button(
+            parent,
+            ui,
+            text(Phrase::CorrespondenceGame),
+            MenuAction::CreateCorrespondence,
+        );
+        spawn_button(parent, ui, text(Phrase::JoinById), MenuAction::JoinById);
+        spawn_button(parent, ui, text(Phrase::QuickMatch), MenuAction::QuickMatch);
+        spawn_button(parent, ui, text(Phrase::VsAi), MenuAction::VsAi);
+        let puzzles = storage::puzzle_pool().len();
+        if puzzles > 0 {
+            spawn_button(
+                parent,
+                ui,
+                &locale.fill(Phrase::Puzzles, &[&puzzles.to_string()]),
+                MenuAction::Puzzle,
+            );
+        }
+        if storage::current_club().is_some() {
+            spawn_button(parent, ui, text(Phrase::Club), MenuAction::Club);
+        }
+        if storage::current_league().is_some() {
+            spawn_button(parent, ui, text(Phrase::League), MenuAction::League);
+        }
+        spawn_button(parent, ui, text(Phrase::Settings), MenuAction::Settings);
+        for game in upcoming_games() {
+            spawn_button(
+                parent,
+                ui,
+                &scheduled_label(&game, &locale),
+                ScheduledGameButton(game.game_id),
+            );
+        }
+        for game in storage::ongoing_games() {
+            spawn_button(
+                parent,
+                ui,
+                &ongoing_label(&game, &locale),
+                OngoingGameButton(game.game_id),
+            );
+        }
+    });
+}
+
+fn ongoing_label(game: &OngoingGame, locale: &Locale) -> String {
+    let opponent = game
+        .opponent
+        .as_deref()
+        .unwrap_or(locale.get(Phrase::Player2));
+    let turn = if game.your_turn {
+        locale.get(Phrase::OwnTurn)
+    } else {
+        locale.get(Phrase::OpponentTurn)
+    };
+    let label = locale.fill(Phrase::OngoingGame, &[opponent, turn]);
+    match game.last_move_at {
+        Some(at) => format!("{}, {}", label, time_ago(at, locale)),
+        None => label,
+    }
+}
+
+fn setup_join_game(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut typed: ResMut<TypedGameId>,
+) {
+    typed.0.clear();
+
+    let ui = theme.ui;
+    menu_screen(&mut commands, ui, locale.get(Phrase::JoinById), |parent| {
+        parent
+            .spawn(TextBundle::from_section(
+                "_",
+                TextStyle {
+                    color: ui.text,
+                    font_size: 22.0,
+                    ..default()
+                },
+            ))
+            .insert(GameIdText);
+        spawn_button(parent, ui, locale.get(Phrase::Join), MenuAction::Join);
+        spawn_button(parent, ui, locale.get(Phrase::Back), MenuAction::Back);
+    });
+}
+
+fn setup_settings(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    cosmetics: Res<Cosmetics>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut edited: ResMut<EditedSetting>,
+) {
+    edited.0 = None;
+
+    let ui = theme.ui;
+    menu_screen(&mut commands, ui, locale.get(Phrase::Settings), |parent| {
+        for toggle in [
+            SettingToggle::Sound,
+            SettingToggle::AnimationSpeed,
+            SettingToggle::DropStyle,
+            SettingToggle::DropPreview,
+            SettingToggle::CoinTheme,
+            SettingToggle::Skin,
+            SettingToggle::UnlockSkin,
+            SettingToggle::Colorblind,
+            SettingToggle::HighContrast,
+            SettingToggle::Appearance,
+            SettingToggle::Accent,
+            SettingToggle::Language,
+            SettingToggle::MessagePack,
+            SettingToggle::TimeControl,
+            SettingToggle::PublishSpeed,
+            SettingToggle::Username,
+            SettingToggle::Privacy,
+            SettingToggle::Performance,
+            SettingToggle::Difficulty,
+            SettingToggle::EvaluationBar,
+            SettingToggle::Relays,
+        ] {
+            spawn_button(
+                parent,
+                ui,
+                &toggle.label(&settings, &cosmetics, &locale, None),
+                toggle,
+            );
+        }
+        spawn_button(parent, ui, locale.get(Phrase::Back), MenuAction::Back);
+    });
+}
+
+fn menu_actions(
+    actions: Query<(&Interaction, &MenuAction), Changed<Interaction>>,
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    typed: Res<TypedGameId>,
+    mut next_state: ResMut<NextState<AppState>>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    let mut pressed: Vec<&MenuAction> = actions
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, action)| action)
+        .collect();
+
+    if *state.get() == AppState::JoinGame && keys.just_pressed(KeyCode::Return) {
+        pressed.push(&MenuAction::Join);
+    }
+    if *state.get() != AppState::Menu && keys.just_pressed(KeyCode::Escape) {
+        pressed.push(&MenuAction::Back);
+    }
+
+    for action in pressed {
+        match action {
+            MenuAction::CreateGame => new_game(),
+            MenuAction::CreateCorrespondence => {
+                new_series(1, "classic".to_string(), 0, 0, true, false)
+            }
+            MenuAction::JoinById => next_state.set(AppState::JoinGame),
+            MenuAction::QuickMatch => quick_match(),
+            MenuAction::VsAi => ghost_plugin::practice_engine(),
+            MenuAction::Puzzle => puzzle_plugin::play_puzzle(),
+            MenuAction::Club => {
+                if let Some(address) = storage::current_club() {
+                    club_plugin::show_club(address);
+                }
+            }
+            MenuAction::League => {
+                if let Some(address) = storage::current_league() {
+                    league_plugin::show_league(address);
+                }
+            }
+            MenuAction::Settings => next_state.set(AppState::Settings),
+            MenuAction::Join if typed.0.is_empty() => {
+                for mut txt in text.iter_mut() {
+                    txt.sections[0].value = locale.get(Phrase::TypeGameId).to_string();
+                }
+            }
+            MenuAction::Join => {
+                platform::push_url(&format!("{}/{}", platform::origin(), typed.0));
+                next_state.set(AppState::InGame);
+            }
+            MenuAction::Back => next_state.set(AppState::Menu),
+        }
+    }
+}
+
+fn open_ongoing_game(
+    games: Query<(&Interaction, &OngoingGameButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some((_, OngoingGameButton(game_id))) = games
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+
+    platform::push_url(&format!("{}{}", platform::origin(), game_id));
+    next_state.set(AppState::InGame);
+}
+
+// game ids are made of the nanoid alphabet, series games add `-{n}`
+fn type_game_id(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut typed: ResMut<TypedGameId>,
+    mut text: Query<&mut Text, With<GameIdText>>,
+) {
+    for character in characters.read() {
+        if (character.char.is_ascii_alphanumeric() || character.char == '-')
+            && typed.0.len() < MAX_GAME_ID_LENGTH
+        {
+            typed.0.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        typed.0.pop();
+    }
+
+    if typed.is_changed() {
+        for mut txt in text.iter_mut() {
+            txt.sections[0].value = format!("{}_", typed.0);
+        }
+    }
+}
+
+// the option after the current one, wrapping around
+fn next_option<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    let index = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |index| (index + 1) % options.len());
+    options[index]
+}
+
+fn toggle_settings(
+    toggles: Query<(&Interaction, &SettingToggle), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut edited: ResMut<EditedSetting>,
+    mut username_entry: ResMut<UsernameEntry>,
+) {
+    for (interaction, toggle) in toggles.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match toggle {
+            SettingToggle::Sound => settings.sound = !settings.sound,
+            SettingToggle::AnimationSpeed => {
+                settings.animation_speed = next_option(&ANIMATION_SPEEDS, settings.animation_speed)
+            }
+            SettingToggle::DropStyle => {
+                settings.drop_style = next_option(&Easing::ALL, settings.drop_style)
+            }
+            SettingToggle::DropPreview => settings.drop_preview = !settings.drop_preview,
+            SettingToggle::CoinTheme => {
+                settings.coin_theme = next_option(&CoinTheme::ALL, settings.coin_theme)
+            }
+            SettingToggle::Skin => settings.skin = next_option(&Skin::ALL, settings.skin),
+            SettingToggle::UnlockSkin => {
+                cosmetics_plugin::unlock_skin(settings.skin.id());
+                continue;
+            }
+            SettingToggle::Colorblind => settings.colorblind = !settings.colorblind,
+            SettingToggle::HighContrast => settings.high_contrast = !settings.high_contrast,
+            SettingToggle::Appearance => {
+                settings.appearance = next_option(&Appearance::ALL, settings.appearance)
+            }
+            SettingToggle::Accent => {
+                edited.0 = Some((*toggle, settings.accent.clone().unwrap_or_default()));
+                continue;
+            }
+            SettingToggle::Language => {
+                settings.language = next_option(&LANGUAGES, settings.language)
+            }
+            SettingToggle::MessagePack => {
+                settings.message_pack = next_option(&MessagePack::ALL, settings.message_pack)
+            }
+            SettingToggle::TimeControl => {
+                settings.time_control = next_option(&TIME_CONTROLS, settings.time_control)
+            }
+            // kept under its own key, the leaderboard reads it when publishing
+            SettingToggle::PublishSpeed => {
+                storage::set_item("publish_speed", &(!publish_speed()).to_string());
+                settings.set_changed();
+                continue;
+            }
+            SettingToggle::Privacy => settings.privacy = !settings.privacy,
+            SettingToggle::Performance => {
+                settings.performance = next_option(&Performance::ALL, settings.performance)
+            }
+            SettingToggle::Difficulty => {
+                settings.difficulty = next_option(&Difficulty::ALL, settings.difficulty)
+            }
+            SettingToggle::EvaluationBar => settings.evaluation_bar = !settings.evaluation_bar,
+            SettingToggle::Username => {
+                username_entry.open(settings.username.as_deref());
+                continue;
+            }
+            SettingToggle::Relays => {
+                edited.0 = Some((*toggle, settings.relays.join(", ")));
+                continue;
+            }
+        }
+
+        settings.save();
+    }
+}
+
+// the relays and the board color are typed, enter saves them. the username has its own
+// entry
+fn type_setting(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut edited: ResMut<EditedSetting>,
+    mut settings: ResMut<Settings>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    if edited.0.is_none() {
+        characters.clear();
+        return;
+    }
+    let Some((toggle, ref mut value)) = edited.0 else {
+        return;
+    };
+
+    for character in characters.read() {
+        if !character.char.is_control() {
+            value.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        value.pop();
+    }
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    if toggle == SettingToggle::Relays {
+        let relays: Vec<String> = value
+            .split([',', ' '])
+            .filter(|relay| relay.starts_with("wss://") || relay.starts_with("ws://"))
+            .map(|relay| relay.to_string())
+            .collect();
+        if relays.is_empty() {
+            for mut txt in text.iter_mut() {
+                txt.sections[0].value = locale.get(Phrase::RelaysInvalid).to_string();
+            }
+            return;
+        }
+        settings.relays = relays;
+        nostr_plugin::relays_changed();
+    }
+
+    // an empty color goes back to the skin's board
+    if toggle == SettingToggle::Accent {
+        let accent = value.trim();
+        if !accent.is_empty() && Color::hex(accent).is_err() {
+            for mut txt in text.iter_mut() {
+                txt.sections[0].value = locale.get(Phrase::BoardColorInvalid).to_string();
+            }
+            return;
+        }
+        settings.accent = (!accent.is_empty()).then(|| accent.to_string());
+    }
+
+    settings.save();
+    edited.0 = None;
+}
+
+fn update_setting_labels(
+    settings: Res<Settings>,
+    cosmetics: Res<Cosmetics>,
+    locale: Res<Locale>,
+    edited: Res<EditedSetting>,
+    toggles: Query<(&SettingToggle, &Children)>,
+    mut text: Query<&mut Text>,
+) {
+    if !settings.is_changed()
+        && !cosmetics.is_changed()
+        && !locale.is_changed()
+        && !edited.is_changed()
+    {
+        return;
+    }
+
+    for (toggle, children) in toggles.iter() {
+        let typed = match &edited.0 {
+            Some((edited_toggle, value)) if edited_toggle == toggle => Some(value.as_str()),
+            _ => None,
+        };
+        for &child in children.iter() {
+            if let Ok(mut txt) = text.get_mut(child) {
+                txt.sections[0].value = toggle.label(&settings, &cosmetics, &locale, typed);
+            }
+        }
+    }
+}
+
+fn check_quick_match_system(
+    mut quick_match: ResMut<QuickMatch>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    if !QUICK_MATCH_CALLED.swap(false, Ordering::SeqCst) || quick_match.read.is_some() {
+        return;
+    }
+
+    for mut txt in text.iter_mut() {
+        txt.sections[0].value = locale.get(Phrase::LookingForGame).to_string();
+    }
+
+    let (mut match_tx, match_rx) = futures::channel::mpsc::channel::<Option<String>>(1);
+    quick_match.read = Some(match_rx);
+
+    let pubkey = game_state.nostr_keys.public_key();
+
+    platform::spawn(async move {
+        let filter = Filter::new()
+            .kind(Kind::Regular(4444))
+            .since(Timestamp::now() - QUICK_MATCH_WINDOW);
+
+        // club games wait for the club's members, see the club lobby
+        let mut events = fetch_events(vec![filter], Vec::new()).await;
+        events.retain(|event| !club::club_game(event));
+        let game_id = open_game_id(&events, pubkey);
+
+        if let Err(e) = match_tx.try_send(game_id) {
+            error!("Error sending quick match: {}", e);
+        }
+    });
+}
+
+// the newest classic single game of another player that nobody joined yet
+pub fn open_game_id(events: &[NostrEvent], pubkey: XOnlyPublicKey) -> Option<String> {
+    let mut created: BTreeMap<String, Timestamp> = BTreeMap::new();
+    let mut joined: HashSet<String> = HashSet::new();
+
+    for event in events {
+        let Some(game_id) = game_id_from_tags(event) else {
+            continue;
+        };
+
+        match serde_json::from_str::<NetworkMessage>(&event.content) {
+            Ok(NetworkMessage::NewGame(_, 1, config))
+                if config == GameConfig::default() && event.pubkey != pubkey =>
+            {
+                created.insert(game_id, event.created_at);
+            }
+            Ok(NetworkMessage::JoinGame(_)) => {
+                joined.insert(game_id);
+            }
+            _ => {}
+        }
+    }
+
+    created
+        .into_iter()
+        .filter(|(game_id, _)| !joined.contains(game_id))
+        .max_by_key(|(_, created_at)| *created_at)
+        .map(|(game_id, _)| game_id)
+}
+
+// joins the game that was found, or creates one for the next player looking
+fn update_quick_match(
+    mut quick_match: ResMut<QuickMatch>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(ref mut match_rx) = quick_match.read else {
+        return;
+    };
+
+    let Ok(Some(game_id)) = match_rx.try_next() else {
+        return;
+    };
+    quick_match.read = None;
+
+    match game_id {
+        Some(game_id) => {
+            info!("quick match joins {}", game_id);
+            platform::push_url(&format!("{}{}", platform::origin(), game_id));
+            next_state.set(AppState::InGame);
+        }
+        None => {
+            info!("no open game, creating one");
+            new_game();
+        }
+    }
+}
+
+fn cleanup_menu(mut commands: Commands, ui: Query<Entity, With<MenuUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+impl SettingToggle {
+    // the typed value is shown while the setting is edited
+    fn label(
+        &self,
+        settings: &Settings,
+        cosmetics: &Cosmetics,
+        locale: &Locale,
+        typed: Option<&str>,
+    ) -> String {
+        let on_off = |on: bool| locale.on_off(on).to_string();
+
+        let (name, value) = match self {
+            SettingToggle::Sound => (Phrase::Sound, on_off(settings.sound)),
+            SettingToggle::AnimationSpeed => (
+                Phrase::AnimationSpeed,
+                format!("{}x", settings.animation_speed),
+            ),
+            SettingToggle::DropStyle => (Phrase::CoinDrop, settings.drop_style.name().to_string()),
+            SettingToggle::DropPreview => (Phrase::DropPreview, on_off(settings.drop_preview)),
+            SettingToggle::CoinTheme => (Phrase::Coins, settings.coin_theme.name().to_string()),
+            SettingToggle::Skin => (Phrase::Skin, settings.skin.name().to_string()),
+            SettingToggle::UnlockSkin => (
+                Phrase::Skin,
+                match settings.skin.price() {
+                    Some(price) if !cosmetics.is_unlocked(settings.skin) => {
+                        locale.fill(Phrase::ZapToUnlock, &[&price.to_string()])
+                    }
+                    Some(_) => locale.get(Phrase::Unlocked).to_string(),
+                    None => locale.get(Phrase::Free).to_string(),
+                },
+            ),
+            SettingToggle::Colorblind => (Phrase::Colorblind, on_off(settings.colorblind)),
+            SettingToggle::HighContrast => (Phrase::HighContrast, on_off(settings.high_contrast)),
+            SettingToggle::Appearance => {
+                (Phrase::Appearance, settings.appearance.name().to_string())
+            }
+            SettingToggle::Accent => (
+                Phrase::BoardColor,
+                settings
+                    .accent
+                    .clone()
+                    .unwrap_or_else(|| locale.get(Phrase::SkinColor).to_string()),
+            ),
+            SettingToggle::Language => (
+                Phrase::Language,
+                match settings.language {
+                    Some(language) => language.name().to_string(),
+                    None => format!("{} ({})", locale.get(Phrase::Auto), locale.language.name()),
+                },
+            ),
+            SettingToggle::MessagePack => (
+                Phrase::EndMessages,
+                settings.message_pack.name().to_string(),
+            ),
+            SettingToggle::TimeControl => (
+                Phrase::TimePerMove,
+                match settings.time_control {
+                    Some(seconds) => format!("{}s", seconds),
+                    None => locale.get(Phrase::NoClock).to_string(),
+                },
+            ),
+            SettingToggle::PublishSpeed => (Phrase::PublishSpeed, on_off(publish_speed())),
+            SettingToggle::Username => (
+                Phrase::NameOrAddress,
+                settings.username.clone().unwrap_or_default(),
+            ),
+            SettingToggle::Privacy => (Phrase::Privacy, on_off(settings.privacy)),
+            SettingToggle::Performance => {
+                (Phrase::Performance, settings.performance.name().to_string())
+            }
+            SettingToggle::Difficulty => (
+                Phrase::Difficulty,
+                locale.difficulty(settings.difficulty).to_string(),
+            ),
+            SettingToggle::EvaluationBar => {
+                (Phrase::EvaluationBar, on_off(settings.evaluation_bar))
+            }
+            SettingToggle::Relays => (Phrase::Relays, settings.relays.len().to_string()),
+        };
+        // the skin's own name stands in front of its price
+        let name = match self {
+            SettingToggle::UnlockSkin => settings.skin.name(),
+            _ => locale.get(name),
+        };
+
+        match typed {
+            Some(typed) => format!("{}: {}_", name, typed),
+            None => format!("{}: {}", name, value),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub fn quick_match() {
+    QUICK_MATCH_CALLED.store(true, Ordering::SeqCst);
+}
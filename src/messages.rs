@@ -1,11 +1,505 @@
+use std::fmt;
+
 use nostr_sdk::secp256k1::XOnlyPublicKey;
 use serde::{Deserialize, Serialize};
 
+// the hashtag of every event of a game is the prefix followed by the game id
+pub const GAME_TAG_PREFIX: &str = "unite4.luvnft.com game_id = ";
+// the ephemeral kind live messages go over between clients that support it, relays pass
+// them on without storing them. everything else is a regular kind 4444 event
+pub const MOVE_KIND: u16 = 24444;
+// the ephemeral kind of the signals, see `MessageCategory::Signal`
+pub const SIGNAL_KIND: u16 = 24445;
+// the replaceable service announcement of the maintainer, see `Announcement`
+pub const ANNOUNCEMENT_KIND: u16 = 34445;
+pub const ANNOUNCEMENT_TAG: &str = "unite4.luvnft.com announcement";
+// a club is a nip-51 follow set of its members, see `ClubInfo`
+pub const CLUB_KIND: u16 = 30000;
+pub const CLUB_TAG: &str = "unite4.luvnft.com club";
+// the replaceable tournament a club admin schedules, see `ClubTournament`
+pub const TOURNAMENT_KIND: u16 = 34446;
+// a scheduled game is a nip-52 time-based calendar event tagged with the game's hashtag
+pub const CALENDAR_KIND: u16 = 31923;
+// a position shared to find the win in, see the challenge plugin
+pub const CHALLENGE_TAG: &str = "unite4.luvnft.com challenge";
+// the characters of generated game ids
+pub const GAME_ID_ALPHABET: [char; 31] = [
+    '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j', 'k', 'm',
+    'n', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+// a league's players and season, see `LeagueInfo`
+pub const LEAGUE_KIND: u16 = 34447;
+pub const LEAGUE_TAG: &str = "unite4.luvnft.com league";
+
+// added to the column of a pop out or power up move in a sync response, past the last
+// column
+pub const POP_OFFSET: usize = 7;
+pub const ANVIL_OFFSET: usize = 14;
+pub const BOMB_OFFSET: usize = 21;
+
+// power up coins, each player has one of each. the anvil crushes every coin in its
+// column and lands at the bottom, the bomb clears the cells around where it lands
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Special {
+    Anvil,
+    Bomb,
+}
+
+impl fmt::Display for Special {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Special::Anvil => write!(f, "anvil"),
+            Special::Bomb => write!(f, "bomb"),
+        }
+    }
+}
+
+// the look of a player's coins and board, sent in the handshake so the opponent sees it
+// too. the classic and ocean skins are free, the others are unlocked by zapping the
+// developer, see the cosmetics plugin
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Skin {
+    #[default]
+    Classic,
+    Ocean,
+    Neon,
+    Gold,
+    Galaxy,
+}
+
+impl Skin {
+    pub const ALL: [Skin; 5] = [
+        Skin::Classic,
+        Skin::Ocean,
+        Skin::Neon,
+        Skin::Gold,
+        Skin::Galaxy,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Skin::Classic => "classic",
+            Skin::Ocean => "ocean",
+            Skin::Neon => "neon",
+            Skin::Gold => "gold",
+            Skin::Galaxy => "galaxy",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Skin> {
+        Skin::ALL.into_iter().find(|skin| skin.id() == id)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Skin::Classic => "Classic",
+            Skin::Ocean => "Ocean",
+            Skin::Neon => "Neon",
+            Skin::Gold => "Gold",
+            Skin::Galaxy => "Galaxy",
+        }
+    }
+
+    // the sats a zap has to bring to unlock the skin, none for a free one
+    pub fn price(&self) -> Option<u64> {
+        match self {
+            Skin::Classic | Skin::Ocean => None,
+            Skin::Neon => Some(1000),
+            Skin::Gold => Some(2100),
+            Skin::Galaxy => Some(5000),
+        }
+    }
+}
+
+// the tone of the messages a winner can send the loser, picked in the settings
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessagePack {
+    #[default]
+    Friendly,
+    Competitive,
+    Silly,
+}
+
+impl MessagePack {
+    pub const ALL: [MessagePack; 3] = [
+        MessagePack::Friendly,
+        MessagePack::Competitive,
+        MessagePack::Silly,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessagePack::Friendly => "Friendly",
+            MessagePack::Competitive => "Competitive",
+            MessagePack::Silly => "Silly",
+        }
+    }
+}
+
+// the rules player 1 created a game with, a field missing from the message is off
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct GameConfig {
+    // the seed of a columns of fortune game
+    pub fortune_seed: Option<u64>,
+    pub pop_out: bool,
+    pub power_up: bool,
+    pub cylinder: bool,
+    // all moves of a correspondence game are stored
+    pub correspondence: bool,
+    pub gravity_shift: bool,
+    // a 2v2 team game
+    pub teams: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NetworkMessage {
-    NewGame(Option<String>),
+    // player 1's name, the series length, 1 for a single game, and the rules of the game
+    NewGame(Option<String>, usize, #[serde(default)] GameConfig),
     JoinGame(Players),
-    Input(usize),
+    Input(PlayerInput),
+    // pop out, the player takes their coin from the bottom of the column
+    Pop(PlayerInput),
+    // power up, the player drops their special coin into the column
+    Anvil(PlayerInput),
+    Bomb(PlayerInput),
+    Resign(usize),
+    Ack(usize),
+    // an ack with the wall clock milliseconds the move was received at, for the latency
+    // traces of the mover
+    TimedAck(usize, u64),
+    SyncRequest,
+    // the columns played, a pop out or power up move as its column plus the offset
+    SyncResponse(Vec<usize>),
+    Rating(PlayerRating),
+    Client(ClientInfo),
+    // index of the move to take back, answered with whether the opponent allowed it
+    UndoRequest(usize),
+    UndoResponse(usize, bool),
+    // webrtc signaling between the players, encrypted to the opponent, see `webrtc`
+    Signal(String),
+    // the number of moves and the `state_hash` of their sync columns, sent after the page
+    // resumed so a board that missed moves while suspended asks for a sync
+    StateHash(usize, u64),
+    // the columns of every move so far like a sync response, stored every few moves when
+    // the moves themselves are ephemeral so a reloaded game starts from the latest one
+    Checkpoint(Vec<usize>),
+    // sent every few seconds while the game is on the player's page, false when they leave
+    Presence(bool),
+    // whether the player's cursor is on the board during their turn
+    Thinking(bool),
+    // the column the player aims at during their turn, none once they don't
+    Hover(Option<usize>),
+    // a shared analysis board: its state, sent by whoever has control whenever it changes,
+    // the column they aim at, a viewer following it or asking for control, and its end
+    Study(Study),
+    StudyCursor(Option<usize>),
+    StudyFollow,
+    StudyRequest(Option<String>),
+    StudyEnd,
+    // player 1's answer to a join once both players are known, naming them so the
+    // latecomer watches instead
+    GameFull(Players),
+    // a latecomer asking player 1 of a team game for one of the teammates' seats, with
+    // their name
+    JoinTeam(Option<String>),
+    // player 1's seating of a team game, sent whenever someone takes a seat
+    Teams(Teams),
+    // the winner's message to the loser, a preset of the pack by its index, so only
+    // the texts the game ships are shown
+    EndMessage(MessagePack, usize),
+}
+
+// how a message is published
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageCategory {
+    // the handshake, resignations, undos and checkpoints, regular kind 4444 events a
+    // reload rebuilds the game from
+    Stored,
+    // moves and what keeps both boards in sync. they only matter while the game is open,
+    // a reload gets the moves from a checkpoint, so they're ephemeral kind 24444 events
+    // once both clients support it and stored ones otherwise
+    Move,
+    // presence, the thinking indicator and studies only matter the moment they're sent.
+    // always ephemeral events of their own kind, sent once without retries and kept out
+    // of the audit log
+    Signal,
+}
+
+impl NetworkMessage {
+    pub fn category(&self) -> MessageCategory {
+        match self {
+            NetworkMessage::Input(_)
+            | NetworkMessage::Pop(_)
+            | NetworkMessage::Anvil(_)
+            | NetworkMessage::Bomb(_)
+            | NetworkMessage::Ack(_)
+            | NetworkMessage::TimedAck(..)
+            | NetworkMessage::SyncRequest
+            | NetworkMessage::SyncResponse(_)
+            | NetworkMessage::StateHash(..) => MessageCategory::Move,
+            NetworkMessage::Presence(_)
+            | NetworkMessage::Thinking(_)
+            | NetworkMessage::Hover(_)
+            | NetworkMessage::Study(_)
+            | NetworkMessage::StudyCursor(_)
+            | NetworkMessage::StudyFollow
+            | NetworkMessage::StudyRequest(_)
+            | NetworkMessage::StudyEnd
+            | NetworkMessage::GameFull(_)
+            | NetworkMessage::EndMessage(..) => MessageCategory::Signal,
+            NetworkMessage::NewGame(..)
+            | NetworkMessage::JoinGame(_)
+            | NetworkMessage::Resign(_)
+            | NetworkMessage::Rating(_)
+            | NetworkMessage::Client(_)
+            | NetworkMessage::UndoRequest(_)
+            | NetworkMessage::UndoResponse(..)
+            | NetworkMessage::Signal(_)
+            | NetworkMessage::Checkpoint(_)
+            | NetworkMessage::JoinTeam(_)
+            | NetworkMessage::Teams(_) => MessageCategory::Stored,
+        }
+    }
+
+    // messages that change the game are only taken from its players once they're known.
+    // spectators ask for the moves, follow studies and show up in presence, the join names
+    // the players itself
+    pub fn players_only(&self) -> bool {
+        match self {
+            NetworkMessage::Input(_)
+            | NetworkMessage::Pop(_)
+            | NetworkMessage::Anvil(_)
+            | NetworkMessage::Bomb(_)
+            | NetworkMessage::Ack(_)
+            | NetworkMessage::TimedAck(..)
+            | NetworkMessage::SyncResponse(_)
+            | NetworkMessage::StateHash(..)
+            | NetworkMessage::NewGame(..)
+            | NetworkMessage::Resign(_)
+            | NetworkMessage::Rating(_)
+            | NetworkMessage::Client(_)
+            | NetworkMessage::UndoRequest(_)
+            | NetworkMessage::UndoResponse(..)
+            | NetworkMessage::Signal(_)
+            | NetworkMessage::Checkpoint(_)
+            | NetworkMessage::GameFull(_)
+            | NetworkMessage::Teams(_)
+            | NetworkMessage::EndMessage(..) => true,
+            NetworkMessage::SyncRequest
+            | NetworkMessage::JoinGame(_)
+            | NetworkMessage::JoinTeam(_)
+            | NetworkMessage::Presence(_)
+            | NetworkMessage::Thinking(_)
+            | NetworkMessage::Hover(_)
+            | NetworkMessage::Study(_)
+            | NetworkMessage::StudyCursor(_)
+            | NetworkMessage::StudyFollow
+            | NetworkMessage::StudyRequest(_)
+            | NetworkMessage::StudyEnd => false,
+        }
+    }
+}
+
+// FNV-1a over the columns of a sync response, the same on every platform and build
+pub fn state_hash(columns: impl IntoIterator<Item = usize>) -> u64 {
+    columns
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, column| {
+            (hash ^ column as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// the state hash as hex for the result event, json numbers lose the low bits in js
+pub fn moves_hash(columns: impl IntoIterator<Item = usize>) -> String {
+    format!("{:016x}", state_hash(columns))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerInput {
+    pub column: usize,
+    pub index: usize,
+}
+
+impl PlayerInput {
+    pub fn new(column: usize, index: usize) -> Self {
+        Self { column, index }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerRating {
+    pub player: usize,
+    pub rating: u32,
+}
+
+impl PlayerRating {
+    pub fn new(player: usize, rating: u32) -> Self {
+        Self { player, rating }
+    }
+}
+
+// sent with the rating during the handshake so each side knows what the other client supports.
+// rng is the fingerprint of the client's game rng, skin the look of the player's coins,
+// older clients send neither
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientInfo {
+    pub player: usize,
+    pub name: String,
+    pub version: String,
+    pub features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rng: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<Skin>,
+}
+
+impl ClientInfo {
+    pub fn new(player: usize, features: &[&str], rng: u64, skin: Skin) -> Self {
+        Self {
+            player,
+            name: "unite4.luvnft.com".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            rng: Some(rng),
+            skin: Some(skin),
+        }
+    }
+}
+
+// content of the replaceable result event, winner is None for a draw. duration is the
+// seconds between the first and the last move, only published when the player allows it,
+// resigned marks games won by resignation rather than four in a row. moves_hash is the
+// `moves_hash` of the final move list, missing from results of older clients
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameResult {
+    pub game_id: String,
+    pub p1_pubkey: XOnlyPublicKey,
+    pub p2_pubkey: XOnlyPublicKey,
+    pub p1_name: Option<String>,
+    pub p2_name: Option<String>,
+    pub winner: Option<usize>,
+    pub moves: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resigned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moves_hash: Option<String>,
+    // the pubkey that signed the result event, set when the results are fetched
+    #[serde(skip)]
+    pub author: Option<XOnlyPublicKey>,
+}
+
+impl GameResult {
+    // both players' results of a game should agree, a move list only one of them
+    // published can't be checked
+    pub fn disputes(&self, other: &GameResult) -> bool {
+        self.winner != other.winner
+            || self.moves != other.moves
+            || matches!(
+                (&self.moves_hash, &other.moves_hash),
+                (Some(hash), Some(other_hash)) if hash != other_hash
+            )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementLevel {
+    Maintenance,
+    Release,
+    Tournament,
+}
+
+// content of the announcement event published with the ops tool, `d` tagged with the
+// announcement tag so a new one replaces the last. an expiration tag ends it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Announcement {
+    pub level: AnnouncementLevel,
+    pub message: String,
+}
+
+// content of a club's follow set, the members are its `p` tags. the author edits the list
+// and is an admin with the ones listed here
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClubInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub admins: Vec<XOnlyPublicKey>,
+}
+
+// content of a tournament event, `d` tagged with its own id and tagged with the club's
+// hashtag. starts_at is a unix timestamp
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClubTournament {
+    pub name: String,
+    pub starts_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_control: Option<u64>,
+}
+
+// content of a league event, the players are its `p` tags. the season starts at
+// starts_at, a unix timestamp, and plays a round a week
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeagueInfo {
+    pub name: String,
+    pub starts_at: u64,
+}
+
+// the hashtag of a challenge's note and its replies, by the position's columns
+pub fn challenge_tag(columns: &[usize]) -> String {
+    let position: String = columns.iter().map(|column| column.to_string()).collect();
+    format!("{} {}", CHALLENGE_TAG, position)
+}
+
+// the hashtag of a club's tournaments and lobby games, by the author and `d` tag of its
+// follow set
+pub fn club_tag(author: &XOnlyPublicKey, identifier: &str) -> String {
+    format!("{} {}:{}", CLUB_TAG, author, identifier)
+}
+
+// the line on a study board as the columns from the empty board, who started the study,
+// who moves on it now and the notes left on positions of the line or of earlier branches
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Study {
+    pub host: XOnlyPublicKey,
+    pub controller: XOnlyPublicKey,
+    pub controller_name: Option<String>,
+    pub line: Vec<usize>,
+    #[serde(default)]
+    pub notes: Vec<StudyNote>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StudyNote {
+    pub line: Vec<usize>,
+    pub text: String,
+}
+
+impl Study {
+    // the note on the position the line leads to
+    pub fn note(&self) -> Option<&str> {
+        self.notes
+            .iter()
+            .find(|note| note.line == self.line)
+            .map(|note| note.text.as_str())
+    }
+
+    // replaces the note on the current position, an empty text removes it
+    pub fn annotate(&mut self, text: &str) {
+        self.notes.retain(|note| note.line != self.line);
+        if !text.trim().is_empty() {
+            self.notes.push(StudyNote {
+                line: self.line.clone(),
+                text: text.trim().to_string(),
+            });
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -31,3 +525,51 @@ impl Players {
         }
     }
 }
+
+// the seats of a 2v2 game in the order they move: player 1, player 2 and then their
+// teammates. teammates alternate the moves of their color, so move `index` is played by
+// the seat `index % 4`, while a win is still the color's
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Teams {
+    pub seats: Vec<XOnlyPublicKey>,
+}
+
+impl Teams {
+    pub const SEATS: usize = 4;
+
+    // player 1 takes the first seat when creating the game
+    pub fn new(player1: XOnlyPublicKey) -> Self {
+        Self {
+            seats: vec![player1],
+        }
+    }
+
+    pub fn full(&self) -> bool {
+        self.seats.len() >= Self::SEATS
+    }
+
+    // no moves are taken until every seat is taken
+    pub fn mover(&self, index: usize) -> Option<XOnlyPublicKey> {
+        if !self.full() {
+            return None;
+        }
+        self.seats.get(index % Self::SEATS).copied()
+    }
+
+    // 1 for red, 2 for yellow
+    pub fn color_of(&self, pubkey: &XOnlyPublicKey) -> Option<usize> {
+        self.seats
+            .iter()
+            .position(|seat| seat == pubkey)
+            .map(|seat| seat % 2 + 1)
+    }
+
+    // whether the pubkey got a seat it didn't have
+    pub fn seat(&mut self, pubkey: XOnlyPublicKey) -> bool {
+        if self.full() || self.seats.contains(&pubkey) {
+            return false;
+        }
+        self.seats.push(pubkey);
+        true
+    }
+}
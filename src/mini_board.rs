@@ -0,0 +1,115 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+
+use crate::resources::PlayerMove;
+
+const COLUMNS: usize = 7;
+const ROWS: usize = 6;
+const CELL_SIZE: usize = 12;
+
+const BACKGROUND: [u8; 4] = [255, 255, 255, 255];
+const EMPTY: [u8; 4] = [230, 230, 230, 255];
+const RED: [u8; 4] = [222, 49, 49, 255];
+const YELLOW: [u8; 4] = [247, 203, 21, 255];
+
+// a board preview drawn into a single texture, the entity also needs a sprite bundle
+#[derive(Component)]
+pub struct MiniBoard {
+    pub moves: Vec<PlayerMove>,
+}
+
+pub struct MiniBoardPlugin;
+
+impl Plugin for MiniBoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, render_mini_boards);
+    }
+}
+
+fn render_mini_boards(
+    mut images: ResMut<Assets<Image>>,
+    mut mini_boards: Query<(&MiniBoard, &mut Handle<Image>), Changed<MiniBoard>>,
+) {
+    for (mini_board, mut texture) in mini_boards.iter_mut() {
+        *texture = images.add(mini_board_image(&mini_board.moves));
+    }
+}
+
+pub fn mini_board_size(cell_size: usize) -> (usize, usize) {
+    (COLUMNS * cell_size, ROWS * cell_size)
+}
+
+pub fn mini_board_image(moves: &[PlayerMove]) -> Image {
+    let (width, height) = mini_board_size(CELL_SIZE);
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rasterize(moves, CELL_SIZE),
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+pub fn mini_board_png(moves: &[PlayerMove]) -> Option<Vec<u8>> {
+    let (width, height) = mini_board_size(CELL_SIZE);
+
+    let mut png = Vec::new();
+    match PngEncoder::new(&mut png).write_image(
+        &rasterize(moves, CELL_SIZE),
+        width as u32,
+        height as u32,
+        ColorType::Rgba8,
+    ) {
+        Ok(()) => Some(png),
+        Err(e) => {
+            error!("Error encoding mini board png: {}", e);
+            None
+        }
+    }
+}
+
+// rgba pixels, top row first
+pub fn rasterize(moves: &[PlayerMove], cell_size: usize) -> Vec<u8> {
+    let mut grid = [[0; ROWS]; COLUMNS];
+    for player_move in moves {
+        if player_move.column < COLUMNS && player_move.row < ROWS {
+            grid[player_move.column][player_move.row] = player_move.player;
+        }
+    }
+
+    let (width, height) = mini_board_size(cell_size);
+    let center = cell_size as f32 / 2.0;
+    let radius = cell_size as f32 * 0.42;
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = ROWS - 1 - y / cell_size;
+        let dy = (y % cell_size) as f32 + 0.5 - center;
+
+        for x in 0..width {
+            let column = x / cell_size;
+            let dx = (x % cell_size) as f32 + 0.5 - center;
+
+            let color = if dx * dx + dy * dy <= radius * radius {
+                match grid[column][row] {
+                    1 => RED,
+                    2 => YELLOW,
+                    _ => EMPTY,
+                }
+            } else {
+                BACKGROUND
+            };
+
+            data.extend_from_slice(&color);
+        }
+    }
+
+    data
+}
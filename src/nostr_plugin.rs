@@ -1,26 +1,108 @@
-use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
-use bevy::prelude::*;
-use futures::StreamExt;
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::texture::{CompressedImageFormats, ImageSampler, ImageType},
+};
+use futures::{
+    channel::mpsc::{Receiver, Sender},
+    StreamExt,
+};
 use nostr_sdk::{
-    serde_json, Client, ClientMessage, Event as NostrEvent, EventBuilder, Filter, Kind,
-    RelayPoolNotification, Tag, Timestamp,
+    nips::{nip01::Coordinate, nip19::Nip19Event},
+    secp256k1::XOnlyPublicKey,
+    serde_json, Client, ClientMessage, Event as NostrEvent, EventBuilder, EventId, Filter,
+    FromBech32, JsonUtil, Keys, Kind, Metadata, RelayMessage, RelayPoolNotification, RelayStatus,
+    Tag, TagKind, Timestamp,
 };
 
-use wasm_bindgen_futures::spawn_local;
-use web_sys::window;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
 
 use crate::{
     components::CoinMove,
-    messages::{NetworkMessage, Players},
-    resources::{Board, GameState, NetworkStuff, PlayerMove},
+    cosmetics_plugin::Cosmetics,
+    events::{GameEnded, GameFull, MoveInput, MoveMade, MoveSource, OpponentJoined, StudyReceived},
+    game::{rng_fingerprint, Game, MoveError, Outcome, Rules, COLUMNS},
+    gui_plugin::spawn_coin,
+    invite::{Invitation, InviteError},
+    latency,
+    locale_plugin::{Locale, Phrase},
+    messages::{
+        state_hash, GameConfig, NetworkMessage, Players, Special, Teams, ANVIL_OFFSET, BOMB_OFFSET,
+        GAME_TAG_PREFIX, MOVE_KIND, POP_OFFSET, SIGNAL_KIND,
+    },
+    platform,
+    profiling_plugin::{self, Span},
+    resources::{
+        Board, Desync, GameSession, GameState, MovePreload, NetworkStuff, PendingAck, PlayerMove,
+        Processed, Profile, Series, UndoState, RESULT_KIND,
+    },
+    settings_plugin::Settings,
+    storage,
+    theme_plugin::Theme,
+    transport::{GameConnection, GameTransport},
+    username_plugin::pseudonym,
+    webrtc::{self, WebRtcTransport},
     AppState,
 };
 
-const COIN_SIZE: Vec2 = Vec2::new(40.0, 40.0);
-const COLUMNS: usize = 7;
-const ROWS: usize = 7;
-const SPACING: f32 = 5.0;
+const ACK_TIMEOUT: f64 = 5.0;
+const CHECKPOINT_MOVES: usize = 6;
+const MAX_RETRIES: usize = 3;
+const RELAY_HEALTH_CHECK_INTERVAL: f64 = 10.0;
+const MIN_RELAY_CONFIRMATIONS: usize = 2;
+const APP_URL: &str = "https://unite4.luvnft.com";
+// memory ceilings for long sessions, the oldest entries are dropped first. moves are
+// never dropped from the game events
+const MAX_GAME_EVENTS: usize = 1000;
+const MAX_TRACKED_DELIVERIES: usize = 200;
+
+// public relays merged with the user's relays when none are configured or none are reachable
+const FALLBACK_RELAYS: [&str; 4] = [
+    "wss://relay.damus.io",
+    "wss://nos.lol",
+    "wss://relay.primal.net",
+    "wss://relay.nostr.band",
+];
+
+pub static RELAY_STATUS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+static RELAYS_CHANGED: AtomicBool = AtomicBool::new(false);
+static RESULT_POSTS: Mutex<Vec<ResultPost>> = Mutex::new(Vec::new());
+// write relays both players share plus a backup, empty until both relay lists are known
+static OUTBOX_RELAYS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// delivery of the last MAX_TRACKED_DELIVERIES events we published, keyed by event id
+pub static EVENT_DELIVERY: Mutex<BTreeMap<String, EventDelivery>> = Mutex::new(BTreeMap::new());
+// the last MAX_GAME_EVENTS signed events of the open games, sent or received, for the
+// audit log
+static GAME_EVENTS: Mutex<VecDeque<NostrEvent>> = Mutex::new(VecDeque::new());
+// last NOTICE sent by each relay
+pub static RELAY_NOTICES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+#[derive(Serialize)]
+pub struct EventDelivery {
+    sent_to: Vec<String>,
+    accepted: Vec<String>,
+    rejected: BTreeMap<String, String>,
+    #[serde(skip)]
+    nostr_msg: ClientMessage,
+    #[serde(skip)]
+    created_at: Timestamp,
+}
+
+// a result note to sign with the game key, or one already signed by a nip-07 extension
+enum ResultPost {
+    Unsigned(String),
+    Signed(String),
+}
 
 pub struct NostrPlugin;
 
@@ -28,127 +110,389 @@ impl Plugin for NostrPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(NetworkStuff::new())
             .insert_resource(GameState::new())
-            .add_systems(OnEnter(AppState::InGame), setup)
-            .add_systems(Update, handle_net_msg.run_if(in_state(AppState::InGame)));
+            .init_resource::<Processed>()
+            .insert_resource(MovePreload::new())
+            .insert_resource(PendingAck::new())
+            .insert_resource(UndoState::new())
+            .init_resource::<Desync>()
+            .add_event::<OpponentJoined>()
+            .add_event::<GameFull>()
+            .add_event::<StudyReceived>()
+            .add_systems(
+                Update,
+                (
+                    preload_opponent_move,
+                    handle_net_msg,
+                    start_game,
+                    connect_peer,
+                    answer_undo_request,
+                    check_pending_ack,
+                )
+                    .chain()
+                    .in_set(MoveInput)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (send_local_moves, publish_checkpoint)
+                    .after(MoveInput)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    check_relays_changed,
+                    check_relay_health,
+                    check_result_posts,
+                    check_move_delivery,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
     }
 }
 
-fn setup(mut network_stuff: ResMut<NetworkStuff>, mut game_state: ResMut<GameState>) {
-    let window = window().expect("no global `window` exists");
-    let local_storage = window
-        .local_storage()
-        .expect("no local storage")
-        .expect("local storage is not available");
+// why the invitation link was refused, in the player's language
+fn invite_error(error: &InviteError, locale: &Locale) -> String {
+    match error {
+        InviteError::UnknownVariant(variant) => locale.fill(Phrase::UnknownVariant, &[variant]),
+        InviteError::InvalidNumber(name, value) => locale.fill(Phrase::NotANumber, &[name, value]),
+        InviteError::SeriesLength(length) => {
+            locale.fill(Phrase::BadSeriesLength, &[&length.to_string()])
+        }
+        InviteError::Wager(sats) => locale.fill(Phrase::BadWager, &[&sats.to_string()]),
+        InviteError::TimeControl(seconds) => {
+            locale.fill(Phrase::BadTimeControl, &[&seconds.to_string()])
+        }
+    }
+}
 
-    if let Ok(Some(username)) = local_storage.get_item("username") {
+// connects the game of the url, the resources are fresh ones for it. games already open
+// are parked by the games plugin and keep their own connection
+pub fn connect_game(
+    network_stuff: &mut NetworkStuff,
+    game_state: &mut GameState,
+    processed: &mut Processed,
+    board: &mut Board,
+    settings: &Settings,
+) {
+    if settings.privacy {
+        info!("privacy mode, playing under a pseudonym");
+    } else if let Some(username) = &settings.username {
         game_state.local_ln_address = Some(username.clone());
-        info!("username found in local storage {:?}", username)
+        info!("username found in settings {:?}", username)
     } else {
-        info!("no username found in local storage")
+        info!("no username found in settings")
     }
 
-    let (send_tx, send_rx) = futures::channel::mpsc::channel::<String>(1000);
-    let (nostr_msg_tx, mut nostr_msg_rx) = futures::channel::mpsc::channel::<ClientMessage>(1000);
+    if game_state.ghost.is_some() {
+        info!("practice game, not connecting to relays");
+        return;
+    }
 
-    let nostr_msg_tx_clone = nostr_msg_tx.clone();
+    let game_id = platform::game_id();
+    let tag = format!("{}{}", GAME_TAG_PREFIX, game_id);
+    game_state.game_tag = Tag::Hashtag(tag);
+    game_state.game_id = game_id.clone();
+    *processed = Processed::default();
+    game_state.end_message = None;
+    latency::clear();
+    if settings.privacy {
+        let pubkey = game_state.nostr_keys.public_key();
+        game_state.local_ln_address = Some(pseudonym(&game_id, &pubkey));
+    }
 
-    let location = web_sys::window().unwrap().location();
-    let game_id = location.pathname().unwrap().to_string();
-    let tag = format!("unite4.luvnft.com game_id = {}", game_id);
-    game_state.game_tag = Tag::Hashtag(tag.clone());
+    let resuming = if let Some(session) = GameSession::load(&game_id) {
+        info!("resuming game as player {}", session.player_type);
+        game_state.player_type = session.player_type;
+        game_state.p2_ln_address = session.p2_ln_address;
+        game_state.start = true;
+        true
+    } else {
+        false
+    };
 
-    let game_state_clone = game_state.clone();
-    let game_state_clone_2 = game_state.clone();
+    game_state.series = Series::load(&game_id);
+    board.fortune_seed = storage::load_fortune_seed(&game_id);
+    board.pop_out = storage::load_pop_out(&game_id);
+    board.power_up = storage::load_power_up(&game_id);
+    board.cylinder = storage::load_cylinder(&game_id);
+    board.gravity_shift = storage::load_gravity_shift(&game_id);
+    game_state.correspondence = storage::load_correspondence(&game_id);
+    game_state.teams = storage::load_teams(&game_id);
 
-    network_stuff.read = Some(send_rx);
-    game_state.send = Some(nostr_msg_tx);
+    let challenger =
+        challenger_param().filter(|pubkey| *pubkey != game_state.nostr_keys.public_key());
+    if let Some(challenger) = challenger {
+        info!("challenge link from {:?}", challenger);
+    }
 
-    spawn_local(async move {
-        let nostr_keys = &game_state_clone.nostr_keys;
-        let client = Client::new(nostr_keys);
+    game_state.invitation = storage::load_invitation(&game_id);
+    if game_state.invitation.is_none() {
+        match Invitation::parse(&platform::query()) {
+            Ok(Some(invitation)) => {
+                info!("invitation: {:?}", invitation);
+                storage::save_invitation(&game_id, &invitation);
+                game_state.invitation = Some(invitation);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("invalid invitation link: {}", e);
+                let locale = Locale::of(settings);
+                platform::alert(
+                    &locale.fill(Phrase::InvitationInvalid, &[&invite_error(&e, &locale)]),
+                );
+            }
+        }
+    }
 
-        let relays: String = if let Ok(Some(relays)) = local_storage.get_item("Relays") {
-            info!("relays found in local storage {:?}", relays);
-            relays
-        } else {
-            info!("no relays found in local storage");
-            "".to_string()
-        };
+    let (relay_update_tx, relay_update_rx) = futures::channel::mpsc::channel::<Vec<String>>(10);
+    let (profile_tx, profile_rx) = futures::channel::mpsc::channel::<Profile>(10);
+    let relays: Arc<dyn GameTransport> = Arc::new(NostrTransport::new(relay_update_rx, profile_tx));
+    // a direct connection shows the opponent the player's ip address
+    let transport: Arc<dyn GameTransport> = if webrtc::enabled() && !settings.privacy {
+        Arc::new(WebRtcTransport::new(relays))
+    } else {
+        relays
+    };
 
-        let relay_urls: Vec<&str> = relays.split(',').collect();
+    let club = storage::load_club(&game_id);
+    let connection = GameConnection {
+        nostr_keys: game_state.nostr_keys.clone(),
+        game_id,
+        resuming,
+        challenger,
+        invitation: game_state.invitation.clone(),
+        local_ln_address: game_state.local_ln_address.clone(),
+        series_length: game_state.series.as_ref().map_or(1, |series| series.length),
+        config: game_state.config(&board),
+        club,
+        private: settings.privacy,
+    };
 
-        for relays in relay_urls {
-            match client.add_relay(relays).await {
-                Ok(_) => {
-                    info!("relay added: {:?}", relays);
-                }
-                Err(e) => {
-                    error!("error adding relay: {:?}", e);
-                }
-            };
+    info!("connecting over {}", transport.name());
+    set_outbox_relays(Vec::new());
+    network_stuff.read = Some(transport.connect(connection));
+    network_stuff.relay_update = Some(relay_update_tx);
+    network_stuff.profile = Some(profile_rx);
+    game_state.transport = Some(transport);
+}
+
+// game events over nostr relays: the messages are published as kind 4444 events tagged
+// with the game id, and the relays' notifications feed the stream. a player only
+// subscribes to their opponent once the game has been joined
+pub struct NostrTransport {
+    outgoing: Sender<ClientMessage>,
+    // taken when the transport connects
+    queued: Mutex<Option<Receiver<ClientMessage>>>,
+    relay_update: Mutex<Option<Receiver<Vec<String>>>>,
+    profile: Sender<Profile>,
+}
+
+impl NostrTransport {
+    pub fn new(relay_update: Receiver<Vec<String>>, profile: Sender<Profile>) -> Self {
+        let (outgoing, queued) = futures::channel::mpsc::channel::<ClientMessage>(1000);
+        Self {
+            outgoing,
+            queued: Mutex::new(Some(queued)),
+            relay_update: Mutex::new(Some(relay_update)),
+            profile,
         }
+    }
+}
+
+impl GameTransport for NostrTransport {
+    fn name(&self) -> &'static str {
+        "nostr"
+    }
+
+    fn connect(&self, connection: GameConnection) -> Receiver<NostrEvent> {
+        let (send_tx, send_rx) = futures::channel::mpsc::channel::<NostrEvent>(1000);
 
-        client.connect().await;
+        let queued = self.queued.lock().ok().and_then(|mut queued| queued.take());
+        let relay_update = self
+            .relay_update
+            .lock()
+            .ok()
+            .and_then(|mut relay_update| relay_update.take());
+        let (Some(mut nostr_msg_rx), Some(mut relay_update_rx)) = (queued, relay_update) else {
+            error!("nostr transport is already connected");
+            return send_rx;
+        };
+
+        let nostr_msg_tx_clone = self.outgoing.clone();
+        let profile_tx = self.profile.clone();
+        let GameConnection {
+            nostr_keys,
+            game_id,
+            resuming,
+            challenger,
+            invitation,
+            local_ln_address,
+            series_length,
+            config,
+            club,
+            private,
+        } = connection;
+        let tag = format!("{}{}", GAME_TAG_PREFIX, game_id);
+        let audit_game_id = game_id.clone();
+        let locale = Locale::saved();
 
-        let client_clone = client.clone();
+        platform::spawn(async move {
+            let nostr_keys = &nostr_keys;
+            let client = Client::new(nostr_keys);
 
-        spawn_local(async move {
-            while let Some(msg) = nostr_msg_rx.next().await {
-                info!("sent event: {:?}", msg);
-                match client_clone.clone().send_msg(msg).await {
-                    Ok(_) => {}
+            let relay_urls = relay_pool(stored_relays(), false);
+
+            for relay in relay_urls {
+                match client.add_relay(relay.as_str()).await {
+                    Ok(_) => {
+                        info!("relay added: {:?}", relay);
+                    }
                     Err(e) => {
-                        let window = web_sys::window().unwrap();
-                        if let Some(window) = Some(window) {
-                            let alert_message = format!("Error connecting to nostr: {:?}", e);
-                            match window.alert_with_message(&alert_message) {
-                                Ok(_) => {}
-                                Err(js_err) => {
-                                    info!("Error sending alert: {:?}", js_err)
-                                }
-                            }
-                        }
-                        error!("Error sending message: {:?}", e);
+                        error!("error adding relay: {:?}", e);
+                        set_relay_status(relay, format!("failed: {}", e));
                     }
                 };
             }
-        });
 
-        let filter = Filter::new().kind(Kind::Regular(4444)).hashtag(tag.clone());
+            client.connect().await;
 
-        client.subscribe(vec![filter.clone()]).await;
+            for (url, relay) in client.relays().await {
+                set_relay_status(url.to_string(), relay.status().await.to_string());
+            }
 
-        let mut events: Vec<NostrEvent> = client
-            .get_events_of(vec![filter], Some(Duration::new(10, 0)))
-            .await
-            .unwrap();
+            let client_clone = client.clone();
 
-        events.reverse();
+            platform::spawn(async move {
+                while let Some(msg) = nostr_msg_rx.next().await {
+                    // a lost signal is made up for by the next one, it skips the outbox,
+                    // the delivery tracking and the alert
+                    if matches!(msg, ClientMessage::Event(ref event) if signal_event(event)) {
+                        if let Err(e) = client_clone.send_msg(msg).await {
+                            info!("Error sending signal: {:?}", e);
+                        }
+                        continue;
+                    }
 
-        info!("nostr_key: {:?}", nostr_keys.public_key());
+                    info!("sent event: {:?}", msg);
+                    if let ClientMessage::Event(ref event) = msg {
+                        record_game_event(event, &audit_game_id);
+                    }
+                    let outbox = outbox_relays();
+                    let sent = if outbox.is_empty() {
+                        track_delivery(&msg, pool_relays(&client_clone).await);
+                        client_clone.send_msg(msg).await
+                    } else {
+                        add_relays(&client_clone, &outbox).await;
+                        track_delivery(&msg, outbox.clone());
+                        client_clone.send_msg_to(outbox, msg).await
+                    };
+                    match sent {
+                        Ok(_) => {}
+                        Err(e) => {
+                            platform::alert(
+                                &locale.fill(Phrase::NostrError, &[&format!("{:?}", e)]),
+                            );
+                            error!("Error sending message: {:?}", e);
+                        }
+                    };
+                }
+            });
 
-        if let Some(last_event) = events.last() {
-            match serde_json::from_str::<NetworkMessage>(&last_event.content) {
-                Ok(NetworkMessage::NewGame(player)) => {
-                    info!("current tip: {:?}", last_event.content);
-                    if last_event.pubkey != nostr_keys.public_key() {
-                        let players = if game_state_clone_2.local_ln_address.is_none() {
-                            Players::new(
-                                player,
-                                None,
-                                last_event.pubkey.clone(),
-                                nostr_keys.public_key(),
-                            )
-                        } else {
-                            Players::new(
-                                player,
-                                game_state_clone_2.local_ln_address.clone(),
-                                last_event.pubkey.clone(),
-                                nostr_keys.public_key(),
-                            )
-                        };
+            let filter = Filter::new().kinds(game_kinds()).hashtag(tag.clone());
+
+            client.subscribe(vec![filter.clone()]).await;
+
+            let client_clone = client.clone();
+            let relay_filter = filter.clone();
+
+            platform::spawn(async move {
+                while let Some(relay_urls) = relay_update_rx.next().await {
+                    info!("relays changed: {:?}", relay_urls);
+                    update_relays(&client_clone, &relay_urls).await;
+                    client_clone
+                        .subscribe(vec![relay_filter.clone().since(Timestamp::now())])
+                        .await;
+                }
+            });
+
+            let mut events: Vec<NostrEvent> = client
+                .get_events_of(vec![filter], Some(Duration::new(10, 0)))
+                .await
+                .unwrap();
 
-                        let msg = NetworkMessage::JoinGame(players);
+            events.sort_by_key(|event| event.created_at);
+
+            for event in events.iter() {
+                record_game_event(event, &game_id);
+            }
+
+            info!("nostr_key: {:?}", nostr_keys.public_key());
+
+            if resuming {
+                info!("saved session found, skipping handshake");
+            } else if let Some(last_event) = events.last() {
+                match serde_json::from_str::<NetworkMessage>(&last_event.content) {
+                    Ok(NetworkMessage::NewGame(..))
+                        if challenger.is_some_and(|challenger| challenger != last_event.pubkey) =>
+                    {
+                        error!("game was not created by the challenger, not joining");
+                        platform::alert(locale.get(Phrase::ChallengerMismatch));
+                    }
+                    Ok(new_game @ NetworkMessage::NewGame(..))
+                        if invitation
+                            .as_ref()
+                            .is_some_and(|invitation| !invitation.accepts(&new_game)) =>
+                    {
+                        error!("game does not match the invitation, not joining");
+                        platform::alert(locale.get(Phrase::InvitationMismatch));
+                    }
+                    Ok(NetworkMessage::NewGame(player, ..)) => {
+                        info!("current tip: {:?}", last_event.content);
+                        if last_event.pubkey != nostr_keys.public_key() {
+                            let players = if local_ln_address.is_none() {
+                                Players::new(
+                                    player,
+                                    None,
+                                    last_event.pubkey.clone(),
+                                    nostr_keys.public_key(),
+                                )
+                            } else {
+                                Players::new(
+                                    player,
+                                    local_ln_address.clone(),
+                                    last_event.pubkey.clone(),
+                                    nostr_keys.public_key(),
+                                )
+                            };
+
+                            let msg = NetworkMessage::JoinGame(players);
+                            let serialized_message = serde_json::to_string(&msg).unwrap();
+
+                            let nostr_msg = ClientMessage::event(
+                                EventBuilder::new(
+                                    Kind::Regular(4444),
+                                    serialized_message,
+                                    [Tag::Hashtag(tag.clone())],
+                                )
+                                .to_event(nostr_keys)
+                                .unwrap(),
+                            );
+
+                            match nostr_msg_tx_clone.clone().try_send(nostr_msg) {
+                                Ok(()) => {}
+                                Err(e) => {
+                                    error!("Error sending join_game message: {}", e)
+                                }
+                            };
+                        } else {
+                            info!("skipping own new game event");
+                        }
+                    }
+                    // the new game was joined, a latecomer asks for a teammate's seat
+                    _ if open_team_seat(&events, nostr_keys.public_key()) => {
+                        info!("asking for a seat in the team game");
+                        let msg = NetworkMessage::JoinTeam(local_ln_address.clone());
                         let serialized_message = serde_json::to_string(&msg).unwrap();
 
                         let nostr_msg = ClientMessage::event(
@@ -161,225 +505,2032 @@ fn setup(mut network_stuff: ResMut<NetworkStuff>, mut game_state: ResMut<GameSta
                             .unwrap(),
                         );
 
-                        match nostr_msg_tx_clone.clone().try_send(nostr_msg) {
-                            Ok(()) => {}
-                            Err(e) => {
-                                error!("Error sending join_game message: {}", e)
-                            }
-                        };
-                    } else {
-                        info!("skipping own new game event");
+                        if let Err(e) = nostr_msg_tx_clone.clone().try_send(nostr_msg) {
+                            error!("Error sending join_team message: {}", e);
+                        }
+                    }
+                    _ => {
+                        info!("current tip: {:?}", last_event.content);
                     }
                 }
-                _ => {
-                    info!("current tip: {:?}", last_event.content);
-                }
-            }
-        } else {
-            info!("current tip: no events");
-            let msg = if game_state_clone_2.local_ln_address.is_none() {
-                NetworkMessage::NewGame(None)
             } else {
-                NetworkMessage::NewGame(game_state_clone_2.local_ln_address.clone())
-            };
+                info!("current tip: no events");
+                let msg = NetworkMessage::NewGame(local_ln_address.clone(), series_length, config);
 
-            let serialized_message = serde_json::to_string(&msg).unwrap();
+                let serialized_message = serde_json::to_string(&msg).unwrap();
+                let tags = std::iter::once(tag.clone()).chain(club).map(Tag::Hashtag);
 
-            let nostr_msg = ClientMessage::event(
-                EventBuilder::new(
-                    Kind::Regular(4444),
-                    serialized_message,
-                    [Tag::Hashtag(tag.clone())],
-                )
-                .to_event(nostr_keys)
-                .unwrap(),
-            );
+                let nostr_msg = ClientMessage::event(
+                    EventBuilder::new(Kind::Regular(4444), serialized_message, tags)
+                        .to_event(nostr_keys)
+                        .unwrap(),
+                );
+
+                match nostr_msg_tx_clone.clone().try_send(nostr_msg) {
+                    Ok(()) => {}
+                    Err(e) => {
+                        error!("Error sending join_game message: {}", e)
+                    }
+                };
 
-            match nostr_msg_tx_clone.clone().try_send(nostr_msg) {
-                Ok(()) => {}
-                Err(e) => {
-                    error!("Error sending join_game message: {}", e)
+                // the announcement would tie the player's key to the app
+                if !private {
+                    match nostr_msg_tx_clone
+                        .clone()
+                        .try_send(handler_announcement(nostr_keys))
+                    {
+                        Ok(()) => {}
+                        Err(e) => {
+                            error!("Error sending handler announcement: {}", e)
+                        }
+                    };
                 }
             };
-        };
 
-        for event in events.drain(..) {
-            if (event.content.contains("NewGame") || event.content.contains("JoinGame"))
-                && event.pubkey == nostr_keys.public_key()
-            {
-                info!("skipping event");
-                continue;
-            }
-            if event.content.contains("NewGame") {
-                //this means you are player 2 so you only sub to p1 events
-                let new_subscription = Filter::new()
-                    .author(event.pubkey)
-                    .kind(Kind::Regular(4444))
-                    .since(Timestamp::now())
-                    .hashtag(tag.clone());
+            for event in events.drain(..) {
+                if (event.content.contains("NewGame") || event.content.contains("JoinGame"))
+                    && event.pubkey == nostr_keys.public_key()
+                {
+                    info!("skipping event");
+                    continue;
+                }
+                if event.content.contains("NewGame")
+                    && challenger.is_some_and(|challenger| challenger != event.pubkey)
+                {
+                    info!("skipping new game not created by the challenger");
+                    continue;
+                }
+                if event.content.contains("NewGame") {
+                    //this means you are player 2 so you only sub to p1 events
+                    let new_subscription = Filter::new()
+                        .author(event.pubkey)
+                        .kinds(game_kinds())
+                        .since(Timestamp::now())
+                        .hashtag(tag.clone());
 
-                info!("sub to player 1 events only {:?}", event.pubkey);
+                    info!("sub to player 1 events only {:?}", event.pubkey);
 
-                client.subscribe(vec![new_subscription]).await;
-            }
-            //this means you are player 1 so you only sub to p2 events
-            if event.content.contains("JoinGame") {
-                let new_subscription = Filter::new()
-                    .author(event.pubkey)
-                    .kind(Kind::Regular(4444))
-                    .since(Timestamp::now())
-                    .hashtag(tag.clone());
+                    client.subscribe(vec![new_subscription]).await;
+                    platform::spawn(fetch_profile(
+                        client.clone(),
+                        event.pubkey,
+                        profile_tx.clone(),
+                    ));
+                    platform::spawn(fetch_outbox_relays(
+                        client.clone(),
+                        [nostr_keys.public_key(), event.pubkey],
+                    ));
+                }
+                //this means you are player 1 so you only sub to p2 events
+                if event.content.contains("JoinGame") {
+                    let new_subscription = Filter::new()
+                        .author(event.pubkey)
+                        .kinds(game_kinds())
+                        .since(Timestamp::now())
+                        .hashtag(tag.clone());
 
-                info!("sub to player 2 events only {:?}", event.pubkey);
+                    info!("sub to player 2 events only {:?}", event.pubkey);
 
-                client.subscribe(vec![new_subscription]).await;
-            }
+                    client.subscribe(vec![new_subscription]).await;
+                    platform::spawn(fetch_profile(
+                        client.clone(),
+                        event.pubkey,
+                        profile_tx.clone(),
+                    ));
+                    platform::spawn(fetch_outbox_relays(
+                        client.clone(),
+                        [nostr_keys.public_key(), event.pubkey],
+                    ));
+                }
 
-            info!("processing stored event: {:?}", event);
+                info!("processing stored event: {:?}", event);
+
+                match send_tx.clone().try_send(event.clone()) {
+                    Ok(()) => {}
+                    Err(e) => {
+                        error!("Error sending message: {} CHANNEL FULL???", e)
+                    }
+                };
+            }
 
-            match send_tx.clone().try_send(event.content.clone()) {
-                Ok(()) => {}
-                Err(e) => {
-                    error!("Error sending message: {} CHANNEL FULL???", e)
+            // the moves after the latest checkpoint were ephemeral, the opponent sends them
+            // again. an opponent on an older client stored every move and never sees this
+            if resuming {
+                let msg = serde_json::to_string(&NetworkMessage::SyncRequest).unwrap();
+                let sync_request = ClientMessage::event(
+                    EventBuilder::new(Kind::Ephemeral(MOVE_KIND), msg, [Tag::Hashtag(tag.clone())])
+                        .to_event(nostr_keys)
+                        .unwrap(),
+                );
+                if let Err(e) = nostr_msg_tx_clone.clone().try_send(sync_request) {
+                    error!("Error sending sync request: {}", e);
                 }
-            };
-        }
+            }
 
-        client
-            .handle_notifications(|notification| async {
-                if let RelayPoolNotification::Event {
-                    relay_url: _,
-                    event,
-                } = notification
-                {
-                    if event.pubkey != nostr_keys.public_key() {
-                        info!("received event: {:?}", event);
-                        if event.content.contains("JoinGame") {
-                            let new_subscription = Filter::new()
-                                .author(event.pubkey)
-                                .kind(Kind::Regular(4444))
-                                .since(Timestamp::now())
-                                .hashtag(tag.clone());
+            client
+                .handle_notifications(|notification| async {
+                    if let RelayPoolNotification::RelayStatus { relay_url, status } = notification {
+                        set_relay_status(relay_url.to_string(), status.to_string());
+                    } else if let RelayPoolNotification::Message { relay_url, message } =
+                        notification
+                    {
+                        match message {
+                            RelayMessage::Ok {
+                                event_id,
+                                status,
+                                message,
+                            } => {
+                                let relay_url = relay_url.to_string();
+                                record_delivery(&event_id, &relay_url, status, message);
+                                if !status {
+                                    retry_delivery(&client, &event_id).await;
+                                }
+                            }
+                            RelayMessage::Notice { message } => {
+                                info!("notice from {}: {}", relay_url, message);
+                                set_relay_notice(relay_url.to_string(), message);
+                            }
+                            _ => {}
+                        }
+                    } else if let RelayPoolNotification::Event {
+                        relay_url: _,
+                        event,
+                    } = notification
+                    {
+                        record_game_event(&event, &game_id);
 
-                            info!("sub to player 2 events only {:?}", event.pubkey);
+                        if event.pubkey != nostr_keys.public_key() {
+                            info!("received event: {:?}", event);
+                            if event.content.contains("JoinGame") {
+                                let new_subscription = Filter::new()
+                                    .author(event.pubkey)
+                                    .kinds(game_kinds())
+                                    .since(Timestamp::now())
+                                    .hashtag(tag.clone());
 
-                            client.subscribe(vec![new_subscription]).await;
-                        }
+                                info!("sub to player 2 events only {:?}", event.pubkey);
 
-                        match send_tx.clone().try_send(event.content.clone()) {
-                            Ok(()) => {}
-                            Err(e) => {
-                                error!("Error sending message: {} CHANNEL FULL???", e)
+                                client.subscribe(vec![new_subscription]).await;
+                                platform::spawn(fetch_profile(
+                                    client.clone(),
+                                    event.pubkey,
+                                    profile_tx.clone(),
+                                ));
+                                platform::spawn(fetch_outbox_relays(
+                                    client.clone(),
+                                    [nostr_keys.public_key(), event.pubkey],
+                                ));
                             }
-                        };
+
+                            match send_tx.clone().try_send(event.clone()) {
+                                Ok(()) => {}
+                                Err(e) => {
+                                    error!("Error sending message: {} CHANNEL FULL???", e)
+                                }
+                            };
+                        }
                     }
-                }
 
-                Ok(false)
-            })
-            .await
-            .unwrap();
-    });
+                    Ok(false)
+                })
+                .await
+                .unwrap();
+        });
+
+        send_rx
+    }
+
+    fn send(&self, msg: ClientMessage) -> Result<(), String> {
+        self.outgoing
+            .clone()
+            .try_send(msg)
+            .map_err(|e| e.to_string())
+    }
 }
 
-#[allow(clippy::too_many_arguments, clippy::type_complexity)]
-fn handle_net_msg(
-    mut network_stuff: ResMut<NetworkStuff>,
-    mut game_state: ResMut<GameState>,
-    mut board: ResMut<Board>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) {
-    if let Some(ref mut receive_rx) = network_stuff.read {
-        while let Ok(Some(message)) = receive_rx.try_next() {
-            match serde_json::from_str::<NetworkMessage>(&message) {
-                Ok(network_message) => match network_message {
-                    NetworkMessage::Input(new_input) => {
-                        let row_pos = board.moves.iter().filter(|m| m.column == new_input).count();
-                        if row_pos <= 5 {
-                            let player_move =
-                                PlayerMove::new(board.player_turn, new_input, row_pos);
-
-                            board.moves.push(player_move);
-
-                            let offset_x = -COIN_SIZE.x * (COLUMNS as f32) / 2.0;
-                            let offset_y = -COIN_SIZE.y * (ROWS as f32) / 2.0;
-
-                            if board.player_turn == 1 {
-                                commands
-                                    .spawn(SpriteBundle {
-                                        sprite: Sprite {
-                                            custom_size: Some(COIN_SIZE),
-                                            ..Default::default()
-                                        },
-                                        texture: asset_server.load("red_circle.png"),
-                                        transform: Transform::from_xyz(
-                                            offset_x + new_input as f32 * (COIN_SIZE.x + SPACING),
-                                            offset_y + 6_f32 * (COIN_SIZE.y + SPACING),
-                                            1.0,
-                                        ),
-                                        ..Default::default()
-                                    })
-                                    .insert(CoinMove::new(player_move));
-                            } else {
-                                commands
-                                    .spawn(SpriteBundle {
-                                        sprite: Sprite {
-                                            custom_size: Some(COIN_SIZE),
-                                            ..Default::default()
-                                        },
-                                        texture: asset_server.load("yellow_circle.png"),
-                                        transform: Transform::from_xyz(
-                                            offset_x + new_input as f32 * (COIN_SIZE.x + SPACING),
-                                            offset_y + 6_f32 * (COIN_SIZE.y + SPACING),
-                                            1.0,
-                                        ),
-                                        ..Default::default()
-                                    })
-                                    .insert(CoinMove::new(player_move));
-                            }
+// the stored game events, the ephemeral moves and the signals, see `MessageCategory`
+fn game_kinds() -> Vec<Kind> {
+    vec![
+        Kind::Regular(4444),
+        Kind::Ephemeral(MOVE_KIND),
+        Kind::Ephemeral(SIGNAL_KIND),
+    ]
+}
 
-                            board.player_turn = if board.player_turn == 1 { 2 } else { 1 };
+// whether the stored events are of a team game with a seat left for the player, who
+// hasn't taken part yet. before player 1 seated anyone only player 2 has joined
+fn open_team_seat(events: &[NostrEvent], own: XOnlyPublicKey) -> bool {
+    let message = |event: &NostrEvent| serde_json::from_str::<NetworkMessage>(&event.content).ok();
+    let Some(creator) = events.iter().find_map(|event| match message(event) {
+        Some(NetworkMessage::NewGame(_, _, GameConfig { teams: true, .. })) => Some(event.pubkey),
+        _ => None,
+    }) else {
+        return false;
+    };
+    if creator == own {
+        return false;
+    }
 
-                            break;
-                        }
-                    }
-                    NetworkMessage::JoinGame(players) => {
-                        if game_state.nostr_keys.public_key() != players.p1_pubkey
-                            && game_state.nostr_keys.public_key() != players.p2_pubkey
-                        {
-                            info!("not your game {:?}", players);
-                            game_state.player_type = 3;
-                            continue;
-                        }
+    let seating = events
+        .iter()
+        .rev()
+        .filter(|event| event.pubkey == creator)
+        .find_map(|event| match message(event) {
+            Some(NetworkMessage::Teams(teams)) => Some(teams),
+            _ => None,
+        });
+    match seating {
+        Some(teams) => !teams.full() && !teams.seats.contains(&own),
+        None => !events.iter().any(|event| event.pubkey == own),
+    }
+}
 
-                        if game_state.start {
-                            continue;
-                        }
+// npub from a challenge link, `/{game_id}?challenger=npub...`
+fn challenger_param() -> Option<XOnlyPublicKey> {
+    let search = platform::query();
+    let npub = search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|param| param.strip_prefix("challenger="))?;
+
+    match XOnlyPublicKey::from_bech32(npub) {
+        Ok(pubkey) => Some(pubkey),
+        Err(e) => {
+            error!("invalid challenger {}: {}", npub, e);
+            None
+        }
+    }
+}
 
-                        game_state.p2_ln_address = players.p2_name;
+// nip-89 announcement so nostr clients can open game events and challenge links with this app
+fn handler_announcement(nostr_keys: &Keys) -> ClientMessage {
+    let web_handler = |path: &str, entity: &str| {
+        Tag::Generic(
+            TagKind::Custom("web".to_string()),
+            vec![format!("{}/{}", APP_URL, path), entity.to_string()],
+        )
+    };
 
-                        game_state.player_type = 1;
-                        info!("player type: 1");
-                        game_state.start = true;
-                    }
-                    NetworkMessage::NewGame(player1) => {
-                        if game_state.start {
-                            continue;
-                        }
+    let content = serde_json::json!({
+        "name": "Connect 4",
+        "about": "Play Connect 4 with me",
+        "website": APP_URL,
+    })
+    .to_string();
 
-                        game_state.p2_ln_address = player1;
-                        //recevied message from p1 so you must be p2
-                        game_state.player_type = 2;
-                        info!("player type: 2");
-                        game_state.start = true;
-                    }
-                },
+    ClientMessage::event(
+        EventBuilder::new(
+            Kind::ParameterizedReplaceable(31990),
+            content,
+            [
+                Tag::Identifier("connect4".to_string()),
+                Tag::Generic(TagKind::Custom("k".to_string()), vec!["4444".to_string()]),
+                Tag::Generic(
+                    TagKind::Custom("k".to_string()),
+                    vec![RESULT_KIND.to_string()],
+                ),
+                web_handler("?nevent=<bech32>", "nevent"),
+                web_handler("?naddr=<bech32>", "naddr"),
+                web_handler("?challenger=<bech32>", "npub"),
+            ],
+        )
+        .to_event(nostr_keys)
+        .unwrap(),
+    )
+}
 
-                Err(e) => {
-                    info!("Failed to deserialize message: {:?}", e);
-                }
+// opened from another nostr client through a handler link, `/?nevent=...` or `/?naddr=...`
+pub fn open_handler_link() -> bool {
+    let search = platform::query();
+
+    let param = |name: &str| {
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|param| param.strip_prefix(name))
+            .map(|value| value.to_string())
+    };
+
+    if let Some(naddr) = param("naddr=") {
+        // result events are addressed by their game id
+        match Coordinate::from_bech32(&naddr) {
+            Ok(coordinate) if coordinate.identifier.starts_with('/') => {
+                open_game(&coordinate.identifier);
+                return true;
+            }
+            Ok(coordinate) => error!("not a game address: {:?}", coordinate),
+            Err(e) => error!("invalid naddr {}: {}", naddr, e),
+        }
+    } else if let Some(nevent) = param("nevent=") {
+        match Nip19Event::from_bech32(&nevent) {
+            Ok(nip19_event) => {
+                platform::spawn(resolve_event_link(nip19_event));
+                return true;
+            }
+            Err(e) => error!("invalid nevent {}: {}", nevent, e),
+        }
+    }
+
+    false
+}
+
+// one-off query outside of a game, with its own short lived client
+pub async fn fetch_events(filters: Vec<Filter>, extra_relays: Vec<String>) -> Vec<NostrEvent> {
+    let client = Client::new(&Keys::generate());
+
+    for relay in extra_relays
+        .into_iter()
+        .chain(relay_pool(stored_relays(), false))
+    {
+        if let Err(e) = client.add_relay(relay.as_str()).await {
+            error!("error adding relay: {:?}", e);
+        }
+    }
+
+    client.connect().await;
+
+    let mut events = match client
+        .get_events_of(filters, Some(Duration::new(10, 0)))
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Error fetching events: {:?}", e);
+            Vec::new()
+        }
+    };
+
+    if let Err(e) = client.disconnect().await {
+        error!("Error disconnecting: {:?}", e);
+    }
+
+    events.sort_by_key(|event| event.created_at);
+    events
+}
+
+// one-off publish outside of a game, like `fetch_events`
+pub async fn publish_events(events: Vec<NostrEvent>) {
+    let client = Client::new(&Keys::generate());
+
+    for relay in relay_pool(stored_relays(), false) {
+        if let Err(e) = client.add_relay(relay.as_str()).await {
+            error!("error adding relay: {:?}", e);
+        }
+    }
+
+    client.connect().await;
+
+    for event in events {
+        if let Err(e) = client.send_event(event).await {
+            error!("Error publishing event: {:?}", e);
+        }
+    }
+
+    if let Err(e) = client.disconnect().await {
+        error!("Error disconnecting: {:?}", e);
+    }
+}
+
+pub fn game_id_from_tags(event: &NostrEvent) -> Option<String> {
+    event.tags.iter().find_map(|tag| match tag {
+        Tag::Hashtag(hashtag) => hashtag
+            .strip_prefix(GAME_TAG_PREFIX)
+            .map(|game_id| game_id.to_string()),
+        _ => None,
+    })
+}
+
+async fn resolve_event_link(nip19_event: Nip19Event) {
+    let filter = Filter::new().id(nip19_event.event_id);
+    let events = fetch_events(vec![filter], nip19_event.relays).await;
+
+    match events.iter().find_map(game_id_from_tags) {
+        Some(game_id) => open_game(&game_id),
+        None => {
+            error!("linked event is not a game event");
+            platform::alert(Locale::saved().get(Phrase::NotAGameLink));
+        }
+    }
+}
+
+pub fn open_game(game_id: &str) {
+    platform::open_game(game_id);
+}
+
+// the profile is sent even without metadata so bevy learns the opponent's pubkey
+async fn fetch_profile(client: Client, pubkey: XOnlyPublicKey, mut profile_tx: Sender<Profile>) {
+    let metadata = fetch_metadata(&client, pubkey).await;
+
+    let avatar = match metadata
+        .as_ref()
+        .and_then(|metadata| metadata.picture.as_ref())
+    {
+        Some(picture) => fetch_avatar(picture).await,
+        None => None,
+    };
+
+    let profile = Profile {
+        pubkey,
+        name: metadata.and_then(|metadata| metadata.display_name.or(metadata.name)),
+        avatar,
+    };
+
+    match profile_tx.try_send(profile) {
+        Ok(()) => {}
+        Err(e) => error!("Error sending profile: {}", e),
+    };
+}
+
+async fn fetch_metadata(client: &Client, pubkey: XOnlyPublicKey) -> Option<Metadata> {
+    let filter = Filter::new().kind(Kind::Metadata).author(pubkey).limit(1);
+
+    let events = match client
+        .get_events_of(vec![filter], Some(Duration::new(10, 0)))
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Error fetching profile: {:?}", e);
+            return None;
+        }
+    };
+
+    let Some(event) = events.into_iter().max_by_key(|event| event.created_at) else {
+        info!("no profile found for {:?}", pubkey);
+        return None;
+    };
+
+    match Metadata::from_json(&event.content) {
+        Ok(metadata) => Some(metadata),
+        Err(e) => {
+            error!("Error parsing profile: {}", e);
+            None
+        }
+    }
+}
+
+async fn fetch_avatar(url: &str) -> Option<Image> {
+    let (mime_type, bytes) = platform::fetch(url).await?;
+
+    match Image::from_buffer(
+        &bytes,
+        ImageType::MimeType(&mime_type),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+    ) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            info!("Error decoding avatar {}: {}", url, e);
+            None
+        }
+    }
+}
+
+// nip-65 outbox model, moves go to the write relays both players list plus a backup relay
+async fn fetch_outbox_relays(client: Client, pubkeys: [XOnlyPublicKey; 2]) {
+    let filter = Filter::new()
+        .kind(Kind::RelayList)
+        .authors(pubkeys.to_vec());
+
+    let events = match client
+        .get_events_of(vec![filter], Some(Duration::new(10, 0)))
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Error fetching relay lists: {:?}", e);
+            return;
+        }
+    };
+
+    let [local_relays, opponent_relays] = pubkeys.map(|pubkey| {
+        events
+            .iter()
+            .filter(|event| event.pubkey == pubkey)
+            .max_by_key(|event| event.created_at)
+            .map(write_relays)
+            .unwrap_or_default()
+    });
+
+    let mut outbox: Vec<String> = local_relays
+        .into_iter()
+        .filter(|relay| opponent_relays.contains(relay))
+        .collect();
+
+    if outbox.is_empty() {
+        info!("no shared write relays, publishing to every relay");
+        return;
+    }
+
+    if let Some(backup) = relay_pool(stored_relays(), false).first() {
+        let backup = backup.trim_end_matches('/').to_string();
+        if !outbox.contains(&backup) {
+            outbox.push(backup);
+        }
+    }
+
+    info!("outbox relays: {:?}", outbox);
+    add_relays(&client, &outbox).await;
+    set_outbox_relays(outbox);
+}
+
+// `r` tags without a marker are both read and write relays
+fn write_relays(event: &NostrEvent) -> Vec<String> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| {
+            let tag = tag.as_vec();
+            let url = tag.get(1)?;
+            let read_only = matches!(tag.get(2), Some(marker) if marker == "read");
+
+            (tag.first()? == "r" && !read_only).then(|| url.trim_end_matches('/').to_string())
+        })
+        .collect()
+}
+
+// relays the user hasn't configured join the pool before anything is sent to them
+async fn add_relays(client: &Client, relay_urls: &[String]) {
+    let mut added = false;
+
+    for relay in relay_urls {
+        match client.add_relay(relay.as_str()).await {
+            Ok(new_relay) => added |= new_relay,
+            Err(e) => {
+                error!("error adding relay: {:?}", e);
+                set_relay_status(relay.as_str(), format!("failed: {}", e));
             }
         }
     }
+
+    if !added {
+        return;
+    }
+
+    client.connect().await;
+
+    for (url, relay) in client.relays().await {
+        set_relay_status(url.to_string(), relay.status().await.to_string());
+    }
+}
+
+fn outbox_relays() -> Vec<String> {
+    match OUTBOX_RELAYS.lock() {
+        Ok(outbox) => outbox.clone(),
+        Err(e) => {
+            error!("Error reading outbox relays: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn set_outbox_relays(relay_urls: Vec<String>) {
+    match OUTBOX_RELAYS.lock() {
+        Ok(mut outbox) => *outbox = relay_urls,
+        Err(e) => error!("Error updating outbox relays: {}", e),
+    }
+}
+
+async fn pool_relays(client: &Client) -> Vec<String> {
+    client
+        .relays()
+        .await
+        .into_keys()
+        .map(|url| url.to_string().trim_end_matches('/').to_string())
+        .collect()
+}
+
+// presence, the thinking indicator and studies, see `MessageCategory::Signal`
+fn signal_event(event: &NostrEvent) -> bool {
+    event.kind == Kind::Ephemeral(SIGNAL_KIND)
+}
+
+// a move of any kind, stored or ephemeral
+fn move_event(event: &NostrEvent) -> bool {
+    matches!(
+        serde_json::from_str::<NetworkMessage>(&event.content),
+        Ok(NetworkMessage::Input(_)
+            | NetworkMessage::Pop(_)
+            | NetworkMessage::Anvil(_)
+            | NetworkMessage::Bomb(_))
+    )
+}
+
+// resent moves keep the relays that already answered
+fn track_delivery(nostr_msg: &ClientMessage, relay_urls: Vec<String>) {
+    let ClientMessage::Event(ref event) = nostr_msg else {
+        return;
+    };
+    if signal_event(event) {
+        return;
+    }
+    latency::published(&event.id);
+
+    match EVENT_DELIVERY.lock() {
+        Ok(mut deliveries) => {
+            let delivery = deliveries
+                .entry(event.id.to_hex())
+                .or_insert_with(|| EventDelivery {
+                    sent_to: Vec::new(),
+                    accepted: Vec::new(),
+                    rejected: BTreeMap::new(),
+                    nostr_msg: nostr_msg.clone(),
+                    created_at: event.created_at,
+                });
+
+            for relay in relay_urls {
+                if !delivery.sent_to.contains(&relay) {
+                    delivery.sent_to.push(relay);
+                }
+            }
+
+            // the oldest events are long acknowledged or given up on
+            while deliveries.len() > MAX_TRACKED_DELIVERIES {
+                let oldest = deliveries
+                    .iter()
+                    .min_by_key(|(_, delivery)| delivery.created_at)
+                    .map(|(event_id, _)| event_id.clone());
+                if let Some(event_id) = oldest {
+                    deliveries.remove(&event_id);
+                }
+            }
+        }
+        Err(e) => error!("Error updating event delivery: {}", e),
+    }
+}
+
+fn record_delivery(event_id: &EventId, relay_url: &str, accepted: bool, message: String) {
+    let relay_url = relay_url.trim_end_matches('/').to_string();
+
+    match EVENT_DELIVERY.lock() {
+        Ok(mut deliveries) => {
+            let Some(delivery) = deliveries.get_mut(&event_id.to_hex()) else {
+                return;
+            };
+
+            if accepted {
+                latency::relay_ok(event_id);
+                delivery.rejected.remove(&relay_url);
+                if !delivery.accepted.contains(&relay_url) {
+                    delivery.accepted.push(relay_url);
+                }
+            } else {
+                warn!(
+                    "{} rejected event {}: {}",
+                    relay_url,
+                    event_id.to_hex(),
+                    message
+                );
+                delivery.rejected.insert(relay_url, message);
+            }
+        }
+        Err(e) => error!("Error updating event delivery: {}", e),
+    }
+}
+
+// a rejected event goes to every pool or fallback relay it wasn't sent to yet
+async fn retry_delivery(client: &Client, event_id: &EventId) {
+    let candidates = relay_pool(pool_relays(client).await, true);
+
+    let retry = match EVENT_DELIVERY.lock() {
+        Ok(mut deliveries) => deliveries.get_mut(&event_id.to_hex()).and_then(|delivery| {
+            let untried: Vec<String> = candidates
+                .into_iter()
+                .filter(|relay| !delivery.sent_to.contains(relay))
+                .collect();
+
+            if untried.is_empty() {
+                return None;
+            }
+
+            delivery.sent_to.extend(untried.iter().cloned());
+            Some((delivery.nostr_msg.clone(), untried))
+        }),
+        Err(e) => {
+            error!("Error reading event delivery: {}", e);
+            None
+        }
+    };
+
+    let Some((nostr_msg, relay_urls)) = retry else {
+        info!("no other relays left for event {}", event_id.to_hex());
+        return;
+    };
+
+    info!("retrying event {} on {:?}", event_id.to_hex(), relay_urls);
+    add_relays(client, &relay_urls).await;
+
+    if let Err(e) = client.send_msg_to(relay_urls, nostr_msg).await {
+        error!("Error retrying event: {:?}", e);
+    }
+}
+
+pub fn delivery_confirmations(event_id: &EventId) -> usize {
+    match EVENT_DELIVERY.lock() {
+        Ok(deliveries) => deliveries
+            .get(&event_id.to_hex())
+            .map_or(0, |delivery| delivery.accepted.len()),
+        Err(e) => {
+            error!("Error reading event delivery: {}", e);
+            0
+        }
+    }
+}
+
+// presence, hover, thinking and study signals are left out, they'd push out the moves
+fn record_game_event(event: &NostrEvent, game_id: &str) {
+    if game_id_from_tags(event).as_deref() != Some(game_id) || signal_event(event) {
+        return;
+    }
+
+    match GAME_EVENTS.lock() {
+        Ok(mut game_events) => {
+            if !game_events
+                .iter()
+                .any(|game_event| game_event.id == event.id)
+            {
+                game_events.push_back(event.clone());
+            }
+            // moves are never dropped, the audit log and the game duration need all of
+            // them and a game has few
+            if game_events.len() > MAX_GAME_EVENTS {
+                if let Some(oldest) = game_events.iter().position(|event| !move_event(event)) {
+                    game_events.remove(oldest);
+                }
+            }
+        }
+        Err(e) => error!("Error recording game event: {}", e),
+    }
+}
+
+// the recorded events of the game open on the page, the other open games record theirs too
+fn current_game_event(event: &NostrEvent) -> bool {
+    game_id_from_tags(event) == Some(platform::game_id())
+}
+
+// wall clock seconds from the first to the last move event of the current game
+pub fn game_duration() -> Option<u64> {
+    let game_events = match GAME_EVENTS.lock() {
+        Ok(game_events) => game_events,
+        Err(e) => {
+            error!("Error reading game events: {}", e);
+            return None;
+        }
+    };
+
+    let move_times: Vec<u64> = game_events
+        .iter()
+        .filter(|event| current_game_event(event))
+        .filter(|event| {
+            matches!(
+                serde_json::from_str::<NetworkMessage>(&event.content),
+                Ok(NetworkMessage::Input(_))
+            )
+        })
+        .map(|event| event.created_at.as_u64())
+        .collect();
+
+    Some(move_times.iter().max()? - move_times.iter().min()?)
+}
+
+fn set_relay_notice(relay_url: String, message: String) {
+    match RELAY_NOTICES.lock() {
+        Ok(mut notices) => {
+            notices.insert(relay_url, message);
+        }
+        Err(e) => error!("Error updating relay notices: {}", e),
+    }
+}
+
+async fn update_relays(client: &Client, relay_urls: &[String]) {
+    for (url, _) in client.relays().await {
+        let url_string = url.to_string();
+        if relay_urls
+            .iter()
+            .any(|relay| relay.trim_end_matches('/') == url_string.trim_end_matches('/'))
+        {
+            continue;
+        }
+
+        match client.remove_relay(url).await {
+            Ok(()) => {
+                info!("relay removed: {:?}", url_string);
+                remove_relay_status(&url_string);
+            }
+            Err(e) => error!("error removing relay: {:?}", e),
+        }
+    }
+
+    for relay in relay_urls {
+        if let Err(e) = client.add_relay(relay.as_str()).await {
+            error!("error adding relay: {:?}", e);
+            set_relay_status(relay.as_str(), format!("failed: {}", e));
+        }
+    }
+
+    client.connect().await;
+
+    for (url, relay) in client.relays().await {
+        set_relay_status(url.to_string(), relay.status().await.to_string());
+    }
+}
+
+fn check_relays_changed(network_stuff: Res<NetworkStuff>) {
+    if !RELAYS_CHANGED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    send_relay_update(
+        &network_stuff,
+        relay_pool(stored_relays(), network_stuff.relay_failover),
+    );
+}
+
+// reconnects dropped relays and fails over to the fallback relays once nothing is reachable
+fn check_relay_health(
+    mut network_stuff: ResMut<NetworkStuff>,
+    time: Res<Time>,
+    mut last_check: Local<f64>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if now - *last_check < RELAY_HEALTH_CHECK_INTERVAL {
+        return;
+    }
+    *last_check = now;
+
+    let statuses: Vec<String> = match RELAY_STATUS.lock() {
+        Ok(relay_status) => relay_status.values().cloned().collect(),
+        Err(e) => {
+            error!("Error reading relay status: {}", e);
+            return;
+        }
+    };
+
+    if statuses.is_empty() {
+        return;
+    }
+
+    let count = |states: &[RelayStatus]| {
+        statuses
+            .iter()
+            .filter(|status| states.iter().any(|state| **status == state.to_string()))
+            .count()
+    };
+
+    let connected = count(&[RelayStatus::Connected]);
+    let connecting = count(&[
+        RelayStatus::Initialized,
+        RelayStatus::Pending,
+        RelayStatus::Connecting,
+    ]);
+    let dropped = count(&[
+        RelayStatus::Disconnected,
+        RelayStatus::Stopped,
+        RelayStatus::Terminated,
+    ]);
+
+    if connected == 0 && connecting == 0 && !network_stuff.relay_failover {
+        warn!("no relays reachable, failing over to fallback relays");
+        network_stuff.relay_failover = true;
+    } else if dropped > 0 {
+        info!("{} relays dropped, reconnecting", dropped);
+    } else {
+        return;
+    }
+
+    send_relay_update(
+        &network_stuff,
+        relay_pool(stored_relays(), network_stuff.relay_failover),
+    );
+}
+
+fn check_result_posts(game_state: Res<GameState>) {
+    let posts: Vec<ResultPost> = match RESULT_POSTS.lock() {
+        Ok(mut posts) => posts.drain(..).collect(),
+        Err(e) => {
+            error!("Error reading result posts: {}", e);
+            return;
+        }
+    };
+
+    for post in posts {
+        match post {
+            ResultPost::Unsigned(content) => {
+                info!("posting result note");
+                game_state.clone().send_text_note(content);
+            }
+            ResultPost::Signed(event) => match NostrEvent::from_json(event) {
+                Ok(event) => {
+                    info!("posting signed result note {:?}", event.id);
+                    game_state.clone().publish(event);
+                }
+                Err(e) => error!("Error parsing signed result note: {}", e),
+            },
+        }
+    }
+}
+
+fn send_relay_update(network_stuff: &NetworkStuff, relay_urls: Vec<String>) {
+    if let Some(ref relay_update) = network_stuff.relay_update {
+        match relay_update.clone().try_send(relay_urls) {
+            Ok(()) => {}
+            Err(e) => error!("Error sending relay update: {}", e),
+        };
+    }
+}
+
+pub fn stored_relays() -> Vec<String> {
+    Settings::load().relays
+}
+
+// user relays first, followed by any fallback relay they don't already cover
+pub fn relay_pool(mut relay_urls: Vec<String>, failover: bool) -> Vec<String> {
+    if !relay_urls.is_empty() && !failover {
+        return relay_urls;
+    }
+
+    info!("using fallback relays");
+
+    for fallback in FALLBACK_RELAYS {
+        if !relay_urls
+            .iter()
+            .any(|relay| relay.trim_end_matches('/') == fallback)
+        {
+            relay_urls.push(fallback.to_string());
+        }
+    }
+
+    relay_urls
+}
+
+fn remove_relay_status(relay_url: &str) {
+    match RELAY_STATUS.lock() {
+        Ok(mut relay_status) => {
+            relay_status.remove(relay_url);
+        }
+        Err(e) => error!("Error updating relay status: {}", e),
+    }
+}
+
+fn set_relay_status(relay_url: impl Into<String>, status: impl Into<String>) {
+    match RELAY_STATUS.lock() {
+        Ok(mut relay_status) => {
+            relay_status.insert(relay_url.into(), status.into());
+        }
+        Err(e) => error!("Error updating relay status: {}", e),
+    }
+}
+
+fn preload_opponent_move(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut preload: ResMut<MovePreload>,
+    theme: Res<Theme>,
+) {
+    if (!board.is_changed() && !theme.is_changed()) || board.player_turn == game_state.player_type {
+        return;
+    }
+
+    preload.landing_rows = board.landing_rows();
+
+    if preload.player != board.player_turn || theme.is_changed() {
+        preload.texture = theme.coin(board.player_turn).clone();
+    }
+
+    preload.player = board.player_turn;
+    preload.move_count = board.moves.len();
+}
+
+fn check_pending_ack(
+    mut pending_ack: ResMut<PendingAck>,
+    game_state: Res<GameState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    let overdue = if let Some(ref mut pending) = pending_ack.pending {
+        if now - pending.last_sent > ACK_TIMEOUT && pending.retries < MAX_RETRIES {
+            pending.retries += 1;
+            pending.last_sent = now;
+            info!("no ack for move {}, retry {}", pending.seq, pending.retries);
+            game_state.clone().resend(pending.nostr_msg.clone());
+        }
+
+        // a correspondence opponent acks whenever they come back
+        now - pending.first_sent > ACK_TIMEOUT && !game_state.correspondence
+    } else {
+        false
+    };
+
+    if pending_ack.overdue != overdue {
+        pending_ack.overdue = overdue;
+    }
+}
+
+// relays get as long as the opponent to answer before a low count is flagged
+fn check_move_delivery(mut pending_ack: ResMut<PendingAck>, time: Res<Time>) {
+    let Some((event_id, sent)) = pending_ack.last_move else {
+        return;
+    };
+
+    let confirmations = delivery_confirmations(&event_id);
+    let low_delivery =
+        time.elapsed_seconds_f64() - sent > ACK_TIMEOUT && confirmations < MIN_RELAY_CONFIRMATIONS;
+
+    if pending_ack.confirmations != confirmations || pending_ack.low_delivery != low_delivery {
+        pending_ack.confirmations = confirmations;
+        pending_ack.low_delivery = low_delivery;
+    }
+}
+
+// the rules of player 1's new game event, taken before the first move. a rule that is
+// already set stays, from the stored game or an earlier copy of the event
+fn apply_game_config(
+    config: GameConfig,
+    creator: XOnlyPublicKey,
+    board: &mut Board,
+    game_state: &mut GameState,
+) {
+    if config.correspondence && !game_state.correspondence {
+        info!("correspondence game");
+        game_state.correspondence = true;
+        storage::save_correspondence(&game_state.game_id);
+    }
+
+    if !board.moves.is_empty() {
+        return;
+    }
+
+    if let (Some(seed), None) = (config.fortune_seed, board.fortune_seed) {
+        info!("columns of fortune game, seed {}", seed);
+        board.fortune_seed = Some(seed);
+        storage::save_fortune_seed(&game_state.game_id, seed);
+    }
+
+    if config.pop_out && !board.pop_out {
+        info!("pop out game");
+        board.pop_out = true;
+        storage::save_pop_out(&game_state.game_id);
+    }
+
+    if config.power_up && !board.power_up {
+        info!("power up game");
+        board.power_up = true;
+        storage::save_power_up(&game_state.game_id);
+    }
+
+    if config.cylinder && !board.cylinder {
+        info!("cylinder game");
+        board.cylinder = true;
+        storage::save_cylinder(&game_state.game_id);
+    }
+
+    if config.gravity_shift && !board.gravity_shift {
+        info!("gravity shift game");
+        board.gravity_shift = true;
+        storage::save_gravity_shift(&game_state.game_id);
+    }
+
+    if config.teams && game_state.teams.is_none() {
+        info!("team game");
+        let teams = Teams::new(creator);
+        storage::save_teams(&game_state.game_id, &teams);
+        game_state.teams = Some(teams);
+    }
+}
+
+// what a message from the network sets off
+#[derive(SystemParam)]
+struct NetEvents<'w> {
+    moves: EventWriter<'w, MoveMade>,
+    opponent_joined: EventWriter<'w, OpponentJoined>,
+    game_ended: EventWriter<'w, GameEnded>,
+    studies: EventWriter<'w, StudyReceived>,
+    game_full: EventWriter<'w, GameFull>,
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn handle_net_msg(
+    mut network_stuff: ResMut<NetworkStuff>,
+    mut game_state: ResMut<GameState>,
+    mut processed: ResMut<Processed>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+    theme: Res<Theme>,
+    preload: Res<MovePreload>,
+    mut pending_ack: ResMut<PendingAck>,
+    coins: Query<(Entity, &CoinMove)>,
+    mut undo: ResMut<UndoState>,
+    mut events: NetEvents,
+    mut desync: ResMut<Desync>,
+    mut sync_requested: Local<Option<usize>>,
+) {
+    let _span = profiling_plugin::span(Span::HandleNetMsg);
+    while let Some(event) = network_stuff.next_event() {
+        if !processed.first(&event) {
+            info!("skipping duplicate event {}", event.id);
+            continue;
+        }
+
+        match serde_json::from_str::<NetworkMessage>(&event.content) {
+            Ok(network_message) => {
+                let sender = game_state.player_of(&event.pubkey);
+                if network_message.players_only()
+                    && game_state.player_pubkeys.is_some()
+                    && sender.is_none()
+                {
+                    info!(
+                        "ignoring {:?} from non player {:?}",
+                        network_message, event.pubkey
+                    );
+                    continue;
+                }
+
+                // any event of the opponent shows when they were last around
+                let from_opponent = sender.is_some_and(|player| player != game_state.player_type);
+                if from_opponent {
+                    game_state.opponent_presence.seen(event.created_at.as_u64());
+                }
+
+                let (pop, special) = match network_message {
+                    NetworkMessage::Pop(_) => (true, None),
+                    NetworkMessage::Anvil(_) => (false, Some(Special::Anvil)),
+                    NetworkMessage::Bomb(_) => (false, Some(Special::Bomb)),
+                    _ => (false, None),
+                };
+                match network_message {
+                    NetworkMessage::Input(input)
+                    | NetworkMessage::Pop(input)
+                    | NetworkMessage::Anvil(input)
+                    | NetworkMessage::Bomb(input) => {
+                        if input.index < board.moves.len() {
+                            if let Err(reason) = board.chain.check_fork(input.index, &event) {
+                                flag_tampering(&mut desync, reason);
+                            }
+                            info!("skipping already applied move {}", input.index);
+                            continue;
+                        }
+                        if processed.move_pending(input.index, board.moves.len()) {
+                            info!("skipping move {} the board is applying", input.index);
+                            continue;
+                        }
+
+                        // before the players are known the move is taken as the side to move
+                        let mover = sender.unwrap_or(board.player_turn);
+
+                        // in a team game only the seat whose move it is plays it
+                        if game_state
+                            .teams
+                            .as_ref()
+                            .is_some_and(|teams| teams.mover(input.index) != Some(event.pubkey))
+                        {
+                            info!(
+                                "ignoring move {} out of turn by {:?}",
+                                input.index, event.pubkey
+                            );
+                            continue;
+                        }
+
+                        if input.index > board.moves.len() {
+                            info!(
+                                "move {} received but board has {} moves",
+                                input.index,
+                                board.moves.len()
+                            );
+                            if *sync_requested != Some(board.moves.len()) {
+                                *sync_requested = Some(board.moves.len());
+                                game_state.clone().send_sync_request();
+                            }
+                            continue;
+                        }
+
+                        let intent = PlayerMove {
+                            player: mover,
+                            column: input.column,
+                            row: 0,
+                            pop,
+                            special,
+                        };
+                        let valid_row = match validate_move(&board, &intent) {
+                            Ok(row) => row,
+                            Err(e) => {
+                                error!(
+                                    "rejected move {} from player {}: {}",
+                                    input.index, mover, e
+                                );
+                                if mover != game_state.player_type {
+                                    desync.reason =
+                                        Some(format!("move {} rejected, {}", input.index + 1, e));
+                                    if *sync_requested != Some(board.moves.len()) {
+                                        *sync_requested = Some(board.moves.len());
+                                        game_state.clone().send_sync_request();
+                                    }
+                                }
+                                continue;
+                            }
+                        };
+
+                        // players' clients announce the chain, spectators can't tell
+                        let chained = mover != game_state.player_type
+                            && game_state.opponent_supports("chain");
+                        if let Err(reason) =
+                            board.chain.check_previous(input.index, &event, chained)
+                        {
+                            flag_tampering(&mut desync, reason);
+                        }
+                        board.chain.record(input.index, event.id);
+
+                        let new_input = input.column;
+
+                        let preloaded = preload.move_count == board.moves.len()
+                            && preload.player == board.player_turn;
+
+                        // preloaded rows follow the move history, which a popped coin breaks
+                        let row_pos = match preload.landing_rows.get(new_input) {
+                            Some(&Some(row)) if preloaded && board.classic() => row,
+                            _ => valid_row,
+                        };
+
+                        let player_move = PlayerMove {
+                            player: board.player_turn,
+                            row: row_pos,
+                            ..intent
+                        };
+
+                        if (game_state.player_type == 1 || game_state.player_type == 2)
+                            && player_move.player != game_state.player_type
+                        {
+                            latency::received(input.index, event.id);
+                            game_state.clone().send_ack(input.index);
+                        }
+
+                        if let Some(ref pending) = pending_ack.pending {
+                            if pending.seq < input.index {
+                                pending_ack.pending = None;
+                            }
+                        }
+
+                        let texture = if preloaded {
+                            preload.texture.clone()
+                        } else {
+                            theme.coin(board.player_turn).clone()
+                        };
+
+                        events.moves.send(MoveMade {
+                            index: input.index,
+                            player_move,
+                            source: MoveSource::Network,
+                            texture,
+                        });
+                        game_state.last_move_at = Some(event.created_at.as_u64());
+                        processed.take_move(input.index);
+
+                        // the next input is read once the board has applied this one
+                        break;
+                    }
+                    NetworkMessage::JoinGame(players) => {
+                        let named = [players.p1_pubkey, players.p2_pubkey];
+                        // a join from someone else, or naming others once the game is on, is
+                        // a spectator's
+                        if !named.contains(&event.pubkey)
+                            || (game_state.start
+                                && game_state
+                                    .player_pubkeys
+                                    .is_some_and(|pubkeys| pubkeys != named))
+                        {
+                            info!("ignoring join {:?} by {:?}", players, event.pubkey);
+                            // someone else answered the new game first, player 1 tells them
+                            let own = game_state.nostr_keys.public_key();
+                            if let (1, Some([p1_pubkey, p2_pubkey])) =
+                                (game_state.player_type, game_state.player_pubkeys)
+                            {
+                                if players.p1_pubkey == own && players.p2_pubkey == event.pubkey {
+                                    game_state.clone().send_game_full(Players::new(
+                                        game_state.local_ln_address.clone(),
+                                        game_state.p2_ln_address.clone(),
+                                        p1_pubkey,
+                                        p2_pubkey,
+                                    ));
+                                }
+                            }
+                            continue;
+                        }
+
+                        game_state.player_pubkeys = Some(named);
+
+                        // player 1 seats player 2 and tells the teammates
+                        let own = game_state.nostr_keys.public_key();
+                        if let Some(mut teams) = game_state.teams.clone() {
+                            if own == players.p1_pubkey && teams.seat(players.p2_pubkey) {
+                                storage::save_teams(&game_state.game_id, &teams);
+                                game_state.teams = Some(teams.clone());
+                                game_state.clone().send_teams(teams);
+                            }
+                        }
+
+                        if game_state.nostr_keys.public_key() != players.p1_pubkey
+                            && game_state.nostr_keys.public_key() != players.p2_pubkey
+                        {
+                            info!("not your game {:?}", players);
+                            game_state.player_type = 3;
+                            continue;
+                        }
+
+                        if game_state.start {
+                            continue;
+                        }
+
+                        events.opponent_joined.send(OpponentJoined {
+                            player_type: 1,
+                            opponent_name: players.p2_name,
+                            series_length: 1,
+                        });
+                        break;
+                    }
+                    NetworkMessage::NewGame(player1, series_length, config) => {
+                        // the join names both players and replaces this guess
+                        if game_state.player_pubkeys.is_none() {
+                            let own = game_state.nostr_keys.public_key();
+                            game_state.player_pubkeys = Some([event.pubkey, own]);
+                        }
+
+                        if game_state.player_of(&event.pubkey) == Some(1) {
+                            apply_game_config(config, event.pubkey, &mut board, &mut game_state);
+                        }
+
+                        if game_state.start {
+                            continue;
+                        }
+
+                        //recevied message from p1 so you must be p2
+                        events.opponent_joined.send(OpponentJoined {
+                            player_type: 2,
+                            opponent_name: player1,
+                            series_length,
+                        });
+                        break;
+                    }
+                    NetworkMessage::GameFull(players) => {
+                        let own = game_state.nostr_keys.public_key();
+                        let named = [players.p1_pubkey, players.p2_pubkey];
+                        if players.p1_pubkey != event.pubkey || named.contains(&own) {
+                            continue;
+                        }
+                        // already watching, e.g. the answer to a join of an earlier visit
+                        if game_state.player_type == 3 {
+                            game_state.player_pubkeys = Some(named);
+                            continue;
+                        }
+
+                        info!("game is full, {:?} play it", players);
+                        game_state.player_pubkeys = Some(named);
+                        game_state.player_type = 3;
+                        game_state.p2_pubkey = None;
+                        game_state.p2_ln_address = None;
+                        game_state.save_session();
+                        events.game_full.send(GameFull { players });
+                    }
+                    NetworkMessage::JoinTeam(name) => {
+                        let own = game_state.nostr_keys.public_key();
+                        let Some(mut teams) = game_state.teams.clone() else {
+                            continue;
+                        };
+                        // player 1 seats the teammates once player 2 joined, before the
+                        // first move
+                        if teams.seats.first() != Some(&own)
+                            || teams.seats.len() < 2
+                            || !board.moves.is_empty()
+                        {
+                            continue;
+                        }
+                        if teams.full() && !teams.seats.contains(&event.pubkey) {
+                            info!("team game is full, {:?} watches", event.pubkey);
+                            game_state.clone().send_game_full(Players::new(
+                                game_state.local_ln_address.clone(),
+                                game_state.p2_ln_address.clone(),
+                                teams.seats[0],
+                                teams.seats[1],
+                            ));
+                            continue;
+                        }
+                        if !teams.seat(event.pubkey) {
+                            continue;
+                        }
+
+                        info!(
+                            "{:?} takes seat {} of the team game",
+                            name,
+                            teams.seats.len()
+                        );
+                        storage::save_teams(&game_state.game_id, &teams);
+                        game_state.teams = Some(teams.clone());
+                        game_state.clone().send_teams(teams);
+                    }
+                    NetworkMessage::Teams(teams) => {
+                        let creator = game_state
+                            .teams
+                            .as_ref()
+                            .and_then(|teams| teams.seats.first().copied());
+                        if creator != Some(event.pubkey)
+                            || teams.seats.first() != Some(&event.pubkey)
+                            || teams.seats.len() < 2
+                        {
+                            continue;
+                        }
+
+                        let own = game_state.nostr_keys.public_key();
+                        let pubkeys = [teams.seats[0], teams.seats[1]];
+                        // a latecomer without a seat watches
+                        let player_type = teams.color_of(&own).unwrap_or(3);
+                        game_state.player_pubkeys = Some(pubkeys);
+                        storage::save_teams(&game_state.game_id, &teams);
+                        game_state.teams = Some(teams);
+
+                        if game_state.player_type != player_type {
+                            info!("seated as player {} of the team game", player_type);
+                            game_state.player_type = player_type;
+                            game_state.p2_pubkey =
+                                (player_type != 3).then(|| pubkeys[2 - player_type]);
+                            game_state.p2_ln_address = None;
+                            game_state.start = true;
+                            game_state.save_session();
+                        }
+                    }
+                    NetworkMessage::SyncRequest => {
+                        if game_state.player_type == 1 || game_state.player_type == 2 {
+                            info!("sending move list for sync");
+                            let columns = board.moves.iter().map(sync_column).collect();
+                            game_state.clone().send_sync_response(columns);
+                        }
+                    }
+                    // a checkpoint rebuilds a board that is behind like a sync response
+                    NetworkMessage::SyncResponse(columns) | NetworkMessage::Checkpoint(columns) => {
+                        let diverged = board
+                            .moves
+                            .iter()
+                            .zip(&columns)
+                            .any(|(player_move, &column)| sync_column(player_move) != column);
+
+                        if diverged {
+                            error!("synced moves contradict the board");
+                            desync.divergent = true;
+                            desync
+                                .reason
+                                .get_or_insert_with(|| "synced moves differ".to_string());
+                            continue;
+                        }
+
+                        if columns.len() <= board.moves.len() {
+                            if columns.len() == board.moves.len() && !desync.divergent {
+                                desync.reason = None;
+                            }
+                            continue;
+                        }
+
+                        info!("rebuilding board from {} synced moves", columns.len());
+                        rebuild_board(&columns, &mut board, &mut commands, &theme, &coins);
+                        *sync_requested = None;
+                        if !desync.divergent {
+                            desync.reason = None;
+                        }
+                        break;
+                    }
+                    NetworkMessage::StateHash(moves, hash) => {
+                        if game_state.player_type != 1 && game_state.player_type != 2 {
+                            continue;
+                        }
+
+                        let local_hash = state_hash(board.moves.iter().map(sync_column));
+                        if moves == board.moves.len() && hash == local_hash {
+                            if !desync.divergent {
+                                desync.reason = None;
+                            }
+                            continue;
+                        }
+
+                        // a board behind ours asks for the moves once it sees our hash
+                        if moves < board.moves.len() {
+                            info!("other board has {} of {} moves", moves, board.moves.len());
+                            game_state
+                                .clone()
+                                .send_state_hash(board.moves.len(), local_hash);
+                        } else if *sync_requested != Some(board.moves.len()) {
+                            info!("board differs from the other player's, requesting a sync");
+                            *sync_requested = Some(board.moves.len());
+                            game_state.clone().send_sync_request();
+                        }
+                    }
+                    ack @ (NetworkMessage::Ack(seq) | NetworkMessage::TimedAck(seq, _)) => {
+                        let received = match ack {
+                            NetworkMessage::TimedAck(_, received) => Some(received as f64),
+                            _ => None,
+                        };
+                        latency::acked(seq, received);
+                        if let Some(ref pending) = pending_ack.pending {
+                            if pending.seq == seq {
+                                info!("move {} acknowledged", seq);
+                                pending_ack.pending = None;
+                            }
+                        }
+                    }
+                    NetworkMessage::Rating(rating) => {
+                        if rating.player == game_state.player_type || game_state.player_type == 3 {
+                            continue;
+                        }
+
+                        info!("player {} rating: {}", rating.player, rating.rating);
+                        game_state.p2_rating = Some(rating.rating);
+                    }
+                    NetworkMessage::Client(client_info) => {
+                        if client_info.player == game_state.player_type
+                            || game_state.player_type == 3
+                        {
+                            continue;
+                        }
+
+                        info!(
+                            "opponent client: {} {} {:?}",
+                            client_info.name, client_info.version, client_info.features
+                        );
+                        game_state.p2_client = Some(client_info);
+                    }
+                    NetworkMessage::UndoRequest(index) => {
+                        let opponent_move = board.moves.get(index).is_some_and(|player_move| {
+                            player_move.player != game_state.player_type
+                        });
+
+                        if index + 1 == board.moves.len()
+                            && opponent_move
+                            && (game_state.player_type == 1 || game_state.player_type == 2)
+                            && board.winner.is_none()
+                            && !board.draw
+                            && board.classic()
+                        {
+                            undo.requested = Some(index);
+                        }
+                    }
+                    NetworkMessage::UndoResponse(index, accepted) => {
+                        // a replayed request is answered by the response stored right after it
+                        if undo.requested == Some(index) {
+                            undo.requested = None;
+                        }
+
+                        let own_request = undo.sent == Some(index);
+                        if own_request {
+                            undo.sent = None;
+                        }
+
+                        if !accepted {
+                            info!("undo of move {} declined", index);
+                            if own_request {
+                                undo.declined = true;
+                            }
+                            continue;
+                        }
+
+                        // the player of the last move asked for the undo, only the other player
+                        // allows it. a player takes back a move only when they asked for it,
+                        // the player who allowed it already did
+                        let from_responder = board.moves.last().is_some_and(|last_move| {
+                            sender.is_some_and(|player| player != last_move.player)
+                        });
+                        let spectator = !matches!(game_state.player_type, 1 | 2);
+                        if index + 1 != board.moves.len()
+                            || !from_responder
+                            || !(own_request || spectator)
+                        {
+                            continue;
+                        }
+
+                        info!("undoing move {}", index);
+                        undo_last_move(&mut board, &mut commands, &coins, &mut pending_ack);
+                        break;
+                    }
+                    // taken by the webrtc transport, nothing to do over the relays only
+                    NetworkMessage::Signal(_) => {}
+                    NetworkMessage::Presence(online) => {
+                        if from_opponent && !online {
+                            game_state.opponent_presence.left = true;
+                            game_state.opponent_presence.thinking = false;
+                            game_state.opponent_presence.hover = None;
+                        }
+                    }
+                    NetworkMessage::Thinking(thinking) => {
+                        if from_opponent {
+                            game_state.opponent_presence.thinking = thinking;
+                        }
+                    }
+                    NetworkMessage::Hover(column) => {
+                        if from_opponent {
+                            game_state.opponent_presence.hover =
+                                column.filter(|&column| column < COLUMNS);
+                        }
+                    }
+                    // only shown to the player who lost the game
+                    NetworkMessage::EndMessage(pack, index) => {
+                        if from_opponent
+                            && board.winner.is_some()
+                            && board.winner != Some(game_state.player_type)
+                        {
+                            game_state.end_message = Some((pack, index));
+                        }
+                    }
+                    message @ (NetworkMessage::Study(_)
+                    | NetworkMessage::StudyCursor(_)
+                    | NetworkMessage::StudyFollow
+                    | NetworkMessage::StudyRequest(_)
+                    | NetworkMessage::StudyEnd) => {
+                        if event.pubkey != game_state.nostr_keys.public_key() {
+                            events.studies.send(StudyReceived {
+                                from: event.pubkey,
+                                message,
+                            });
+                        }
+                    }
+                    NetworkMessage::Resign(player) => {
+                        if board.winner.is_some() || board.draw {
+                            continue;
+                        }
+
+                        if game_state.player_pubkeys.is_some() && sender != Some(player) {
+                            info!(
+                                "ignoring resignation of player {} by {:?}",
+                                player, event.pubkey
+                            );
+                            continue;
+                        }
+
+                        info!("player {} resigned", player);
+                        events.game_ended.send(GameEnded {
+                            winner: if player == 1 { Some(2) } else { Some(1) },
+                            resigned: Some(player),
+                        });
+                    }
+                }
+            }
+            Err(e) => {
+                info!("Failed to deserialize message: {:?}", e);
+            }
+        }
+    }
+}
+
+fn start_game(
+    mut game_state: ResMut<GameState>,
+    mut opponent_joined: EventReader<OpponentJoined>,
+    settings: Res<Settings>,
+    cosmetics: Res<Cosmetics>,
+) {
+    for joined in opponent_joined.read() {
+        if game_state.start {
+            continue;
+        }
+
+        game_state.p2_ln_address = joined.opponent_name.clone();
+        game_state.player_type = joined.player_type;
+        if let Some(player_pubkeys) = game_state.player_pubkeys {
+            game_state.p2_pubkey = player_pubkeys.get(2 - joined.player_type).copied();
+        }
+        info!("player type: {}", joined.player_type);
+
+        if joined.series_length > 1 && game_state.series.is_none() {
+            info!("joining a best of {} series", joined.series_length);
+            let series = Series::new(&game_state.game_id, joined.series_length);
+            series.save(&game_state.game_id);
+            game_state.series = Some(series);
+        }
+
+        game_state.start = true;
+        game_state.save_session();
+        game_state.clone().send_rating();
+        game_state
+            .clone()
+            .send_client_info(rng_fingerprint(), cosmetics.skin(&settings));
+    }
+}
+
+// once, when both players are known, also for a resumed game
+fn connect_peer(game_state: Res<GameState>, mut connected: Local<HashSet<String>>) {
+    if !game_state.start || connected.contains(&game_state.game_id) {
+        return;
+    }
+
+    let player_type = game_state.player_type;
+    let (Some(transport), Some(pubkeys)) = (&game_state.transport, game_state.player_pubkeys)
+    else {
+        return;
+    };
+    if player_type != 1 && player_type != 2 {
+        return;
+    }
+
+    connected.insert(game_state.game_id.clone());
+    transport.opponent_known(player_type, pubkeys[2 - player_type]);
+}
+
+// practice games have no opponent to acknowledge the move
+fn send_local_moves(
+    mut game_state: ResMut<GameState>,
+    mut board: ResMut<Board>,
+    mut pending_ack: ResMut<PendingAck>,
+    mut moves: EventReader<MoveMade>,
+    time: Res<Time>,
+) {
+    for move_made in moves.read() {
+        if move_made.source != MoveSource::Local || game_state.ghost.is_some() {
+            continue;
+        }
+
+        latency::clicked(move_made.index);
+        let player_move = move_made.player_move;
+        let previous = board.chain.previous(move_made.index);
+        let nostr_msg = if let Some(special) = player_move.special {
+            game_state
+                .clone()
+                .send_special(special, player_move.column, move_made.index, previous)
+        } else if player_move.pop {
+            game_state
+                .clone()
+                .send_pop(player_move.column, move_made.index, previous)
+        } else {
+            game_state
+                .clone()
+                .send_input(player_move.column, move_made.index, previous)
+        };
+        if let ClientMessage::Event(ref event) = nostr_msg {
+            board.chain.record(move_made.index, event.id);
+            latency::signed(move_made.index, event.id);
+        }
+        pending_ack.send(move_made.index, nostr_msg, time.elapsed_seconds_f64());
+        game_state.last_move_at = Some(Timestamp::now().as_u64());
+    }
+}
+
+// with the moves going over ephemeral events the move list is stored every few moves of
+// the player and once the game is over
+fn publish_checkpoint(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut checkpointed: Local<HashMap<String, usize>>,
+) {
+    if !board.is_changed() || !game_state.ephemeral_moves() {
+        return;
+    }
+    let checkpointed = checkpointed.entry(game_state.game_id.clone()).or_default();
+
+    let moves = board.moves.len();
+    // an undo or a new game
+    if moves < *checkpointed {
+        *checkpointed = 0;
+    }
+
+    let finished = board.winner.is_some() || board.draw;
+    let own_move = board
+        .moves
+        .last()
+        .is_some_and(|player_move| player_move.player == game_state.player_type);
+    let due = own_move && moves.is_multiple_of(CHECKPOINT_MOVES);
+    if moves == *checkpointed || !(due || finished) {
+        return;
+    }
+    *checkpointed = moves;
+
+    info!("checkpoint after {} moves", moves);
+    let columns = board.moves.iter().map(sync_column).collect();
+    game_state.clone().send_checkpoint(columns);
+}
+
+// asks once the queue is drained, so a replayed request that was already answered is skipped.
+// a declined own request is told here too, the queue has no locale
+fn answer_undo_request(
+    mut undo: ResMut<UndoState>,
+    mut board: ResMut<Board>,
+    mut commands: Commands,
+    mut pending_ack: ResMut<PendingAck>,
+    game_state: Res<GameState>,
+    locale: Res<Locale>,
+    coins: Query<(Entity, &CoinMove)>,
+) {
+    if undo.declined {
+        undo.declined = false;
+        platform::alert(locale.get(Phrase::UndoDeclined));
+    }
+    let Some(index) = undo.requested.take() else {
+        return;
+    };
+
+    if index + 1 != board.moves.len() || board.winner.is_some() || board.draw {
+        return;
+    }
+
+    let accepted = platform::confirm(locale.get(Phrase::AllowUndo));
+
+    if accepted {
+        info!("undoing move {}", index);
+        undo_last_move(&mut board, &mut commands, &coins, &mut pending_ack);
+    }
+
+    game_state.clone().send_undo_response(index, accepted);
+}
+
+// the move is applied anyway, it's signed by a player and the board checks the rules
+fn flag_tampering(desync: &mut Desync, reason: String) {
+    error!("move chain broken: {}", reason);
+    if desync.tampering.is_none() {
+        platform::dispatch_event("tampering", Some(&reason));
+    }
+    desync.tampering = Some(reason);
+}
+
+fn undo_last_move(
+    board: &mut Board,
+    commands: &mut Commands,
+    coins: &Query<(Entity, &CoinMove)>,
+    pending_ack: &mut PendingAck,
+) {
+    let Some(last_move) = board.moves.pop() else {
+        return;
+    };
+    board.chain.undo(board.moves.len());
+
+    for (entity, coin) in coins.iter() {
+        if coin.player_move.column == last_move.column && coin.player_move.row == last_move.row {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    if let Some(ref pending) = pending_ack.pending {
+        if pending.seq == board.moves.len() {
+            pending_ack.pending = None;
+        }
+    }
+
+    board.player_turn = last_move.player;
+    board.in_progress = false;
+}
+
+// checks a remote move against the board, a resigned game is over even though the
+// rules don't know about resignations
+fn validate_move(board: &Board, player_move: &PlayerMove) -> Result<usize, MoveError> {
+    if board.winner.is_some() || board.draw {
+        return Err(MoveError::GameOver);
+    }
+
+    Game::from_board(board)?.check(player_move)
+}
+
+// a move as sent in a sync response
+pub fn sync_column(player_move: &PlayerMove) -> usize {
+    let offset = match player_move.special {
+        Some(Special::Anvil) => ANVIL_OFFSET,
+        Some(Special::Bomb) => BOMB_OFFSET,
+        None if player_move.pop => POP_OFFSET,
+        None => 0,
+    };
+    player_move.column + offset
+}
+
+// the kind of a synced move, its player and row are taken from the rules
+fn synced_move(column: usize) -> PlayerMove {
+    match column {
+        _ if column >= BOMB_OFFSET => {
+            PlayerMove::power_up(0, column - BOMB_OFFSET, 0, Special::Bomb)
+        }
+        _ if column >= ANVIL_OFFSET => {
+            PlayerMove::power_up(0, column - ANVIL_OFFSET, 0, Special::Anvil)
+        }
+        _ if column >= POP_OFFSET => PlayerMove::popped(0, column - POP_OFFSET),
+        _ => PlayerMove::new(0, column, 0),
+    }
+}
+
+fn rebuild_board(
+    columns: &[usize],
+    board: &mut Board,
+    commands: &mut Commands,
+    theme: &Theme,
+    coins: &Query<(Entity, &CoinMove)>,
+) {
+    for (entity, _) in coins.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let mut game = Game::with_rules(Rules::of(board));
+    for &column in columns {
+        if let Err(e) = game.play(&synced_move(column)) {
+            error!("invalid synced move: {}", e);
+            break;
+        }
+    }
+
+    // coins are spawned in their slot, the result is shown when the last one lands
+    let last = game.coins().len().saturating_sub(1);
+    for (index, &player_move) in game.coins().iter().enumerate() {
+        let outcome = if index == last {
+            game.outcome()
+        } else {
+            Outcome::Ongoing
+        };
+        let texture = theme.coin(player_move.player).clone();
+        spawn_coin(commands, texture, player_move, outcome, player_move.row);
+    }
+
+    let mut rebuilt = Board::new();
+    rebuilt.moves = game.moves().to_vec();
+    rebuilt.player_turn = game.player_turn();
+    rebuilt.fortune_seed = board.fortune_seed;
+    rebuilt.pop_out = board.pop_out;
+    rebuilt.power_up = board.power_up;
+    rebuilt.cylinder = board.cylinder;
+    rebuilt.gravity_shift = board.gravity_shift;
+    rebuilt.last_modifier = game.modifier();
+    rebuilt.resigned = board.resigned;
+    rebuilt.winner = board.resigned.map(|player| if player == 1 { 2 } else { 1 });
+
+    *board = rebuilt;
+}
+
+// the coins of a game switched to are put straight into their slots, landed, so a finished
+// game doesn't end a second time
+pub fn redraw_coins(
+    board: &Board,
+    commands: &mut Commands,
+    theme: &Theme,
+    coins: &Query<(Entity, &CoinMove)>,
+) {
+    for (entity, _) in coins.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let game = match Game::from_board(board) {
+        Ok(game) => game,
+        Err(e) => {
+            error!("Error redrawing the board: {}", e);
+            return;
+        }
+    };
+    for &player_move in game.coins() {
+        let texture = theme.coin(player_move.player).clone();
+        let coin = spawn_coin(
+            commands,
+            texture,
+            player_move,
+            Outcome::Ongoing,
+            player_move.row,
+        );
+        commands.entity(coin).insert(CoinMove {
+            reached_target: true,
+            ..CoinMove::new(player_move, Outcome::Ongoing)
+        });
+    }
+}
+
+#[wasm_bindgen(js_name = relayStatus)]
+pub fn relay_status() -> String {
+    match RELAY_STATUS.lock() {
+        Ok(relay_status) => serde_json::to_string(&*relay_status).unwrap(),
+        Err(_) => "{}".to_string(),
+    }
+}
+
+// raw signed events of the current game, one per line, oldest first
+#[wasm_bindgen(js_name = auditLog)]
+pub fn audit_log() -> String {
+    match GAME_EVENTS.lock() {
+        Ok(game_events) => {
+            let mut events: Vec<&NostrEvent> = game_events
+                .iter()
+                .filter(|event| current_game_event(event))
+                .collect();
+            events.sort_by_key(|event| event.created_at);
+            events
+                .iter()
+                .map(|event| format!("{}\n", event.as_json()))
+                .collect()
+        }
+        Err(_) => String::new(),
+    }
+}
+
+#[wasm_bindgen(js_name = relaysChanged)]
+pub fn relays_changed() {
+    RELAYS_CHANGED.store(true, Ordering::SeqCst);
+}
+
+#[wasm_bindgen(js_name = postResult)]
+pub fn post_result(content: String) {
+    queue_result_post(ResultPost::Unsigned(content));
+}
+
+#[wasm_bindgen(js_name = publishSignedEvent)]
+pub fn publish_signed_event(event: String) {
+    queue_result_post(ResultPost::Signed(event));
+}
+
+fn queue_result_post(post: ResultPost) {
+    match RESULT_POSTS.lock() {
+        Ok(mut posts) => posts.push(post),
+        Err(e) => error!("Error queueing result post: {}", e),
+    }
 }
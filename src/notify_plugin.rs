@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+
+use crate::{
+    locale_plugin::{Locale, Phrase},
+    platform,
+    resources::{Board, GameState},
+    settings_plugin::Settings,
+    AppState,
+};
+
+// while it's the local player's turn and the tab is in the background the page title says
+// so, and once per turn the page shows a notification when the player allowed them, so a
+// slow game doesn't stall in a forgotten tab
+pub struct NotifyPlugin;
+
+impl Plugin for NotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnNotice>()
+            .add_systems(Update, notify_turn.run_if(in_state(AppState::InGame)))
+            .add_systems(OnExit(AppState::InGame), restore_title);
+    }
+}
+
+#[derive(Resource, Default)]
+struct TurnNotice {
+    // the page's own title, put back when the player is back
+    page_title: Option<String>,
+    // the game and its number of moves when the turn was last notified
+    notified: Option<(String, usize)>,
+}
+
+fn notify_turn(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut notice: ResMut<TurnNotice>,
+) {
+    let player = game_state.player_type;
+    let your_turn = game_state.start
+        && (player == 1 || player == 2)
+        && game_state.own_turn(&board)
+        && board.winner.is_none()
+        && !board.draw;
+
+    if !your_turn || !platform::page_hidden() {
+        if let Some(title) = notice.page_title.take() {
+            platform::set_title(&title);
+        }
+        return;
+    }
+
+    if notice.page_title.is_none() {
+        notice.page_title = Some(platform::title());
+        platform::set_title(locale.get(Phrase::YourTurnTitle));
+    }
+
+    let turn = (game_state.game_id.clone(), board.moves.len());
+    if settings.turn_notifications && notice.notified.as_ref() != Some(&turn) {
+        notice.notified = Some(turn);
+        let opponent = game_state
+            .p2_ln_address
+            .as_deref()
+            .unwrap_or(locale.get(Phrase::YourOpponent));
+        // the page shows it, it asked for the permission when the setting was turned on
+        platform::dispatch_event(
+            "yourTurn",
+            Some(&locale.fill(Phrase::OpponentPlayed, &[opponent])),
+        );
+    }
+}
+
+fn restore_title(mut notice: ResMut<TurnNotice>) {
+    if let Some(title) = notice.page_title.take() {
+        platform::set_title(&title);
+    }
+    notice.notified = None;
+}
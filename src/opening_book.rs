@@ -0,0 +1,80 @@
+use crate::game::COLUMNS;
+
+// the perfect-play results of the first plies on the standard 7x6 board, solved ahead of
+// time by a full search. keys are the columns played, from 0, like a challenge link's
+// position, and only one of a position and its mirror image is kept. a score is for the
+// side to move: 0 is a draw, a positive score wins and a negative one loses, the further
+// from 0 the sooner
+const BOOK: [(&str, i8); 30] = [
+    ("", 1),
+    ("0", 2),
+    ("1", 1),
+    ("2", 0),
+    ("3", -1),
+    ("00", 1),
+    ("01", -2),
+    ("02", -1),
+    ("03", -2),
+    ("04", 1),
+    ("05", -1),
+    ("06", 2),
+    ("10", 2),
+    ("11", 0),
+    ("12", -1),
+    ("13", 0),
+    ("14", 2),
+    ("15", 2),
+    ("16", 3),
+    ("20", 2),
+    ("21", 2),
+    ("22", 0),
+    ("23", 0),
+    ("24", 0),
+    ("25", 0),
+    ("26", 3),
+    ("30", 4),
+    ("31", 2),
+    ("32", 2),
+    ("33", 1),
+];
+
+// the plies the book covers, its moves are played up to one ply before
+pub const BOOK_PLIES: usize = 2;
+
+fn mirrored(columns: &[usize]) -> String {
+    columns
+        .iter()
+        .map(|column| (COLUMNS - 1 - column).to_string())
+        .collect()
+}
+
+// the result with perfect play for the side to move, None past the book
+pub fn score(columns: &[usize]) -> Option<i32> {
+    if columns.len() > BOOK_PLIES {
+        return None;
+    }
+
+    let key: String = columns.iter().map(|column| column.to_string()).collect();
+    let mirror = mirrored(columns);
+    BOOK.iter()
+        .find(|(position, _)| *position == key || *position == mirror)
+        .map(|(_, score)| *score as i32)
+}
+
+// the column that keeps the best result, the center one of equal columns
+pub fn best_move(columns: &[usize]) -> Option<usize> {
+    if columns.len() >= BOOK_PLIES {
+        return None;
+    }
+
+    let mut best: Option<(usize, i32)> = None;
+    for column in [3, 2, 4, 1, 5, 0, 6] {
+        let mut next = columns.to_vec();
+        next.push(column);
+        let score = -score(&next)?;
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((column, score));
+        }
+    }
+    best.map(|(column, _)| column)
+}
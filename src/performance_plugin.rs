@@ -0,0 +1,207 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    components::ReducedModeNotice,
+    locale_plugin::{Locale, Phrase},
+    platform,
+    settings_plugin::{Performance, Settings},
+    tween_plugin::Easing,
+};
+
+// below this frame rate for LOW_SAMPLES samples in a row the game switches to reduced mode
+const LOW_FPS: f64 = 30.0;
+const SAMPLE_SECONDS: f64 = 5.0;
+const LOW_SAMPLES: usize = 2;
+// a longer frame is a suspended tab or a page load, not a slow device
+const MAX_FRAME_SECONDS: f32 = 1.0;
+// coins drop this much faster and without a bounce in reduced mode
+const REDUCED_DROP_SPEED: f32 = 2.0;
+// the canvas is drawn at one pixel per css pixel, the browser scales it up on dense screens
+const REDUCED_SCALE_FACTOR: f64 = 1.0;
+const NOTICE_SECONDS: f64 = 8.0;
+
+// whether the game runs without effects, with quicker flat drops, plain coins and a canvas
+// of a lower resolution, for devices that can't keep up
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReducedMode(pub bool);
+
+impl ReducedMode {
+    pub fn drop_speed(&self) -> f32 {
+        if self.0 {
+            REDUCED_DROP_SPEED
+        } else {
+            1.0
+        }
+    }
+
+    pub fn easing(&self, easing: Easing) -> Easing {
+        if self.0 {
+            Easing::Linear
+        } else {
+            easing
+        }
+    }
+}
+
+// the frame rate of the current sample, and whether it stayed low
+#[derive(Resource, Default)]
+struct FrameRateMonitor {
+    started: Option<f64>,
+    frames: u32,
+    low_samples: usize,
+    detected: bool,
+    notice_at: Option<f64>,
+}
+
+pub struct PerformancePlugin;
+
+impl Plugin for PerformancePlugin {
+    fn build(&self, app: &mut App) {
+        // chosen in the settings it applies from the first frame
+        let reduced = app
+            .world
+            .get_resource::<Settings>()
+            .is_some_and(|settings| settings.performance == Performance::Reduced);
+
+        app.insert_resource(ReducedMode(reduced))
+            .init_resource::<FrameRateMonitor>()
+            .add_systems(
+                Update,
+                (
+                    detect_low_frame_rate,
+                    update_reduced_mode,
+                    apply_canvas_resolution,
+                    hide_notice,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn detect_low_frame_rate(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    mut monitor: ResMut<FrameRateMonitor>,
+) {
+    if monitor.detected || settings.performance != Performance::Auto {
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    if time.delta_seconds() > MAX_FRAME_SECONDS {
+        monitor.started = None;
+        return;
+    }
+    let Some(started) = monitor.started else {
+        monitor.started = Some(now);
+        monitor.frames = 0;
+        return;
+    };
+
+    monitor.frames += 1;
+    if now - started < SAMPLE_SECONDS {
+        return;
+    }
+
+    let fps = monitor.frames as f64 / (now - started);
+    monitor.started = Some(now);
+    monitor.frames = 0;
+    if fps >= LOW_FPS {
+        monitor.low_samples = 0;
+        return;
+    }
+    monitor.low_samples += 1;
+    if monitor.low_samples < LOW_SAMPLES {
+        return;
+    }
+
+    info!(
+        "frame rate stays at {:.0} fps, switching to reduced mode",
+        fps
+    );
+    monitor.detected = true;
+    monitor.notice_at = Some(now);
+    platform::dispatch_event("reducedMode", Some(&format!("{:.0}", fps)));
+    spawn_notice(&mut commands, &locale);
+}
+
+fn update_reduced_mode(
+    settings: Res<Settings>,
+    monitor: Res<FrameRateMonitor>,
+    mut reduced: ResMut<ReducedMode>,
+) {
+    let on = match settings.performance {
+        Performance::Auto => monitor.detected,
+        Performance::Full => false,
+        Performance::Reduced => true,
+    };
+    reduced.set_if_neq(ReducedMode(on));
+}
+
+// the logical size stays, so the layout doesn't change, only the pixels drawn for it
+fn apply_canvas_resolution(
+    reduced: Res<ReducedMode>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !reduced.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+
+    let scale_factor = match reduced.0 {
+        true if window.resolution.base_scale_factor() > REDUCED_SCALE_FACTOR => {
+            Some(REDUCED_SCALE_FACTOR)
+        }
+        _ => None,
+    };
+    if window.resolution.scale_factor_override() != scale_factor {
+        window.resolution.set_scale_factor_override(scale_factor);
+    }
+}
+
+fn spawn_notice(commands: &mut Commands, locale: &Locale) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                locale.get(Phrase::SlowDevice),
+                TextStyle {
+                    color: Color::BLACK,
+                    font_size: 16.0,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.0),
+                left: Val::Px(8.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            })
+            .with_background_color(Color::rgb(1.0, 0.93, 0.6)),
+        )
+        .insert(ZIndex::Global(5))
+        .insert(ReducedModeNotice);
+}
+
+fn hide_notice(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut monitor: ResMut<FrameRateMonitor>,
+    notice: Query<Entity, With<ReducedModeNotice>>,
+) {
+    let Some(notice_at) = monitor.notice_at else {
+        return;
+    };
+    if time.elapsed_seconds_f64() - notice_at < NOTICE_SECONDS {
+        return;
+    }
+
+    monitor.notice_at = None;
+    for entity in notice.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
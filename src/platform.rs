@@ -0,0 +1,514 @@
+// what the game needs from its host: the browser when built for wasm32, the operating
+// system for the native desktop build. game ids are url paths, `/{game_id}`, on both
+
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+
+use std::sync::OnceLock;
+
+#[cfg(target_arch = "wasm32")]
+use web::{read_item, read_safe_item, read_session_item, write_item, write_safe_item};
+
+#[cfg(not(target_arch = "wasm32"))]
+use native::{read_item, read_safe_item, read_session_item, write_item, write_safe_item};
+
+static SAFE_MODE: OnceLock<bool> = OnceLock::new();
+
+// `?safemode=1`, or `--safe-mode` on the desktop, starts without reading anything stored:
+// an ephemeral key, the default relays and settings. for when a broken setting or relay
+// list keeps the app from working, the stored items are left as they are and what the
+// session stores is dropped with it
+pub fn safe_mode() -> bool {
+    *SAFE_MODE.get_or_init(|| {
+        query()
+            .trim_start_matches('?')
+            .split('&')
+            .any(|param| param == "safemode=1")
+    })
+}
+
+// before anything is read, the desktop's flag
+#[cfg(not(target_arch = "wasm32"))]
+pub fn start_safe_mode() {
+    let _ = SAFE_MODE.set(true);
+}
+
+pub fn get_item(key: &str) -> Option<String> {
+    if safe_mode() {
+        return read_safe_item(key);
+    }
+    read_item(key)
+}
+
+pub fn set_item(key: &str, value: &str) -> Result<(), String> {
+    if safe_mode() {
+        return write_safe_item(key, value);
+    }
+    write_item(key, value)
+}
+
+// kept for the tab only
+pub fn get_session_item(key: &str) -> Option<String> {
+    if safe_mode() {
+        return None;
+    }
+    read_session_item(key)
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::future::Future;
+
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{window, Document, Response, Storage};
+
+    const SAFE_MODE_PREFIX: &str = "safemode:";
+
+    fn local_storage() -> Option<Storage> {
+        window()?.local_storage().ok()?
+    }
+
+    fn document() -> Option<Document> {
+        window()?.document()
+    }
+
+    pub(super) fn read_item(key: &str) -> Option<String> {
+        local_storage()?.get_item(key).ok()?
+    }
+
+    pub(super) fn write_item(key: &str, value: &str) -> Result<(), String> {
+        let local_storage = local_storage().ok_or("local storage is not available")?;
+        local_storage
+            .set_item(key, value)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    pub(super) fn read_session_item(key: &str) -> Option<String> {
+        window()?.session_storage().ok()??.get_item(key).ok()?
+    }
+
+    // a safe mode tab keeps its items in session storage, the page reads the same keys
+    pub(super) fn read_safe_item(key: &str) -> Option<String> {
+        read_session_item(&format!("{}{}", SAFE_MODE_PREFIX, key))
+    }
+
+    pub(super) fn write_safe_item(key: &str, value: &str) -> Result<(), String> {
+        let session_storage = window()
+            .and_then(|window| window.session_storage().ok().flatten())
+            .ok_or("session storage is not available")?;
+        session_storage
+            .set_item(&format!("{}{}", SAFE_MODE_PREFIX, key), value)
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    pub fn spawn<F: Future<Output = ()> + 'static>(future: F) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+
+    pub fn random() -> f64 {
+        js_sys::Math::random()
+    }
+
+    // wall clock milliseconds, for timing across the two players' clients
+    pub fn now_millis() -> f64 {
+        js_sys::Date::now()
+    }
+
+    pub fn game_id() -> String {
+        window()
+            .and_then(|window| window.location().pathname().ok())
+            .unwrap_or_else(|| "/".to_string())
+    }
+
+    // the query string, `?challenger=...`, empty when there is none
+    pub fn query() -> String {
+        window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn origin() -> String {
+        window()
+            .and_then(|window| window.location().origin().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn current_url() -> String {
+        window()
+            .and_then(|window| window.location().href().ok())
+            .unwrap_or_default()
+    }
+
+    // switches to a new game without reloading, the page is told through `urlChanged`
+    pub fn push_url(url: &str) {
+        let Some(window) = window() else {
+            return;
+        };
+
+        if let Ok(history) = window.history() {
+            history
+                .push_state_with_url(&JsValue::from_str("Create New Game"), "", Some(url))
+                .expect("pushState failed");
+        }
+        dispatch_event("urlChanged", None);
+    }
+
+    // loads the game from scratch, as a fresh page
+    pub fn open_game(game_id: &str) {
+        let location = window().unwrap().location();
+        // a safe mode session stays in safe mode
+        let url = match super::safe_mode() {
+            true => format!("{}{}?safemode=1", origin(), game_id),
+            false => format!("{}{}", origin(), game_id),
+        };
+
+        bevy::log::info!("opening game {}", url);
+        if let Err(e) = location.set_href(&url) {
+            bevy::log::error!("Error opening game: {:?}", e);
+        }
+    }
+
+    // custom events are how the page around the canvas hears from the game
+    pub fn dispatch_event(name: &str, detail: Option<&str>) {
+        let event = match detail {
+            Some(detail) => {
+                let mut event_init = web_sys::CustomEventInit::new();
+                event_init.detail(&JsValue::from_str(detail));
+                web_sys::CustomEvent::new_with_event_init_dict(name, &event_init)
+            }
+            None => web_sys::CustomEvent::new(name),
+        };
+
+        if let (Some(window), Ok(event)) = (window(), event) {
+            let _ = window.dispatch_event(&event);
+        }
+    }
+
+    // the html page around the canvas has the menu
+    pub fn page_menu() -> bool {
+        true
+    }
+
+    // the tab is in the background or the browser window lost the focus
+    pub fn page_hidden() -> bool {
+        document()
+            .is_some_and(|document| document.hidden() || !document.has_focus().unwrap_or(true))
+    }
+
+    pub fn title() -> String {
+        document()
+            .map(|document| document.title())
+            .unwrap_or_default()
+    }
+
+    pub fn set_title(title: &str) {
+        if let Some(document) = document() {
+            document.set_title(title);
+        }
+    }
+
+    pub fn alert(message: &str) {
+        if let Some(window) = window() {
+            if let Err(e) = window.alert_with_message(message) {
+                bevy::log::info!("Error sending alert: {:?}", e);
+            }
+        }
+    }
+
+    pub fn confirm(message: &str) -> bool {
+        window()
+            .and_then(|window| window.confirm_with_message(message).ok())
+            .unwrap_or(false)
+    }
+
+    // none when cancelled
+    pub fn prompt(message: &str, default: Option<&str>) -> Option<String> {
+        window()?
+            .prompt_with_message_and_default(message, default.unwrap_or(""))
+            .ok()
+            .flatten()
+    }
+
+    // the language the browser asks pages in, `pt-BR`
+    pub fn language() -> Option<String> {
+        window()?.navigator().language()
+    }
+
+    pub fn format_date(seconds: u64) -> String {
+        let date = js_sys::Date::new(&JsValue::from_f64(seconds as f64 * 1000.0));
+        date.to_locale_date_string("default", &JsValue::UNDEFINED)
+            .into()
+    }
+
+    // the body and its content type
+    pub async fn fetch(url: &str) -> Option<(String, Vec<u8>)> {
+        let response: Response = match JsFuture::from(window()?.fetch_with_str(url)).await {
+            Ok(response) => response.dyn_into().ok()?,
+            Err(e) => {
+                bevy::log::info!("Error fetching {}: {:?}", url, e);
+                return None;
+            }
+        };
+
+        let mime_type = response.headers().get("content-type").ok()??;
+        let buffer = JsFuture::from(response.array_buffer().ok()?).await.ok()?;
+        Some((mime_type, js_sys::Uint8Array::new(&buffer).to_vec()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::{
+        collections::{hash_map::RandomState, BTreeMap},
+        future::Future,
+        hash::{BuildHasher, Hasher},
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex, OnceLock,
+        },
+    };
+
+    use bevy::log::{error, info, warn};
+    use nostr_sdk::serde_json;
+
+    // links shared from the desktop open in the browser
+    const WEB_ORIGIN: &str = "https://unite4.luvnft.com";
+
+    static STORAGE: OnceLock<Mutex<BTreeMap<String, String>>> = OnceLock::new();
+    // the storage file didn't parse and couldn't be moved aside, it's kept as it is
+    static STORAGE_UNREADABLE: AtomicBool = AtomicBool::new(false);
+    static SAFE_MODE_ITEMS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+    static GAME_ID: OnceLock<Mutex<String>> = OnceLock::new();
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+    // local storage is a json file in the data directory, `UNITE4_DATA` overrides it
+    fn storage_path() -> PathBuf {
+        let dir = std::env::var_os("UNITE4_DATA")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("XDG_DATA_HOME").map(|dir| PathBuf::from(dir).join("unite4"))
+            })
+            .or_else(|| std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("unite4")))
+            .or_else(|| {
+                std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".local/share/unite4"))
+            })
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        dir.join("storage.json")
+    }
+
+    // a file that doesn't parse is moved aside to storage.json.bak rather than overwritten
+    // by the next write, it holds the nostr key
+    fn storage() -> &'static Mutex<BTreeMap<String, String>> {
+        STORAGE.get_or_init(|| {
+            let path = storage_path();
+            let Ok(items) = std::fs::read_to_string(&path) else {
+                return Mutex::new(BTreeMap::new());
+            };
+            match serde_json::from_str(&items) {
+                Ok(items) => Mutex::new(items),
+                Err(e) => {
+                    let backup = path.with_extension("json.bak");
+                    error!("unreadable storage, moving it to {:?}: {}", backup, e);
+                    if let Err(e) = std::fs::rename(&path, &backup) {
+                        error!("Error moving storage aside, not saving: {}", e);
+                        STORAGE_UNREADABLE.store(true, Ordering::SeqCst);
+                    }
+                    Mutex::new(BTreeMap::new())
+                }
+            }
+        })
+    }
+
+    pub(super) fn read_item(key: &str) -> Option<String> {
+        storage().lock().ok()?.get(key).cloned()
+    }
+
+    pub(super) fn write_item(key: &str, value: &str) -> Result<(), String> {
+        let mut items = storage().lock().map_err(|e| e.to_string())?;
+        items.insert(key.to_string(), value.to_string());
+        if STORAGE_UNREADABLE.load(Ordering::SeqCst) {
+            return Err("storage is unreadable, not overwriting it".to_string());
+        }
+
+        let path = storage_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let items = serde_json::to_string(&*items).map_err(|e| e.to_string())?;
+        // written next to the file and renamed over it, a crash mid write leaves the old one
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, items).map_err(|e| e.to_string())?;
+        std::fs::rename(temp, path).map_err(|e| e.to_string())
+    }
+
+    // an unlocked identity is kept by the browser tab, the desktop has none
+    pub(super) fn read_session_item(_key: &str) -> Option<String> {
+        None
+    }
+
+    // a safe mode run keeps its items in memory
+    pub(super) fn read_safe_item(key: &str) -> Option<String> {
+        SAFE_MODE_ITEMS.lock().ok()?.get(key).cloned()
+    }
+
+    pub(super) fn write_safe_item(key: &str, value: &str) -> Result<(), String> {
+        let mut items = SAFE_MODE_ITEMS.lock().map_err(|e| e.to_string())?;
+        items.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn runtime() -> &'static tokio::runtime::Runtime {
+        RUNTIME.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start the tokio runtime")
+        })
+    }
+
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(future: F) {
+        runtime().spawn(future);
+    }
+
+    pub fn random() -> f64 {
+        let bits = RandomState::new().build_hasher().finish();
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn now_millis() -> f64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs_f64() * 1000.0)
+            .unwrap_or_default()
+    }
+
+    fn current_game() -> &'static Mutex<String> {
+        GAME_ID.get_or_init(|| Mutex::new("/".to_string()))
+    }
+
+    pub fn game_id() -> String {
+        match current_game().lock() {
+            Ok(game_id) => game_id.clone(),
+            Err(_) => "/".to_string(),
+        }
+    }
+
+    // the game to open on launch, from the command line
+    pub fn set_game_id(game_id: &str) {
+        if let Ok(mut current) = current_game().lock() {
+            *current = format!("/{}", game_id.trim_start_matches('/'));
+        }
+    }
+
+    pub fn query() -> String {
+        String::new()
+    }
+
+    pub fn origin() -> String {
+        WEB_ORIGIN.to_string()
+    }
+
+    pub fn current_url() -> String {
+        format!("{}{}", WEB_ORIGIN, game_id())
+    }
+
+    pub fn push_url(url: &str) {
+        let path = url.strip_prefix(WEB_ORIGIN).unwrap_or(url);
+        let game_id = path.split('?').next().unwrap_or(path);
+        set_game_id(game_id);
+        info!("share this url to play: {}", url);
+    }
+
+    // a fresh start, like loading the page, so the app is launched again on the game
+    pub fn open_game(game_id: &str) {
+        info!("opening game {}", game_id);
+
+        let relaunched = std::env::current_exe().and_then(|exe| {
+            let mut command = std::process::Command::new(exe);
+            if super::safe_mode() {
+                command.arg("--safe-mode");
+            }
+            command.arg(game_id).spawn()
+        });
+        match relaunched {
+            Ok(_) => std::process::exit(0),
+            Err(e) => error!("Error opening game: {}", e),
+        }
+    }
+
+    // there is no page around the window to tell
+    pub fn dispatch_event(name: &str, _detail: Option<&str>) {
+        info!("{} event has no listener on desktop", name);
+    }
+
+    // the menu is drawn in the window, see `menu_plugin`
+    pub fn page_menu() -> bool {
+        false
+    }
+
+    // the desktop window gets no turn notifications yet
+    pub fn page_hidden() -> bool {
+        false
+    }
+
+    pub fn title() -> String {
+        String::new()
+    }
+
+    pub fn set_title(_title: &str) {}
+
+    pub fn alert(message: &str) {
+        warn!("{}", message);
+    }
+
+    // no dialogs on desktop yet, requests that need one are declined
+    pub fn confirm(message: &str) -> bool {
+        warn!("{} declined, no dialog on desktop", message);
+        false
+    }
+
+    pub fn prompt(message: &str, _default: Option<&str>) -> Option<String> {
+        warn!("{} skipped, no dialog on desktop", message);
+        None
+    }
+
+    // the locale of the environment, `pt_BR.UTF-8`
+    pub fn language() -> Option<String> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+    }
+
+    pub fn format_date(seconds: u64) -> String {
+        // days since 1970 to a civil date, Howard Hinnant's algorithm
+        let days = (seconds / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        format!("{}-{:02}-{:02}", year, month, day)
+    }
+
+    // avatars are skipped on desktop, there is no http client
+    pub async fn fetch(url: &str) -> Option<(String, Vec<u8>)> {
+        info!("not fetching {} on desktop", url);
+        None
+    }
+}
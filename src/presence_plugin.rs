@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use nostr_sdk::Timestamp;
+
+use crate::{
+    components::OpponentPresence,
+    correspondence_plugin::time_ago,
+    gui_plugin::HoveredColumn,
+    locale_plugin::{Locale, Phrase},
+    resources::{Board, GameState},
+    AppState,
+};
+
+const HEARTBEAT_INTERVAL: f64 = 15.0;
+// seconds without an event of the opponent before they are shown offline
+const OFFLINE_AFTER: u64 = 40;
+// the thinking indicator changes at most this often, the last change is sent after it
+const THINKING_THROTTLE: f64 = 2.0;
+// the aimed at column is sent at most this often, the latest one after it
+const HOVER_THROTTLE: f64 = 0.25;
+
+// while a game is on the page both clients send a heartbeat, and whether the player's
+// cursor is on the board during their turn. both are ephemeral events, only sent to an
+// opponent whose client lists the presence feature. the opponent is shown online,
+// thinking or when they were last seen, older clients show up through their moves. an
+// opponent listing the hover feature also gets the column the cursor aims at
+pub struct PresencePlugin;
+
+impl Plugin for PresencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (send_heartbeat, send_thinking, send_hover, show_presence)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(OnExit(AppState::InGame), send_leaving);
+    }
+}
+
+fn presence_shared(game_state: &GameState) -> bool {
+    let player = game_state.player_type;
+    game_state.start
+        && (player == 1 || player == 2)
+        && game_state.transport.is_some()
+        && game_state.opponent_supports("presence")
+}
+
+fn finished(board: &Board) -> bool {
+    board.winner.is_some() || board.draw
+}
+
+// sent again every interval and right away for a game switched to
+fn send_heartbeat(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    time: Res<Time>,
+    mut sent: Local<Option<(String, f64)>>,
+) {
+    if !presence_shared(&game_state) || finished(&board) {
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    let due = match *sent {
+        Some((ref game_id, at)) => *game_id != game_state.game_id || now - at >= HEARTBEAT_INTERVAL,
+        None => true,
+    };
+    if due {
+        *sent = Some((game_state.game_id.clone(), now));
+        game_state.clone().send_presence(true);
+    }
+}
+
+#[derive(Default)]
+struct ThinkingSent {
+    game_id: String,
+    thinking: bool,
+    at: f64,
+}
+
+fn send_thinking(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    hovered: Res<HoveredColumn>,
+    time: Res<Time>,
+    mut sent: Local<ThinkingSent>,
+) {
+    if !presence_shared(&game_state) {
+        return;
+    }
+    if sent.game_id != game_state.game_id {
+        *sent = ThinkingSent {
+            game_id: game_state.game_id.clone(),
+            ..default()
+        };
+    }
+
+    let thinking = hovered.0.is_some() && game_state.own_turn(&board) && !finished(&board);
+    let now = time.elapsed_seconds_f64();
+    if thinking == sent.thinking || now - sent.at < THINKING_THROTTLE {
+        return;
+    }
+
+    sent.thinking = thinking;
+    sent.at = now;
+    game_state.clone().send_thinking(thinking);
+}
+
+#[derive(Default)]
+struct HoverSent {
+    game_id: String,
+    column: Option<usize>,
+    at: f64,
+}
+
+fn send_hover(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    hovered: Res<HoveredColumn>,
+    time: Res<Time>,
+    mut sent: Local<HoverSent>,
+) {
+    if !presence_shared(&game_state) || !game_state.opponent_supports("hover") {
+        return;
+    }
+    if sent.game_id != game_state.game_id {
+        *sent = HoverSent {
+            game_id: game_state.game_id.clone(),
+            ..default()
+        };
+    }
+
+    let column = hovered
+        .0
+        .filter(|_| game_state.own_turn(&board) && !finished(&board));
+    let now = time.elapsed_seconds_f64();
+    if column == sent.column || now - sent.at < HOVER_THROTTLE {
+        return;
+    }
+
+    sent.column = column;
+    sent.at = now;
+    game_state.clone().send_hover(column);
+}
+
+fn send_leaving(game_state: Res<GameState>) {
+    if presence_shared(&game_state) {
+        game_state.clone().send_presence(false);
+    }
+}
+
+// refreshed every second, an opponent goes offline without an event telling so
+fn show_presence(
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    time: Res<Time>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<OpponentPresence>>,
+    mut refreshed: Local<f64>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if !game_state.is_changed() && !locale.is_changed() && now - *refreshed < 1.0 {
+        return;
+    }
+    *refreshed = now;
+
+    let presence = &game_state.opponent_presence;
+    let player = game_state.player_type;
+    let value = match presence.last_seen {
+        _ if player != 1 && player != 2 => String::new(),
+        None => String::new(),
+        // without heartbeats only their events tell
+        Some(last_seen) if !game_state.opponent_supports("presence") => {
+            locale.fill(Phrase::LastSeen, &[&time_ago(last_seen, &locale)])
+        }
+        Some(last_seen) => {
+            let idle = Timestamp::now().as_u64().saturating_sub(last_seen);
+            if presence.left || idle > OFFLINE_AFTER {
+                locale.fill(Phrase::OfflineLastSeen, &[&time_ago(last_seen, &locale)])
+            } else if presence.thinking && board.player_turn != player && !finished(&board) {
+                locale.get(Phrase::OpponentThinking).to_string()
+            } else {
+                locale.get(Phrase::OpponentOnline).to_string()
+            }
+        }
+    };
+
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
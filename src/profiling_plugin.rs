@@ -0,0 +1,218 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Mutex,
+};
+
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin, RegisterDiagnostic,
+    },
+    prelude::*,
+    utils::Instant,
+};
+use nostr_sdk::serde_json;
+use wasm_bindgen::prelude::*;
+
+use crate::{components::ProfilingText, platform};
+
+// how often the overlay and the debug snapshot are refreshed, in seconds
+const REFRESH_INTERVAL: f64 = 0.5;
+
+static PROFILING_CALLED: AtomicBool = AtomicBool::new(false);
+// the numbers last shown, read by `debugState`
+static PROFILE: Mutex<Option<serde_json::Value>> = Mutex::new(None);
+
+// the systems timed by a span, the time of each is summed over a frame
+#[derive(Clone, Copy)]
+pub enum Span {
+    Place,
+    MoveCoin,
+    HandleNetMsg,
+}
+
+const SPANS: [(Span, &str, DiagnosticId); 3] = [
+    (
+        Span::Place,
+        "place",
+        DiagnosticId::from_u128(95_412_306_781_624_533_190_847_262_019_374_552_183),
+    ),
+    (
+        Span::MoveCoin,
+        "move_coin",
+        DiagnosticId::from_u128(95_412_306_781_624_533_190_847_262_019_374_552_184),
+    ),
+    (
+        Span::HandleNetMsg,
+        "handle_net_msg",
+        DiagnosticId::from_u128(95_412_306_781_624_533_190_847_262_019_374_552_185),
+    ),
+];
+
+// nanoseconds spent in each span this frame
+static SPAN_TIMES: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+// measures until dropped, `let _span = span(Span::Place);` at the top of a system
+pub struct SpanGuard {
+    span: Span,
+    started: Instant,
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let nanos = self.started.elapsed().as_nanos() as u64;
+        SPAN_TIMES[self.span as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+pub fn span(span: Span) -> SpanGuard {
+    SpanGuard {
+        span,
+        started: Instant::now(),
+    }
+}
+
+// frame rate, entity count and the time of the busiest systems, shown over the game
+// with F3 or `?profile`, so a slow phone can report numbers
+pub struct ProfilingPlugin;
+
+impl Plugin for ProfilingPlugin {
+    fn build(&self, app: &mut App) {
+        for (_, name, id) in SPANS {
+            app.register_diagnostic(Diagnostic::new(id, name, 120));
+        }
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
+            .add_systems(Update, toggle_overlay)
+            .add_systems(Last, (record_spans, update_profile).chain());
+    }
+}
+
+fn record_spans(mut diagnostics: Diagnostics) {
+    for (span, _, id) in SPANS {
+        let nanos = SPAN_TIMES[span as usize].swap(0, Ordering::Relaxed);
+        diagnostics.add_measurement(id, || nanos as f64 / 1_000_000.0);
+    }
+}
+
+fn toggle_overlay(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    overlay: Query<Entity, With<ProfilingText>>,
+    mut checked: Local<bool>,
+) {
+    let mut toggled =
+        PROFILING_CALLED.swap(false, Ordering::SeqCst) || keys.just_pressed(KeyCode::F3);
+    if !*checked {
+        *checked = true;
+        toggled |= platform::query()
+            .trim_start_matches('?')
+            .split('&')
+            .any(|param| param == "profile");
+    }
+    if !toggled {
+        return;
+    }
+
+    if overlay.is_empty() {
+        spawn_overlay(&mut commands);
+    } else {
+        for entity in overlay.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    color: Color::WHITE,
+                    font_size: 14.0,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(4.0),
+                left: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            })
+            .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+        )
+        .insert(ZIndex::Global(20))
+        .insert(ProfilingText);
+}
+
+// the average and the worst of the last frames
+fn average_and_max(store: &DiagnosticsStore, id: DiagnosticId) -> (f64, f64) {
+    let Some(diagnostic) = store.get(id) else {
+        return (0.0, 0.0);
+    };
+    let max = diagnostic.values().copied().fold(0.0, f64::max);
+    (diagnostic.average().unwrap_or(0.0), max)
+}
+
+fn update_profile(
+    time: Res<Time>,
+    store: Res<DiagnosticsStore>,
+    mut text: Query<&mut Text, With<ProfilingText>>,
+    mut refreshed_at: Local<f64>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if now - *refreshed_at < REFRESH_INTERVAL {
+        return;
+    }
+    *refreshed_at = now;
+
+    let fps = store
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.0);
+    let (frame_time, worst_frame) = average_and_max(&store, FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let entities = store
+        .get(EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+        .and_then(|count| count.value())
+        .unwrap_or(0.0);
+
+    let mut profile = serde_json::json!({
+        "fps": fps,
+        "frame_ms": frame_time,
+        "worst_frame_ms": worst_frame,
+        "entities": entities,
+    });
+    let mut lines = vec![
+        format!(
+            "{:.0} fps, {:.1} ms (worst {:.1})",
+            fps, frame_time, worst_frame
+        ),
+        format!("{:.0} entities", entities),
+    ];
+    for (_, name, id) in SPANS {
+        let (average, max) = average_and_max(&store, id);
+        profile[name] = serde_json::json!({ "avg_ms": average, "max_ms": max });
+        lines.push(format!("{} {:.2} ms (worst {:.2})", name, average, max));
+    }
+
+    if let Ok(mut snapshot) = PROFILE.lock() {
+        *snapshot = Some(profile);
+    }
+
+    let value = lines.join("\n");
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+pub fn profile() -> Option<serde_json::Value> {
+    PROFILE.lock().ok().and_then(|profile| profile.clone())
+}
+
+#[wasm_bindgen]
+pub fn toggle_profiler() {
+    PROFILING_CALLED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine,
+    game::{Game, Outcome, COLUMNS, ROWS},
+};
+
+// the longest forced win a puzzle asks for, in the solver's own moves
+pub const MAX_PUZZLE_MOVES: usize = 3;
+
+// a position of a classic game where one column, and no other, wins by force within a few
+// moves. the columns replay the game up to the position
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub columns: Vec<usize>,
+    pub solution: usize,
+    // the own moves the quickest win takes
+    pub moves: usize,
+}
+
+impl Puzzle {
+    // a position someone set up, the quickest forced win the engine finds is its solution
+    pub fn from_position(columns: Vec<usize>) -> Option<Self> {
+        let mut game = Game::new();
+        for &column in &columns {
+            game.apply_move(column).ok()?;
+        }
+        if game.outcome() != Outcome::Ongoing {
+            return None;
+        }
+
+        let wins = engine::forced_wins(game.moves(), game.player_turn(), MAX_PUZZLE_MOVES);
+        let (solution, moves) = wins.into_iter().min_by_key(|(_, moves)| *moves)?;
+        Some(Self {
+            columns,
+            solution,
+            moves,
+        })
+    }
+
+    // the player to move, who solves it
+    pub fn player(&self) -> usize {
+        self.columns.len() % 2 + 1
+    }
+
+    pub fn game(&self) -> Option<Game> {
+        let mut game = Game::new();
+        for &column in &self.columns {
+            game.apply_move(column).ok()?;
+        }
+        Some(game)
+    }
+
+    // one character per cell, the same position reached by other move orders is the
+    // same puzzle
+    pub fn key(&self) -> String {
+        let mut cells = [[b'0'; COLUMNS]; ROWS];
+        if let Some(game) = self.game() {
+            for coin in game.coins() {
+                cells[coin.row][coin.column] = b'0' + coin.player as u8;
+            }
+        }
+        cells
+            .iter()
+            .map(|row| String::from_utf8_lossy(row).into_owned())
+            .collect()
+    }
+}
+
+// goes through a game one position per step, so the app can spread a game over frames
+pub struct GameMiner {
+    columns: Vec<usize>,
+    game: Game,
+    // the ply and column of the latest forced win found
+    previous: Option<(usize, usize)>,
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl GameMiner {
+    pub fn new(columns: Vec<usize>) -> Self {
+        Self {
+            columns,
+            game: Game::new(),
+            previous: None,
+            puzzles: Vec::new(),
+        }
+    }
+
+    // looks at the next position, false once the game is through. a forced win found two
+    // plies before is the same puzzle further along when its first move was played, or
+    // still the same when it wasn't and the first move is too
+    pub fn step(&mut self) -> bool {
+        let ply = self.game.moves().len();
+        let Some(&played) = self.columns.get(ply) else {
+            return false;
+        };
+        if self.game.outcome() != Outcome::Ongoing {
+            return false;
+        }
+
+        let wins =
+            engine::forced_wins(self.game.moves(), self.game.player_turn(), MAX_PUZZLE_MOVES);
+        if let [(solution, moves)] = wins[..] {
+            let continued = self
+                .previous
+                .is_some_and(|(previous_ply, previous_solution)| {
+                    previous_ply + 2 == ply
+                        && (self.columns[previous_ply] == previous_solution
+                            || previous_solution == solution)
+                });
+            if !continued {
+                self.puzzles.push(Puzzle {
+                    columns: self.columns[..ply].to_vec(),
+                    solution,
+                    moves,
+                });
+            }
+            self.previous = Some((ply, solution));
+        }
+
+        self.game.apply_move(played).is_ok()
+    }
+}
+
+// every puzzle of a classic game at once
+pub fn mine(columns: &[usize]) -> Vec<Puzzle> {
+    let mut miner = GameMiner::new(columns.to_vec());
+    while miner.step() {}
+    miner.puzzles
+}
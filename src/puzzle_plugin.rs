@@ -0,0 +1,299 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use bevy::prelude::*;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    challenge_plugin::{moves_text, report_challenge},
+    components::CoinMove,
+    game::{Game, Outcome},
+    locale_plugin::{Locale, Phrase},
+    nostr_plugin::redraw_coins,
+    platform,
+    puzzle::{self, GameMiner, Puzzle},
+    resources::{Board, GameState, Ghost},
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+// how often the archive is looked at for games not mined yet
+const SCAN_INTERVAL: f64 = 30.0;
+
+static PUZZLE_CALLED: AtomicBool = AtomicBool::new(false);
+// a challenge link's position and its author
+static CHALLENGE: Mutex<Option<(Puzzle, Option<XOnlyPublicKey>)>> = Mutex::new(None);
+
+// the archived games waiting to be searched for puzzles and the one being searched,
+// a position per frame
+#[derive(Resource, Default)]
+struct PuzzleMining {
+    queue: Vec<String>,
+    current: Option<(String, GameMiner)>,
+    scanned_at: Option<f64>,
+}
+
+// the puzzle on the board until it's solved or missed, and who to tell when it came
+// from a challenge
+#[derive(Resource, Default)]
+struct ActivePuzzle {
+    puzzle: Option<Puzzle>,
+    challenge: Option<Option<XOnlyPublicKey>>,
+}
+
+// finished classic games are searched in the background for positions with a single
+// forced win, which grow the local puzzle pool. a puzzle is played against the engine
+// from its position
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PuzzleMining>()
+            .init_resource::<ActivePuzzle>()
+            .add_systems(Update, mine_puzzles)
+            .add_systems(Update, check_puzzle_system.run_if(in_state(AppState::Menu)))
+            .add_systems(OnEnter(AppState::InGame), place_puzzle)
+            .add_systems(Update, track_puzzle.run_if(in_state(AppState::InGame)))
+            .add_systems(OnExit(AppState::InGame), leave_puzzle);
+    }
+}
+
+fn mine_puzzles(time: Res<Time>, mut mining: ResMut<PuzzleMining>) {
+    let mining = mining.as_mut();
+
+    if let Some((game_id, miner)) = mining.current.as_mut() {
+        if miner.step() {
+            return;
+        }
+
+        let added = storage::add_puzzles(std::mem::take(&mut miner.puzzles));
+        storage::mark_mined(game_id);
+        if added > 0 {
+            info!("{} puzzles mined from game {}", added, game_id);
+            platform::dispatch_event("puzzlesMined", Some(&puzzle_stats()));
+        }
+        mining.current = None;
+    }
+
+    if let Some(game_id) = mining.queue.pop() {
+        match storage::load_moves(&game_id) {
+            Some(moves) => mining.current = Some((game_id, GameMiner::new(moves.columns))),
+            // variants and spectated games keep no moves, there's nothing to search
+            None => storage::mark_mined(&game_id),
+        }
+        return;
+    }
+
+    let now = time.elapsed_seconds_f64();
+    if mining
+        .scanned_at
+        .is_some_and(|scanned_at| now - scanned_at < SCAN_INTERVAL)
+    {
+        return;
+    }
+    mining.scanned_at = Some(now);
+    mining.queue = unmined_games();
+}
+
+fn unmined_games() -> Vec<String> {
+    let mined = storage::mined_games();
+    storage::archived_games()
+        .into_iter()
+        .filter(|game_id| !mined.contains(game_id))
+        .collect()
+}
+
+// the whole archive at once, for the desktop's `--mine-puzzles`
+pub fn mine_archive() -> (usize, usize) {
+    let games = unmined_games();
+    let mut added = 0;
+    for game_id in &games {
+        if let Some(moves) = storage::load_moves(game_id) {
+            added += storage::add_puzzles(puzzle::mine(&moves.columns));
+        }
+        storage::mark_mined(game_id);
+    }
+    (games.len(), added)
+}
+
+// the oldest puzzle not solved yet, once all are solved they come round again
+fn next_puzzle() -> Option<Puzzle> {
+    let pool = storage::puzzle_pool();
+    let solved = storage::solved_puzzles();
+    pool.iter()
+        .find(|puzzle| !solved.contains(&puzzle.key()))
+        .or_else(|| pool.first())
+        .cloned()
+}
+
+fn puzzle_name(puzzle: &Puzzle, challenge: bool, locale: &Locale) -> String {
+    let phrase = if challenge {
+        Phrase::ChallengeWinIn
+    } else {
+        Phrase::PuzzleWinIn
+    };
+    locale.fill(phrase, &[&moves_text(puzzle.moves, locale)])
+}
+
+fn check_puzzle_system(
+    locale: Res<Locale>,
+    mut game_state: ResMut<GameState>,
+    mut board: ResMut<Board>,
+    mut active: ResMut<ActivePuzzle>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let challenge = CHALLENGE
+        .lock()
+        .ok()
+        .and_then(|mut challenge| challenge.take());
+    let (puzzle, challenge) = match challenge {
+        Some((puzzle, author)) => (puzzle, Some(author)),
+        None if PUZZLE_CALLED.swap(false, Ordering::SeqCst) => {
+            let Some(puzzle) = next_puzzle() else {
+                error!("no puzzles mined yet");
+                return;
+            };
+            (puzzle, None)
+        }
+        None => return,
+    };
+    let Some(game) = puzzle.game() else {
+        error!("puzzle doesn't replay");
+        return;
+    };
+
+    info!(
+        "playing puzzle {} as player {}",
+        puzzle.key(),
+        puzzle.player()
+    );
+
+    let player = puzzle.player();
+    let opponent = if player == 1 { 2 } else { 1 };
+    let name = puzzle_name(&puzzle, challenge.is_some(), &locale);
+    game_state.player_type = player;
+    game_state.p2_ln_address = Some(name.clone());
+    game_state.start = true;
+    // the engine defends, the puzzle's game was no recorded game of it
+    game_state.ghost = Some(Ghost {
+        name,
+        player: opponent,
+        games: Vec::new(),
+    });
+
+    *board = Board::new();
+    board.moves = game.moves().to_vec();
+    board.player_turn = game.player_turn();
+
+    *active = ActivePuzzle {
+        puzzle: Some(puzzle),
+        challenge,
+    };
+    next_state.set(AppState::InGame);
+}
+
+// the puzzle's coins go straight into their slots
+fn place_puzzle(
+    mut commands: Commands,
+    board: Res<Board>,
+    theme: Res<Theme>,
+    coins: Query<(Entity, &CoinMove)>,
+    active: Res<ActivePuzzle>,
+) {
+    if active.puzzle.is_some() {
+        redraw_coins(&board, &mut commands, &theme, &coins);
+    }
+}
+
+fn leave_puzzle(mut active: ResMut<ActivePuzzle>) {
+    *active = ActivePuzzle::default();
+}
+
+// solved by winning within the puzzle's moves, after that the engine plays on as usual
+fn track_puzzle(
+    board: Res<Board>,
+    locale: Res<Locale>,
+    mut game_state: ResMut<GameState>,
+    mut active: ResMut<ActivePuzzle>,
+) {
+    let Some(puzzle) = active.puzzle.as_ref() else {
+        return;
+    };
+    // switched to another game
+    if game_state.ghost.is_none() {
+        *active = ActivePuzzle::default();
+        return;
+    }
+    if !board.is_changed() {
+        return;
+    }
+
+    // the board's result comes once the coin landed, the rules tell right away
+    let outcome = match Game::from_board(&board) {
+        Ok(game) => game.outcome(),
+        Err(e) => {
+            error!("Error replaying the puzzle: {}", e);
+            return;
+        }
+    };
+    let player = puzzle.player();
+    let own_moves = board
+        .moves
+        .len()
+        .saturating_sub(puzzle.columns.len())
+        .div_ceil(2);
+    let result = if outcome == Outcome::Win(player) && own_moves <= puzzle.moves {
+        if active.challenge.is_none() {
+            storage::mark_solved(&puzzle.key());
+        }
+        "solved"
+    } else if outcome != Outcome::Ongoing || own_moves >= puzzle.moves {
+        "missed"
+    } else {
+        return;
+    };
+
+    info!("puzzle {}", result);
+    platform::dispatch_event("puzzleResult", Some(result));
+    if let Some(Some(author)) = active.challenge {
+        report_challenge(
+            puzzle,
+            author,
+            result == "solved",
+            game_state.nostr_keys.clone(),
+            &locale,
+        );
+    }
+    if result == "missed" {
+        game_state.p2_ln_address = Some(locale.get(Phrase::PuzzleMissed).to_string());
+    }
+    *active = ActivePuzzle::default();
+}
+
+// the size of the pool and how many were solved, as json
+#[wasm_bindgen(js_name = puzzleStats)]
+pub fn puzzle_stats() -> String {
+    let pool = storage::puzzle_pool();
+    let solved = storage::solved_puzzles();
+    let solved = pool
+        .iter()
+        .filter(|puzzle| solved.contains(&puzzle.key()))
+        .count();
+    format!(r#"{{"total":{},"solved":{}}}"#, pool.len(), solved)
+}
+
+#[wasm_bindgen]
+pub fn play_puzzle() {
+    PUZZLE_CALLED.store(true, Ordering::SeqCst);
+}
+
+// played like a puzzle from the pool, the author hears how it went
+pub fn play_challenge(puzzle: Puzzle, author: Option<XOnlyPublicKey>) {
+    if let Ok(mut challenge) = CHALLENGE.lock() {
+        *challenge = Some((puzzle, author));
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+
+use crate::{messages::GameResult, resources::DEFAULT_RATING};
+
+const K_FACTOR: f64 = 16.0;
+
+// provisional rating change for a win, draw and loss against the opponent
+pub struct RatingChange {
+    pub win: i32,
+    pub draw: i32,
+    pub loss: i32,
+}
+
+impl RatingChange {
+    pub fn new(rating: u32, opponent_rating: u32) -> Self {
+        let expected = expected_score(rating, opponent_rating);
+        let change = |score: f64| (K_FACTOR * (score - expected)).round() as i32;
+
+        Self {
+            win: change(1.0),
+            draw: change(0.5),
+            loss: change(0.0),
+        }
+    }
+}
+
+pub fn expected_score(rating: u32, opponent_rating: u32) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating as f64 - rating as f64) / 400.0))
+}
+
+pub struct LeaderboardEntry {
+    pub pubkey: XOnlyPublicKey,
+    pub name: Option<String>,
+    pub rating: u32,
+    pub games: usize,
+}
+
+// games whose players published results that disagree
+pub fn disputed(results: &[GameResult]) -> HashSet<&str> {
+    let mut first: HashMap<&str, &GameResult> = HashMap::new();
+    results
+        .iter()
+        .filter(|result| match first.get(result.game_id.as_str()) {
+            Some(first_result) => first_result.disputes(result),
+            None => {
+                first.insert(&result.game_id, result);
+                false
+            }
+        })
+        .map(|result| result.game_id.as_str())
+        .collect()
+}
+
+// games whose loser signed a result, or both players for a draw. anyone can publish a
+// result naming two players, a win only one side claims doesn't count
+pub fn confirmed(results: &[GameResult]) -> HashSet<&str> {
+    let mut signers: HashMap<&str, HashSet<XOnlyPublicKey>> = HashMap::new();
+    for result in results {
+        if let Some(author) = result.author {
+            signers
+                .entry(result.game_id.as_str())
+                .or_default()
+                .insert(author);
+        }
+    }
+
+    results
+        .iter()
+        .filter(|result| {
+            let Some(signers) = signers.get(result.game_id.as_str()) else {
+                return false;
+            };
+            match result.winner {
+                Some(1) => signers.contains(&result.p2_pubkey),
+                Some(2) => signers.contains(&result.p1_pubkey),
+                _ => signers.contains(&result.p1_pubkey) && signers.contains(&result.p2_pubkey),
+            }
+        })
+        .map(|result| result.game_id.as_str())
+        .collect()
+}
+
+// replays results in the order they were played, each game is only counted once,
+// disputed and unconfirmed games not at all
+pub fn leaderboard(results: &[GameResult]) -> Vec<LeaderboardEntry> {
+    let disputed = disputed(results);
+    let confirmed = confirmed(results);
+    let mut counted = HashSet::new();
+    let mut entries: HashMap<XOnlyPublicKey, LeaderboardEntry> = HashMap::new();
+
+    for result in results {
+        if disputed.contains(result.game_id.as_str())
+            || !confirmed.contains(result.game_id.as_str())
+            || !counted.insert(&result.game_id)
+            || result.p1_pubkey == result.p2_pubkey
+        {
+            continue;
+        }
+
+        let rating = |pubkey| {
+            entries
+                .get(pubkey)
+                .map_or(DEFAULT_RATING, |entry: &LeaderboardEntry| entry.rating)
+        };
+        let p1_rating = rating(&result.p1_pubkey);
+        let p2_rating = rating(&result.p2_pubkey);
+
+        let p1_change = RatingChange::new(p1_rating, p2_rating);
+        let p2_change = RatingChange::new(p2_rating, p1_rating);
+
+        let (p1_delta, p2_delta) = match result.winner {
+            Some(1) => (p1_change.win, p2_change.loss),
+            Some(2) => (p1_change.loss, p2_change.win),
+            _ => (p1_change.draw, p2_change.draw),
+        };
+
+        for (pubkey, name, delta) in [
+            (result.p1_pubkey, &result.p1_name, p1_delta),
+            (result.p2_pubkey, &result.p2_name, p2_delta),
+        ] {
+            let entry = entries.entry(pubkey).or_insert(LeaderboardEntry {
+                pubkey,
+                name: None,
+                rating: DEFAULT_RATING,
+                games: 0,
+            });
+
+            entry.rating = (entry.rating as i32 + delta).max(0) as u32;
+            entry.games += 1;
+            if name.is_some() {
+                entry.name = name.clone();
+            }
+        }
+    }
+
+    let mut leaderboard: Vec<LeaderboardEntry> = entries.into_values().collect();
+    leaderboard.sort_by(|a, b| b.rating.cmp(&a.rating).then(b.games.cmp(&a.games)));
+    leaderboard
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_sdk::Keys;
+
+    use super::*;
+
+    fn result(p1: XOnlyPublicKey, p2: XOnlyPublicKey, winner: Option<usize>) -> GameResult {
+        GameResult {
+            game_id: "game".to_string(),
+            p1_pubkey: p1,
+            p2_pubkey: p2,
+            p1_name: None,
+            p2_name: None,
+            winner,
+            moves: 7,
+            duration: None,
+            resigned: false,
+            moves_hash: None,
+            author: None,
+        }
+    }
+
+    fn signed(result: &GameResult, author: XOnlyPublicKey) -> GameResult {
+        GameResult {
+            author: Some(author),
+            ..result.clone()
+        }
+    }
+
+    fn players() -> (XOnlyPublicKey, XOnlyPublicKey) {
+        (Keys::generate().public_key(), Keys::generate().public_key())
+    }
+
+    #[test]
+    fn one_sided_win_is_ignored() {
+        let (p1, p2) = players();
+        let win = result(p1, p2, Some(1));
+        assert!(leaderboard(&[signed(&win, p1)]).is_empty());
+    }
+
+    #[test]
+    fn win_signed_by_the_loser_counts() {
+        let (p1, p2) = players();
+        let win = result(p1, p2, Some(1));
+        let entries = leaderboard(&[signed(&win, p2)]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].pubkey, p1);
+        assert!(entries[0].rating > DEFAULT_RATING);
+        assert!(entries[1].rating < DEFAULT_RATING);
+    }
+
+    #[test]
+    fn win_signed_by_both_counts_once() {
+        let (p1, p2) = players();
+        let win = result(p1, p2, Some(2));
+        let entries = leaderboard(&[signed(&win, p1), signed(&win, p2)]);
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.games == 1));
+        assert_eq!(entries[0].pubkey, p2);
+    }
+
+    #[test]
+    fn draw_needs_both_players() {
+        let (p1, p2) = players();
+        let draw = result(p1, p2, None);
+        assert!(leaderboard(&[signed(&draw, p1)]).is_empty());
+        assert_eq!(
+            leaderboard(&[signed(&draw, p1), signed(&draw, p2)]).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn unsigned_result_is_ignored() {
+        let (p1, p2) = players();
+        assert!(leaderboard(&[result(p1, p2, Some(2))]).is_empty());
+    }
+}
@@ -1,11 +1,117 @@
-use bevy::{log::error, prelude::Resource};
+use bevy::{
+    asset::Handle,
+    log::{error, info},
+    prelude::{Image, Resource},
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
 use futures::channel::mpsc::{Receiver, Sender};
 
-use nostr_sdk::{serde_json, ClientMessage, EventBuilder, FromBech32, Keys, Kind, Tag, ToBech32};
+use nostr_sdk::{
+    event::tag::Marker, secp256k1::XOnlyPublicKey, serde_json, ClientMessage, Event, EventBuilder,
+    EventId, FromBech32, Keys, Kind, Tag, Timestamp, ToBech32,
+};
 use serde::{Deserialize, Serialize};
-use web_sys::window;
 
-use crate::messages::NetworkMessage;
+use crate::{
+    bitboard::{Bitboard, COLUMNS},
+    game::Modifier,
+    invite::Invitation,
+    messages::{
+        ClientInfo, GameConfig, GameResult, MessageCategory, MessagePack, NetworkMessage,
+        PlayerInput, PlayerRating, Players, Skin, Special, Study, Teams, GAME_TAG_PREFIX,
+        MOVE_KIND, SIGNAL_KIND,
+    },
+    platform,
+    transport::GameTransport,
+};
+
+pub const DEFAULT_RATING: u32 = 1200;
+// relays don't keep ephemeral events, their copies from other relays come within seconds
+// and their ids are remembered this long
+const EPHEMERAL_WINDOW: Duration = Duration::from_secs(600);
+pub const RESULT_KIND: u16 = 34444;
+pub const RESULT_TAG: &str = "unite4.luvnft.com result";
+// optional features this client supports, announced to the opponent in the handshake
+pub const CLIENT_FEATURES: [&str; 13] = [
+    "ack",
+    "sync",
+    "rating",
+    "series",
+    "outbox",
+    "variants",
+    "resume",
+    "ephemeral",
+    "presence",
+    "chain",
+    "hover",
+    "trace",
+    "end_message",
+];
+
+// the event ids of the moves on the board. each move event references the one of the move
+// before like a nip-10 reply, so a forked, reordered or injected history shows. moves that
+// came with a sync response or a checkpoint have no event to check against
+#[derive(Default, Debug)]
+pub struct MoveChain {
+    ids: Vec<Option<EventId>>,
+    // undone moves may still come around from the relays
+    undone: Vec<EventId>,
+}
+
+impl MoveChain {
+    pub fn previous(&self, index: usize) -> Option<EventId> {
+        let previous = index.checked_sub(1)?;
+        self.ids.get(previous).copied().flatten()
+    }
+
+    // the move at the index, ids after it belonged to an undone line
+    pub fn record(&mut self, index: usize, id: EventId) {
+        self.ids.truncate(index);
+        self.ids.resize(index, None);
+        self.ids.push(Some(id));
+    }
+
+    pub fn undo(&mut self, moves: usize) {
+        let moves = moves.min(self.ids.len());
+        self.undone.extend(self.ids.drain(moves..).flatten());
+    }
+
+    // a move already on the board coming again, from another event
+    pub fn check_fork(&self, index: usize, event: &Event) -> Result<(), String> {
+        match self.ids.get(index) {
+            Some(Some(id)) if *id != event.id && !self.undone.contains(&event.id) => {
+                Err(format!("two different events for move {}", index + 1))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // a new move, chained tells the sender's client references the previous move
+    pub fn check_previous(&self, index: usize, event: &Event, chained: bool) -> Result<(), String> {
+        match (self.previous(index), previous_move(event)) {
+            (Some(previous), Some(reference)) if previous != reference => {
+                Err(format!("move {} doesn't follow move {}", index + 1, index))
+            }
+            (Some(_), None) if chained => {
+                Err(format!("move {} references no previous move", index + 1))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn previous_move(event: &Event) -> Option<EventId> {
+    event.tags.iter().find_map(|tag| match tag {
+        Tag::Event(id, _, Some(Marker::Reply)) => Some(*id),
+        _ => None,
+    })
+}
 
 #[derive(Resource)]
 pub struct Board {
@@ -14,6 +120,18 @@ pub struct Board {
     pub winner: Option<usize>,
     pub in_progress: bool,
     pub draw: bool,
+    pub resigned: Option<usize>,
+    // columns of fortune games, the seed every modifier is drawn from
+    pub fortune_seed: Option<u64>,
+    // what the last modifier did, shown under the turn display
+    pub last_modifier: Option<Modifier>,
+    pub pop_out: bool,
+    pub power_up: bool,
+    // lines wrap around the left and right edge
+    pub cylinder: bool,
+    // the board turns upside down every few moves
+    pub gravity_shift: bool,
+    pub chain: MoveChain,
 }
 
 impl Board {
@@ -24,14 +142,44 @@ impl Board {
             winner: None,
             in_progress: false,
             draw: false,
+            resigned: None,
+            fortune_seed: None,
+            last_modifier: None,
+            pop_out: false,
+            power_up: false,
+            cylinder: false,
+            gravity_shift: false,
+            chain: MoveChain::default(),
         }
     }
+
+    // no variant, the game the ghost, the stats and the leaderboard know
+    pub fn classic(&self) -> bool {
+        self.fortune_seed.is_none()
+            && !self.pop_out
+            && !self.power_up
+            && !self.cylinder
+            && !self.gravity_shift
+    }
+
+    pub fn landing_row(&self, column: usize) -> Option<usize> {
+        Bitboard::from_moves(&self.moves).landing_row(column)
+    }
+
+    pub fn landing_rows(&self) -> [Option<usize>; COLUMNS] {
+        Bitboard::from_moves(&self.moves).landing_rows()
+    }
 }
 #[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 pub struct PlayerMove {
     pub player: usize,
     pub column: usize,
     pub row: usize,
+    // a pop out move, the player's coin was taken from the bottom of the column
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pop: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub special: Option<Special>,
 }
 
 impl PlayerMove {
@@ -40,116 +188,736 @@ impl PlayerMove {
             player,
             column,
             row,
+            pop: false,
+            special: None,
         }
     }
-    pub fn is_winner(&self, moves: &[PlayerMove]) -> bool {
-        [(0, 1), (1, 0), (1, 1), (1, -1)]
-            .iter()
-            .any(|&(column_direction, row_direction)| {
-                self.check_direction(moves, column_direction, row_direction)
-                    + self.check_direction(moves, -column_direction, -row_direction)
-                    + 1
-                    >= 4
-            })
+
+    pub fn popped(player: usize, column: usize) -> Self {
+        Self {
+            player,
+            column,
+            row: 0,
+            pop: true,
+            special: None,
+        }
     }
 
-    pub fn check_direction(
-        &self,
-        moves: &[PlayerMove],
-        column_direction: isize,
-        row_direction: isize,
-    ) -> usize {
-        let mut count = 0;
-        let mut current_column = self.column as isize + column_direction;
-        let mut current_row = self.row as isize + row_direction;
-
-        while current_column >= 0
-            && current_row >= 0
-            && current_column < 7
-            && current_row < 6
-            && moves.iter().any(|m| {
-                m.player == self.player
-                    && m.column == current_column as usize
-                    && m.row == current_row as usize
-            })
-        {
-            count += 1;
-            current_column += column_direction;
-            current_row += row_direction;
+    pub fn power_up(player: usize, column: usize, row: usize, special: Special) -> Self {
+        Self {
+            player,
+            column,
+            row,
+            pop: false,
+            special: Some(special),
         }
+    }
+}
 
-        count
+#[derive(Resource)]
+pub struct MovePreload {
+    pub player: usize,
+    pub move_count: usize,
+    pub landing_rows: [Option<usize>; COLUMNS],
+    pub texture: Handle<Image>,
+}
+
+impl MovePreload {
+    pub fn new() -> Self {
+        Self {
+            player: 0,
+            move_count: usize::MAX,
+            landing_rows: [None; COLUMNS],
+            texture: Handle::default(),
+        }
+    }
+}
+
+pub struct PendingMove {
+    pub seq: usize,
+    pub nostr_msg: ClientMessage,
+    pub first_sent: f64,
+    pub last_sent: f64,
+    pub retries: usize,
+}
+
+impl PendingMove {
+    pub fn new(seq: usize, nostr_msg: ClientMessage, now: f64) -> Self {
+        Self {
+            seq,
+            nostr_msg,
+            first_sent: now,
+            last_sent: now,
+            retries: 0,
+        }
     }
 }
 
+#[derive(Resource)]
+pub struct PendingAck {
+    pub pending: Option<PendingMove>,
+    pub overdue: bool,
+    pub last_move: Option<(EventId, f64)>,
+    pub confirmations: usize,
+    pub low_delivery: bool,
+}
+
+impl PendingAck {
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            overdue: false,
+            last_move: None,
+            confirmations: 0,
+            low_delivery: false,
+        }
+    }
+
+    pub fn send(&mut self, seq: usize, nostr_msg: ClientMessage, now: f64) {
+        if let ClientMessage::Event(ref event) = nostr_msg {
+            self.last_move = Some((event.id, now));
+            self.confirmations = 0;
+            self.low_delivery = false;
+        }
+
+        self.pending = Some(PendingMove::new(seq, nostr_msg, now));
+    }
+
+    // the timers started before the page was suspended, the move gets another full round
+    // of retries over the reconnected relays
+    pub fn restart(&mut self, now: f64) {
+        if let Some(ref mut pending) = self.pending {
+            pending.first_sent = now;
+            pending.last_sent = now;
+            pending.retries = 0;
+        }
+        if let Some((_, ref mut sent)) = self.last_move {
+            *sent = now;
+        }
+        self.overdue = false;
+        self.low_delivery = false;
+    }
+}
+
+// set when the opponent sends a move our board rejects, cleared once a sync confirms
+// the boards agree. divergent means the synced moves contradict ours, tampering that a
+// move event broke the `MoveChain` and stays for the rest of the game
+#[derive(Resource, Default)]
+pub struct Desync {
+    pub reason: Option<String>,
+    pub divergent: bool,
+    pub tampering: Option<String>,
+}
+
+// what a deployment pins when it is built. `UNITE4_MAINTAINER` is the npub or hex key that
+// signs the service announcements, a build without it shows none.
+// `UNITE4_LIGHTNING_ADDRESS` is the maintainer's lightning address premium skins are zapped
+// to, a build without it or without a maintainer only has the free skins
+#[derive(Resource)]
+pub struct ProtocolConfig {
+    pub maintainer: Option<XOnlyPublicKey>,
+    pub lightning_address: Option<String>,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        let maintainer = option_env!("UNITE4_MAINTAINER").and_then(|key| {
+            XOnlyPublicKey::from_bech32(key)
+                .ok()
+                .or_else(|| XOnlyPublicKey::from_str(key).ok())
+        });
+        let lightning_address = option_env!("UNITE4_LIGHTNING_ADDRESS")
+            .map(|address| address.trim().to_lowercase())
+            .filter(|address| address.contains('@'));
+        Self {
+            maintainer,
+            lightning_address,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct UndoState {
+    pub sent: Option<usize>,
+    pub requested: Option<usize>,
+    // the opponent turned down the undo that was sent, told once the queue is drained
+    pub declined: bool,
+}
+
+impl UndoState {
+    pub fn new() -> Self {
+        Self {
+            sent: None,
+            requested: None,
+            declined: false,
+        }
+    }
+}
+
+// relays deliver an event more than once, from each relay it's on and again when the
+// subscription is renewed, and a move sent again as a new event may come before the board
+// applied the first. a resource of its own, as the ids pile up over a game and
+// `GameState` is cloned for every message sent
+#[derive(Resource, Default)]
+pub struct Processed {
+    // stored events come again whenever the subscription is renewed, their ids are kept
+    // for the game
+    ids: HashSet<EventId>,
+    // ephemeral moves, presence, hover and study signals, when each was first seen
+    recent: HashMap<EventId, Timestamp>,
+    // the index of the move handed to the board and not on it yet
+    pending_move: Option<usize>,
+}
+
+impl Processed {
+    // true the first time the event comes
+    pub fn first(&mut self, event: &Event) -> bool {
+        if !matches!(event.kind, Kind::Ephemeral(_)) {
+            return self.ids.insert(event.id);
+        }
+
+        let now = Timestamp::now();
+        self.recent.retain(|_, seen| *seen > now - EPHEMERAL_WINDOW);
+        self.recent.insert(event.id, now).is_none()
+    }
+
+    // the board holds `moves` moves, a move it applied isn't pending anymore
+    pub fn move_pending(&mut self, index: usize, moves: usize) -> bool {
+        self.pending_move = self.pending_move.filter(|pending| *pending >= moves);
+        self.pending_move == Some(index)
+    }
+
+    pub fn take_move(&mut self, index: usize) {
+        self.pending_move = Some(index);
+    }
+}
+
+// what the opponent's events tell about them being on the game's page
+#[derive(Clone, Default)]
+pub struct Presence {
+    // the wall clock time of their latest event
+    pub last_seen: Option<u64>,
+    pub left: bool,
+    pub thinking: bool,
+    // the column they aim at during their turn
+    pub hover: Option<usize>,
+}
+
+impl Presence {
+    // stored events replayed on load are older than the live ones
+    pub fn seen(&mut self, at: u64) {
+        if self.last_seen.is_none_or(|last_seen| at >= last_seen) {
+            self.last_seen = Some(at);
+            self.left = false;
+        }
+    }
+}
+
+// opponent's kind-0 metadata, the avatar is decoded before it reaches bevy
+pub struct Profile {
+    pub pubkey: XOnlyPublicKey,
+    pub name: Option<String>,
+    pub avatar: Option<Image>,
+}
+
 #[derive(Resource)]
 pub struct NetworkStuff {
-    pub read: Option<Receiver<String>>,
+    pub read: Option<Receiver<Event>>,
+    // events that arrived while the game was parked behind another one
+    pub backlog: VecDeque<Event>,
+    pub profile: Option<Receiver<Profile>>,
+    pub relay_update: Option<Sender<Vec<String>>>,
+    pub relay_failover: bool,
 }
 
 impl NetworkStuff {
     pub fn new() -> Self {
-        Self { read: None }
+        Self {
+            read: None,
+            backlog: VecDeque::new(),
+            profile: None,
+            relay_update: None,
+            relay_failover: false,
+        }
+    }
+
+    // the backlog first, so the moves stay in the order they arrived
+    pub fn next_event(&mut self) -> Option<Event> {
+        if let Some(event) = self.backlog.pop_front() {
+            return Some(event);
+        }
+        match self.read {
+            Some(ref mut receive_rx) => receive_rx.try_next().ok().flatten(),
+            None => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GameSession {
+    pub player_type: usize,
+    pub p2_ln_address: Option<String>,
+}
+
+impl GameSession {
+    pub fn load(game_id: &str) -> Option<Self> {
+        let session = platform::get_item(&format!("game_session{}", game_id))?;
+
+        serde_json::from_str(&session).ok()
+    }
+
+    pub fn save(&self, game_id: &str) {
+        let session = serde_json::to_string(self).unwrap();
+        if let Err(e) = platform::set_item(&format!("game_session{}", game_id), &session) {
+            error!("Error saving game session: {}", e);
+        }
+    }
+}
+
+// best of n series, saved under each game id so the next game picks up the score
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Series {
+    pub series_id: String,
+    pub length: usize,
+    pub game: usize,
+    pub local_wins: usize,
+    pub opponent_wins: usize,
+}
+
+impl Series {
+    pub fn new(series_id: &str, length: usize) -> Self {
+        Self {
+            series_id: series_id.to_string(),
+            length,
+            game: 1,
+            local_wins: 0,
+            opponent_wins: 0,
+        }
+    }
+
+    pub fn load(game_id: &str) -> Option<Self> {
+        let series = platform::get_item(&format!("series{}", game_id))?;
+
+        serde_json::from_str(&series).ok()
+    }
+
+    pub fn save(&self, game_id: &str) {
+        let series = serde_json::to_string(self).unwrap();
+        if let Err(e) = platform::set_item(&format!("series{}", game_id), &series) {
+            error!("Error saving series: {}", e);
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        let wins_needed = self.length / 2 + 1;
+        self.local_wins >= wins_needed
+            || self.opponent_wins >= wins_needed
+            || self.game >= self.length
+    }
+
+    pub fn next_game_id(&self) -> String {
+        format!("{}-{}", self.series_id, self.game + 1)
+    }
+}
+
+// a past opponent replayed from archived games, the engine plays where their games run out
+#[derive(Clone)]
+pub struct Ghost {
+    pub name: String,
+    pub player: usize,
+    pub games: Vec<Vec<usize>>,
+}
+
+impl Ghost {
+    // the most recent game that reached this position decides the move
+    pub fn recorded_move(&self, moves: &[PlayerMove]) -> Option<usize> {
+        self.games.iter().rev().find_map(|columns| {
+            let reached = columns.len() > moves.len()
+                && moves
+                    .iter()
+                    .zip(columns.iter())
+                    .all(|(player_move, column)| player_move.column == *column);
+
+            reached.then(|| columns[moves.len()])
+        })
     }
 }
 
 #[derive(Resource, Clone)]
 pub struct GameState {
-    pub send: Option<Sender<ClientMessage>>,
+    // none in practice games, which never connect
+    pub transport: Option<Arc<dyn GameTransport>>,
     pub start: bool,
     pub nostr_keys: Keys,
+    pub game_id: String,
     pub game_tag: Tag,
     pub player_type: usize,
     pub local_ln_address: Option<String>,
     pub p2_ln_address: Option<String>,
+    pub local_rating: u32,
+    pub p2_rating: Option<u32>,
+    pub p2_pubkey: Option<XOnlyPublicKey>,
+    // [player 1, player 2], known once the game has been joined
+    pub player_pubkeys: Option<[XOnlyPublicKey; 2]>,
+    pub series: Option<Series>,
+    // the terms of the invitation link the game was created or opened with
+    pub invitation: Option<Invitation>,
+    pub p2_client: Option<ClientInfo>,
+    pub ghost: Option<Ghost>,
+    // moves may come hours apart, every move is stored so a reload replays the whole game
+    pub correspondence: bool,
+    // the seats of a 2v2 game, none in a game between two players
+    pub teams: Option<Teams>,
+    // the wall clock time of the latest move, shown in correspondence games
+    pub last_move_at: Option<u64>,
+    pub opponent_presence: Presence,
+    // the message the winner sent after beating the local player, see the end message plugin
+    pub end_message: Option<(MessagePack, usize)>,
 }
 
 impl GameState {
     pub fn new() -> Self {
-        let window = window().expect("no global `window` exists");
-        let local_storage = window
-            .local_storage()
-            .expect("no local storage")
-            .expect("local storage is not available");
+        // an unlocked passphrase protected identity is kept in session storage for the tab
+        let unlocked_key = platform::get_session_item("nostr_key");
 
-        let nostr_keys = if let Ok(Some(nostr_keys)) = local_storage.get_item("nostr_key") {
+        let stored_key = unlocked_key.or(platform::get_item("nostr_key"));
+
+        let nostr_keys = if let Some(nostr_keys) = stored_key {
             let secret_key = nostr_sdk::key::SecretKey::from_bech32(&nostr_keys).unwrap();
             let keys = Keys::new(secret_key);
             keys
+        } else if platform::get_item("nostr_key_encrypted").is_some() {
+            // locked until the passphrase is entered in settings
+            Keys::generate()
         } else {
             let nostr_keys = Keys::generate();
             let secret_key =
                 nostr_sdk::key::SecretKey::to_bech32(&nostr_keys.secret_key().unwrap());
-            local_storage
-                .set_item("nostr_key", &secret_key.unwrap())
+            platform::set_item("nostr_key", &secret_key.unwrap())
                 .expect("Error setting nostr_key in local storage");
             nostr_keys
         };
 
+        let local_rating = match platform::get_item("rating") {
+            Some(rating) => rating.parse().unwrap_or(DEFAULT_RATING),
+            None => DEFAULT_RATING,
+        };
+
         Self {
-            send: None,
+            transport: None,
             start: false,
             nostr_keys,
+            game_id: String::new(),
             game_tag: Tag::Hashtag("".to_string()),
             player_type: 0,
             local_ln_address: None,
             p2_ln_address: None,
+            local_rating,
+            p2_rating: None,
+            p2_pubkey: None,
+            player_pubkeys: None,
+            series: None,
+            invitation: None,
+            p2_client: None,
+            ghost: None,
+            correspondence: false,
+            teams: None,
+            last_move_at: None,
+            opponent_presence: Presence::default(),
+            end_message: None,
         }
     }
 
-    pub fn send_input(self, input: usize) {
-        let msg = NetworkMessage::Input(input);
+    // a teammate plays their team's color
+    pub fn player_of(&self, pubkey: &XOnlyPublicKey) -> Option<usize> {
+        if let Some(color) = self.teams.as_ref().and_then(|teams| teams.color_of(pubkey)) {
+            return Some(color);
+        }
+        let player_pubkeys = self.player_pubkeys?;
+        player_pubkeys
+            .iter()
+            .position(|player_pubkey| player_pubkey == pubkey)
+            .map(|index| index + 1)
+    }
+
+    // the side to move is the player's, in a team game their seat's move too
+    pub fn own_turn(&self, board: &Board) -> bool {
+        board.player_turn == self.player_type
+            && self.teams.as_ref().is_none_or(|teams| {
+                teams.mover(board.moves.len()) == Some(self.nostr_keys.public_key())
+            })
+    }
+
+    // the rules of the board and the game, as announced in a new game event
+    pub fn config(&self, board: &Board) -> GameConfig {
+        GameConfig {
+            fortune_seed: board.fortune_seed,
+            pop_out: board.pop_out,
+            power_up: board.power_up,
+            cylinder: board.cylinder,
+            correspondence: self.correspondence,
+            gravity_shift: board.gravity_shift,
+            teams: self.teams.is_some(),
+        }
+    }
+
+    pub fn save_session(&self) {
+        GameSession {
+            player_type: self.player_type,
+            p2_ln_address: self.p2_ln_address.clone(),
+        }
+        .save(&self.game_id);
+    }
+
+    // the next game of a series is announced on its own game tag, with the variant of the
+    // board and a fresh seed for columns of fortune
+    pub fn send_new_game(
+        self,
+        game_id: &str,
+        series_length: usize,
+        fortune_seed: Option<u64>,
+        board: &Board,
+    ) {
+        let config = GameConfig {
+            fortune_seed,
+            ..self.config(board)
+        };
+        let msg = NetworkMessage::NewGame(self.local_ln_address.clone(), series_length, config);
+        let event = EventBuilder::new(
+            Kind::Regular(4444),
+            serde_json::to_string(&msg).unwrap(),
+            [Tag::Hashtag(format!("{}{}", GAME_TAG_PREFIX, game_id))],
+        )
+        .to_event(&self.nostr_keys)
+        .unwrap();
+        self.publish(event);
+    }
+
+    // moves reference the event of the previous move, see `MoveChain`
+    pub fn send_input(
+        self,
+        column: usize,
+        index: usize,
+        previous: Option<EventId>,
+    ) -> ClientMessage {
+        let msg = NetworkMessage::Input(PlayerInput::new(column, index));
+        self.send_chained(msg, previous)
+    }
+
+    pub fn send_pop(self, column: usize, index: usize, previous: Option<EventId>) -> ClientMessage {
+        let msg = NetworkMessage::Pop(PlayerInput::new(column, index));
+        self.send_chained(msg, previous)
+    }
+
+    pub fn send_special(
+        self,
+        special: Special,
+        column: usize,
+        index: usize,
+        previous: Option<EventId>,
+    ) -> ClientMessage {
+        let input = PlayerInput::new(column, index);
+        let msg = match special {
+            Special::Anvil => NetworkMessage::Anvil(input),
+            Special::Bomb => NetworkMessage::Bomb(input),
+        };
+        self.send_chained(msg, previous)
+    }
+
+    pub fn send_resign(self) {
+        let player = self.player_type;
+        self.send_message(NetworkMessage::Resign(player));
+    }
+
+    pub fn send_end_message(self, pack: MessagePack, index: usize) {
+        self.send_message(NetworkMessage::EndMessage(pack, index));
+    }
+
+    pub fn send_undo_request(self, index: usize) {
+        self.send_message(NetworkMessage::UndoRequest(index));
+    }
+
+    pub fn send_undo_response(self, index: usize, accepted: bool) {
+        self.send_message(NetworkMessage::UndoResponse(index, accepted));
+    }
+
+    // with the time of receipt for an opponent tracing their moves' latency
+    pub fn send_ack(self, seq: usize) {
+        let msg = if self.opponent_supports("trace") {
+            NetworkMessage::TimedAck(seq, platform::now_millis() as u64)
+        } else {
+            NetworkMessage::Ack(seq)
+        };
+        self.send_message(msg);
+    }
+
+    pub fn send_sync_request(self) {
+        self.send_message(NetworkMessage::SyncRequest);
+    }
+
+    pub fn send_sync_response(self, columns: Vec<usize>) {
+        self.send_message(NetworkMessage::SyncResponse(columns));
+    }
+
+    pub fn send_state_hash(self, moves: usize, hash: u64) {
+        self.send_message(NetworkMessage::StateHash(moves, hash));
+    }
+
+    pub fn send_checkpoint(self, columns: Vec<usize>) {
+        self.send_message(NetworkMessage::Checkpoint(columns));
+    }
+
+    pub fn send_presence(self, online: bool) {
+        self.send_message(NetworkMessage::Presence(online));
+    }
+
+    pub fn send_thinking(self, thinking: bool) {
+        self.send_message(NetworkMessage::Thinking(thinking));
+    }
+
+    pub fn send_hover(self, column: Option<usize>) {
+        self.send_message(NetworkMessage::Hover(column));
+    }
+
+    pub fn send_study(self, study: Study) {
+        self.send_message(NetworkMessage::Study(study));
+    }
+
+    pub fn send_study_cursor(self, column: Option<usize>) {
+        self.send_message(NetworkMessage::StudyCursor(column));
+    }
+
+    pub fn send_study_follow(self) {
+        self.send_message(NetworkMessage::StudyFollow);
+    }
+
+    pub fn send_study_request(self) {
+        let name = self.local_ln_address.clone();
+        self.send_message(NetworkMessage::StudyRequest(name));
+    }
+
+    pub fn send_study_end(self) {
+        self.send_message(NetworkMessage::StudyEnd);
+    }
+
+    pub fn send_game_full(self, players: Players) {
+        self.send_message(NetworkMessage::GameFull(players));
+    }
+
+    pub fn send_teams(self, teams: Teams) {
+        self.send_message(NetworkMessage::Teams(teams));
+    }
+
+    // both players' clients take live messages as ephemeral events. a correspondence
+    // opponent is rarely online to answer a sync request, and a team game has four clients
+    // to keep in step, so their moves are stored
+    pub fn ephemeral_moves(&self) -> bool {
+        !self.correspondence
+            && self.teams.is_none()
+            && (self.player_type == 1 || self.player_type == 2)
+            && self.opponent_supports("ephemeral")
+    }
+
+    // one of the features the opponent's client listed in the handshake
+    pub fn opponent_supports(&self, feature: &str) -> bool {
+        self.p2_client.as_ref().is_some_and(|client_info| {
+            client_info
+                .features
+                .iter()
+                .any(|supported| supported == feature)
+        })
+    }
+
+    pub fn send_rating(self) {
+        let rating = PlayerRating::new(self.player_type, self.local_rating);
+        self.send_message(NetworkMessage::Rating(rating));
+    }
+
+    pub fn send_client_info(self, rng: u64, skin: Skin) {
+        let client_info = ClientInfo::new(self.player_type, &CLIENT_FEATURES, rng, skin);
+        self.send_message(NetworkMessage::Client(client_info));
+    }
+
+    pub fn send_result(self, result: GameResult) {
+        let content = serde_json::to_string(&result).unwrap();
+        let event = EventBuilder::new(
+            Kind::ParameterizedReplaceable(RESULT_KIND),
+            content,
+            [
+                Tag::Identifier(result.game_id.clone()),
+                Tag::Hashtag(RESULT_TAG.to_string()),
+                self.game_tag.clone(),
+                Tag::PubKey(result.p1_pubkey, None),
+                Tag::PubKey(result.p2_pubkey, None),
+            ],
+        )
+        .to_event(&self.nostr_keys)
+        .unwrap();
+        self.publish(event);
+    }
+
+    pub fn send_text_note(self, content: String) {
+        let event = EventBuilder::new_text_note(content, [])
+            .to_event(&self.nostr_keys)
+            .unwrap();
+        self.publish(event);
+    }
+
+    pub fn publish(self, event: Event) {
+        let Some(transport) = self.transport else {
+            info!("offline, not publishing event {:?}", event.id);
+            return;
+        };
+
+        match transport.send(ClientMessage::event(event)) {
+            Ok(()) => {}
+            Err(e) => error!("Error publishing event: {}", e),
+        };
+    }
+
+    pub fn resend(self, nostr_msg: ClientMessage) {
+        let Some(transport) = self.transport else {
+            info!("offline, not resending {:?}", nostr_msg);
+            return;
+        };
+
+        match transport.send(nostr_msg) {
+            Ok(()) => {}
+            Err(e) => error!("Error resending message: {}", e),
+        };
+    }
+
+    fn send_message(self, msg: NetworkMessage) -> ClientMessage {
+        self.send_chained(msg, None)
+    }
+
+    fn send_chained(self, msg: NetworkMessage, previous: Option<EventId>) -> ClientMessage {
         let serialized_message = serde_json::to_string(&msg).unwrap();
+        let kind = match msg.category() {
+            MessageCategory::Signal => Kind::Ephemeral(SIGNAL_KIND),
+            MessageCategory::Move if self.ephemeral_moves() => Kind::Ephemeral(MOVE_KIND),
+            _ => Kind::Regular(4444),
+        };
+        let tags = std::iter::once(self.game_tag.clone())
+            .chain(previous.map(|previous| Tag::Event(previous, None, Some(Marker::Reply))));
 
         let nostr_msg = ClientMessage::event(
-            EventBuilder::new(Kind::Regular(4444), serialized_message, [self.game_tag])
+            EventBuilder::new(kind, serialized_message, tags)
                 .to_event(&self.nostr_keys)
                 .unwrap(),
         );
 
-        match self.send.clone().unwrap().try_send(nostr_msg) {
+        // practice games are never connected to relays
+        let Some(transport) = self.transport else {
+            info!("offline, not sending {:?}", msg);
+            return nostr_msg;
+        };
+
+        match transport.send(nostr_msg.clone()) {
             Ok(()) => {}
-            Err(e) => error!("Error sending send_input message: {}", e),
+            Err(e) => error!("Error sending {:?} message: {}", msg, e),
         };
+
+        nostr_msg
     }
 }
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use nostr_sdk::Timestamp;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    messages::state_hash,
+    nostr_plugin::{self, sync_column},
+    resources::{Board, GameState, PendingAck},
+    AppState,
+};
+
+// seconds of wall clock between two frames that count as the page having been suspended
+const RESUME_GAP: u64 = 5;
+// seconds the relays get to reconnect before the boards are compared
+const RESYNC_DELAY: f64 = 2.0;
+
+static PAGE_RESUMED: AtomicBool = AtomicBool::new(false);
+
+// mobile browsers suspend a hidden tab, its websockets drop, no frame runs and the bevy
+// clock stands still while the opponent keeps playing
+#[derive(Event)]
+struct Resumed {
+    away: u64,
+}
+
+pub struct ResumePlugin;
+
+impl Plugin for ResumePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Resumed>()
+            .add_systems(First, detect_resume)
+            .add_systems(Update, recover);
+    }
+}
+
+// the page reports becoming visible again, a frame starting long after the previous one
+// catches the suspensions it doesn't report and the desktop build waking from sleep
+fn detect_resume(
+    mut resumed: EventWriter<Resumed>,
+    mut last_frame: Local<u64>,
+    mut last_resume: Local<u64>,
+) {
+    let now = Timestamp::now().as_u64();
+    let away = now.saturating_sub(*last_frame);
+    let jumped = *last_frame != 0 && away >= RESUME_GAP;
+    *last_frame = now;
+
+    if !PAGE_RESUMED.swap(false, Ordering::SeqCst) && !jumped {
+        return;
+    }
+    // the visibility change and the time jump of the same resume
+    if now.saturating_sub(*last_resume) < RESUME_GAP {
+        return;
+    }
+    *last_resume = now;
+
+    resumed.send(Resumed { away });
+}
+
+// reconnects the relays, restarts the ack timers and once the relays had time to come
+// back, compares boards with the opponent. spectators and opponents on older clients
+// are asked for the move list instead
+#[allow(clippy::too_many_arguments)]
+fn recover(
+    mut resumed: EventReader<Resumed>,
+    state: Res<State<AppState>>,
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    mut pending_ack: ResMut<PendingAck>,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    mut resync_at: Local<Option<f64>>,
+) {
+    if let Some(resumed) = resumed.read().last() {
+        info!("page resumed after {} seconds, recovering", resumed.away);
+        nostr_plugin::relays_changed();
+        pending_ack.restart(time.elapsed_seconds_f64());
+        *resync_at = Some(real_time.elapsed_seconds_f64() + RESYNC_DELAY);
+    }
+
+    match *resync_at {
+        Some(at) if real_time.elapsed_seconds_f64() >= at => *resync_at = None,
+        _ => return,
+    }
+
+    if *state.get() != AppState::InGame || game_state.transport.is_none() {
+        return;
+    }
+
+    let player = game_state.player_type == 1 || game_state.player_type == 2;
+    let hashes = game_state.p2_client.as_ref().is_some_and(|client_info| {
+        client_info
+            .features
+            .iter()
+            .any(|feature| feature == "resume")
+    });
+
+    if player && hashes {
+        let hash = state_hash(board.moves.iter().map(sync_column));
+        game_state.clone().send_state_hash(board.moves.len(), hash);
+    } else {
+        game_state.clone().send_sync_request();
+    }
+}
+
+// the page became visible again
+#[wasm_bindgen]
+pub fn page_resumed() {
+    PAGE_RESUMED.store(true, Ordering::SeqCst);
+}
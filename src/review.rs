@@ -0,0 +1,223 @@
+use crate::{
+    engine,
+    game::{Game, Outcome},
+    opening_book,
+};
+
+// a move this many points below the engine's best is an inaccuracy, closer it's as good
+const INACCURACY_LOSS: i32 = 3;
+// the score that fills the evaluation bar to either end, short of a forced win
+const EVALUATION_RANGE: f32 = 20.0;
+// the bar of a book position won with perfect play, the win is still far off
+const BOOK_WIN: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Best,
+    Inaccuracy,
+    // a forced win missed or a forced loss walked into
+    Blunder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewedMove {
+    pub column: usize,
+    pub player: usize,
+    pub verdict: Verdict,
+    // the engine's pick in the position before the move
+    pub best: usize,
+    // how good the position after the move is for red, from 0 lost to 1 won
+    pub evaluation: f32,
+    // the forced win the player had instead, the best column and the moves after it
+    pub missed_win: Vec<usize>,
+}
+
+// the engine's verdict on every move of a classic game, one move per step so the app can
+// spread the review over frames
+pub struct GameReviewer {
+    columns: Vec<usize>,
+    game: Game,
+    pub moves: Vec<ReviewedMove>,
+}
+
+impl GameReviewer {
+    pub fn new(columns: Vec<usize>) -> Self {
+        Self {
+            moves: Vec::with_capacity(columns.len()),
+            columns,
+            game: Game::new(),
+        }
+    }
+
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    pub fn done(&self) -> bool {
+        self.moves.len() == self.columns.len()
+    }
+
+    // reviews the next move, false once the game is through or a move doesn't replay
+    pub fn step(&mut self) -> bool {
+        let Some(&column) = self.columns.get(self.moves.len()) else {
+            return false;
+        };
+        match review_move(&mut self.game, column) {
+            Some(reviewed) => {
+                self.moves.push(reviewed);
+                true
+            }
+            None => {
+                self.columns.truncate(self.moves.len());
+                false
+            }
+        }
+    }
+}
+
+// judges the column against the engine's pick and plays it. the engine looks as far as it
+// does in practice games, a win it can't see isn't counted as missed
+fn review_move(game: &mut Game, column: usize) -> Option<ReviewedMove> {
+    let player = game.player_turn();
+    let scored = engine::scored_moves(game.moves(), player);
+    let (best, best_score) = scored.iter().copied().fold(
+        None,
+        |best: Option<(usize, i32)>, (column, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((column, score)),
+        },
+    )?;
+    let (_, played_score) = scored.iter().copied().find(|(c, _)| *c == column)?;
+
+    let verdict = if (engine::is_win(best_score) && !engine::is_win(played_score))
+        || (engine::is_loss(played_score) && !engine::is_loss(best_score))
+    {
+        Verdict::Blunder
+    } else if best_score - played_score >= INACCURACY_LOSS {
+        Verdict::Inaccuracy
+    } else {
+        Verdict::Best
+    };
+    let missed_win = if verdict == Verdict::Blunder && engine::is_win(best_score) {
+        winning_line(game, best)
+    } else {
+        Vec::new()
+    };
+
+    game.apply_move(column).ok()?;
+    Some(ReviewedMove {
+        column,
+        player,
+        verdict,
+        best,
+        evaluation: evaluation(game),
+        missed_win,
+    })
+}
+
+// red's side of the bar for one position, the engine's search goes one column per step
+// so the app can spread it over frames
+pub struct PositionEvaluator {
+    player: usize,
+    search: Option<engine::ColumnSearch>,
+    share: Option<f32>,
+}
+
+impl PositionEvaluator {
+    pub fn new(game: &Game) -> Self {
+        let player = game.player_turn();
+        let share = settled(game);
+        Self {
+            player,
+            search: share
+                .is_none()
+                .then(|| engine::ColumnSearch::new(game.moves(), player)),
+            share,
+        }
+    }
+
+    // searches the next column, false once the share is known
+    pub fn step(&mut self) -> bool {
+        if self.share.is_some() {
+            return false;
+        }
+        let Some(search) = self.search.as_mut() else {
+            return false;
+        };
+        if search.step() {
+            return true;
+        }
+        self.share = Some(searched(search.best_score(), self.player));
+        false
+    }
+
+    pub fn share(&self) -> Option<f32> {
+        self.share
+    }
+}
+
+// red's side of the bar at once
+pub fn evaluation(game: &Game) -> f32 {
+    let mut evaluator = PositionEvaluator::new(game);
+    while evaluator.step() {}
+    evaluator.share.unwrap_or(0.5)
+}
+
+// a finished game and a position of the opening book need no search
+fn settled(game: &Game) -> Option<f32> {
+    match game.outcome() {
+        Outcome::Win(1) => return Some(1.0),
+        Outcome::Win(_) => return Some(0.0),
+        Outcome::Draw => return Some(0.5),
+        Outcome::Ongoing => {}
+    }
+
+    let columns: Vec<usize> = game.moves().iter().map(|m| m.column).collect();
+    let score = opening_book::score(&columns)?;
+    let red_score = if game.player_turn() == 1 {
+        score
+    } else {
+        -score
+    };
+    Some(match red_score.signum() {
+        1 => BOOK_WIN,
+        -1 => 1.0 - BOOK_WIN,
+        _ => 0.5,
+    })
+}
+
+// the search's best score for the side to move as red's side of the bar
+fn searched(score: Option<i32>, player: usize) -> f32 {
+    let Some(score) = score else {
+        return 0.5;
+    };
+    let red_score = if player == 1 { score } else { -score };
+    if engine::is_win(red_score) {
+        1.0
+    } else if engine::is_loss(red_score) {
+        0.0
+    } else {
+        (0.5 + red_score as f32 / (2.0 * EVALUATION_RANGE)).clamp(0.05, 0.95)
+    }
+}
+
+// the engine's best moves for both sides from the winning column until the game is won
+fn winning_line(game: &Game, best: usize) -> Vec<usize> {
+    let mut line = game.clone();
+    let mut columns = vec![best];
+    if line.apply_move(best).is_err() {
+        return columns;
+    }
+
+    while line.outcome() == Outcome::Ongoing && columns.len() < engine::SEARCH_DEPTH {
+        let Some(column) = engine::best_move(line.moves(), line.player_turn()) else {
+            break;
+        };
+        if line.apply_move(column).is_err() {
+            break;
+        }
+        columns.push(column);
+    }
+
+    columns
+}
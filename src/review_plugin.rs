@@ -0,0 +1,502 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis_plugin::analysis_closed,
+    components::{ReviewAction, ReviewBar, ReviewCell, ReviewText, ReviewUi},
+    game::Game,
+    locale_plugin::{Locale, Phrase},
+    resources::Board,
+    review::{GameReviewer, ReviewedMove, Verdict},
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+const CELL_SIZE: f32 = 36.0;
+const BAR_WIDTH: f32 = 7.0 * (CELL_SIZE + 4.0);
+
+static REVIEW_CALLED: AtomicBool = AtomicBool::new(false);
+
+// the finished game's moves as the engine judged them, and the one shown
+#[derive(Resource)]
+struct Review {
+    reviewer: GameReviewer,
+    // the moves played on the board shown
+    step: usize,
+    // the winning line missed by the move shown instead of the move
+    show_line: bool,
+}
+
+impl Review {
+    fn reviewed(&self) -> Option<&ReviewedMove> {
+        self.step
+            .checked_sub(1)
+            .and_then(|index| self.reviewer.moves.get(index))
+    }
+}
+
+// once a classic game is over the engine goes through its moves, a move per frame, and
+// marks each as best, an inaccuracy or a blunder. the review steps through the game with
+// an evaluation bar and shows the winning line a blunder missed
+pub struct ReviewPlugin;
+
+impl Plugin for ReviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                show_review_button,
+                open_review.run_if(analysis_closed),
+                review_moves,
+                review_input,
+                update_review,
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(OnExit(AppState::InGame), close_review);
+    }
+}
+
+// the engine only knows the classic rules
+fn review_allowed(board: &Board) -> bool {
+    board.classic() && !board.moves.is_empty() && (board.winner.is_some() || board.draw)
+}
+
+fn show_review_button(board: Res<Board>) {
+    if review_allowed(&board) {
+        showReviewButton();
+    } else {
+        hideReviewButton();
+    }
+}
+
+fn open_review(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    board: Res<Board>,
+    keys: Res<Input<KeyCode>>,
+    ui: Query<(), With<ReviewUi>>,
+) {
+    let called = REVIEW_CALLED.swap(false, Ordering::SeqCst) || keys.just_pressed(KeyCode::R);
+    if !called || !ui.is_empty() || !review_allowed(&board) {
+        return;
+    }
+
+    let game = match Game::from_board(&board) {
+        Ok(game) => game,
+        Err(e) => {
+            error!("Error replaying board for review: {}", e);
+            return;
+        }
+    };
+    let columns: Vec<usize> = game.moves().iter().map(|m| m.column).collect();
+
+    info!("reviewing {} moves", columns.len());
+
+    commands.insert_resource(Review {
+        reviewer: GameReviewer::new(columns),
+        step: 0,
+        show_line: false,
+    });
+
+    spawn_review_ui(&mut commands, theme.ui, &locale);
+}
+
+fn spawn_review_ui(commands: &mut Commands, ui: UiColors, locale: &Locale) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: ui.panel.with_a(0.97).into(),
+            ..default()
+        })
+        .insert(ReviewUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(ReviewText);
+
+            // red's share grows from the left, yellow's is what's left of it
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(14.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.97, 0.8, 0.08).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(50.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.87, 0.19, 0.19).into(),
+                        ..default()
+                    })
+                    .insert(ReviewBar);
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        padding: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.35, 0.6).into(),
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for c in 0..7 {
+                        grid.spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::ColumnReverse,
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|column| {
+                            for r in 0..6 {
+                                column
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(CELL_SIZE),
+                                            height: Val::Px(CELL_SIZE),
+                                            border: UiRect::all(Val::Px(3.0)),
+                                            ..default()
+                                        },
+                                        background_color: ui.background.into(),
+                                        ..default()
+                                    })
+                                    .insert(ReviewCell { c, r });
+                            }
+                        });
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|buttons| {
+                    for (action, label) in [
+                        (ReviewAction::First, "|<"),
+                        (ReviewAction::Back, "<"),
+                        (ReviewAction::Forward, ">"),
+                        (ReviewAction::Last, ">|"),
+                        (ReviewAction::Line, locale.get(Phrase::ShowMissedWin)),
+                        (ReviewAction::Close, locale.get(Phrase::Close)),
+                    ] {
+                        buttons
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                    ..default()
+                                },
+                                background_color: ui.button.into(),
+                                ..default()
+                            })
+                            .insert(action)
+                            .with_children(|button| {
+                                button.spawn(TextBundle::from_section(
+                                    label,
+                                    TextStyle {
+                                        color: ui.text,
+                                        font_size: 14.0,
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+// a move per frame, the deep enough search of a whole game would stall the page
+fn review_moves(review: Option<ResMut<Review>>) {
+    let Some(mut review) = review else {
+        return;
+    };
+    if review.reviewer.done() {
+        return;
+    }
+
+    let review = review.as_mut();
+    review.reviewer.step();
+    // the game is shown from its end once it's all reviewed
+    if review.reviewer.done() {
+        review.step = review.reviewer.moves.len();
+    }
+}
+
+fn review_input(
+    commands: Commands,
+    review: Option<ResMut<Review>>,
+    keys: Res<Input<KeyCode>>,
+    actions: Query<(&Interaction, &ReviewAction), Changed<Interaction>>,
+    ui: Query<Entity, With<ReviewUi>>,
+) {
+    let Some(mut review) = review else {
+        return;
+    };
+
+    let pressed = actions
+        .iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, action)| *action);
+    let keyed = [
+        (KeyCode::Left, ReviewAction::Back),
+        (KeyCode::Right, ReviewAction::Forward),
+        (KeyCode::Home, ReviewAction::First),
+        (KeyCode::End, ReviewAction::Last),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, action)| action);
+
+    let reviewed = review.reviewer.moves.len();
+    for action in pressed.chain(keyed).collect::<Vec<_>>() {
+        let step = match action {
+            ReviewAction::First => 0,
+            ReviewAction::Back => review.step.saturating_sub(1),
+            ReviewAction::Forward => (review.step + 1).min(reviewed),
+            ReviewAction::Last => reviewed,
+            ReviewAction::Line => {
+                review.show_line = !review.show_line;
+                continue;
+            }
+            ReviewAction::Close => {
+                close_review(commands, ui);
+                return;
+            }
+        };
+        if step != review.step {
+            review.step = step;
+            review.show_line = false;
+        }
+    }
+}
+
+fn close_review(mut commands: Commands, ui: Query<Entity, With<ReviewUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Review>();
+}
+
+fn verdict_color(verdict: Verdict) -> Color {
+    match verdict {
+        Verdict::Best => Color::rgb(0.1, 0.7, 0.25),
+        Verdict::Inaccuracy => Color::rgb(1.0, 0.55, 0.0),
+        Verdict::Blunder => Color::rgb(0.55, 0.0, 0.6),
+    }
+}
+
+fn side(locale: &Locale, player: usize) -> &'static str {
+    locale.get(if player == 1 {
+        Phrase::Red
+    } else {
+        Phrase::Yellow
+    })
+}
+
+// the blunders and inaccuracies of each side in the moves reviewed
+fn summary(locale: &Locale, moves: &[ReviewedMove]) -> String {
+    [1, 2]
+        .map(|player| {
+            let count = |verdict| {
+                moves
+                    .iter()
+                    .filter(|m| m.player == player && m.verdict == verdict)
+                    .count()
+            };
+            locale.fill(
+                Phrase::ReviewSummary,
+                &[
+                    side(locale, player),
+                    &count(Verdict::Blunder).to_string(),
+                    &count(Verdict::Inaccuracy).to_string(),
+                ],
+            )
+        })
+        .join(" - ")
+}
+
+fn update_review(
+    review: Option<Res<Review>>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut cells: Query<(&ReviewCell, &mut BackgroundColor, &mut BorderColor)>,
+    mut bar: Query<&mut Style, With<ReviewBar>>,
+    mut text: Query<&mut Text, With<ReviewText>>,
+) {
+    let Some(review) = review else {
+        return;
+    };
+    if !review.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    let reviewed = review.reviewed();
+    let line = reviewed
+        .filter(|_| review.show_line)
+        .map(|m| m.missed_win.as_slice())
+        .filter(|line| !line.is_empty());
+
+    // the missed win replaces the move, its coins are faded
+    let columns = review.reviewer.columns();
+    let mut game = Game::new();
+    let played = match line {
+        Some(_) => review.step - 1,
+        None => review.step,
+    };
+    for &column in columns[..played].iter().chain(line.unwrap_or_default()) {
+        if game.apply_move(column).is_err() {
+            break;
+        }
+    }
+    let coins = game.coins();
+    let last = match line {
+        None => coins.last(),
+        Some(_) => None,
+    };
+
+    for (cell, mut color, mut border) in cells.iter_mut() {
+        let coin = coins
+            .iter()
+            .position(|coin| coin.column == cell.c && coin.row == cell.r);
+        let faded = coin.is_some_and(|index| index >= played);
+
+        let player_color = match coin.map(|index| coins[index].player) {
+            Some(1) => Color::rgb(0.87, 0.19, 0.19),
+            Some(_) => Color::rgb(0.97, 0.8, 0.08),
+            None => theme.ui.background,
+        };
+        *color = if faded {
+            player_color.with_a(0.45)
+        } else {
+            player_color
+        }
+        .into();
+
+        let is_last = last.is_some_and(|coin| coin.column == cell.c && coin.row == cell.r);
+        *border = match reviewed {
+            Some(reviewed) if is_last => verdict_color(reviewed.verdict),
+            _ => Color::NONE,
+        }
+        .into();
+    }
+
+    let evaluation = reviewed.map_or(0.5, |m| m.evaluation);
+    for mut style in bar.iter_mut() {
+        style.width = Val::Percent(evaluation * 100.0);
+    }
+
+    let total = columns.len();
+    let status = if !review.reviewer.done() {
+        locale.fill(
+            Phrase::ReviewingMove,
+            &[
+                &(review.reviewer.moves.len() + 1).to_string(),
+                &total.to_string(),
+            ],
+        )
+    } else {
+        let position = match reviewed {
+            None => locale.get(Phrase::StartOfGame).to_string(),
+            Some(m) if line.is_some() => locale.fill(
+                Phrase::WinMissed,
+                &[
+                    side(&locale, m.player),
+                    &(m.best + 1).to_string(),
+                    &(m.column + 1).to_string(),
+                ],
+            ),
+            Some(m) if m.verdict == Verdict::Best => locale.fill(
+                Phrase::PlaysBest,
+                &[side(&locale, m.player), &(m.column + 1).to_string()],
+            ),
+            Some(m) => locale.fill(
+                Phrase::PlaysVerdict,
+                &[
+                    side(&locale, m.player),
+                    &(m.column + 1).to_string(),
+                    locale.get(if m.verdict == Verdict::Blunder {
+                        Phrase::Blunder
+                    } else {
+                        Phrase::Inaccuracy
+                    }),
+                    &(m.best + 1).to_string(),
+                ],
+            ),
+        };
+        format!(
+            "{}\n{}",
+            position,
+            summary(&locale, &review.reviewer.moves[..review.step])
+        )
+    };
+    let value = locale.fill(
+        Phrase::GameReview,
+        &[&review.step.to_string(), &total.to_string(), &status],
+    );
+
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn showReviewButton();
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn hideReviewButton();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(non_snake_case)]
+mod page {
+    pub fn showReviewButton() {}
+    pub fn hideReviewButton() {}
+}
+#[cfg(not(target_arch = "wasm32"))]
+use page::*;
+
+#[wasm_bindgen]
+pub fn review_game() {
+    REVIEW_CALLED.store(true, Ordering::SeqCst);
+}
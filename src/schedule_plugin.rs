@@ -0,0 +1,378 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{
+    secp256k1::XOnlyPublicKey, serde_json, Event as NostrEvent, EventBuilder, Filter, FromBech32,
+    Kind, Tag, TagKind, Timestamp,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::ScheduledGameButton,
+    gui_plugin::new_game_id,
+    leaderboard_plugin::short_pubkey,
+    locale_plugin::{Locale, Phrase},
+    messages::{CALENDAR_KIND, GAME_TAG_PREFIX},
+    nostr_plugin::{fetch_events, game_id_from_tags, publish_events},
+    platform,
+    resources::GameState,
+    storage::{self, ScheduledGame},
+    AppState,
+};
+
+// the reminder comes this long before the start
+const REMINDER_LEAD: u64 = 5 * 60;
+// a scheduled game stays listed this long after its start
+const SCHEDULE_LISTED: u64 = 60 * 60;
+// how often the start times are looked at
+const SCHEDULE_CHECK_INTERVAL: f64 = 15.0;
+
+static SCHEDULE_GAME: Mutex<Option<(String, u64)>> = Mutex::new(None);
+static START_GAME: Mutex<Option<String>> = Mutex::new(None);
+static SCHEDULE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+// the calendar events fetched when the menu opens and the reminders already given, the
+// game ids with whether the start was announced too
+#[derive(Resource, Default)]
+struct Schedule {
+    read: Option<Receiver<Vec<NostrEvent>>>,
+    reminded: Vec<(String, bool)>,
+    checked_at: Option<f64>,
+}
+
+// what the page's list gets
+#[derive(Serialize)]
+struct PageScheduledGame {
+    game_id: String,
+    title: String,
+    opponent: String,
+    starts_at: u64,
+    due: bool,
+}
+
+// two players agree on a time for a game. the game is published as a nip-52 calendar event
+// both are tagged in, the menu lists the upcoming ones, reminds the players shortly before
+// and opens the game once its time has come
+pub struct SchedulePlugin;
+
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Schedule>()
+            .add_systems(OnEnter(AppState::Menu), fetch_scheduled_games)
+            .add_systems(Update, (receive_scheduled_games, remind_scheduled_games))
+            .add_systems(
+                Update,
+                (
+                    check_schedule_system,
+                    start_scheduled_game_system,
+                    open_scheduled_game,
+                )
+                    .run_if(in_state(AppState::Menu)),
+            );
+    }
+}
+
+fn fetch_scheduled_games(game_state: Res<GameState>, mut schedule: ResMut<Schedule>) {
+    if schedule.read.is_some() {
+        return;
+    }
+
+    let (mut events_tx, events_rx) = futures::channel::mpsc::channel::<Vec<NostrEvent>>(1);
+    schedule.read = Some(events_rx);
+
+    let own = game_state.nostr_keys.public_key();
+    platform::spawn(async move {
+        let filter = Filter::new()
+            .kind(Kind::ParameterizedReplaceable(CALENDAR_KIND))
+            .pubkey(own)
+            .since(Timestamp::now() - std::time::Duration::from_secs(SCHEDULE_LISTED));
+        let events = fetch_events(vec![filter], Vec::new()).await;
+
+        if let Err(e) = events_tx.try_send(events) {
+            error!("Error sending scheduled games: {}", e);
+        }
+    });
+}
+
+fn receive_scheduled_games(game_state: Res<GameState>, mut schedule: ResMut<Schedule>) {
+    let Some(ref mut events_rx) = schedule.read else {
+        return;
+    };
+    let Ok(Some(events)) = events_rx.try_next() else {
+        return;
+    };
+    schedule.read = None;
+
+    let own = game_state.nostr_keys.public_key();
+    let fetched = events
+        .iter()
+        .filter_map(|event| scheduled_game(event, &own));
+    let games: Vec<ScheduledGame> = storage::scheduled_games()
+        .into_iter()
+        .chain(fetched)
+        .filter(|game| listed(game, Timestamp::now().as_u64()))
+        .collect();
+    info!("{} scheduled games", games.len());
+    storage::save_scheduled_games(games);
+    SCHEDULE_CHANGED.store(true, Ordering::SeqCst);
+}
+
+fn listed(game: &ScheduledGame, now: u64) -> bool {
+    game.starts_at + SCHEDULE_LISTED > now
+}
+
+fn tag_value(event: &NostrEvent, name: &str) -> Option<String> {
+    event.tags.iter().find_map(|tag| {
+        let values = tag.as_vec();
+        (values.first().map(String::as_str) == Some(name))
+            .then(|| values.get(1).cloned())
+            .flatten()
+    })
+}
+
+// a calendar event of a game with the player, the opponent is the other participant
+fn scheduled_game(event: &NostrEvent, own: &XOnlyPublicKey) -> Option<ScheduledGame> {
+    let game_id = game_id_from_tags(event)?;
+    let starts_at = tag_value(event, "start")?.parse().ok()?;
+    let participants: Vec<XOnlyPublicKey> = event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag {
+            Tag::PubKey(pubkey, _) => Some(*pubkey),
+            _ => None,
+        })
+        .collect();
+    if !participants.contains(own) {
+        return None;
+    }
+
+    let opponent = if event.pubkey == *own {
+        participants.into_iter().find(|pubkey| pubkey != own)?
+    } else {
+        event.pubkey
+    };
+
+    Some(ScheduledGame {
+        game_id,
+        title: tag_value(event, "title").unwrap_or_else(|| "Connect 4".to_string()),
+        opponent: short_pubkey(&opponent),
+        starts_at,
+    })
+}
+
+fn calendar_event(
+    game: &ScheduledGame,
+    own: XOnlyPublicKey,
+    opponent: XOnlyPublicKey,
+) -> EventBuilder {
+    let custom =
+        |name: &str, value: String| Tag::Generic(TagKind::Custom(name.to_string()), vec![value]);
+    let content = format!(
+        "A game of Connect 4, open {}{} to play",
+        platform::origin(),
+        game.game_id
+    );
+
+    EventBuilder::new(
+        Kind::ParameterizedReplaceable(CALENDAR_KIND),
+        content,
+        [
+            Tag::Identifier(game.game_id.clone()),
+            custom("title", game.title.clone()),
+            custom("start", game.starts_at.to_string()),
+            Tag::PubKey(own, None),
+            Tag::PubKey(opponent, None),
+            Tag::Hashtag(format!("{}{}", GAME_TAG_PREFIX, game.game_id)),
+        ],
+    )
+}
+
+fn check_schedule_system(game_state: Res<GameState>, locale: Res<Locale>) {
+    let Some((npub, starts_at)) = SCHEDULE_GAME.lock().ok().and_then(|mut game| game.take()) else {
+        return;
+    };
+
+    let opponent = match XOnlyPublicKey::from_bech32(npub.trim()) {
+        Ok(opponent) => opponent,
+        Err(e) => {
+            platform::alert(&locale.fill(Phrase::NotAnNpub, &[&e.to_string()]));
+            return;
+        }
+    };
+    let own = game_state.nostr_keys.public_key();
+    if opponent == own {
+        platform::alert(locale.get(Phrase::ScheduleWithOther));
+        return;
+    }
+
+    let name = game_state
+        .local_ln_address
+        .clone()
+        .unwrap_or_else(|| short_pubkey(&own));
+    let game = ScheduledGame {
+        game_id: format!("/{}", new_game_id()),
+        title: format!("Connect 4: {} vs {}", name, short_pubkey(&opponent)),
+        opponent: short_pubkey(&opponent),
+        starts_at,
+    };
+    info!("scheduling {:?}", game);
+
+    match calendar_event(&game, own, opponent).to_event(&game_state.nostr_keys) {
+        Ok(event) => platform::spawn(publish_events(vec![event])),
+        Err(e) => error!("Error signing calendar event: {}", e),
+    }
+
+    let mut games = storage::scheduled_games();
+    games.push(game);
+    storage::save_scheduled_games(games);
+    SCHEDULE_CHANGED.store(true, Ordering::SeqCst);
+}
+
+// the page shows a reminder shortly before the start and again once it's time
+fn remind_scheduled_games(time: Res<Time>, locale: Res<Locale>, mut schedule: ResMut<Schedule>) {
+    let elapsed = time.elapsed_seconds_f64();
+    let changed = SCHEDULE_CHANGED.swap(false, Ordering::SeqCst);
+    if !changed
+        && schedule
+            .checked_at
+            .is_some_and(|checked_at| elapsed - checked_at < SCHEDULE_CHECK_INTERVAL)
+    {
+        return;
+    }
+    schedule.checked_at = Some(elapsed);
+
+    let now = Timestamp::now().as_u64();
+    let mut due_changed = changed;
+    for game in upcoming_games() {
+        let due = game.starts_at <= now;
+        let soon = game.starts_at <= now + REMINDER_LEAD;
+        let reminded = schedule
+            .reminded
+            .iter()
+            .find(|(game_id, _)| *game_id == game.game_id)
+            .map(|(_, started)| *started);
+
+        let message = match reminded {
+            None | Some(false) if due => locale.fill(Phrase::GameStartsNow, &[&game.opponent]),
+            None if soon => locale.fill(
+                Phrase::GameStartsIn,
+                &[
+                    &game.opponent,
+                    &(game.starts_at - now).div_ceil(60).to_string(),
+                ],
+            ),
+            _ => continue,
+        };
+
+        info!("{}", message);
+        platform::dispatch_event("gameReminder", Some(&message));
+        schedule
+            .reminded
+            .retain(|(game_id, _)| *game_id != game.game_id);
+        schedule.reminded.push((game.game_id.clone(), due));
+        due_changed |= due;
+    }
+
+    if due_changed {
+        platform::dispatch_event("scheduledGames", Some(&scheduled_games()));
+    }
+}
+
+fn start_scheduled_game_system(mut next_state: ResMut<NextState<AppState>>) {
+    let Some(game_id) = START_GAME.lock().ok().and_then(|mut game| game.take()) else {
+        return;
+    };
+    start_game(&game_id, &mut next_state);
+}
+
+// whoever opens the game first creates it, the other one joins
+fn start_game(game_id: &str, next_state: &mut NextState<AppState>) {
+    let due = storage::scheduled_games()
+        .iter()
+        .any(|game| game.game_id == game_id && game.starts_at <= Timestamp::now().as_u64());
+    if !due {
+        info!("scheduled game {} hasn't started yet", game_id);
+        return;
+    }
+
+    info!("starting scheduled game {}", game_id);
+    platform::push_url(&format!("{}{}", platform::origin(), game_id));
+    next_state.set(AppState::InGame);
+}
+
+fn open_scheduled_game(
+    games: Query<(&Interaction, &ScheduledGameButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some((_, ScheduledGameButton(game_id))) = games
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+
+    start_game(game_id, &mut next_state);
+}
+
+// how long until the start, or that it's time
+pub fn scheduled_label(game: &ScheduledGame, locale: &Locale) -> String {
+    let now = Timestamp::now().as_u64();
+    if game.starts_at <= now {
+        return locale.fill(Phrase::StartNowVs, &[&game.opponent]);
+    }
+    let minutes = (game.starts_at - now).div_ceil(60);
+    locale.fill(
+        Phrase::ScheduledVs,
+        &[
+            &game.opponent,
+            &(minutes / 60).to_string(),
+            &(minutes % 60).to_string(),
+        ],
+    )
+}
+
+// the scheduled games that didn't start long ago, soonest first
+pub fn upcoming_games() -> Vec<ScheduledGame> {
+    let now = Timestamp::now().as_u64();
+    storage::scheduled_games()
+        .into_iter()
+        .filter(|game| listed(game, now))
+        .collect()
+}
+
+#[wasm_bindgen(js_name = scheduledGames)]
+pub fn scheduled_games() -> String {
+    let now = Timestamp::now().as_u64();
+    let games: Vec<PageScheduledGame> = upcoming_games()
+        .into_iter()
+        .map(|game| PageScheduledGame {
+            due: game.starts_at <= now,
+            game_id: game.game_id,
+            title: game.title,
+            opponent: game.opponent,
+            starts_at: game.starts_at,
+        })
+        .collect();
+
+    serde_json::to_string(&games).unwrap()
+}
+
+// an npub and the unix time the game starts
+#[wasm_bindgen]
+pub fn schedule_game(npub: String, starts_at: u32) {
+    if let Ok(mut game) = SCHEDULE_GAME.lock() {
+        *game = Some((npub, starts_at as u64));
+    }
+}
+
+#[wasm_bindgen]
+pub fn start_scheduled_game(game_id: String) {
+    if let Ok(mut game) = START_GAME.lock() {
+        *game = Some(game_id);
+    }
+}
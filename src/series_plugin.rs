@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    components::{SeriesText, SeriesUi},
+    events::GameSwitched,
+    games_plugin::switch_to_game,
+    gui_plugin::new_fortune_seed,
+    locale_plugin::{Locale, Phrase},
+    resources::{Board, GameState, Series},
+    storage,
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+const NEXT_GAME_DELAY: f64 = 5.0;
+
+#[derive(Resource, Default)]
+struct NextSeriesGame {
+    game_id: Option<String>,
+    starts_at: f64,
+}
+
+pub struct SeriesPlugin;
+
+impl Plugin for SeriesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NextSeriesGame>()
+            .add_systems(
+                Update,
+                (advance_series, next_game_countdown)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(Update, leave_series_game.run_if(on_event::<GameSwitched>()));
+    }
+}
+
+// the score is counted from the series saved for this game, so replaying a finished game
+// counts it the same way again
+#[allow(clippy::too_many_arguments)]
+fn advance_series(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    board: Res<Board>,
+    mut game_state: ResMut<GameState>,
+    mut next_game: ResMut<NextSeriesGame>,
+    time: Res<Time>,
+    mut scored: Local<HashSet<String>>,
+) {
+    if scored.contains(&game_state.game_id)
+        || board.in_progress
+        || (board.winner.is_none() && !board.draw)
+        || (game_state.player_type != 1 && game_state.player_type != 2)
+    {
+        return;
+    }
+
+    let Some(mut series) = game_state.series.clone() else {
+        return;
+    };
+
+    scored.insert(game_state.game_id.clone());
+
+    match board.winner {
+        Some(winner) if winner == game_state.player_type => series.local_wins += 1,
+        Some(_) => series.opponent_wins += 1,
+        None => {}
+    }
+
+    info!(
+        "series game {} of {} over, {} - {}",
+        series.game, series.length, series.local_wins, series.opponent_wins
+    );
+
+    if series.finished() {
+        spawn_series_result(&mut commands, theme.ui, &locale, &series);
+    } else {
+        let next_game_id = series.next_game_id();
+
+        // player 2 opens the next game, so colors swap every game
+        if game_state.player_type == 2 && Series::load(&next_game_id).is_none() {
+            info!("opening next series game {}", next_game_id);
+
+            // a columns of fortune series keeps the mode with a fresh seed
+            let fortune_seed = board.fortune_seed.map(|_| new_fortune_seed());
+            if let Some(seed) = fortune_seed {
+                storage::save_fortune_seed(&next_game_id, seed);
+            }
+            if board.pop_out {
+                storage::save_pop_out(&next_game_id);
+            }
+            if board.power_up {
+                storage::save_power_up(&next_game_id);
+            }
+            if board.cylinder {
+                storage::save_cylinder(&next_game_id);
+            }
+            if board.gravity_shift {
+                storage::save_gravity_shift(&next_game_id);
+            }
+            if game_state.correspondence {
+                storage::save_correspondence(&next_game_id);
+            }
+
+            game_state
+                .clone()
+                .send_new_game(&next_game_id, series.length, fortune_seed, &board);
+        }
+
+        let mut next_series = series.clone();
+        next_series.game += 1;
+        next_series.save(&next_game_id);
+
+        next_game.game_id = Some(next_game_id);
+        next_game.starts_at = time.elapsed_seconds_f64() + NEXT_GAME_DELAY;
+
+        commands
+            .spawn(
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 20.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..default()
+                }),
+            )
+            .insert(SeriesText);
+    }
+
+    game_state.series = Some(series);
+}
+
+fn next_game_countdown(
+    mut next_game: ResMut<NextSeriesGame>,
+    time: Res<Time>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<SeriesText>>,
+) {
+    let Some(ref game_id) = next_game.game_id else {
+        return;
+    };
+
+    let remaining = next_game.starts_at - time.elapsed_seconds_f64();
+    if remaining <= 0.0 {
+        switch_to_game(game_id.clone());
+        next_game.game_id = None;
+        return;
+    }
+
+    let value = locale.fill(Phrase::NextGameIn, &[&remaining.ceil().to_string()]);
+    for mut txt in text.iter_mut() {
+        if txt.sections[0].value != value {
+            txt.sections[0].value = value.clone();
+        }
+    }
+}
+
+// the score and the countdown belong to the game switched away from
+#[allow(clippy::type_complexity)]
+fn leave_series_game(
+    mut commands: Commands,
+    mut next_game: ResMut<NextSeriesGame>,
+    series_ui: Query<Entity, Or<(With<SeriesText>, With<SeriesUi>)>>,
+) {
+    next_game.game_id = None;
+    for entity in series_ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_series_result(commands: &mut Commands, ui: UiColors, locale: &Locale, series: &Series) {
+    let result = if series.local_wins > series.opponent_wins {
+        Phrase::WonSeries
+    } else if series.local_wins < series.opponent_wins {
+        Phrase::LostSeries
+    } else {
+        Phrase::SeriesDrawn
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: ui.background.with_a(0.85).into(),
+            ..default()
+        })
+        .insert(SeriesUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                locale.fill(
+                    Phrase::SeriesOver,
+                    &[
+                        locale.get(result),
+                        &series.local_wins.to_string(),
+                        &series.opponent_wins.to_string(),
+                    ],
+                ),
+                TextStyle {
+                    color: ui.text,
+                    font_size: 28.0,
+                    ..default()
+                },
+            ));
+        });
+}
@@ -0,0 +1,260 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bevy::prelude::*;
+use nostr_sdk::serde_json;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    engine::Difficulty,
+    locale_plugin::Language,
+    messages::{MessagePack, Skin},
+    platform, storage,
+    tween_plugin::Easing,
+};
+
+const SETTINGS_KEY: &str = "settings";
+const DEFAULT_RELAYS: [&str; 2] = ["wss://relay.highlighter.com", "wss://nostr.lu.ke"];
+
+pub const ANIMATION_SPEEDS: [f32; 3] = [0.5, 1.0, 2.0];
+// seconds per move offered to new games, none for no clock
+pub const TIME_CONTROLS: [Option<u64>; 5] = [None, Some(15), Some(30), Some(60), Some(300)];
+
+static SETTINGS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+// tints applied over the red and yellow coins, or patterns that tell the sides apart
+// without their color, see the theme plugin
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CoinTheme {
+    #[default]
+    Classic,
+    Soft,
+    Dark,
+    Symbols,
+    Stripes,
+}
+
+impl CoinTheme {
+    pub const ALL: [CoinTheme; 5] = [
+        CoinTheme::Classic,
+        CoinTheme::Soft,
+        CoinTheme::Dark,
+        CoinTheme::Symbols,
+        CoinTheme::Stripes,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CoinTheme::Classic => "Classic",
+            CoinTheme::Soft => "Soft",
+            CoinTheme::Dark => "Dark",
+            CoinTheme::Symbols => "Symbols",
+            CoinTheme::Stripes => "Stripes",
+        }
+    }
+
+    pub fn tint(&self, color: Color) -> Color {
+        let shade = match self {
+            CoinTheme::Soft => 0.85,
+            CoinTheme::Dark => 0.6,
+            _ => 1.0,
+        };
+        Color::rgba(
+            color.r() * shade,
+            color.g() * shade,
+            color.b() * shade,
+            color.a(),
+        )
+    }
+}
+
+// the colors of the screens around the board, see the theme plugin
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    pub const ALL: [Appearance; 2] = [Appearance::Light, Appearance::Dark];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Appearance::Light => "Light",
+            Appearance::Dark => "Dark",
+        }
+    }
+}
+
+// whether the game gives up effects, animation and resolution for a steady frame rate.
+// auto switches to reduced when the frame rate stays low, see the performance plugin
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Performance {
+    #[default]
+    Auto,
+    Full,
+    Reduced,
+}
+
+impl Performance {
+    pub const ALL: [Performance; 3] = [Performance::Auto, Performance::Full, Performance::Reduced];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Performance::Auto => "Auto",
+            Performance::Full => "Full",
+            Performance::Reduced => "Reduced",
+        }
+    }
+}
+
+// the player's preferences, saved together as json under `settings` in local storage.
+// the html page edits the same json and calls `settingsChanged` to reload it
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub sound: bool,
+    pub animation_speed: f32,
+    pub drop_style: Easing,
+    // a faint coin where a drop in the hovered column would land
+    pub drop_preview: bool,
+    pub coin_theme: CoinTheme,
+    // the look of the coins and the board, a locked skin shows as classic, see the
+    // cosmetics plugin
+    pub skin: Skin,
+    pub colorblind: bool,
+    pub appearance: Appearance,
+    // a hex color like #3366ff the board takes instead of the skin's, none for the skin's
+    pub accent: Option<String>,
+    // none follows the browser's language, see the locale plugin
+    pub language: Option<Language>,
+    // the messages offered after a win, and the ones shown after a loss in a friendly pack
+    pub message_pack: MessagePack,
+    // dark empty slots, the red and yellow coins stand out against them
+    pub high_contrast: bool,
+    pub relays: Vec<String>,
+    // the name shown to other players, a lightning address to receive zaps
+    pub username: Option<String>,
+    pub time_control: Option<u64>,
+    // games are played under a pseudonym, without the lightning address, the game duration
+    // or a direct connection
+    pub privacy: bool,
+    // a browser notification when the opponent moved while the tab is in the background
+    pub turn_notifications: bool,
+    pub performance: Performance,
+    // how the engine plays in games against it
+    pub difficulty: Difficulty,
+    // the engine's evaluation above the board, where the analysis board is allowed
+    pub evaluation_bar: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            sound: true,
+            animation_speed: 1.0,
+            drop_style: Easing::Bounce,
+            drop_preview: true,
+            coin_theme: CoinTheme::Classic,
+            skin: Skin::Classic,
+            colorblind: false,
+            appearance: Appearance::Light,
+            accent: None,
+            language: None,
+            message_pack: MessagePack::Friendly,
+            high_contrast: false,
+            relays: DEFAULT_RELAYS
+                .iter()
+                .map(|relay| relay.to_string())
+                .collect(),
+            username: None,
+            time_control: None,
+            privacy: false,
+            turn_notifications: false,
+            performance: Performance::Auto,
+            difficulty: Difficulty::Deep,
+            evaluation_bar: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        match storage::get_item(SETTINGS_KEY) {
+            Some(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                error!("Error reading settings: {}", e);
+                Settings::default()
+            }),
+            None => Settings::migrate(),
+        }
+    }
+
+    // the preferences kept under their own keys before they were saved together
+    fn migrate() -> Self {
+        let mut settings = Settings::default();
+
+        if let Some(muted) = storage::get_item("muted") {
+            settings.sound = muted != "true";
+        }
+        if let Some(relays) = storage::get_item("Relays") {
+            let relays: Vec<String> = relays
+                .split(',')
+                .map(|relay| relay.trim().to_string())
+                .filter(|relay| !relay.is_empty())
+                .collect();
+            if !relays.is_empty() {
+                settings.relays = relays;
+            }
+        }
+        settings.username = storage::get_item("username");
+
+        settings
+    }
+
+    // an accent that isn't a valid hex color is left out
+    pub fn accent_color(&self) -> Option<Color> {
+        self.accent
+            .as_deref()
+            .and_then(|accent| Color::hex(accent.trim()).ok())
+    }
+
+    pub fn save(&self) {
+        storage::set_item(SETTINGS_KEY, &serde_json::to_string(self).unwrap());
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        if platform::safe_mode() {
+            info!("safe mode, starting with the default settings and relays");
+        }
+        let settings = Settings::load();
+        settings.save();
+
+        app.insert_resource(settings)
+            .add_systems(Update, reload_settings);
+    }
+}
+
+fn reload_settings(mut settings: ResMut<Settings>) {
+    if !SETTINGS_CHANGED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    let loaded = Settings::load();
+    if *settings != loaded {
+        *settings = loaded;
+    }
+}
+
+// the page saved new settings
+#[wasm_bindgen]
+pub fn settings_changed() {
+    SETTINGS_CHANGED.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::prelude::*;
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{StatsText, StatsUi},
+    leaderboard_plugin::{fetch_game_results, short_pubkey},
+    locale_plugin::{Locale, Phrase},
+    messages::GameResult,
+    nostr_plugin::game_duration,
+    platform,
+    resources::{Board, GameState},
+    storage,
+    theme_plugin::Theme,
+    AppState,
+};
+
+const STATS_SIZE: usize = 10;
+
+static STATS_CALLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Resource, Default)]
+struct StatsResults {
+    read: Option<Receiver<Vec<GameResult>>>,
+}
+
+// the best wins of a player, only wins by four in a row count
+struct SpeedEntry {
+    pubkey: XOnlyPublicKey,
+    name: Option<String>,
+    fewest_moves: Option<usize>,
+    fastest: Option<u64>,
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StatsResults>()
+            .add_systems(Update, check_stats_system.run_if(in_state(AppState::Menu)))
+            .add_systems(
+                Update,
+                record_personal_best.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnEnter(AppState::Stats), (setup_stats, fetch_stats))
+            .add_systems(Update, update_stats.run_if(in_state(AppState::Stats)))
+            .add_systems(OnExit(AppState::Stats), cleanup_stats);
+    }
+}
+
+fn check_stats_system(mut next_state: ResMut<NextState<AppState>>) {
+    if STATS_CALLED.swap(false, Ordering::SeqCst) {
+        next_state.set(AppState::Stats);
+    }
+}
+
+// the number of moves the winner needed, player 1 moves first
+fn winner_moves(winner: usize, moves: usize) -> usize {
+    if winner == 1 {
+        moves.div_ceil(2)
+    } else {
+        moves / 2
+    }
+}
+
+// practice games, resignations and variant games don't count
+fn record_personal_best(
+    board: Res<Board>,
+    game_state: Res<GameState>,
+    mut recorded: Local<HashSet<String>>,
+) {
+    if recorded.contains(&game_state.game_id)
+        || board.in_progress
+        || board.winner != Some(game_state.player_type)
+        || board.resigned.is_some()
+        || game_state.ghost.is_some()
+        || game_state.teams.is_some()
+        || storage::hinted(&game_state.game_id)
+        || !board.classic()
+    {
+        return;
+    }
+
+    recorded.insert(game_state.game_id.clone());
+
+    let mut bests = storage::load_personal_bests();
+    let previous = bests;
+
+    let moves = winner_moves(game_state.player_type, board.moves.len());
+    bests.fewest_moves = Some(bests.fewest_moves.map_or(moves, |fewest| fewest.min(moves)));
+
+    if let Some(duration) = game_duration() {
+        bests.fastest = Some(
+            bests
+                .fastest
+                .map_or(duration, |fastest| fastest.min(duration)),
+        );
+    }
+
+    if bests != previous {
+        info!("new personal best {:?}", bests);
+        storage::save_personal_bests(&bests);
+    }
+}
+
+fn setup_stats(mut commands: Commands, theme: Res<Theme>, locale: Res<Locale>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::top(Val::Px(60.0)),
+                ..default()
+            },
+            background_color: theme.ui.background.into(),
+            ..default()
+        })
+        .insert(StatsUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    locale.get(Phrase::StatsLoading),
+                    TextStyle {
+                        color: theme.ui.text,
+                        font_size: 18.0,
+                        ..default()
+                    },
+                ))
+                .insert(StatsText);
+        });
+}
+
+fn fetch_stats(mut results: ResMut<StatsResults>) {
+    let (mut results_tx, results_rx) = futures::channel::mpsc::channel::<Vec<GameResult>>(1);
+    results.read = Some(results_rx);
+
+    platform::spawn(async move {
+        let game_results = fetch_game_results().await;
+
+        if let Err(e) = results_tx.try_send(game_results) {
+            error!("Error sending results: {}", e);
+        }
+    });
+}
+
+// both players publish a result, the one with a duration is kept
+fn speed_entries(results: &[GameResult]) -> Vec<SpeedEntry> {
+    let mut games: HashMap<&str, &GameResult> = HashMap::new();
+    for result in results {
+        let game = games.entry(&result.game_id).or_insert(result);
+        if game.duration.is_none() {
+            *game = result;
+        }
+    }
+
+    let mut entries: HashMap<XOnlyPublicKey, SpeedEntry> = HashMap::new();
+    for result in games.into_values() {
+        let Some(winner) = result.winner else {
+            continue;
+        };
+
+        if result.resigned || result.p1_pubkey == result.p2_pubkey {
+            continue;
+        }
+
+        let (pubkey, name) = if winner == 1 {
+            (result.p1_pubkey, &result.p1_name)
+        } else {
+            (result.p2_pubkey, &result.p2_name)
+        };
+
+        let entry = entries.entry(pubkey).or_insert(SpeedEntry {
+            pubkey,
+            name: None,
+            fewest_moves: None,
+            fastest: None,
+        });
+
+        if name.is_some() {
+            entry.name = name.clone();
+        }
+
+        let moves = winner_moves(winner, result.moves);
+        entry.fewest_moves = Some(entry.fewest_moves.map_or(moves, |fewest| fewest.min(moves)));
+
+        if let Some(duration) = result.duration {
+            entry.fastest = Some(
+                entry
+                    .fastest
+                    .map_or(duration, |fastest| fastest.min(duration)),
+            );
+        }
+    }
+
+    entries.into_values().collect()
+}
+
+fn format_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+fn update_stats(
+    mut results: ResMut<StatsResults>,
+    locale: Res<Locale>,
+    mut text: Query<&mut Text, With<StatsText>>,
+) {
+    let Some(ref mut results_rx) = results.read else {
+        return;
+    };
+
+    let Ok(Some(game_results)) = results_rx.try_next() else {
+        return;
+    };
+
+    let mut entries = speed_entries(&game_results);
+    let name = |entry: &SpeedEntry| match entry.name {
+        Some(ref name) => name.clone(),
+        None => short_pubkey(&entry.pubkey),
+    };
+
+    let mut value = format!("{}\n\n", locale.get(Phrase::SpeedStats));
+
+    let bests = storage::load_personal_bests();
+    if let Some(fewest_moves) = bests.fewest_moves {
+        value.push_str(&locale.fill(Phrase::YourBest, &[&fewest_moves.to_string()]));
+        if let Some(fastest) = bests.fastest {
+            value.push_str(&locale.fill(Phrase::FastestWin, &[&format_duration(fastest)]));
+        }
+        value.push_str("\n\n");
+    }
+
+    value.push_str(&format!("{}\n", locale.get(Phrase::FewestMovesToWin)));
+    entries.sort_by_key(|entry| entry.fewest_moves);
+    for (rank, entry) in entries.iter().take(STATS_SIZE).enumerate() {
+        if let Some(fewest_moves) = entry.fewest_moves {
+            value.push_str(&locale.fill(
+                Phrase::MovesEntry,
+                &[
+                    &(rank + 1).to_string(),
+                    &name(entry),
+                    &fewest_moves.to_string(),
+                ],
+            ));
+            value.push('\n');
+        }
+    }
+
+    value.push_str(&format!("\n{}\n", locale.get(Phrase::FastestWins)));
+    entries.retain(|entry| entry.fastest.is_some());
+    entries.sort_by_key(|entry| entry.fastest);
+    if entries.is_empty() {
+        value.push_str(&format!("{}\n", locale.get(Phrase::NoTimedGames)));
+    }
+    for (rank, entry) in entries.iter().take(STATS_SIZE).enumerate() {
+        if let Some(fastest) = entry.fastest {
+            value.push_str(&format!(
+                "{}. {}  {}\n",
+                rank + 1,
+                name(entry),
+                format_duration(fastest)
+            ));
+        }
+    }
+
+    for mut txt in text.iter_mut() {
+        txt.sections[0].value = value.clone();
+    }
+}
+
+fn cleanup_stats(mut commands: Commands, ui: Query<Entity, With<StatsUi>>) {
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[wasm_bindgen]
+pub fn show_stats() {
+    STATS_CALLED.store(true, Ordering::SeqCst);
+}
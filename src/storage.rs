@@ -0,0 +1,310 @@
+use bevy::log::error;
+use nostr_sdk::serde_json;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{invite::Invitation, messages::Teams, platform, puzzle::Puzzle};
+
+const ARCHIVE_KEY: &str = "archive";
+const ONGOING_KEY: &str = "ongoing_games";
+const PUZZLES_KEY: &str = "puzzles";
+const MINED_KEY: &str = "puzzles_mined";
+const SOLVED_KEY: &str = "puzzles_solved";
+// the address of the club opened last, the page reads the same key
+const CLUB_KEY: &str = "club";
+// the address of the league opened last, the page reads the same key
+const LEAGUE_KEY: &str = "league";
+const SCHEDULED_KEY: &str = "scheduled_games";
+// the oldest puzzles make room for new ones
+const MAX_PUZZLES: usize = 500;
+
+pub fn get_item(key: &str) -> Option<String> {
+    platform::get_item(key)
+}
+
+pub fn set_item(key: &str, value: &str) {
+    if let Err(e) = platform::set_item(key, value) {
+        error!("Error saving {} to local storage: {}", key, e);
+    }
+}
+
+pub fn archived_games() -> Vec<String> {
+    get_item(ARCHIVE_KEY)
+        .and_then(|archive| serde_json::from_str(&archive).ok())
+        .unwrap_or_default()
+}
+
+pub fn archive_game(game_id: &str, thumbnail: &str) {
+    set_item(&format!("thumbnail{}", game_id), thumbnail);
+
+    let mut archive = archived_games();
+    if !archive.iter().any(|archived| archived == game_id) {
+        archive.push(game_id.to_string());
+        set_item(ARCHIVE_KEY, &serde_json::to_string(&archive).unwrap());
+    }
+}
+
+// moves of an archived game, replayed by the ghost opponent in practice games
+#[derive(Serialize, Deserialize)]
+pub struct ArchivedMoves {
+    pub opponent: String,
+    pub opponent_player: usize,
+    pub columns: Vec<usize>,
+}
+
+pub fn archive_moves(game_id: &str, moves: &ArchivedMoves) {
+    set_item(
+        &format!("moves{}", game_id),
+        &serde_json::to_string(moves).unwrap(),
+    );
+}
+
+pub fn load_moves(game_id: &str) -> Option<ArchivedMoves> {
+    let moves = get_item(&format!("moves{}", game_id))?;
+    serde_json::from_str(&moves).ok()
+}
+
+pub fn load_thumbnail(game_id: &str) -> Option<String> {
+    get_item(&format!("thumbnail{}", game_id))
+}
+
+// your own fastest wins by four in a row, kept for the stats page and achievements
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct PersonalBests {
+    pub fewest_moves: Option<usize>,
+    pub fastest: Option<u64>,
+}
+
+pub fn load_personal_bests() -> PersonalBests {
+    get_item("personal_bests")
+        .and_then(|bests| serde_json::from_str(&bests).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_personal_bests(bests: &PersonalBests) {
+    set_item("personal_bests", &serde_json::to_string(bests).unwrap());
+}
+
+// columns of fortune games keep their seed under the game id, so a reload spins the
+// same modifiers
+pub fn save_fortune_seed(game_id: &str, seed: u64) {
+    set_item(&format!("fortune{}", game_id), &seed.to_string());
+}
+
+pub fn load_fortune_seed(game_id: &str) -> Option<u64> {
+    get_item(&format!("fortune{}", game_id))?.parse().ok()
+}
+
+// pop out, power up, cylinder and gravity shift games are marked under the game id, a
+// reload keeps the rules
+pub fn save_pop_out(game_id: &str) {
+    set_item(&format!("popout{}", game_id), "true");
+}
+
+pub fn load_pop_out(game_id: &str) -> bool {
+    get_item(&format!("popout{}", game_id)).is_some()
+}
+
+pub fn save_power_up(game_id: &str) {
+    set_item(&format!("powerup{}", game_id), "true");
+}
+
+pub fn load_power_up(game_id: &str) -> bool {
+    get_item(&format!("powerup{}", game_id)).is_some()
+}
+
+pub fn save_cylinder(game_id: &str) {
+    set_item(&format!("cylinder{}", game_id), "true");
+}
+
+pub fn load_cylinder(game_id: &str) -> bool {
+    get_item(&format!("cylinder{}", game_id)).is_some()
+}
+
+pub fn save_gravity_shift(game_id: &str) {
+    set_item(&format!("gravity{}", game_id), "true");
+}
+
+pub fn load_gravity_shift(game_id: &str) -> bool {
+    get_item(&format!("gravity{}", game_id)).is_some()
+}
+
+// correspondence games are marked under the game id like the variants
+pub fn save_correspondence(game_id: &str) {
+    set_item(&format!("correspondence{}", game_id), "true");
+}
+
+pub fn load_correspondence(game_id: &str) -> bool {
+    get_item(&format!("correspondence{}", game_id)).is_some()
+}
+
+// the seats of a team game as player 1 last sent them, the creator's seat alone until then
+pub fn save_teams(game_id: &str, teams: &Teams) {
+    set_item(
+        &format!("teams{}", game_id),
+        &serde_json::to_string(teams).unwrap(),
+    );
+}
+
+pub fn load_teams(game_id: &str) -> Option<Teams> {
+    get_item(&format!("teams{}", game_id)).and_then(|teams| serde_json::from_str(&teams).ok())
+}
+
+// a game the player asked the engine for a hint in, its result isn't published
+pub fn mark_hinted(game_id: &str) {
+    set_item(&format!("hinted{}", game_id), "true");
+}
+
+pub fn hinted(game_id: &str) -> bool {
+    get_item(&format!("hinted{}", game_id)).is_some()
+}
+
+// the hashtag of the club whose lobby created the game
+pub fn save_club(game_id: &str, club_tag: &str) {
+    set_item(&format!("club{}", game_id), club_tag);
+}
+
+pub fn load_club(game_id: &str) -> Option<String> {
+    get_item(&format!("club{}", game_id))
+}
+
+pub fn current_club() -> Option<String> {
+    get_item(CLUB_KEY)
+}
+
+pub fn set_current_club(address: &str) {
+    set_item(CLUB_KEY, address);
+}
+
+pub fn current_league() -> Option<String> {
+    get_item(LEAGUE_KEY)
+}
+
+pub fn set_current_league(address: &str) {
+    set_item(LEAGUE_KEY, address);
+}
+
+// the correspondence games in progress, listed in the menu to switch between them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OngoingGame {
+    pub game_id: String,
+    pub opponent: Option<String>,
+    pub your_turn: bool,
+    pub last_move_at: Option<u64>,
+}
+
+pub fn ongoing_games() -> Vec<OngoingGame> {
+    get_item(ONGOING_KEY)
+        .and_then(|games| serde_json::from_str(&games).ok())
+        .unwrap_or_default()
+}
+
+// replaces the game's entry, most recently played first
+pub fn save_ongoing_game(game: OngoingGame) {
+    let mut games = ongoing_games();
+    games.retain(|ongoing| ongoing.game_id != game.game_id);
+    games.insert(0, game);
+    set_item(ONGOING_KEY, &serde_json::to_string(&games).unwrap());
+}
+
+pub fn remove_ongoing_game(game_id: &str) {
+    let mut games = ongoing_games();
+    let count = games.len();
+    games.retain(|ongoing| ongoing.game_id != game_id);
+    if games.len() != count {
+        set_item(ONGOING_KEY, &serde_json::to_string(&games).unwrap());
+    }
+}
+
+// a game the player scheduled with an opponent or was invited to, see the schedule plugin
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduledGame {
+    pub game_id: String,
+    pub title: String,
+    pub opponent: String,
+    pub starts_at: u64,
+}
+
+pub fn scheduled_games() -> Vec<ScheduledGame> {
+    load_list(SCHEDULED_KEY)
+}
+
+// the soonest first, a game scheduled again replaces its entry
+pub fn save_scheduled_games(games: Vec<ScheduledGame>) {
+    let mut saved: Vec<ScheduledGame> = Vec::new();
+    for game in games {
+        saved.retain(|scheduled| scheduled.game_id != game.game_id);
+        saved.push(game);
+    }
+    saved.sort_by_key(|game| game.starts_at);
+    set_item(SCHEDULED_KEY, &serde_json::to_string(&saved).unwrap());
+}
+
+// the invitation is kept as its query string
+pub fn save_invitation(game_id: &str, invitation: &Invitation) {
+    set_item(&format!("invite{}", game_id), &invitation.query());
+}
+
+pub fn load_invitation(game_id: &str) -> Option<Invitation> {
+    Invitation::parse(&get_item(&format!("invite{}", game_id))?)
+        .ok()
+        .flatten()
+}
+
+fn load_list<T: DeserializeOwned>(key: &str) -> Vec<T> {
+    get_item(key)
+        .and_then(|list| serde_json::from_str(&list).ok())
+        .unwrap_or_default()
+}
+
+// the local puzzle pool, mined from the archived games
+pub fn puzzle_pool() -> Vec<Puzzle> {
+    load_list(PUZZLES_KEY)
+}
+
+// adds the puzzles not in the pool yet, returns how many
+pub fn add_puzzles(puzzles: Vec<Puzzle>) -> usize {
+    let mut pool = puzzle_pool();
+    let mut keys: Vec<String> = pool.iter().map(Puzzle::key).collect();
+    let count = pool.len();
+    for puzzle in puzzles {
+        let key = puzzle.key();
+        if !keys.contains(&key) {
+            keys.push(key);
+            pool.push(puzzle);
+        }
+    }
+
+    let added = pool.len() - count;
+    if added > 0 {
+        let excess = pool.len().saturating_sub(MAX_PUZZLES);
+        pool.drain(..excess);
+        set_item(PUZZLES_KEY, &serde_json::to_string(&pool).unwrap());
+    }
+    added
+}
+
+// archived games already searched for puzzles
+pub fn mined_games() -> Vec<String> {
+    load_list(MINED_KEY)
+}
+
+pub fn mark_mined(game_id: &str) {
+    let mut mined = mined_games();
+    if !mined.iter().any(|mined| mined == game_id) {
+        mined.push(game_id.to_string());
+        set_item(MINED_KEY, &serde_json::to_string(&mined).unwrap());
+    }
+}
+
+// the keys of the puzzles solved
+pub fn solved_puzzles() -> Vec<String> {
+    load_list(SOLVED_KEY)
+}
+
+pub fn mark_solved(key: &str) {
+    let mut solved = solved_puzzles();
+    if !solved.iter().any(|solved| solved == key) {
+        solved.push(key.to_string());
+        set_item(SOLVED_KEY, &serde_json::to_string(&solved).unwrap());
+    }
+}
@@ -0,0 +1,560 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use nostr_sdk::secp256k1::XOnlyPublicKey;
+
+use crate::{
+    analysis_plugin::Analysis,
+    components::{AnalysisColumn, StudyAction, StudyText, StudyUi},
+    events::{GameSwitched, StudyReceived},
+    leaderboard_plugin::short_pubkey,
+    locale_plugin::{Locale, Phrase},
+    messages::{NetworkMessage, Study},
+    platform,
+    resources::{Board, GameState},
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+// the host sends the study again this often, so viewers opening the analysis board later
+// find it
+const STUDY_HEARTBEAT: f64 = 10.0;
+// followers leave a study whose host went quiet
+const STUDY_TIMEOUT: f64 = 30.0;
+const CURSOR_THROTTLE: f64 = 0.3;
+
+// the study shared on this game's analysis board, hosted or followed, and one seen on
+// the game that isn't followed yet
+#[derive(Resource, Default)]
+pub struct StudySession {
+    study: Option<Study>,
+    offered: Option<Study>,
+    followers: HashSet<XOnlyPublicKey>,
+    // viewers asking for control, with their names
+    requests: Vec<(XOnlyPublicKey, Option<String>)>,
+    // the column the one in control aims at
+    cursor: Option<usize>,
+    // when the host was last heard of
+    heard_at: f64,
+}
+
+impl StudySession {
+    // outside of a study everyone moves on their own board
+    pub fn controls(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.study
+            .as_ref()
+            .is_none_or(|study| study.controller == *pubkey)
+    }
+
+    fn hosts(&self, pubkey: &XOnlyPublicKey) -> bool {
+        self.study
+            .as_ref()
+            .is_some_and(|study| study.host == *pubkey)
+    }
+
+    fn leave(&mut self) {
+        *self = StudySession::default();
+    }
+}
+
+// the analysis board of a game can be shared as a study: the host branches into lines
+// of their own and everyone on the game following it sees the board, the column aimed at
+// and the notes live. a viewer asks for control and the host hands it over or takes it
+// back. every study message is ephemeral, a study ends with its host
+pub struct StudyPlugin;
+
+impl Plugin for StudyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StudySession>()
+            .add_systems(
+                Update,
+                (
+                    receive_study,
+                    study_actions,
+                    broadcast_study,
+                    send_cursor,
+                    update_study_ui,
+                    show_cursor,
+                    drop_study.run_if(on_event::<GameSwitched>()),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnExit(AppState::InGame), leave_study);
+    }
+}
+
+fn name(name: &Option<String>, pubkey: &XOnlyPublicKey) -> String {
+    name.clone().unwrap_or_else(|| short_pubkey(pubkey))
+}
+
+fn receive_study(
+    mut received: EventReader<StudyReceived>,
+    mut session: ResMut<StudySession>,
+    mut analysis: Option<ResMut<Analysis>>,
+    game_state: Res<GameState>,
+    time: Res<Time>,
+) {
+    let own = game_state.nostr_keys.public_key();
+
+    for StudyReceived { from, message } in received.read() {
+        match message {
+            NetworkMessage::Study(study) => {
+                // only the host and whoever has control change a study
+                if *from != study.host && *from != study.controller {
+                    continue;
+                }
+
+                let following = session
+                    .study
+                    .as_ref()
+                    .is_some_and(|current| current.host == study.host);
+                if !following {
+                    if session.study.is_none() {
+                        session.offered = Some(study.clone());
+                    }
+                    continue;
+                }
+
+                if *from == study.host {
+                    session.heard_at = time.elapsed_seconds_f64();
+                }
+                if study.controller != own {
+                    if let Some(analysis) = analysis.as_mut() {
+                        if analysis.line() != study.line {
+                            analysis.show_line(&study.line);
+                        }
+                    }
+                }
+                if study.controller != *from {
+                    session.cursor = None;
+                }
+                session
+                    .requests
+                    .retain(|(pubkey, _)| *pubkey != study.controller);
+                session.study = Some(study.clone());
+            }
+            NetworkMessage::StudyCursor(column)
+                if session
+                    .study
+                    .as_ref()
+                    .is_some_and(|study| study.controller == *from) =>
+            {
+                session.cursor = *column;
+            }
+            NetworkMessage::StudyFollow => {
+                let Some(study) = session.study.clone() else {
+                    continue;
+                };
+                if study.host == own || study.controller == own {
+                    session.followers.insert(*from);
+                }
+                if study.controller == own {
+                    game_state.clone().send_study(study);
+                }
+            }
+            NetworkMessage::StudyRequest(requester)
+                if session.hosts(&own)
+                    && !session.requests.iter().any(|(pubkey, _)| pubkey == from) =>
+            {
+                info!("{} asks for control of the study", name(requester, from));
+                session.requests.push((*from, requester.clone()));
+            }
+            // the host ends the study, a follower leaves it
+            NetworkMessage::StudyEnd => {
+                if session
+                    .study
+                    .as_ref()
+                    .is_some_and(|study| study.host == *from)
+                {
+                    info!("the study ended");
+                    session.leave();
+                } else if session
+                    .offered
+                    .as_ref()
+                    .is_some_and(|study| study.host == *from)
+                {
+                    session.offered = None;
+                }
+                session.followers.remove(from);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn study_actions(
+    actions: Query<(&Interaction, &StudyAction), Changed<Interaction>>,
+    mut session: ResMut<StudySession>,
+    mut analysis: Option<ResMut<Analysis>>,
+    game_state: Res<GameState>,
+    time: Res<Time>,
+    locale: Res<Locale>,
+) {
+    let own = game_state.nostr_keys.public_key();
+
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            StudyAction::Share => {
+                let Some(analysis) = analysis.as_ref() else {
+                    continue;
+                };
+                info!("sharing the analysis board as a study");
+                let study = Study {
+                    host: own,
+                    controller: own,
+                    controller_name: game_state.local_ln_address.clone(),
+                    line: analysis.line(),
+                    notes: Vec::new(),
+                };
+                session.leave();
+                session.study = Some(study.clone());
+                game_state.clone().send_study(study);
+            }
+            StudyAction::Follow => {
+                let Some(study) = session.offered.take() else {
+                    continue;
+                };
+                if let Some(analysis) = analysis.as_mut() {
+                    analysis.show_line(&study.line);
+                }
+                session.study = Some(study);
+                session.heard_at = time.elapsed_seconds_f64();
+                game_state.clone().send_study_follow();
+            }
+            StudyAction::RequestControl => game_state.clone().send_study_request(),
+            StudyAction::HandOver(pubkey) => {
+                let requester = session
+                    .requests
+                    .iter()
+                    .find(|(requester, _)| requester == pubkey)
+                    .map(|(_, name)| name.clone());
+                let Some(study) = session.study.as_mut() else {
+                    continue;
+                };
+                study.controller = *pubkey;
+                study.controller_name = requester.flatten();
+                let study = study.clone();
+                session
+                    .requests
+                    .retain(|(requester, _)| requester != pubkey);
+                session.cursor = None;
+                game_state.clone().send_study(study);
+            }
+            StudyAction::TakeControl => {
+                let Some(study) = session.study.as_mut() else {
+                    continue;
+                };
+                study.controller = own;
+                study.controller_name = game_state.local_ln_address.clone();
+                // the board goes on from where the last one in control left it
+                let study = study.clone();
+                session.cursor = None;
+                game_state.clone().send_study(study);
+            }
+            StudyAction::Note => {
+                let Some(study) = session.study.as_mut() else {
+                    continue;
+                };
+                let Some(text) = platform::prompt(locale.get(Phrase::NotePrompt), study.note())
+                else {
+                    continue;
+                };
+                study.annotate(&text);
+                let study = study.clone();
+                game_state.clone().send_study(study);
+            }
+            StudyAction::Leave => {
+                game_state.clone().send_study_end();
+                session.leave();
+            }
+        }
+    }
+}
+
+// the one in control sends the line whenever it changes, the host also every few seconds
+// so followers know the study goes on
+fn broadcast_study(
+    mut session: ResMut<StudySession>,
+    analysis: Option<Res<Analysis>>,
+    game_state: Res<GameState>,
+    time: Res<Time>,
+    mut sent_at: Local<f64>,
+) {
+    let own = game_state.nostr_keys.public_key();
+    let hosting = session.hosts(&own);
+    if session.study.is_none() {
+        return;
+    }
+
+    // closing the analysis board leaves the study, the host's ends it
+    let Some(analysis) = analysis else {
+        game_state.clone().send_study_end();
+        session.leave();
+        return;
+    };
+
+    let now = time.elapsed_seconds_f64();
+    if !hosting && now - session.heard_at > STUDY_TIMEOUT {
+        info!("the study's host went quiet, leaving it");
+        session.leave();
+        return;
+    }
+    let Some(study) = session.study.as_mut() else {
+        return;
+    };
+    let changed = study.controller == own && analysis.line() != study.line;
+    if changed {
+        study.line = analysis.line();
+    }
+    if changed || (hosting && now - *sent_at >= STUDY_HEARTBEAT) {
+        *sent_at = now;
+        game_state.clone().send_study(study.clone());
+    }
+}
+
+fn send_cursor(
+    session: Res<StudySession>,
+    game_state: Res<GameState>,
+    columns: Query<(&Interaction, &AnalysisColumn)>,
+    time: Res<Time>,
+    mut sent: Local<(Option<usize>, f64)>,
+) {
+    let own = game_state.nostr_keys.public_key();
+    if session.study.is_none() || !session.controls(&own) {
+        return;
+    }
+
+    let cursor = columns
+        .iter()
+        .find(|(interaction, _)| **interaction != Interaction::None)
+        .map(|(_, column)| column.0);
+    let now = time.elapsed_seconds_f64();
+    if cursor == sent.0 || now - sent.1 < CURSOR_THROTTLE {
+        return;
+    }
+
+    *sent = (cursor, now);
+    game_state.clone().send_study_cursor(cursor);
+}
+
+// the column the one in control aims at is tinted on the followers' boards
+fn show_cursor(
+    session: Res<StudySession>,
+    game_state: Res<GameState>,
+    mut columns: Query<(&AnalysisColumn, &mut BackgroundColor)>,
+) {
+    let cursor = if session.controls(&game_state.nostr_keys.public_key()) {
+        None
+    } else {
+        session.cursor
+    };
+
+    for (column, mut color) in columns.iter_mut() {
+        let tint = if Some(column.0) == cursor {
+            Color::rgba(1.0, 1.0, 1.0, 0.35)
+        } else {
+            Color::NONE
+        };
+        if color.0 != tint {
+            color.0 = tint;
+        }
+    }
+}
+
+fn study_buttons(
+    session: &StudySession,
+    own: &XOnlyPublicKey,
+    shareable: bool,
+    locale: &Locale,
+) -> Vec<(StudyAction, String)> {
+    let label = |phrase| locale.get(phrase).to_string();
+    let Some(study) = session.study.as_ref() else {
+        let mut buttons = Vec::new();
+        if let Some(offered) = session.offered.as_ref() {
+            let host = name(&offered.controller_name, &offered.controller);
+            buttons.push((
+                StudyAction::Follow,
+                locale.fill(Phrase::FollowStudy, &[&host]),
+            ));
+        }
+        if shareable {
+            buttons.push((StudyAction::Share, label(Phrase::ShareAsStudy)));
+        }
+        return buttons;
+    };
+
+    let mut buttons = Vec::new();
+    if study.controller == *own {
+        buttons.push((StudyAction::Note, label(Phrase::Note)));
+    } else if study.host == *own {
+        buttons.push((StudyAction::TakeControl, label(Phrase::TakeControl)));
+    } else {
+        buttons.push((StudyAction::RequestControl, label(Phrase::RequestControl)));
+    }
+    if study.host == *own {
+        for (pubkey, requester) in session.requests.iter() {
+            buttons.push((
+                StudyAction::HandOver(*pubkey),
+                locale.fill(Phrase::HandOverTo, &[&name(requester, pubkey)]),
+            ));
+        }
+        buttons.push((StudyAction::Leave, label(Phrase::EndStudy)));
+    } else {
+        buttons.push((StudyAction::Leave, label(Phrase::LeaveStudy)));
+    }
+    buttons
+}
+
+fn study_status(session: &StudySession, own: &XOnlyPublicKey, locale: &Locale) -> String {
+    let Some(study) = session.study.as_ref() else {
+        return String::new();
+    };
+
+    let mut status = if study.controller == *own && study.host == *own {
+        match session.followers.len() {
+            1 => locale.get(Phrase::StudyLeadOne).to_string(),
+            following => locale.fill(Phrase::StudyLead, &[&following.to_string()]),
+        }
+    } else if study.controller == *own {
+        locale.get(Phrase::StudyYouControl).to_string()
+    } else {
+        locale.fill(
+            Phrase::StudyHasControl,
+            &[&name(&study.controller_name, &study.controller)],
+        )
+    };
+    if let Some(note) = study.note() {
+        status = locale.fill(Phrase::StudyNote, &[&status, note]);
+    }
+    status
+}
+
+// a row under the analysis board, rebuilt when its buttons change
+#[allow(clippy::too_many_arguments)]
+fn update_study_ui(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    session: Res<StudySession>,
+    analysis: Option<Res<Analysis>>,
+    game_state: Res<GameState>,
+    board: Res<Board>,
+    ui: Query<Entity, With<StudyUi>>,
+    mut text: Query<&mut Text, With<StudyText>>,
+    mut shown: Local<Vec<(StudyAction, String)>>,
+) {
+    if analysis.is_none() {
+        for entity in ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        shown.clear();
+        return;
+    }
+
+    let own = game_state.nostr_keys.public_key();
+    // studies go over the game's relays and share classic lines only
+    let shareable = game_state.transport.is_some() && board.classic();
+    let buttons = study_buttons(&session, &own, shareable, &locale);
+    let status = study_status(&session, &own, &locale);
+
+    if let Ok(mut txt) = text.get_single_mut() {
+        if txt.sections[0].value != status {
+            txt.sections[0].value = status.clone();
+        }
+    }
+    if !ui.is_empty() && *shown == buttons {
+        return;
+    }
+
+    for entity in ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_study_ui(&mut commands, theme.ui, &buttons, &status);
+    *shown = buttons;
+}
+
+fn spawn_study_ui(
+    commands: &mut Commands,
+    ui: UiColors,
+    buttons: &[(StudyAction, String)],
+    status: &str,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(StudyUi)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    status,
+                    TextStyle {
+                        color: ui.muted,
+                        font_size: 14.0,
+                        ..default()
+                    },
+                ))
+                .insert(StudyText);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (action, label) in buttons {
+                        row.spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: ui.button.into(),
+                            ..default()
+                        })
+                        .insert(action.clone())
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(
+                                label.clone(),
+                                TextStyle {
+                                    color: ui.text,
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                            ));
+                        });
+                    }
+                });
+        });
+}
+
+// back to the menu, still on the study's game
+fn leave_study(mut session: ResMut<StudySession>, game_state: Res<GameState>) {
+    if session.study.is_some() {
+        game_state.clone().send_study_end();
+    }
+    session.leave();
+}
+
+// the game on the board is another one already, its followers find out by the timeout
+fn drop_study(mut session: ResMut<StudySession>) {
+    session.leave();
+}
@@ -0,0 +1,511 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    components::{BoardFrame, CoinMove, CoinSlot},
+    cosmetics_plugin::Cosmetics,
+    messages::Skin,
+    performance_plugin::ReducedMode,
+    resources::GameState,
+    settings_plugin::{Appearance, CoinTheme, Settings},
+};
+
+// coins are drawn at three times their size on the board, sharp on dense screens too
+const COIN_PIXELS: usize = 120;
+// where the darker rim of a drawn coin starts, as a share of its radius
+const RIM: f32 = 0.88;
+// how much lighter the top of a glossy coin is than its bottom
+const GLOSS: f32 = 0.35;
+// the frame is drawn at this many pixels per world unit
+const FRAME_PIXELS: usize = 2;
+
+const LIGHT_MARK: Color = Color::WHITE;
+const DARK_MARK: Color = Color::rgb(0.27, 0.18, 0.0);
+const DARK_SLOT: Color = Color::rgb(0.18, 0.18, 0.18);
+// the empty slots of the dark appearance, as a share of the skin's
+const DARK_SLOT_SHADE: f32 = 0.3;
+// the frame is drawn white and tinted with the board color of the skin
+const FRAME_FACE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const FRAME_EDGE: [f32; 4] = [0.68, 0.68, 0.7, 1.0];
+// the darker band around the frame and around each hole, in world units
+const FRAME_EDGE_WIDTH: f32 = 2.0;
+
+// the colors the coins, empty slots and the board are drawn in, any color draws. the red
+// side is the darker one in every skin, so a game between two skins still tells the
+// sides apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub red: Color,
+    pub yellow: Color,
+    pub empty: Color,
+    pub board: Color,
+}
+
+impl Palette {
+    pub fn of(skin: Skin) -> Palette {
+        match skin {
+            Skin::Classic => Palette {
+                red: Color::rgb(0.85, 0.09, 0.09),
+                yellow: Color::rgb(1.0, 0.85, 0.0),
+                empty: Color::rgb(0.92, 0.92, 0.92),
+                board: Color::rgb(0.11, 0.31, 0.77),
+            },
+            Skin::Ocean => Palette {
+                red: Color::rgb(0.9, 0.33, 0.25),
+                yellow: Color::rgb(1.0, 0.9, 0.55),
+                empty: Color::rgb(0.88, 0.95, 0.97),
+                board: Color::rgb(0.0, 0.45, 0.55),
+            },
+            Skin::Neon => Palette {
+                red: Color::rgb(0.85, 0.05, 0.55),
+                yellow: Color::rgb(0.75, 1.0, 0.15),
+                empty: Color::rgb(0.2, 0.2, 0.25),
+                board: Color::rgb(0.25, 0.1, 0.5),
+            },
+            Skin::Gold => Palette {
+                red: Color::rgb(0.55, 0.05, 0.12),
+                yellow: Color::rgb(0.95, 0.78, 0.3),
+                empty: Color::rgb(0.9, 0.88, 0.82),
+                board: Color::rgb(0.35, 0.27, 0.18),
+            },
+            Skin::Galaxy => Palette {
+                red: Color::rgb(0.5, 0.2, 0.8),
+                yellow: Color::rgb(0.55, 0.9, 1.0),
+                empty: Color::rgb(0.12, 0.12, 0.2),
+                board: Color::rgb(0.1, 0.1, 0.25),
+            },
+        }
+    }
+}
+
+// the colors of the screens around the board. every screen spawns its text and panels in
+// these, and swaps them when the appearance changes while it's open
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiColors {
+    // the clear color and the full screen panels
+    pub background: Color,
+    pub text: Color,
+    // hints and secondary lines
+    pub muted: Color,
+    pub button: Color,
+    // the review and analysis overlays over the board
+    pub panel: Color,
+    // the selected one of a row of buttons
+    pub highlight: Color,
+}
+
+impl UiColors {
+    pub const LIGHT: UiColors = UiColors {
+        background: Color::WHITE,
+        text: Color::BLACK,
+        muted: Color::DARK_GRAY,
+        button: Color::rgb(0.9, 0.9, 0.9),
+        panel: Color::rgb(0.92, 0.95, 1.0),
+        highlight: Color::rgb(0.75, 0.85, 1.0),
+    };
+
+    pub const DARK: UiColors = UiColors {
+        background: Color::rgb(0.1, 0.1, 0.12),
+        text: Color::rgb(0.92, 0.92, 0.92),
+        muted: Color::rgb(0.65, 0.65, 0.68),
+        button: Color::rgb(0.22, 0.22, 0.26),
+        panel: Color::rgb(0.13, 0.15, 0.21),
+        highlight: Color::rgb(0.2, 0.3, 0.5),
+    };
+
+    pub fn of(appearance: Appearance) -> UiColors {
+        match appearance {
+            Appearance::Light => UiColors::LIGHT,
+            Appearance::Dark => UiColors::DARK,
+        }
+    }
+}
+
+// what tells the sides apart besides red and yellow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinPattern {
+    // a cross on red, a ring on yellow
+    Symbols,
+    // stripes on red, dots on yellow
+    Stripes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Cross,
+    Ring,
+    Stripes,
+    Dots,
+}
+
+impl Mark {
+    // u and v run from -1 to 1 across the coin
+    fn covers(&self, u: f32, v: f32) -> bool {
+        let r = (u * u + v * v).sqrt();
+        match self {
+            Mark::Cross => r < 0.6 && ((u - v).abs() < 0.18 || (u + v).abs() < 0.18),
+            Mark::Ring => r > 0.35 && r < 0.58,
+            Mark::Stripes => r < RIM && ((u + v) * 2.5).rem_euclid(1.0) < 0.35,
+            Mark::Dots => {
+                let (du, dv) = (u - (u * 2.0).round() / 2.0, v - (v * 2.0).round() / 2.0);
+                r < RIM && du * du + dv * dv < 0.13 * 0.13
+            }
+        }
+    }
+}
+
+// the coin and slot textures of the chosen theme, everything that draws a coin takes its
+// texture from here. they are all drawn from the palettes of the skins when the settings
+// change: glossy coins for the classic look, flat ones with a mark for the patterns and
+// plain flat ones in reduced mode. each side's coins are drawn in its player's skin, the
+// empty slots and the board in the player's own, or the board in the accent color. the
+// dark appearance darkens the empty slots and the screens around the board
+#[derive(Resource)]
+pub struct Theme {
+    red: Handle<Image>,
+    yellow: Handle<Image>,
+    pub empty: Handle<Image>,
+    pub board: Color,
+    pub ui: UiColors,
+}
+
+impl Theme {
+    fn new(
+        settings: &Settings,
+        reduced: bool,
+        skins: [Skin; 2],
+        own: Skin,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let glossy = !reduced;
+        let (red_palette, yellow_palette) = (Palette::of(skins[0]), Palette::of(skins[1]));
+        let palette = Palette::of(own);
+        let (red, yellow) = match pattern(settings) {
+            None => (
+                images.add(coin_image(red_palette.red, None, glossy)),
+                images.add(coin_image(yellow_palette.yellow, None, glossy)),
+            ),
+            Some(CoinPattern::Symbols) => (
+                images.add(coin_image(
+                    red_palette.red,
+                    Some((Mark::Cross, LIGHT_MARK)),
+                    false,
+                )),
+                images.add(coin_image(
+                    yellow_palette.yellow,
+                    Some((Mark::Ring, DARK_MARK)),
+                    false,
+                )),
+            ),
+            Some(CoinPattern::Stripes) => (
+                images.add(coin_image(
+                    red_palette.red,
+                    Some((Mark::Stripes, LIGHT_MARK)),
+                    false,
+                )),
+                images.add(coin_image(
+                    yellow_palette.yellow,
+                    Some((Mark::Dots, DARK_MARK)),
+                    false,
+                )),
+            ),
+        };
+
+        let empty = if settings.high_contrast {
+            images.add(coin_image(DARK_SLOT, None, false))
+        } else if settings.appearance == Appearance::Dark {
+            images.add(coin_image(palette.empty * DARK_SLOT_SHADE, None, glossy))
+        } else {
+            images.add(coin_image(palette.empty, None, glossy))
+        };
+
+        Theme {
+            red,
+            yellow,
+            empty,
+            board: settings.accent_color().unwrap_or(palette.board),
+            ui: UiColors::of(settings.appearance),
+        }
+    }
+
+    pub fn coin(&self, player: usize) -> &Handle<Image> {
+        if player == 1 {
+            &self.red
+        } else {
+            &self.yellow
+        }
+    }
+
+    // the hover coin of a spectator or of a game nobody joined yet is an empty slot
+    pub fn hover(&self, player: usize) -> &Handle<Image> {
+        match player {
+            1 | 2 => self.coin(player),
+            _ => &self.empty,
+        }
+    }
+}
+
+// the theme is built at startup, before a game, so both sides show the player's own skin
+impl FromWorld for Theme {
+    fn from_world(world: &mut World) -> Self {
+        world.init_resource::<Cosmetics>();
+        let settings = world.resource::<Settings>().clone();
+        let reduced = world
+            .get_resource::<ReducedMode>()
+            .is_some_and(|reduced| reduced.0);
+        let own = world.resource::<Cosmetics>().skin(&settings);
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Theme::new(&settings, reduced, [own, own], own, &mut images)
+    }
+}
+
+// colorblind mode falls back to symbols when the coin theme has no pattern
+pub fn pattern(settings: &Settings) -> Option<CoinPattern> {
+    match settings.coin_theme {
+        CoinTheme::Symbols => Some(CoinPattern::Symbols),
+        CoinTheme::Stripes => Some(CoinPattern::Stripes),
+        _ if settings.colorblind => Some(CoinPattern::Symbols),
+        _ => None,
+    }
+}
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Theme>()
+            .add_systems(Update, (update_theme, retexture, recolor_ui).chain());
+    }
+}
+
+// the skins of player 1 and 2: the player's own and the one the opponent sent in the
+// handshake, classic for an opponent that sent none. spectators see their own on both sides
+fn skins(own: Skin, game_state: &GameState) -> [Skin; 2] {
+    let opponent = game_state
+        .p2_client
+        .as_ref()
+        .and_then(|client_info| client_info.skin)
+        .unwrap_or_default();
+    match game_state.player_type {
+        1 => [own, opponent],
+        2 => [opponent, own],
+        _ => [own, own],
+    }
+}
+
+// what the theme is drawn from, it's drawn again when any of it changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Look {
+    pattern: Option<CoinPattern>,
+    high_contrast: bool,
+    reduced: bool,
+    skins: [Skin; 2],
+    own: Skin,
+    appearance: Appearance,
+    accent: Option<Color>,
+}
+
+fn update_theme(
+    settings: Res<Settings>,
+    cosmetics: Res<Cosmetics>,
+    game_state: Res<GameState>,
+    reduced: Res<ReducedMode>,
+    mut images: ResMut<Assets<Image>>,
+    mut theme: ResMut<Theme>,
+    mut shown: Local<Option<Look>>,
+) {
+    let own = cosmetics.skin(&settings);
+    let look = Look {
+        pattern: pattern(&settings),
+        high_contrast: settings.high_contrast,
+        reduced: reduced.0,
+        skins: skins(own, &game_state),
+        own,
+        appearance: settings.appearance,
+        accent: settings.accent_color(),
+    };
+    if *shown == Some(look) {
+        return;
+    }
+    // the theme was built from these settings at startup
+    if shown.replace(look).is_none() {
+        return;
+    }
+
+    *theme = Theme::new(&settings, reduced.0, look.skins, own, &mut images);
+}
+
+// coins and slots already on the board take the new textures, the board its color
+fn retexture(
+    theme: Res<Theme>,
+    mut coins: Query<(&CoinMove, &mut Handle<Image>)>,
+    mut slots: Query<&mut Handle<Image>, (With<CoinSlot>, Without<CoinMove>)>,
+    mut frames: Query<&mut Sprite, With<BoardFrame>>,
+) {
+    if !theme.is_changed() || theme.is_added() {
+        return;
+    }
+
+    for (coin, mut texture) in coins.iter_mut() {
+        *texture = theme.coin(coin.player_move.player).clone();
+    }
+    for mut texture in slots.iter_mut() {
+        *texture = theme.empty.clone();
+    }
+    for mut sprite in frames.iter_mut() {
+        sprite.color = theme.board;
+    }
+}
+
+// the screens already open swap the colors of the previous appearance for the new ones,
+// text and panels in other colors keep them. the clear color is the background
+fn recolor_ui(
+    theme: Res<Theme>,
+    mut shown: Local<Option<UiColors>>,
+    mut texts: Query<&mut Text>,
+    mut backgrounds: Query<&mut BackgroundColor>,
+    mut cameras: Query<&mut Camera2d>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    let Some(previous) = shown.replace(theme.ui) else {
+        return;
+    };
+    if previous == theme.ui {
+        return;
+    }
+
+    let swap = |color: Color, pairs: &[(Color, Color)]| {
+        pairs
+            .iter()
+            .find(|(from, _)| *from == color.with_a(1.0))
+            .map(|(_, to)| to.with_a(color.a()))
+    };
+    let text_colors = [
+        (previous.text, theme.ui.text),
+        (previous.muted, theme.ui.muted),
+    ];
+    let panel_colors = [
+        (previous.background, theme.ui.background),
+        (previous.button, theme.ui.button),
+        (previous.panel, theme.ui.panel),
+        (previous.highlight, theme.ui.highlight),
+    ];
+
+    for mut text in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            if let Some(color) = swap(section.style.color, &text_colors) {
+                section.style.color = color;
+            }
+        }
+    }
+    for mut background in backgrounds.iter_mut() {
+        if let Some(color) = swap(background.0, &panel_colors) {
+            background.0 = color;
+        }
+    }
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = ClearColorConfig::Custom(theme.ui.background);
+    }
+}
+
+// the front of the board, a square cell around each slot with a hole the coin behind it
+// shows through. drawn white for the sprite to tint with `Theme::board`. sizes are in
+// world units, drawn at FRAME_PIXELS pixels per unit
+pub fn frame_image(columns: usize, rows: usize, cell: usize, hole: f32, margin: usize) -> Image {
+    let width = (columns * cell + 2 * margin) * FRAME_PIXELS;
+    let height = (rows * cell + 2 * margin) * FRAME_PIXELS;
+    let scale = FRAME_PIXELS as f32;
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let (px, py) = ((x as f32 + 0.5) / scale, (y as f32 + 0.5) / scale);
+            let (w, h) = (width as f32 / scale, height as f32 / scale);
+            let border = px.min(py).min(w - px).min(h - py);
+
+            // the distance to the centre of the nearest slot
+            let to_slot = |p: f32| {
+                let offset = p - margin as f32;
+                let index = (offset / cell as f32).floor().max(0.0);
+                offset - (index + 0.5) * cell as f32
+            };
+            let (dx, dy) = (to_slot(px), to_slot(py));
+            let r = (dx * dx + dy * dy).sqrt();
+
+            let color = if r < hole + FRAME_EDGE_WIDTH || border < FRAME_EDGE_WIDTH {
+                FRAME_EDGE
+            } else {
+                FRAME_FACE
+            };
+            // the hole fades out over a pixel so its edge stays smooth
+            let solid = ((r - hole) * scale).clamp(0.0, 1.0);
+            data.extend_from_slice(&to_pixel(color, solid));
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// a coin with a darker rim and the mark of its side. a glossy coin is lit from above
+fn coin_image(color: Color, mark: Option<(Mark, Color)>, glossy: bool) -> Image {
+    let color = color.as_rgba_f32();
+    let rim = mix(color, [0.0, 0.0, 0.0, color[3]], 0.2);
+    let center = COIN_PIXELS as f32 / 2.0;
+
+    let mut data = Vec::with_capacity(COIN_PIXELS * COIN_PIXELS * 4);
+    for y in 0..COIN_PIXELS {
+        let v = (y as f32 + 0.5 - center) / center;
+        for x in 0..COIN_PIXELS {
+            let u = (x as f32 + 0.5 - center) / center;
+            let r = (u * u + v * v).sqrt();
+
+            let mut pixel = match mark {
+                _ if r > RIM => rim,
+                Some((mark, mark_color)) if mark.covers(u, v) => mark_color.as_rgba_f32(),
+                _ => color,
+            };
+            // images are stored top row first, so the top of the coin has a negative v
+            if glossy {
+                let light = ((1.0 - v) / 2.0).powi(2) * GLOSS;
+                pixel = mix(pixel, [1.0, 1.0, 1.0, pixel[3]], light);
+            }
+            // the outline fades out over a pixel so it stays smooth at any size
+            let coverage = ((1.0 - r) * center).clamp(0.0, 1.0);
+            data.extend_from_slice(&to_pixel(pixel, coverage));
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: COIN_PIXELS as u32,
+            height: COIN_PIXELS as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn mix(from: [f32; 4], to: [f32; 4], amount: f32) -> [f32; 4] {
+    std::array::from_fn(|i| from[i] + (to[i] - from[i]) * amount)
+}
+
+fn to_pixel(color: [f32; 4], coverage: f32) -> [u8; 4] {
+    let [r, g, b, a] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+    [r, g, b, (a as f32 * coverage).round() as u8]
+}
@@ -0,0 +1,301 @@
+use nostr_sdk::serde_json;
+use serde::Serialize;
+
+use crate::game::{Game, Outcome, COLUMNS, ROWS};
+
+// the format written by this version, see `training.schema.json` and the readme
+pub const SCHEMA: &str = "unite4-training/1";
+
+// one position of an archived game and the move played from it. the position is two
+// planes of rows x columns, plane 0 holds the coins of the player to move and plane 1
+// the opponent's, row 0 is the bottom row. the result is the game's from the view of
+// the player to move, 1 for a win, 0 for a draw and -1 for a loss
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub game: usize,
+    pub ply: usize,
+    pub player: usize,
+    // whether the move was yours rather than the opponent's
+    pub own: bool,
+    pub position: [[[u8; COLUMNS]; ROWS]; 2],
+    pub column: usize,
+    pub result: i8,
+}
+
+// an archived classic game, its columns and which player the opponent was
+pub struct TrainingGame<'a> {
+    pub columns: &'a [usize],
+    pub opponent_player: usize,
+}
+
+#[derive(Serialize)]
+struct TrainingSet<'a> {
+    schema: &'static str,
+    rows: usize,
+    columns: usize,
+    samples: &'a [Sample],
+}
+
+// every position of every finished game. games ended by a resignation or that don't
+// replay have no result to learn from and are left out
+pub fn samples(games: &[TrainingGame]) -> Vec<Sample> {
+    let mut samples = Vec::new();
+
+    for (index, training_game) in games.iter().enumerate() {
+        let mut game = Game::new();
+        let mut positions = Vec::with_capacity(training_game.columns.len());
+        let mut replayed = true;
+        for &column in training_game.columns {
+            let player = game.player_turn();
+            positions.push((player, planes(&game, player), column));
+            if game.apply_move(column).is_err() {
+                replayed = false;
+                break;
+            }
+        }
+
+        let winner = match game.outcome() {
+            Outcome::Win(winner) => Some(winner),
+            Outcome::Draw => None,
+            Outcome::Ongoing => continue,
+        };
+        if !replayed {
+            continue;
+        }
+
+        for (ply, (player, position, column)) in positions.into_iter().enumerate() {
+            samples.push(Sample {
+                game: index,
+                ply,
+                player,
+                own: player != training_game.opponent_player,
+                position,
+                column,
+                result: match winner {
+                    Some(winner) if winner == player => 1,
+                    Some(_) => -1,
+                    None => 0,
+                },
+            });
+        }
+    }
+
+    samples
+}
+
+fn planes(game: &Game, player: usize) -> [[[u8; COLUMNS]; ROWS]; 2] {
+    let mut position = [[[0; COLUMNS]; ROWS]; 2];
+    for coin in game.coins() {
+        let plane = if coin.player == player { 0 } else { 1 };
+        position[plane][coin.row][coin.column] = 1;
+    }
+    position
+}
+
+pub fn to_json(samples: &[Sample]) -> String {
+    serde_json::to_string(&TrainingSet {
+        schema: SCHEMA,
+        rows: ROWS,
+        columns: COLUMNS,
+        samples,
+    })
+    .unwrap()
+}
+
+// numpy's `np.load` reads it, the arrays share the sample order: `positions` (n, 2, rows,
+// columns) uint8, `moves`, `players` and `plies` (n,) uint8, `own` (n,) bool and
+// `results` (n,) int8
+pub fn to_npz(samples: &[Sample]) -> Vec<u8> {
+    let count = samples.len();
+    let positions: Vec<u8> = samples
+        .iter()
+        .flat_map(|sample| sample.position.iter().flatten().flatten().copied())
+        .collect();
+    let bytes = |value: fn(&Sample) -> u8| samples.iter().map(value).collect::<Vec<u8>>();
+
+    let arrays = [
+        (
+            "positions.npy",
+            npy(
+                "|u1",
+                &format!("({}, 2, {}, {})", count, ROWS, COLUMNS),
+                &positions,
+            ),
+        ),
+        (
+            "moves.npy",
+            npy("|u1", &format!("({},)", count), &bytes(|s| s.column as u8)),
+        ),
+        (
+            "players.npy",
+            npy("|u1", &format!("({},)", count), &bytes(|s| s.player as u8)),
+        ),
+        (
+            "plies.npy",
+            npy("|u1", &format!("({},)", count), &bytes(|s| s.ply as u8)),
+        ),
+        (
+            "own.npy",
+            npy("|b1", &format!("({},)", count), &bytes(|s| s.own as u8)),
+        ),
+        (
+            "results.npy",
+            npy("|i1", &format!("({},)", count), &bytes(|s| s.result as u8)),
+        ),
+    ];
+
+    stored_zip(&arrays)
+}
+
+// an npy 1.0 file, the header is padded so the data starts on a 64 byte boundary
+fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    npy.extend_from_slice(data);
+    npy
+}
+
+// a zip archive without compression, all an npz needs
+fn stored_zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    // 1980-01-01, the earliest date zip can hold
+    const DOS_DATE: u16 = 0x21;
+
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let offset = zip.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        zip.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        zip.extend_from_slice(&common);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(data);
+
+        directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // comment length, disk, internal and external attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = zip.len() as u32;
+    zip.extend_from_slice(&directory);
+    zip.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]);
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&directory_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+    zip
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // red wins on the fourth drop in column 3
+    const WON: [usize; 7] = [3, 4, 3, 4, 3, 4, 3];
+
+    #[test]
+    fn samples_count_for_the_player_to_move() {
+        let samples = samples(&[TrainingGame {
+            columns: &WON,
+            opponent_player: 2,
+        }]);
+        assert_eq!(samples.len(), WON.len());
+
+        // plane 0 holds red's coins before their winning drop, plane 1 yellow's
+        assert_eq!(samples[6].position[0][2][3], 1);
+        assert_eq!(samples[6].position[1][2][4], 1);
+        assert_eq!(samples[6].position[0][3][3], 0);
+        assert_eq!((samples[6].column, samples[6].result), (3, 1));
+        assert_eq!(
+            (samples[1].player, samples[1].own, samples[1].result),
+            (2, false, -1)
+        );
+        assert_eq!((samples[0].player, samples[0].own), (1, true));
+        assert!(samples
+            .iter()
+            .enumerate()
+            .all(|(ply, sample)| sample.ply == ply));
+    }
+
+    #[test]
+    fn unfinished_and_invalid_games_are_left_out() {
+        let samples = samples(&[
+            TrainingGame {
+                columns: &WON[..5],
+                opponent_player: 2,
+            },
+            TrainingGame {
+                columns: &[3, COLUMNS],
+                opponent_player: 1,
+            },
+            TrainingGame {
+                columns: &WON,
+                opponent_player: 1,
+            },
+        ]);
+        assert_eq!(samples.len(), WON.len());
+        assert!(samples.iter().all(|sample| sample.game == 2));
+        assert_eq!((samples[0].own, samples[1].own), (false, true));
+    }
+
+    #[test]
+    fn exports_npz_and_json() {
+        let samples = samples(&[TrainingGame {
+            columns: &WON,
+            opponent_player: 2,
+        }]);
+
+        let npz = to_npz(&samples);
+        assert!(npz.starts_with(b"PK\x03\x04"));
+        assert!(npz.ends_with(&[0, 0]));
+        assert!(to_json(&samples).starts_with("{\"schema\":\"unite4-training/1\""));
+
+        // the data of an npy file starts on a 64 byte boundary
+        let npy = npy("|u1", "(3,)", &[1, 2, 3]);
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        assert_eq!(npy.len() % 64, 3);
+        assert!(npy.ends_with(b"\n\x01\x02\x03"));
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
@@ -0,0 +1,44 @@
+use futures::channel::mpsc::Receiver;
+use nostr_sdk::{secp256k1::XOnlyPublicKey, ClientMessage, Event, Keys};
+
+use crate::{invite::Invitation, messages::GameConfig};
+
+// what a transport needs to know to carry a game, and to announce or join it
+#[derive(Clone)]
+pub struct GameConnection {
+    pub nostr_keys: Keys,
+    pub game_id: String,
+    // a stored session skips the new game / join game handshake
+    pub resuming: bool,
+    // only join a game created by this pubkey, from a challenge link
+    pub challenger: Option<XOnlyPublicKey>,
+    // only join a game with the variant and series length of the invitation link
+    pub invitation: Option<Invitation>,
+    pub local_ln_address: Option<String>,
+    pub series_length: usize,
+    // the rules announced in the new game event
+    pub config: GameConfig,
+    // the hashtag of the club whose lobby created the game, added to the new game event
+    pub club: Option<String>,
+    // privacy mode, nothing about the player beyond the game is published
+    pub private: bool,
+}
+
+// how game events travel between players. the events are signed nostr events whatever
+// carries them, so another transport (webrtc data channels, a plain websocket relay, a
+// local loopback) only has to deliver them. the nostr relays are the default, see
+// `nostr_plugin::NostrTransport`, `webrtc::WebRtcTransport` adds a direct connection
+pub trait GameTransport: Send + Sync {
+    // shown in logs and the debug snapshot
+    fn name(&self) -> &'static str;
+
+    // starts carrying the game. the stream yields the stored events of the game, oldest
+    // first, then the events of the other players as they arrive
+    fn connect(&self, connection: GameConnection) -> Receiver<Event>;
+
+    // queues a message for the other players, it doesn't wait for delivery
+    fn send(&self, msg: ClientMessage) -> Result<(), String>;
+
+    // both players are known, a transport that connects them directly starts here
+    fn opponent_known(&self, _player_type: usize, _opponent: XOnlyPublicKey) {}
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// the share of a bounce's time spent falling, the rest is the hop after landing
+const BOUNCE_IMPACT: f32 = 0.8;
+// how high the hop goes, as a share of the fall
+const BOUNCE_HEIGHT: f32 = 0.1;
+
+// how a tween moves between its two points, picked in the settings for the coin drops
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Easing {
+    // constant speed
+    Linear,
+    // speeds up like a falling coin
+    Gravity,
+    // falls and hops once where it lands
+    #[default]
+    Bounce,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 3] = [Easing::Linear, Easing::Gravity, Easing::Bounce];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::Gravity => "Gravity",
+            Easing::Bounce => "Bounce",
+        }
+    }
+
+    // the share of the way covered after a share t of the time
+    fn sample(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Gravity => t * t,
+            Easing::Bounce if t < BOUNCE_IMPACT => (t / BOUNCE_IMPACT).powi(2),
+            Easing::Bounce => {
+                let hop = (t - BOUNCE_IMPACT) / (1.0 - BOUNCE_IMPACT);
+                1.0 - BOUNCE_HEIGHT * 4.0 * hop * (1.0 - hop)
+            }
+        }
+    }
+
+    // the share of the time when the end is first reached
+    fn impact(&self) -> f32 {
+        match self {
+            Easing::Bounce => BOUNCE_IMPACT,
+            _ => 1.0,
+        }
+    }
+}
+
+// moves an entity's translation from one point to another, the system advances it every
+// frame and leaves it in place once finished
+#[derive(Component, Debug, Clone)]
+pub struct Tween {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub easing: Easing,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Tween {
+    // reaches `to` after `seconds`, a bounce hops on a little longer
+    pub fn new(from: Vec3, to: Vec3, seconds: f32, easing: Easing) -> Self {
+        Tween {
+            from,
+            to,
+            easing,
+            duration: seconds / easing.impact(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn landed(&self) -> bool {
+        self.elapsed >= self.duration * self.easing.impact()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tweening;
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, advance_tweens.in_set(Tweening));
+    }
+}
+
+fn advance_tweens(time: Res<Time>, mut tweens: Query<(&mut Tween, &mut Transform)>) {
+    for (mut tween, mut transform) in tweens.iter_mut() {
+        if tween.finished() {
+            continue;
+        }
+
+        tween.elapsed = (tween.elapsed + time.delta_seconds()).min(tween.duration);
+        let progress = if tween.duration > 0.0 {
+            tween.easing.sample(tween.elapsed / tween.duration)
+        } else {
+            1.0
+        };
+        transform.translation = tween.from.lerp(tween.to, progress);
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    ui::{FocusPolicy, UiSystem},
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    components::{UsernameAction, UsernameHint, UsernameText, UsernameUi},
+    locale_plugin::{Locale, Phrase},
+    resources::GameState,
+    settings_plugin::Settings,
+    theme_plugin::{Theme, UiColors},
+    AppState,
+};
+
+pub const MAX_USERNAME_LENGTH: usize = 64;
+
+const PSEUDONYM_ADJECTIVES: [&str; 16] = [
+    "Amber", "Brave", "Calm", "Daring", "Eager", "Fuzzy", "Gentle", "Hidden", "Jolly", "Lucky",
+    "Misty", "Nimble", "Quiet", "Rapid", "Sunny", "Witty",
+];
+const PSEUDONYM_ANIMALS: [&str; 16] = [
+    "Badger", "Crane", "Dolphin", "Falcon", "Gecko", "Heron", "Koala", "Lynx", "Marten", "Newt",
+    "Otter", "Panda", "Quokka", "Raven", "Stoat", "Wombat",
+];
+
+static EDIT_USERNAME_CALLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UsernameError {
+    Empty,
+    TooLong(usize),
+    LightningAddress(String),
+}
+
+impl UsernameError {
+    pub fn text(&self, locale: &Locale) -> String {
+        match self {
+            UsernameError::Empty => locale.get(Phrase::UsernameEmpty).to_string(),
+            UsernameError::TooLong(length) => locale.fill(
+                Phrase::UsernameTooLong,
+                &[&length.to_string(), &MAX_USERNAME_LENGTH.to_string()],
+            ),
+            UsernameError::LightningAddress(address) => {
+                locale.fill(Phrase::NotLightningAddress, &[address])
+            }
+        }
+    }
+}
+
+// a plain name is kept as typed, anything with an @ has to be a lightning address
+// (LUD-16, `name@domain`) and is lowercased
+pub fn validate_username(input: &str) -> Result<String, UsernameError> {
+    let username = input.trim();
+
+    if username.is_empty() {
+        return Err(UsernameError::Empty);
+    }
+    let length = username.chars().count();
+    if length > MAX_USERNAME_LENGTH {
+        return Err(UsernameError::TooLong(length));
+    }
+
+    let Some((name, domain)) = username.split_once('@') else {
+        return Ok(username.to_string());
+    };
+
+    let address = username.to_lowercase();
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c));
+    let valid_domain = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    if valid_name && valid_domain {
+        Ok(address)
+    } else {
+        Err(UsernameError::LightningAddress(username.to_string()))
+    }
+}
+
+// the name being typed. while the entry is open it takes all keyboard input, so typing
+// doesn't arm power ups or leave the screen, and clicks don't drop coins
+#[derive(Resource, Default)]
+pub struct UsernameEntry {
+    open: bool,
+    value: String,
+    error: Option<UsernameError>,
+    // the key that opened the entry isn't typed into it
+    just_opened: bool,
+}
+
+impl UsernameEntry {
+    pub fn open(&mut self, current: Option<&str>) {
+        self.open = true;
+        self.value = current.unwrap_or_default().to_string();
+        self.error = None;
+        self.just_opened = true;
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.error = None;
+    }
+
+    fn hint(&self, locale: &Locale) -> String {
+        match &self.error {
+            Some(error) => error.text(locale),
+            None => locale.get(Phrase::UsernameHint).to_string(),
+        }
+    }
+}
+
+// the name shown instead of the player's in privacy mode, the same for a game every time
+// it's opened so a resumed game keeps it, and a different one in each game
+pub fn pseudonym(game_id: &str, pubkey: &impl Hash) -> String {
+    let mut hasher = DefaultHasher::new();
+    game_id.hash(&mut hasher);
+    pubkey.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    format!(
+        "{} {} {}",
+        PSEUDONYM_ADJECTIVES[hash as usize % PSEUDONYM_ADJECTIVES.len()],
+        PSEUDONYM_ANIMALS[(hash >> 8) as usize % PSEUDONYM_ANIMALS.len()],
+        (hash >> 16) % 100
+    )
+}
+
+pub fn entry_closed(entry: Res<UsernameEntry>) -> bool {
+    !entry.open
+}
+
+pub struct UsernamePlugin;
+
+impl Plugin for UsernamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UsernameEntry>()
+            // the typed keys are taken before focus navigation sees them
+            .add_systems(
+                PreUpdate,
+                type_username.after(InputSystem).before(UiSystem::Focus),
+            )
+            .add_systems(Update, check_edit_username_system)
+            // after the board handled its clicks, a click on save doesn't also drop a coin
+            .add_systems(PostUpdate, (username_actions, show_entry).chain());
+    }
+}
+
+// N in a game, or the page's name button. in the page's menu the name has its own input
+fn check_edit_username_system(
+    keys: Res<Input<KeyCode>>,
+    state: Res<State<AppState>>,
+    settings: Res<Settings>,
+    mut entry: ResMut<UsernameEntry>,
+) {
+    let called = EDIT_USERNAME_CALLED.swap(false, Ordering::SeqCst);
+    if entry.open || *state.get() != AppState::InGame || !(called || keys.just_pressed(KeyCode::N))
+    {
+        return;
+    }
+
+    entry.open(settings.username.as_deref());
+}
+
+fn type_username(
+    mut characters: ResMut<Events<ReceivedCharacter>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut entry: ResMut<UsernameEntry>,
+    mut settings: ResMut<Settings>,
+    mut game_state: ResMut<GameState>,
+) {
+    if !entry.open {
+        return;
+    }
+
+    let typed: Vec<char> = characters.drain().map(|character| character.char).collect();
+    if entry.just_opened {
+        entry.just_opened = false;
+    } else {
+        for c in typed.into_iter().filter(|c| !c.is_control()) {
+            if entry.value.chars().count() < MAX_USERNAME_LENGTH {
+                entry.value.push(c);
+            }
+        }
+        if keys.just_pressed(KeyCode::Back) {
+            entry.value.pop();
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Return) {
+        save(&mut entry, &mut settings, &mut game_state);
+    } else if keys.just_pressed(KeyCode::Escape) {
+        entry.close();
+    }
+
+    keys.reset_all();
+}
+
+fn save(entry: &mut UsernameEntry, settings: &mut Settings, game_state: &mut GameState) {
+    match validate_username(&entry.value) {
+        Ok(username) => {
+            info!("username set to {}", username);
+            settings.username = Some(username.clone());
+            settings.save();
+            // a private game keeps its pseudonym, the name is used from the next game on
+            if !settings.privacy {
+                game_state.local_ln_address = Some(username);
+            }
+            entry.close();
+        }
+        Err(e) => entry.error = Some(e),
+    }
+}
+
+fn username_actions(
+    actions: Query<(&Interaction, &UsernameAction), Changed<Interaction>>,
+    mut entry: ResMut<UsernameEntry>,
+    mut settings: ResMut<Settings>,
+    mut game_state: ResMut<GameState>,
+) {
+    for (interaction, action) in actions.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            UsernameAction::Save => save(&mut entry, &mut settings, &mut game_state),
+            UsernameAction::Cancel => entry.close(),
+        }
+    }
+}
+
+// spawns the entry when it opens and keeps its text up to date
+fn show_entry(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    entry: Res<UsernameEntry>,
+    ui: Query<Entity, With<UsernameUi>>,
+    mut text: Query<&mut Text, (With<UsernameText>, Without<UsernameHint>)>,
+    mut hint: Query<&mut Text, (With<UsernameHint>, Without<UsernameText>)>,
+) {
+    if !entry.is_changed() && !locale.is_changed() {
+        return;
+    }
+
+    if !entry.open {
+        for entity in ui.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if ui.is_empty() {
+        spawn_entry(&mut commands, theme.ui, &locale, &entry);
+        return;
+    }
+
+    for mut txt in text.iter_mut() {
+        txt.sections[0].value = format!("{}_", entry.value);
+    }
+    for mut txt in hint.iter_mut() {
+        txt.sections[0].value = entry.hint(&locale);
+    }
+}
+
+fn spawn_entry(commands: &mut Commands, ui: UiColors, locale: &Locale, entry: &UsernameEntry) {
+    let text_style = |color: Color, font_size: f32| TextStyle {
+        color,
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            background_color: ui.background.with_a(0.95).into(),
+            focus_policy: FocusPolicy::Block,
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(UsernameUi)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                locale.get(Phrase::NameOrLightningAddress),
+                text_style(ui.text, 24.0),
+            ));
+            parent
+                .spawn(TextBundle::from_section(
+                    format!("{}_", entry.value),
+                    text_style(ui.text, 20.0),
+                ))
+                .insert(UsernameText);
+            parent
+                .spawn(TextBundle::from_section(
+                    entry.hint(locale),
+                    text_style(ui.muted, 14.0),
+                ))
+                .insert(UsernameHint);
+
+            for (label, action) in [
+                (Phrase::Save, UsernameAction::Save),
+                (Phrase::Cancel, UsernameAction::Cancel),
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            width: Val::Px(160.0),
+                            justify_content: JustifyContent::Center,
+                            padding: UiRect::all(Val::Px(8.0)),
+                            ..default()
+                        },
+                        background_color: ui.button.into(),
+                        ..default()
+                    })
+                    .insert(action)
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            locale.get(label),
+                            text_style(ui.text, 18.0),
+                        ));
+                    });
+            }
+        });
+}
+
+#[wasm_bindgen]
+pub fn edit_username() {
+    EDIT_USERNAME_CALLED.store(true, Ordering::SeqCst);
+}
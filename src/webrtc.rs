@@ -0,0 +1,617 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use bevy::log::{error, info};
+use futures::channel::mpsc::{Receiver, Sender};
+use nostr_sdk::{
+    nips::nip04, secp256k1::XOnlyPublicKey, serde_json, ClientMessage, Event, EventBuilder,
+    JsonUtil, Keys, Kind, Tag,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    messages::{NetworkMessage, GAME_TAG_PREFIX, MOVE_KIND, SIGNAL_KIND},
+    storage,
+    transport::{GameConnection, GameTransport},
+};
+
+// public stun server, enough for most home networks. there is no turn server, players
+// behind strict nats stay on the relays
+#[cfg(target_arch = "wasm32")]
+const STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+#[cfg(target_arch = "wasm32")]
+const CHANNEL_LABEL: &str = "unite4";
+
+// the player turned on peer to peer moves in the settings, it applies from the next game.
+// browsers only, the desktop build has no webrtc
+pub fn enabled() -> bool {
+    cfg!(target_arch = "wasm32") && storage::get_item("peer_to_peer").as_deref() == Some("true")
+}
+
+// webrtc signaling, sent nip-04 encrypted to the opponent in a `Signal` message since
+// the session descriptions and candidates hold the players' ip addresses
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum PeerSignal {
+    // the answering player is ready for an offer, sent again after a reload
+    Ready,
+    Offer(String),
+    Answer(String),
+    Candidate(IceCandidate),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IceCandidate {
+    pub candidate: String,
+    pub sdp_mid: Option<String>,
+    pub sdp_m_line_index: Option<u16>,
+}
+
+// read by the peer task, which only runs in the browser
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+enum PeerCommand {
+    // both players are known, player 2 makes the offer
+    Start {
+        player_type: usize,
+        opponent: XOnlyPublicKey,
+    },
+    // a signed event for the data channel
+    Send(String),
+}
+
+// game events over a webrtc data channel between the two players. the relays carry the
+// signaling and still get a copy of every event, so spectators and reloads can follow the
+// game and the game keeps going over the relays when the channel can't be opened or
+// drops. whichever copy of an event arrives first is used
+pub struct WebRtcTransport {
+    relays: Arc<dyn GameTransport>,
+    commands: Sender<PeerCommand>,
+    // taken when the transport connects
+    queued: Mutex<Option<Receiver<PeerCommand>>>,
+    open: Arc<AtomicBool>,
+}
+
+impl WebRtcTransport {
+    pub fn new(relays: Arc<dyn GameTransport>) -> Self {
+        let (commands, queued) = futures::channel::mpsc::channel::<PeerCommand>(1000);
+        Self {
+            relays,
+            commands,
+            queued: Mutex::new(Some(queued)),
+            open: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl GameTransport for WebRtcTransport {
+    fn name(&self) -> &'static str {
+        if self.open.load(Ordering::SeqCst) {
+            "webrtc"
+        } else {
+            "webrtc, falling back to nostr"
+        }
+    }
+
+    fn connect(&self, connection: GameConnection) -> Receiver<Event> {
+        let relayed = self.relays.connect(connection.clone());
+        let Some(commands) = self.queued.lock().ok().and_then(|mut queued| queued.take()) else {
+            error!("webrtc transport is already connected");
+            return relayed;
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let (events_tx, events_rx) = futures::channel::mpsc::channel::<Event>(1000);
+            let signaling = Signaling::new(&connection, self.relays.clone());
+            crate::platform::spawn(peer::run(
+                relayed,
+                commands,
+                events_tx,
+                signaling,
+                self.open.clone(),
+            ));
+            events_rx
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            info!("no webrtc on desktop, game events go over the relays");
+            drop(commands);
+            relayed
+        }
+    }
+
+    fn send(&self, msg: ClientMessage) -> Result<(), String> {
+        if let ClientMessage::Event(ref event) = msg {
+            let game_event = event.kind == Kind::Regular(4444)
+                || event.kind == Kind::Ephemeral(MOVE_KIND)
+                || event.kind == Kind::Ephemeral(SIGNAL_KIND);
+            if game_event && self.open.load(Ordering::SeqCst) {
+                if let Err(e) = self
+                    .commands
+                    .clone()
+                    .try_send(PeerCommand::Send(event.as_json()))
+                {
+                    error!("Error queueing event for the data channel: {}", e);
+                }
+            }
+        }
+
+        self.relays.send(msg)
+    }
+
+    fn opponent_known(&self, player_type: usize, opponent: XOnlyPublicKey) {
+        let start = PeerCommand::Start {
+            player_type,
+            opponent,
+        };
+        if let Err(e) = self.commands.clone().try_send(start) {
+            error!("Error starting the peer connection: {}", e);
+        }
+    }
+}
+
+// signals to the opponent go out as game events over the relays
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+struct Signaling {
+    nostr_keys: Keys,
+    tag: String,
+    relays: Arc<dyn GameTransport>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+impl Signaling {
+    fn new(connection: &GameConnection, relays: Arc<dyn GameTransport>) -> Self {
+        Self {
+            nostr_keys: connection.nostr_keys.clone(),
+            tag: format!("{}{}", GAME_TAG_PREFIX, connection.game_id),
+            relays,
+        }
+    }
+
+    fn send(&self, opponent: &XOnlyPublicKey, signal: &PeerSignal) {
+        let sent = self
+            .nostr_keys
+            .secret_key()
+            .map_err(|e| e.to_string())
+            .and_then(|secret_key| {
+                let signal = serde_json::to_string(signal).map_err(|e| e.to_string())?;
+                nip04::encrypt(&secret_key, opponent, signal).map_err(|e| e.to_string())
+            })
+            .and_then(|encrypted| {
+                let msg = serde_json::to_string(&NetworkMessage::Signal(encrypted))
+                    .map_err(|e| e.to_string())?;
+                EventBuilder::new(Kind::Regular(4444), msg, [Tag::Hashtag(self.tag.clone())])
+                    .to_event(&self.nostr_keys)
+                    .map_err(|e| e.to_string())
+            })
+            .and_then(|event| self.relays.send(ClientMessage::event(event)));
+
+        if let Err(e) = sent {
+            error!("Error sending webrtc signal: {}", e);
+        }
+    }
+
+    // a signal from the opponent, None for any other event
+    fn read(&self, opponent: &XOnlyPublicKey, event: &Event) -> Option<PeerSignal> {
+        let Ok(NetworkMessage::Signal(encrypted)) = serde_json::from_str(&event.content) else {
+            return None;
+        };
+
+        let secret_key = self.nostr_keys.secret_key().ok()?;
+        let signal = nip04::decrypt(&secret_key, opponent, encrypted).ok()?;
+        serde_json::from_str(&signal).ok()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod peer {
+    use std::{collections::HashMap, time::Duration};
+
+    use futures::{stream, StreamExt};
+    use js_sys::{Array, Reflect};
+    use nostr_sdk::{EventId, Timestamp};
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{
+        MessageEvent, RtcConfiguration, RtcDataChannel, RtcDataChannelEvent, RtcDataChannelState,
+        RtcIceCandidateInit, RtcIceServer, RtcPeerConnection, RtcPeerConnectionIceEvent,
+        RtcSdpType, RtcSessionDescriptionInit,
+    };
+
+    use super::*;
+
+    // the relayed copy of an event sent over the data channel arrives within seconds, ids
+    // are remembered this long to drop it
+    const FORWARDED_WINDOW: Duration = Duration::from_secs(600);
+
+    type Callback = Closure<dyn FnMut(JsValue)>;
+
+    // what the browser reports from the connection's callbacks
+    enum PeerEvent {
+        Channel(RtcDataChannel),
+        Open,
+        Closed,
+        Message(String),
+        Candidate(IceCandidate),
+    }
+
+    enum Incoming {
+        Relayed(Event),
+        Command(PeerCommand),
+        Peer(PeerEvent),
+    }
+
+    struct Peer {
+        connection: RtcPeerConnection,
+        channel: Option<RtcDataChannel>,
+        remote_set: bool,
+        // kept alive with the connection
+        callbacks: Vec<Callback>,
+    }
+
+    impl Peer {
+        fn new(events: &Sender<PeerEvent>, offer: bool) -> Result<Self, JsValue> {
+            let mut ice_server = RtcIceServer::new();
+            ice_server.urls(&JsValue::from_str(STUN_SERVER));
+            let mut config = RtcConfiguration::new();
+            config.ice_servers(&Array::of1(&ice_server));
+            let connection = RtcPeerConnection::new_with_configuration(&config)?;
+
+            let mut peer = Self {
+                connection,
+                channel: None,
+                remote_set: false,
+                callbacks: Vec::new(),
+            };
+
+            let tx = events.clone();
+            peer.callback(move |event| {
+                let event: RtcPeerConnectionIceEvent = event.unchecked_into();
+                if let Some(candidate) = event.candidate() {
+                    let _ = tx.clone().try_send(PeerEvent::Candidate(IceCandidate {
+                        candidate: candidate.candidate(),
+                        sdp_mid: candidate.sdp_mid(),
+                        sdp_m_line_index: candidate.sdp_m_line_index(),
+                    }));
+                }
+            });
+            peer.connection
+                .set_onicecandidate(Some(peer.last_callback()));
+
+            // the offering player opens the channel, the other gets it with the offer
+            if offer {
+                let channel = peer.connection.create_data_channel(CHANNEL_LABEL);
+                peer.attach(channel, events);
+            } else {
+                let tx = events.clone();
+                peer.callback(move |event| {
+                    let event: RtcDataChannelEvent = event.unchecked_into();
+                    let _ = tx.clone().try_send(PeerEvent::Channel(event.channel()));
+                });
+                peer.connection
+                    .set_ondatachannel(Some(peer.last_callback()));
+            }
+
+            Ok(peer)
+        }
+
+        fn callback(&mut self, callback: impl FnMut(JsValue) + 'static) {
+            self.callbacks.push(Closure::new(callback));
+        }
+
+        fn last_callback(&self) -> &js_sys::Function {
+            self.callbacks.last().unwrap().as_ref().unchecked_ref()
+        }
+
+        fn attach(&mut self, channel: RtcDataChannel, events: &Sender<PeerEvent>) {
+            let tx = events.clone();
+            self.callback(move |_| {
+                let _ = tx.clone().try_send(PeerEvent::Open);
+            });
+            channel.set_onopen(Some(self.last_callback()));
+
+            let tx = events.clone();
+            self.callback(move |_| {
+                let _ = tx.clone().try_send(PeerEvent::Closed);
+            });
+            channel.set_onclose(Some(self.last_callback()));
+
+            let tx = events.clone();
+            self.callback(move |event| {
+                let event: MessageEvent = event.unchecked_into();
+                if let Some(data) = event.data().as_string() {
+                    let _ = tx.clone().try_send(PeerEvent::Message(data));
+                }
+            });
+            channel.set_onmessage(Some(self.last_callback()));
+
+            // the answering side may get the channel after it opened
+            if channel.ready_state() == RtcDataChannelState::Open {
+                let _ = events.clone().try_send(PeerEvent::Open);
+            }
+            self.channel = Some(channel);
+        }
+
+        async fn describe(&self, sdp_type: RtcSdpType) -> Result<String, JsValue> {
+            let description = match sdp_type {
+                RtcSdpType::Offer => JsFuture::from(self.connection.create_offer()).await?,
+                _ => JsFuture::from(self.connection.create_answer()).await?,
+            };
+            let sdp = Reflect::get(&description, &JsValue::from_str("sdp"))?
+                .as_string()
+                .unwrap_or_default();
+
+            let mut local = RtcSessionDescriptionInit::new(sdp_type);
+            local.sdp(&sdp);
+            JsFuture::from(self.connection.set_local_description(&local)).await?;
+            Ok(sdp)
+        }
+
+        async fn set_remote(&mut self, sdp_type: RtcSdpType, sdp: &str) -> Result<(), JsValue> {
+            let mut remote = RtcSessionDescriptionInit::new(sdp_type);
+            remote.sdp(sdp);
+            JsFuture::from(self.connection.set_remote_description(&remote)).await?;
+            self.remote_set = true;
+            Ok(())
+        }
+
+        async fn add_candidate(&self, candidate: &IceCandidate) -> Result<(), JsValue> {
+            let mut init = RtcIceCandidateInit::new(&candidate.candidate);
+            init.sdp_mid(candidate.sdp_mid.as_deref());
+            init.sdp_m_line_index(candidate.sdp_m_line_index);
+            JsFuture::from(
+                self.connection
+                    .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)),
+            )
+            .await?;
+            Ok(())
+        }
+
+        // the callbacks are cleared first, a replaced connection doesn't report its close
+        fn close(self) {
+            if let Some(channel) = self.channel {
+                channel.set_onopen(None);
+                channel.set_onclose(None);
+                channel.set_onmessage(None);
+                channel.close();
+            }
+            self.connection.set_onicecandidate(None);
+            self.connection.set_ondatachannel(None);
+            self.connection.close();
+        }
+    }
+
+    struct PeerTask {
+        signaling: Signaling,
+        events: Sender<PeerEvent>,
+        forward: Sender<Event>,
+        open: Arc<AtomicBool>,
+        opponent: Option<XOnlyPublicKey>,
+        offering: bool,
+        peer: Option<Peer>,
+        // candidates that arrived before the session description
+        candidates: Vec<IceCandidate>,
+        // signals that arrived before the opponent was known
+        early: Vec<Event>,
+        // when each forwarded event was first seen
+        forwarded: HashMap<EventId, Timestamp>,
+        // signals stored on the relays from an earlier connection are ignored
+        started: Timestamp,
+    }
+
+    pub(super) async fn run(
+        relayed: Receiver<Event>,
+        commands: Receiver<PeerCommand>,
+        forward: Sender<Event>,
+        signaling: Signaling,
+        open: Arc<AtomicBool>,
+    ) {
+        let (events, peer_events) = futures::channel::mpsc::channel::<PeerEvent>(1000);
+        let mut task = PeerTask {
+            signaling,
+            events,
+            forward,
+            open,
+            opponent: None,
+            offering: false,
+            peer: None,
+            candidates: Vec::new(),
+            early: Vec::new(),
+            forwarded: HashMap::new(),
+            started: Timestamp::now(),
+        };
+
+        let mut incoming = stream::select(
+            relayed.map(Incoming::Relayed),
+            stream::select(
+                commands.map(Incoming::Command),
+                peer_events.map(Incoming::Peer),
+            ),
+        );
+
+        while let Some(incoming) = incoming.next().await {
+            let handled = match incoming {
+                Incoming::Relayed(event) => task.relayed(event).await,
+                Incoming::Command(command) => task.command(command).await,
+                Incoming::Peer(event) => task.peer_event(event).await,
+            };
+            if let Err(e) = handled {
+                error!("webrtc error: {:?}", e);
+            }
+        }
+    }
+
+    impl PeerTask {
+        fn forward(&mut self, event: Event) {
+            let now = Timestamp::now();
+            self.forwarded
+                .retain(|_, seen| *seen > now - FORWARDED_WINDOW);
+            if self.forwarded.insert(event.id, now).is_some() {
+                return;
+            }
+            if let Err(e) = self.forward.try_send(event) {
+                error!("Error forwarding event: {}", e);
+            }
+        }
+
+        async fn relayed(&mut self, event: Event) -> Result<(), JsValue> {
+            if !event.content.contains("\"Signal\"") {
+                self.forward(event);
+                return Ok(());
+            }
+
+            let Some(opponent) = self.opponent else {
+                self.early.push(event);
+                return Ok(());
+            };
+
+            if event.pubkey != opponent || event.created_at < self.started {
+                return Ok(());
+            }
+
+            match self.signaling.read(&opponent, &event) {
+                Some(signal) => self.signal(signal).await,
+                None => Ok(()),
+            }
+        }
+
+        async fn command(&mut self, command: PeerCommand) -> Result<(), JsValue> {
+            match command {
+                PeerCommand::Start {
+                    player_type,
+                    opponent,
+                } => {
+                    if self.opponent.is_some() {
+                        return Ok(());
+                    }
+                    info!("starting webrtc with {:?}", opponent);
+                    self.opponent = Some(opponent);
+                    self.offering = player_type == 2;
+
+                    if self.offering {
+                        self.offer().await?;
+                    } else {
+                        self.signaling.send(&opponent, &PeerSignal::Ready);
+                    }
+
+                    for event in std::mem::take(&mut self.early) {
+                        self.relayed(event).await?;
+                    }
+                }
+                PeerCommand::Send(event) => {
+                    let channel = self.peer.as_ref().and_then(|peer| peer.channel.as_ref());
+                    if let Some(channel) = channel {
+                        channel.send_with_str(&event)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn signal(&mut self, signal: PeerSignal) -> Result<(), JsValue> {
+            match signal {
+                PeerSignal::Ready => {
+                    if self.offering && !self.open.load(Ordering::SeqCst) {
+                        self.offer().await?;
+                    }
+                }
+                PeerSignal::Offer(sdp) => {
+                    if self.offering {
+                        return Ok(());
+                    }
+                    let mut peer = self.replace_peer(false)?;
+                    peer.set_remote(RtcSdpType::Offer, &sdp).await?;
+                    let answer = peer.describe(RtcSdpType::Answer).await?;
+                    self.peer = Some(peer);
+                    self.send_signal(PeerSignal::Answer(answer));
+                    self.add_candidates().await?;
+                }
+                PeerSignal::Answer(sdp) => {
+                    if let Some(peer) = self.peer.as_mut().filter(|_| self.offering) {
+                        peer.set_remote(RtcSdpType::Answer, &sdp).await?;
+                        self.add_candidates().await?;
+                    }
+                }
+                PeerSignal::Candidate(candidate) => {
+                    self.candidates.push(candidate);
+                    self.add_candidates().await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn peer_event(&mut self, event: PeerEvent) -> Result<(), JsValue> {
+            match event {
+                PeerEvent::Channel(channel) => {
+                    if let Some(peer) = self.peer.as_mut() {
+                        peer.attach(channel, &self.events);
+                    }
+                }
+                PeerEvent::Open => {
+                    info!("webrtc data channel open");
+                    self.open.store(true, Ordering::SeqCst);
+                }
+                PeerEvent::Closed => {
+                    info!("webrtc data channel closed, back to the relays");
+                    self.open.store(false, Ordering::SeqCst);
+                }
+                PeerEvent::Message(json) => {
+                    // the same signed events as on the relays, only the opponent's are taken
+                    let event = match Event::from_json(json) {
+                        Ok(event) if event.verify().is_ok() => event,
+                        _ => {
+                            error!("invalid event on the data channel");
+                            return Ok(());
+                        }
+                    };
+                    if Some(event.pubkey) == self.opponent {
+                        self.forward(event);
+                    }
+                }
+                PeerEvent::Candidate(candidate) => {
+                    self.send_signal(PeerSignal::Candidate(candidate));
+                }
+            }
+            Ok(())
+        }
+
+        // candidates of an earlier answer are dropped, the new answer brings its own
+        async fn offer(&mut self) -> Result<(), JsValue> {
+            self.candidates.clear();
+            let peer = self.replace_peer(true)?;
+            let offer = peer.describe(RtcSdpType::Offer).await?;
+            self.peer = Some(peer);
+            self.send_signal(PeerSignal::Offer(offer));
+            Ok(())
+        }
+
+        fn replace_peer(&mut self, offer: bool) -> Result<Peer, JsValue> {
+            if let Some(peer) = self.peer.take() {
+                peer.close();
+            }
+            self.open.store(false, Ordering::SeqCst);
+            Peer::new(&self.events, offer)
+        }
+
+        // a stale candidate fails on its own, the others are still added
+        async fn add_candidates(&mut self) -> Result<(), JsValue> {
+            let Some(peer) = self.peer.as_ref().filter(|peer| peer.remote_set) else {
+                return Ok(());
+            };
+            for candidate in std::mem::take(&mut self.candidates) {
+                if let Err(e) = peer.add_candidate(&candidate).await {
+                    info!("ice candidate rejected: {:?}", e);
+                }
+            }
+            Ok(())
+        }
+
+        fn send_signal(&self, signal: PeerSignal) {
+            if let Some(opponent) = self.opponent {
+                self.signaling.send(&opponent, &signal);
+            }
+        }
+    }
+}